@@ -7,22 +7,27 @@
 //!   cargo run --bin dev-commission -- commission
 //!   cargo run --bin dev-commission -- remove <node-id>
 //!   cargo run --bin dev-commission -- status
+//!   cargo run --bin dev-commission -- open-window
+//!   cargo run --bin dev-commission -- revoke-window
+//!   cargo run --bin dev-commission -- qr [--output bridge-qr.svg]
+//!   cargo run --bin dev-commission -- decode MT:-24J0AFN00KA0648G00
 
 use clap::{Parser, Subcommand};
 use futures_util::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 use tokio_tungstenite::{connect_async, tungstenite::Message};
+use virtual_matter_bridge::config::Config;
+use virtual_matter_bridge::control;
+use virtual_matter_bridge::instance_lock::InstanceLock;
+use virtual_matter_bridge::matter::comm_data::CommissioningData;
+use virtual_matter_bridge::matter::get_comm_data_path;
+use virtual_matter_bridge::onboarding::{self, OnboardingPayload, generate_pairing_code};
 
 /// Default python-matter-server WebSocket URL
 const DEFAULT_MATTER_SERVER_URL: &str = "ws://localhost:5580/ws";
 
-/// Default discriminator (from rs-matter TEST_DEV_COMM)
-const DEFAULT_DISCRIMINATOR: u16 = 3840;
-
-/// Default passcode (from rs-matter TEST_DEV_COMM)
-const DEFAULT_PASSCODE: u32 = 20202021;
-
 #[derive(Parser)]
 #[command(name = "dev-commission")]
 #[command(about = "Development tool for commissioning virtual matter bridge")]
@@ -39,13 +44,13 @@ struct Cli {
 enum Commands {
     /// Commission the virtual matter bridge to python-matter-server
     Commission {
-        /// Override the discriminator
-        #[arg(long, env = "MATTER_DISCRIMINATOR", default_value_t = DEFAULT_DISCRIMINATOR)]
-        discriminator: u16,
+        /// Override the discriminator (default: the bridge's per-install value)
+        #[arg(long, env = "MATTER_DISCRIMINATOR")]
+        discriminator: Option<u16>,
 
-        /// Override the passcode
-        #[arg(long, env = "MATTER_PASSCODE", default_value_t = DEFAULT_PASSCODE)]
-        passcode: u32,
+        /// Override the passcode (default: the bridge's per-install value)
+        #[arg(long, env = "MATTER_PASSCODE")]
+        passcode: Option<u32>,
     },
     /// Remove a commissioned node from python-matter-server
     Remove {
@@ -54,6 +59,41 @@ enum Commands {
     },
     /// Get status of all commissioned nodes
     Status,
+    /// Open a commissioning window on the running bridge to add another ecosystem
+    OpenWindow {
+        /// Window timeout in seconds (180-900)
+        #[arg(long, default_value_t = 900)]
+        timeout: u16,
+
+        /// Use the per-install passcode instead of a one-time passcode
+        #[arg(long)]
+        basic: bool,
+    },
+    /// Close an open commissioning window on the running bridge
+    RevokeWindow,
+    /// Print the bridge's QR code and pairing code (works while the bridge is stopped)
+    Qr {
+        /// Override the discriminator (default: the bridge's per-install value)
+        #[arg(long, env = "MATTER_DISCRIMINATOR")]
+        discriminator: Option<u16>,
+
+        /// Override the passcode (default: the bridge's per-install value)
+        #[arg(long, env = "MATTER_PASSCODE")]
+        passcode: Option<u32>,
+
+        /// Also write the QR code to a file (.svg or .png)
+        #[arg(long)]
+        output: Option<PathBuf>,
+
+        /// Pixels per module for PNG output
+        #[arg(long, default_value_t = 8)]
+        scale: u32,
+    },
+    /// Decode an `MT:` QR code payload
+    Decode {
+        /// QR code payload
+        payload: String,
+    },
 }
 
 /// Request message for python-matter-server WebSocket API
@@ -77,70 +117,62 @@ struct WsResponse {
     details: Option<String>,
 }
 
-/// Generate the manual pairing code from discriminator and passcode.
-///
-/// Matter short manual pairing code is an 11-digit decimal number:
-/// - Chunk 1 (1 digit): discriminator bits 11-10
-/// - Chunk 2 (5 digits): discriminator bits 9-8 (upper 2 bits) + passcode bits 13-0 (lower 14 bits)
-/// - Chunk 3 (4 digits): passcode bits 26-14
-/// - Check digit (1 digit): Verhoeff checksum
-///
-/// Reference: Matter spec Section 5.1.4.1, connectedhomeip ManualSetupPayloadGenerator.h
-fn generate_pairing_code(discriminator: u16, passcode: u32) -> String {
-    // Chunk 1: top 2 bits of discriminator (bits 11-10)
-    let chunk1 = (discriminator >> 10) & 0x03;
-
-    // Chunk 2: discriminator bits 9-8 in upper 2 bits, passcode bits 13-0 in lower 14 bits
-    let discriminator_bits_9_8 = ((discriminator >> 8) & 0x03) as u32;
-    let passcode_bits_13_0 = passcode & 0x3FFF;
-    let chunk2 = (discriminator_bits_9_8 << 14) | passcode_bits_13_0;
-
-    // Chunk 3: passcode bits 26-14
-    let chunk3 = (passcode >> 14) & 0x1FFF;
-
-    // Format as 10 digits (1 + 5 + 4), then add Verhoeff check digit
-    let payload = format!("{}{:05}{:04}", chunk1, chunk2, chunk3);
-    let check_digit = verhoeff_checksum(&payload);
-    format!("{}{}", payload, check_digit)
+/// Resolve the discriminator and passcode, falling back to the commissioning data
+/// generated by the bridge on first run (of the instance selected by MATTER_INSTANCE).
+fn resolve_comm_data(
+    config: &Config,
+    discriminator: Option<u16>,
+    passcode: Option<u32>,
+) -> Result<(u16, u32), Box<dyn std::error::Error>> {
+    let comm_data_path = get_comm_data_path(&config.matter);
+    let persisted = CommissioningData::load(&comm_data_path);
+    match (
+        discriminator.or(persisted.map(|data| data.discriminator)),
+        passcode.or(persisted.map(|data| data.passcode)),
+    ) {
+        (Some(discriminator), Some(passcode)) => Ok((discriminator, passcode)),
+        _ => {
+            eprintln!("No commissioning data found at {:?}", comm_data_path);
+            eprintln!("Start the bridge once or pass --discriminator and --passcode.");
+            Err("missing commissioning data".into())
+        }
+    }
+}
+
+/// Write a QR code file, choosing the format from the file extension.
+fn write_qr_file(payload: &str, path: &Path, scale: u32) -> Result<(), Box<dyn std::error::Error>> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("svg") => std::fs::write(path, onboarding::render_svg(payload)?)?,
+        Some("png") => std::fs::write(path, onboarding::render_png(payload, scale)?)?,
+        _ => return Err(format!("unsupported QR code file type: {:?}", path).into()),
+    }
+    Ok(())
 }
 
-/// Compute Verhoeff check digit for a string of decimal digits.
-fn verhoeff_checksum(input: &str) -> u8 {
-    // Verhoeff dihedral group D5 multiplication table
-    const D: [[u8; 10]; 10] = [
-        [0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
-        [1, 2, 3, 4, 0, 6, 7, 8, 9, 5],
-        [2, 3, 4, 0, 1, 7, 8, 9, 5, 6],
-        [3, 4, 0, 1, 2, 8, 9, 5, 6, 7],
-        [4, 0, 1, 2, 3, 9, 5, 6, 7, 8],
-        [5, 9, 8, 7, 6, 0, 4, 3, 2, 1],
-        [6, 5, 9, 8, 7, 1, 0, 4, 3, 2],
-        [7, 6, 5, 9, 8, 2, 1, 0, 4, 3],
-        [8, 7, 6, 5, 9, 3, 2, 1, 0, 4],
-        [9, 8, 7, 6, 5, 4, 3, 2, 1, 0],
-    ];
-
-    // Verhoeff permutation table
-    const P: [[u8; 10]; 8] = [
-        [0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
-        [1, 5, 7, 6, 2, 8, 3, 0, 9, 4],
-        [5, 8, 0, 3, 7, 9, 6, 1, 4, 2],
-        [8, 9, 1, 6, 0, 4, 3, 5, 2, 7],
-        [9, 4, 5, 3, 1, 2, 6, 8, 7, 0],
-        [4, 2, 8, 6, 5, 7, 3, 9, 0, 1],
-        [2, 7, 9, 3, 8, 0, 6, 4, 1, 5],
-        [7, 0, 4, 6, 9, 1, 3, 2, 5, 8],
-    ];
-
-    // Verhoeff inverse table
-    const INV: [u8; 10] = [0, 4, 3, 2, 1, 5, 6, 7, 8, 9];
-
-    let digits: Vec<u8> = input.bytes().map(|b| b - b'0').collect();
-    let mut c: u8 = 0;
-    for (i, &d) in digits.iter().rev().enumerate() {
-        c = D[c as usize][P[(i + 1) % 8][d as usize] as usize];
+/// Print the fields of an onboarding payload.
+fn print_payload(payload: &OnboardingPayload) {
+    let mut discovery = Vec::new();
+    for (bit, name) in [
+        (onboarding::DISCOVERY_SOFT_AP, "SoftAP"),
+        (onboarding::DISCOVERY_BLE, "BLE"),
+        (onboarding::DISCOVERY_ON_NETWORK, "on-network"),
+    ] {
+        if payload.discovery & bit != 0 {
+            discovery.push(name);
+        }
     }
-    INV[c as usize]
+    println!("  QR payload:    {}", payload);
+    println!("  Pairing code:  {}", payload.manual_code());
+    println!("  Vendor ID:     0x{:04X}", payload.vendor_id);
+    println!("  Product ID:    0x{:04X}", payload.product_id);
+    println!("  Flow:          {:?}", payload.flow);
+    println!("  Discovery:     {}", discovery.join(", "));
+    println!("  Discriminator: {}", payload.discriminator);
+    println!("  Passcode:      {}", payload.passcode);
 }
 
 #[tokio::main]
@@ -150,6 +182,81 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let cli = Cli::parse();
 
+    // Onboarding payload commands work offline
+    match &cli.command {
+        Commands::Qr {
+            discriminator,
+            passcode,
+            output,
+            scale,
+        } => {
+            let config = Config::from_env()?;
+            let (discriminator, passcode) = resolve_comm_data(&config, *discriminator, *passcode)?;
+            let payload = OnboardingPayload::new(
+                config.matter.vendor_id,
+                config.matter.product_id,
+                discriminator,
+                passcode,
+            );
+            let qr_payload = payload.qr_payload();
+            print!("{}", onboarding::render_terminal(&qr_payload)?);
+            print_payload(&payload);
+            if let Some(path) = output {
+                write_qr_file(&qr_payload, path, *scale)?;
+                println!("QR code written to {:?}", path);
+            }
+            return Ok(());
+        }
+        Commands::Decode { payload } => {
+            print_payload(&OnboardingPayload::parse(payload)?);
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    // Commissioning window commands talk to the bridge itself, not python-matter-server
+    let window_request = match &cli.command {
+        Commands::OpenWindow { timeout, basic } => Some((
+            "open_commissioning_window",
+            serde_json::json!({ "timeout_secs": timeout, "basic": basic }),
+        )),
+        Commands::RevokeWindow => Some(("revoke_commissioning_window", serde_json::Value::Null)),
+        _ => None,
+    };
+    if let Some((method, params)) = window_request {
+        let socket_path = InstanceLock::socket_path(
+            &InstanceLock::runtime_dir(),
+            &Config::from_env()?.matter.instance_name(),
+        );
+        let result = control::call(&socket_path, method, params).await?;
+        match cli.command {
+            Commands::OpenWindow { .. } => {
+                println!(
+                    "Commissioning window open for {} seconds",
+                    result["timeout_secs"]
+                );
+                println!(
+                    "  Pairing code: {}",
+                    result["pairing_code"].as_str().unwrap_or_default()
+                );
+                println!(
+                    "  QR payload:   {}",
+                    result["qr_payload"].as_str().unwrap_or_default()
+                );
+                println!("Add the bridge in the other controller with one of these codes.");
+            }
+            _ => println!(
+                "{}",
+                if result.as_bool().unwrap_or_default() {
+                    "Commissioning window closed"
+                } else {
+                    "No commissioning window was open"
+                }
+            ),
+        }
+        return Ok(());
+    }
+
     println!("Connecting to python-matter-server at {}...", cli.server);
 
     let (ws_stream, _) = connect_async(&cli.server).await.map_err(|e| {
@@ -168,6 +275,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             discriminator,
             passcode,
         } => {
+            let (discriminator, passcode) =
+                resolve_comm_data(&Config::from_env()?, discriminator, passcode)?;
+
             let pairing_code = generate_pairing_code(discriminator, passcode);
             println!("Commissioning with code: {}", pairing_code);
 
@@ -296,6 +406,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
             }
         }
+        Commands::OpenWindow { .. }
+        | Commands::RevokeWindow
+        | Commands::Qr { .. }
+        | Commands::Decode { .. } => {
+            unreachable!("bridge and offline commands are handled before connecting")
+        }
     }
 
     Ok(())
@@ -7,7 +7,7 @@
 //! and logs state changes and button presses. It also tests
 //! setting the external display values.
 
-use log::{info, warn};
+use log::{error, info, warn};
 use std::sync::Arc;
 use tokio::sync::mpsc;
 
@@ -26,14 +26,26 @@ async fn main() {
     info!("Starting W100 MQTT test");
 
     // Load configuration
-    let config = Config::from_env();
+    let config = match Config::from_env() {
+        Ok(config) => config,
+        Err(e) => {
+            error!("{}", e);
+            return;
+        }
+    };
     info!(
         "Connecting to MQTT broker at {}:{}",
         config.mqtt.broker_host, config.mqtt.broker_port
     );
 
     // Create MQTT client
-    let mqtt_client = MqttClient::new(&config.mqtt);
+    let mqtt_client = match MqttClient::new(&config.mqtt) {
+        Ok(client) => client,
+        Err(e) => {
+            error!("Failed to create MQTT client: {}", e);
+            return;
+        }
+    };
     let async_client = mqtt_client.client();
 
     // Create W100 device handler
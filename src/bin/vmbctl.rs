@@ -0,0 +1,329 @@
+//! Command line client for the local control plane of a running bridge.
+//!
+//! Talks JSON-RPC over the instance socket (see `virtual_matter_bridge::control`).
+//! The instance is selected like the bridge itself (`MATTER_INSTANCE`, or `--instance`).
+//!
+//! Usage:
+//!   cargo run --bin vmbctl -- list
+//!   cargo run --bin vmbctl -- set 5 21.5
+//!   cargo run --bin vmbctl -- toggle 7
+//!   cargo run --bin vmbctl -- commission --timeout 300
+//!   cargo run --bin vmbctl -- revoke
+//!   cargo run --bin vmbctl -- fabrics
+//!   cargo run --bin vmbctl -- fabric-alias 2 "Apple Home"
+//!   cargo run --bin vmbctl -- remove-fabric 2
+//!   cargo run --bin vmbctl -- factory-reset --yes
+//!   cargo run --bin vmbctl -- shutdown
+
+use clap::{Parser, Subcommand};
+use serde_json::{Value, json};
+use virtual_matter_bridge::config::{self, Config};
+use virtual_matter_bridge::control;
+use virtual_matter_bridge::instance_lock::InstanceLock;
+
+#[derive(Parser)]
+#[command(name = "vmbctl")]
+#[command(about = "Control a running virtual matter bridge")]
+struct Cli {
+    /// Bridge instance to control (default: MATTER_INSTANCE)
+    #[arg(long)]
+    instance: Option<String>,
+
+    /// Print raw JSON results
+    #[arg(long)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// List devices and endpoints with their current values
+    List,
+    /// Show the current values of an endpoint
+    Get {
+        /// Endpoint ID
+        endpoint: u16,
+    },
+    /// Set a value (true/false, a number, or a button action like single_press)
+    Set {
+        /// Endpoint ID
+        endpoint: u16,
+        /// New value
+        value: String,
+        /// Value kind when the endpoint has several (e.g. temperature, battery)
+        #[arg(long)]
+        kind: Option<String>,
+    },
+    /// Toggle a switch
+    Toggle {
+        /// Endpoint ID
+        endpoint: u16,
+    },
+    /// Open a commissioning window to add another controller (one-time passcode)
+    Commission {
+        /// Window timeout in seconds (180-900)
+        #[arg(long, default_value_t = 900)]
+        timeout: u16,
+
+        /// Use the per-install passcode instead of a one-time passcode
+        #[arg(long)]
+        basic: bool,
+    },
+    /// Close an open commissioning window
+    Revoke,
+    /// List the fabrics the bridge is commissioned into
+    Fabrics,
+    /// Set the local alias of a fabric (omit the alias to clear it)
+    FabricAlias {
+        /// Fabric index (see `fabrics`)
+        fabric_index: u8,
+        /// New alias
+        alias: Option<String>,
+    },
+    /// Remove a fabric (the controller loses access to the bridge)
+    RemoveFabric {
+        /// Fabric index (see `fabrics`)
+        fabric_index: u8,
+    },
+    /// Remove all fabrics and controller-assigned state, and reopen commissioning
+    FactoryReset {
+        /// Confirm the reset
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Show node diagnostics (boot reason, reboot count, uptime, memory)
+    Diagnostics,
+    /// Shut the bridge down gracefully
+    Shutdown,
+}
+
+/// Interpret a command line value as JSON (bool/number), falling back to a string.
+fn parse_value(value: &str) -> Value {
+    match value {
+        "on" => json!(true),
+        "off" => json!(false),
+        _ => serde_json::from_str(value).unwrap_or_else(|_| json!(value)),
+    }
+}
+
+/// Format the values of an endpoint snapshot as `kind=value` pairs.
+fn format_values(endpoint: &Value) -> String {
+    endpoint["values"]
+        .as_object()
+        .map(|values| {
+            values
+                .iter()
+                .map(|(kind, value)| format!("{}={}", kind, value))
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .unwrap_or_default()
+}
+
+/// Format a Unix timestamp in local time.
+fn format_timestamp(secs: Option<u64>) -> String {
+    secs.and_then(|secs| chrono::DateTime::from_timestamp(secs as i64, 0))
+        .map(|time| {
+            time.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        })
+        .unwrap_or_else(|| "never".to_string())
+}
+
+fn print_endpoint(endpoint: &Value) {
+    println!(
+        "  [{:>3}] {:<20} {}",
+        endpoint["endpoint_id"],
+        endpoint["label"].as_str().unwrap_or_default(),
+        format_values(endpoint)
+    );
+}
+
+#[tokio::main]
+async fn main() {
+    // Load .env file so the instance name matches the bridge
+    config::load_dotenv();
+
+    let cli = Cli::parse();
+
+    let mut matter_config = match Config::from_env() {
+        Ok(config) => config.matter,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Some(instance) = cli.instance {
+        if let Err(e) = config::validate_instance(&instance) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        matter_config.instance = Some(instance);
+    }
+    let socket_path =
+        InstanceLock::socket_path(&InstanceLock::runtime_dir(), &matter_config.instance_name());
+
+    if let Commands::FactoryReset { yes: false } = cli.command {
+        eprintln!("Factory reset removes all fabrics and controller-assigned names.");
+        eprintln!("Run again with --yes to confirm.");
+        std::process::exit(1);
+    }
+
+    let (method, params) = match &cli.command {
+        Commands::List => ("list_devices", Value::Null),
+        Commands::Get { endpoint } => ("get", json!({ "endpoint": endpoint })),
+        Commands::Set {
+            endpoint,
+            value,
+            kind,
+        } => (
+            "set",
+            json!({ "endpoint": endpoint, "value": parse_value(value), "kind": kind }),
+        ),
+        Commands::Toggle { endpoint } => ("toggle", json!({ "endpoint": endpoint })),
+        Commands::Commission { timeout, basic } => (
+            "open_commissioning_window",
+            json!({ "timeout_secs": timeout, "basic": basic }),
+        ),
+        Commands::Revoke => ("revoke_commissioning_window", Value::Null),
+        Commands::Fabrics => ("list_fabrics", Value::Null),
+        Commands::FabricAlias {
+            fabric_index,
+            alias,
+        } => (
+            "set_fabric_alias",
+            json!({ "fabric_index": fabric_index, "alias": alias }),
+        ),
+        Commands::RemoveFabric { fabric_index } => {
+            ("remove_fabric", json!({ "fabric_index": fabric_index }))
+        }
+        Commands::FactoryReset { .. } => ("factory_reset", Value::Null),
+        Commands::Diagnostics => ("diagnostics", Value::Null),
+        Commands::Shutdown => ("shutdown", Value::Null),
+    };
+
+    let result = match control::call(&socket_path, method, params).await {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if cli.json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&result).unwrap_or_default()
+        );
+        return;
+    }
+
+    match cli.command {
+        Commands::List => {
+            for device in result.as_array().into_iter().flatten() {
+                println!("{}", device["device"].as_str().unwrap_or_default());
+                for endpoint in device["endpoints"].as_array().into_iter().flatten() {
+                    print_endpoint(endpoint);
+                }
+            }
+        }
+        Commands::Get { .. } => print_endpoint(&result),
+        Commands::Set { .. } | Commands::Toggle { .. } => println!("{}", result),
+        Commands::Commission { .. } => {
+            println!(
+                "Commissioning window open for {} seconds",
+                result["timeout_secs"]
+            );
+            println!("  Discriminator: {}", result["discriminator"]);
+            println!(
+                "  Pairing code:  {}",
+                result["pairing_code"].as_str().unwrap_or_default()
+            );
+            println!(
+                "  QR payload:    {}",
+                result["qr_payload"].as_str().unwrap_or_default()
+            );
+        }
+        Commands::Revoke => {
+            if result.as_bool().unwrap_or_default() {
+                println!("Commissioning window closed");
+            } else {
+                println!("No commissioning window was open");
+            }
+        }
+        Commands::Fabrics => {
+            let fabrics = result.as_array().cloned().unwrap_or_default();
+            if fabrics.is_empty() {
+                println!("Not commissioned into any fabric");
+            }
+            for fabric in fabrics {
+                println!(
+                    "  [{}] vendor 0x{:04X}  fabric 0x{:016X}  node 0x{:016X}  {}",
+                    fabric["fabric_index"],
+                    fabric["vendor_id"].as_u64().unwrap_or_default(),
+                    fabric["fabric_id"].as_u64().unwrap_or_default(),
+                    fabric["node_id"].as_u64().unwrap_or_default(),
+                    fabric["label"].as_str().unwrap_or_default()
+                );
+                if let Some(alias) = fabric["alias"].as_str() {
+                    println!("      alias:        {}", alias);
+                }
+                println!(
+                    "      last session: {}",
+                    format_timestamp(fabric["last_session"].as_u64())
+                );
+            }
+        }
+        Commands::FabricAlias { .. } => println!("Fabric alias updated"),
+        Commands::RemoveFabric { fabric_index } => println!("Fabric {} removed", fabric_index),
+        Commands::FactoryReset { .. } => {
+            match result["controller"]["removed_nodes"].as_u64() {
+                Some(removed) => println!("Removed {} node(s) from python-matter-server", removed),
+                None => {
+                    if let Some(error) = result["controller"]["error"].as_str() {
+                        eprintln!("Warning: controller cleanup failed: {}", error);
+                    }
+                }
+            }
+            println!(
+                "Factory reset complete, {} fabric(s) removed",
+                result["removed_fabrics"]
+            );
+            println!(
+                "Commissioning window open for {} seconds, pairing code {}",
+                result["timeout_secs"],
+                result["pairing_code"].as_str().unwrap_or_default()
+            );
+        }
+        Commands::Diagnostics => {
+            let uptime = result["uptime_secs"].as_u64().unwrap_or_default();
+            println!(
+                "Boot #{} ({})",
+                result["reboot_count"],
+                result["boot_reason"].as_str().unwrap_or_default()
+            );
+            println!(
+                "  uptime:            {}d {:02}:{:02}:{:02}",
+                uptime / 86400,
+                uptime / 3600 % 24,
+                uptime / 60 % 60,
+                uptime % 60
+            );
+            println!("  operational hours: {}", result["total_operational_hours"]);
+            println!(
+                "  interface:         {}",
+                result["interface"].as_str().unwrap_or_default()
+            );
+            println!(
+                "  memory:            {} MiB used, {} MiB available",
+                result["memory_used"].as_u64().unwrap_or_default() / (1024 * 1024),
+                result["memory_free"].as_u64().unwrap_or_default() / (1024 * 1024)
+            );
+            println!("  threads:           {}", result["threads"]);
+        }
+        Commands::Shutdown => println!("Shutdown requested"),
+    }
+}
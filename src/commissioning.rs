@@ -6,9 +6,11 @@
 //! Uses `commission_with_code` with `network_only: true` to commission via
 //! mDNS/IP without requiring Bluetooth.
 
+use crate::onboarding::generate_pairing_code;
 use futures_util::{SinkExt, StreamExt};
 use log::{info, warn};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::time::Duration;
 use tokio_tungstenite::{connect_async, tungstenite::Message};
 
@@ -33,73 +35,55 @@ struct WsResponse {
     details: Option<String>,
 }
 
-/// Generate the manual pairing code from discriminator and passcode.
+/// BasicInformation cluster ID
+const BASIC_INFORMATION_CLUSTER: u32 = 0x0028;
+
+/// BasicInformation UniqueID attribute ID
+const UNIQUE_ID_ATTRIBUTE: u32 = 0x0012;
+
+/// Read an attribute from python-matter-server node data.
 ///
-/// Matter short manual pairing code is an 11-digit decimal number.
-/// Reference: Matter spec Section 5.1.4.1
-pub fn generate_pairing_code(discriminator: u16, passcode: u32) -> String {
-    // Chunk 1: top 2 bits of discriminator (bits 11-10)
-    let chunk1 = (discriminator >> 10) & 0x03;
-
-    // Chunk 2: discriminator bits 9-8 in upper 2 bits, passcode bits 13-0 in lower 14 bits
-    let discriminator_bits_9_8 = ((discriminator >> 8) & 0x03) as u32;
-    let passcode_bits_13_0 = passcode & 0x3FFF;
-    let chunk2 = (discriminator_bits_9_8 << 14) | passcode_bits_13_0;
-
-    // Chunk 3: passcode bits 26-14
-    let chunk3 = (passcode >> 14) & 0x1FFF;
-
-    // Format as 10 digits (1 + 5 + 4), then add Verhoeff check digit
-    let payload = format!("{}{:05}{:04}", chunk1, chunk2, chunk3);
-    let check_digit = verhoeff_checksum(&payload);
-    format!("{}{}", payload, check_digit)
+/// Attributes are keyed by `"<endpoint>/<cluster>/<attribute>"`; nested objects per
+/// endpoint and cluster are accepted as well.
+fn node_attribute(node: &Value, endpoint: u16, cluster: u32, attribute: u32) -> Option<&Value> {
+    let attributes = node.get("attributes")?;
+    attributes
+        .get(format!("{}/{}/{}", endpoint, cluster, attribute))
+        .or_else(|| {
+            attributes
+                .get(endpoint.to_string())?
+                .get(cluster.to_string())?
+                .get(attribute.to_string())
+        })
 }
 
-/// Compute Verhoeff check digit for a string of decimal digits.
-fn verhoeff_checksum(input: &str) -> u8 {
-    const D: [[u8; 10]; 10] = [
-        [0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
-        [1, 2, 3, 4, 0, 6, 7, 8, 9, 5],
-        [2, 3, 4, 0, 1, 7, 8, 9, 5, 6],
-        [3, 4, 0, 1, 2, 8, 9, 5, 6, 7],
-        [4, 0, 1, 2, 3, 9, 5, 6, 7, 8],
-        [5, 9, 8, 7, 6, 0, 4, 3, 2, 1],
-        [6, 5, 9, 8, 7, 1, 0, 4, 3, 2],
-        [7, 6, 5, 9, 8, 2, 1, 0, 4, 3],
-        [8, 7, 6, 5, 9, 3, 2, 1, 0, 4],
-        [9, 8, 7, 6, 5, 4, 3, 2, 1, 0],
-    ];
-
-    const P: [[u8; 10]; 8] = [
-        [0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
-        [1, 5, 7, 6, 2, 8, 3, 0, 9, 4],
-        [5, 8, 0, 3, 7, 9, 6, 1, 4, 2],
-        [8, 9, 1, 6, 0, 4, 3, 5, 2, 7],
-        [9, 4, 5, 3, 1, 2, 6, 8, 7, 0],
-        [4, 2, 8, 6, 5, 7, 3, 9, 0, 1],
-        [2, 7, 9, 3, 8, 0, 6, 4, 1, 5],
-        [7, 0, 4, 6, 9, 1, 3, 2, 5, 8],
-    ];
-
-    const INV: [u8; 10] = [0, 4, 3, 2, 1, 5, 6, 7, 8, 9];
-
-    let digits: Vec<u8> = input.bytes().map(|b| b - b'0').collect();
-    let mut c: u8 = 0;
-    for (i, &d) in digits.iter().rev().enumerate() {
-        c = D[c as usize][P[(i + 1) % 8][d as usize] as usize];
-    }
-    INV[c as usize]
+/// IDs of the nodes that are this bridge, by the UniqueID of their root
+/// BasicInformation.
+///
+/// Other bridge instances on the same controller, and other devices sharing the
+/// vendor ID, report a different UniqueID and are left alone.
+fn bridge_node_ids(nodes: &[Value], unique_id: &str) -> Vec<u64> {
+    nodes
+        .iter()
+        .filter(|node| {
+            node_attribute(node, 0, BASIC_INFORMATION_CLUSTER, UNIQUE_ID_ATTRIBUTE)
+                .and_then(Value::as_str)
+                == Some(unique_id)
+        })
+        .filter_map(|node| node.get("node_id").and_then(Value::as_u64))
+        .collect()
 }
 
-/// Remove existing bridge nodes from python-matter-server.
+/// Remove existing nodes of this bridge from python-matter-server.
 ///
 /// This should be called before re-commissioning after a schema change
-/// to clean up orphaned device entries from the controller.
+/// to clean up orphaned device entries from the controller. Nodes are matched by
+/// the bridge's BasicInformation `unique_id`.
 ///
 /// Returns the number of nodes removed.
 pub async fn remove_bridge_nodes(
     server_url: &str,
-    vendor_id: u16,
+    unique_id: &str,
 ) -> Result<u32, Box<dyn std::error::Error + Send + Sync>> {
     info!(
         "[Commission] Connecting to {} to cleanup old nodes",
@@ -141,82 +125,68 @@ pub async fn remove_bridge_nodes(
     .map_err(|_| "Timeout waiting for get_nodes response")?
     .ok_or("Connection closed")?;
 
-    // Parse nodes and find ones matching our vendor ID
+    // Parse nodes and find the ones that are this bridge
     let nodes = nodes_response.result.ok_or("No nodes in response")?;
     let nodes_array = nodes.as_array().ok_or("Nodes is not an array")?;
 
     let mut removed_count = 0u32;
-    for node in nodes_array {
-        // Extract node_id and vendor_id from the node data
-        let node_id = node.get("node_id").and_then(|v| v.as_u64());
-        let node_vendor_id = node
-            .get("attributes")
-            .and_then(|a| a.get("0"))
-            .and_then(|ep| ep.get("40"))
-            .and_then(|basic| basic.get("1"))
-            .and_then(|v| v.as_u64())
-            .map(|v| v as u16);
-
-        if let (Some(nid), Some(vid)) = (node_id, node_vendor_id)
-            && vid == vendor_id
+    for nid in bridge_node_ids(nodes_array, unique_id) {
+        info!(
+            "[Commission] Removing old bridge node {} (unique_id={})",
+            nid, unique_id
+        );
+
+        let remove_request = WsRequest {
+            message_id: format!("remove-{}", nid),
+            command: "remove_node".to_string(),
+            args: Some(serde_json::json!({ "node_id": nid })),
+        };
+
+        if let Err(e) = write
+            .send(Message::Text(
+                serde_json::to_string(&remove_request)?.into(),
+            ))
+            .await
         {
-            info!(
-                "[Commission] Removing old bridge node {} (vendor_id={:#06x})",
-                nid, vid
+            warn!(
+                "[Commission] Failed to send remove request for node {}: {}",
+                nid, e
             );
+            continue;
+        }
 
-            let remove_request = WsRequest {
-                message_id: format!("remove-{}", nid),
-                command: "remove_node".to_string(),
-                args: Some(serde_json::json!({ "node_id": nid })),
-            };
-
-            if let Err(e) = write
-                .send(Message::Text(
-                    serde_json::to_string(&remove_request)?.into(),
-                ))
-                .await
-            {
+        // Wait for remove response
+        let remove_timeout = tokio::time::timeout(Duration::from_secs(30), async {
+            while let Some(msg) = read.next().await {
+                if let Ok(Message::Text(text)) = msg {
+                    let text_str: &str = &text;
+                    if let Ok(response) = serde_json::from_str::<WsResponse>(text_str)
+                        && response.message_id == format!("remove-{}", nid)
+                    {
+                        return Some(response);
+                    }
+                }
+            }
+            None
+        })
+        .await;
+
+        match remove_timeout {
+            Ok(Some(response)) if response.error_code.is_none() => {
+                info!("[Commission] Successfully removed node {}", nid);
+                removed_count += 1;
+            }
+            Ok(Some(response)) => {
                 warn!(
-                    "[Commission] Failed to send remove request for node {}: {}",
-                    nid, e
+                    "[Commission] Failed to remove node {}: {:?}",
+                    nid, response.details
                 );
-                continue;
             }
-
-            // Wait for remove response
-            let remove_timeout = tokio::time::timeout(Duration::from_secs(30), async {
-                while let Some(msg) = read.next().await {
-                    if let Ok(Message::Text(text)) = msg {
-                        let text_str: &str = &text;
-                        if let Ok(response) = serde_json::from_str::<WsResponse>(text_str)
-                            && response.message_id == format!("remove-{}", nid)
-                        {
-                            return Some(response);
-                        }
-                    }
-                }
-                None
-            })
-            .await;
-
-            match remove_timeout {
-                Ok(Some(response)) if response.error_code.is_none() => {
-                    info!("[Commission] Successfully removed node {}", nid);
-                    removed_count += 1;
-                }
-                Ok(Some(response)) => {
-                    warn!(
-                        "[Commission] Failed to remove node {}: {:?}",
-                        nid, response.details
-                    );
-                }
-                Ok(None) => {
-                    warn!("[Commission] Connection closed while removing node {}", nid);
-                }
-                Err(_) => {
-                    warn!("[Commission] Timeout removing node {}", nid);
-                }
+            Ok(None) => {
+                warn!("[Commission] Connection closed while removing node {}", nid);
+            }
+            Err(_) => {
+                warn!("[Commission] Timeout removing node {}", nid);
             }
         }
     }
@@ -325,3 +295,29 @@ pub async fn auto_commission(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_bridge_node_ids() {
+        let nodes = [
+            // This bridge
+            json!({ "node_id": 1, "attributes": {
+                "0/40/1": "Test Vendor", "0/40/2": 0xFFF1, "0/40/18": "AAAA" } }),
+            // Another bridge instance with the same vendor ID
+            json!({ "node_id": 2, "attributes": { "0/40/2": 0xFFF1, "0/40/18": "BBBB" } }),
+            // A test device without UniqueID
+            json!({ "node_id": 3, "attributes": { "0/40/2": 0xFFF1, "0/40/15": "AAAA" } }),
+            // This bridge, nested attribute format
+            json!({ "node_id": 4, "attributes": { "0": { "40": { "18": "AAAA" } } } }),
+            // UniqueID on a bridged endpoint, not the root
+            json!({ "node_id": 5, "attributes": { "3/57/18": "AAAA" } }),
+        ];
+        assert_eq!(bridge_node_ids(&nodes, "AAAA"), vec![1, 4]);
+        assert_eq!(bridge_node_ids(&nodes, "BBBB"), vec![2]);
+        assert!(bridge_node_ids(&nodes, "CCCC").is_empty());
+    }
+}
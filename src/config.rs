@@ -1,6 +1,7 @@
+use crate::error::BridgeError;
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// Load environment variables from .env file with robust parsing.
 /// Handles values with spaces without requiring quotes.
@@ -44,6 +45,24 @@ pub fn load_dotenv() {
     }
 }
 
+/// Base name for the storage directory, lock socket and MQTT client ID
+pub const APP_NAME: &str = "virtual-matter-bridge";
+
+/// Default Matter UDP port
+pub const DEFAULT_MATTER_PORT: u16 = 5540;
+
+/// Parse a 16-bit ID given in hex (`0xFFF1`) or decimal.
+fn parse_id(value: &str) -> Option<u16> {
+    let value = value.trim();
+    match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub rtsp: RtspConfig,
@@ -60,6 +79,63 @@ pub struct MqttConfig {
     pub client_id: String,
     pub username: Option<String>,
     pub password: Option<String>,
+    /// Rename zigbee2mqtt devices when a controller changes their NodeLabel
+    pub rename_devices: bool,
+    /// MQTT over TLS (None = plain TCP)
+    pub tls: Option<MqttTlsConfig>,
+    /// Keep the session (subscriptions, queued QoS 1 messages) across reconnects
+    pub persistent_session: bool,
+    /// QoS level per topic class
+    pub qos: MqttQosConfig,
+    /// Mirror endpoint values to MQTT and accept `/set` commands
+    pub mirror: bool,
+    /// Topic prefix of the mirror (None = instance name)
+    pub mirror_prefix: Option<String>,
+    /// Remote list file (None = `remotes.json` in storage)
+    pub remotes: Option<PathBuf>,
+}
+
+/// MQTT QoS level (0-2) per topic class.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct MqttQosConfig {
+    /// Device state topics (`zigbee2mqtt/<device>`)
+    pub state: u8,
+    /// Button action topics (`zigbee2mqtt/<device>/action`)
+    pub action: u8,
+    /// Availability topics (device and bridge)
+    pub availability: u8,
+    /// Commands published to devices (`/set`, `/get`, bridge requests)
+    pub command: u8,
+}
+
+impl Default for MqttQosConfig {
+    fn default() -> Self {
+        Self {
+            state: 0,
+            action: 1,
+            availability: 1,
+            command: 1,
+        }
+    }
+}
+
+/// Parse an MQTT QoS level (0, 1 or 2).
+fn parse_qos(value: &str) -> Option<u8> {
+    value.trim().parse().ok().filter(|qos| *qos <= 2)
+}
+
+/// Default MQTT port for TLS connections
+pub const DEFAULT_MQTTS_PORT: u16 = 8883;
+
+/// MQTT over TLS (MQTTS) certificate files (PEM).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MqttTlsConfig {
+    /// CA certificate of the broker (None = system root certificates)
+    pub ca_cert: Option<PathBuf>,
+    /// Client certificate for mutual TLS
+    pub client_cert: Option<PathBuf>,
+    /// Client private key for mutual TLS
+    pub client_key: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -71,13 +147,190 @@ pub struct RtspConfig {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MatterConfig {
+    /// Instance name for running several bridges side by side (None = default instance)
+    pub instance: Option<String>,
+    /// Matter UDP port
+    pub port: u16,
     pub vendor_id: u16,
     pub product_id: u16,
     pub device_name: String,
-    pub discriminator: u16,
-    pub passcode: u32,
+    /// Vendor name reported in BasicInformation
+    pub vendor_name: String,
+    /// Product URL reported in BasicInformation
+    pub product_url: String,
+    /// Serial number (None = generated per install and persisted)
+    pub serial_number: Option<String>,
+    /// BasicInformation UniqueID (None = generated per install and persisted)
+    pub unique_id: Option<String>,
+    /// Pairing discriminator (None = generated per install and persisted)
+    pub discriminator: Option<u16>,
+    /// Pairing passcode (None = generated per install and persisted)
+    pub passcode: Option<u32>,
     /// python-matter-server WebSocket URL for auto-commissioning
     pub server_url: Option<String>,
+    /// Expose the bridge master on/off switch on endpoint 1
+    pub master_switch: bool,
+    /// Device attestation credential files (None = Matter SDK test credentials)
+    pub attestation: Option<AttestationConfig>,
+    /// Local button-to-endpoint bindings file (None = `local_bindings.json` in storage)
+    pub local_bindings: Option<PathBuf>,
+    /// Per-endpoint reading transforms file (None = `transforms.json` in storage)
+    pub transforms: Option<PathBuf>,
+    /// Derived endpoints file (None = `derived.json` in storage)
+    pub derived: Option<PathBuf>,
+}
+
+/// Maximum length in bytes of the BasicInformation name strings (VendorName, ProductName,
+/// NodeLabel, SerialNumber, UniqueID), per Matter spec
+const MAX_BASIC_INFO_NAME_LEN: usize = 32;
+
+/// Maximum length in bytes of the BasicInformation ProductURL
+const MAX_PRODUCT_URL_LEN: usize = 256;
+
+/// Maximum length of an instance name
+const MAX_INSTANCE_LEN: usize = 32;
+
+/// Check an instance name: 1 to 32 ASCII letters, digits, `_` or `-`.
+///
+/// The name becomes part of the storage path, the socket path, the MQTT client ID and
+/// the mirror topic prefix, so path separators and MQTT wildcards must not get in.
+pub fn validate_instance(instance: &str) -> Result<(), BridgeError> {
+    let valid = (1..=MAX_INSTANCE_LEN).contains(&instance.len())
+        && instance
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-');
+    if valid {
+        Ok(())
+    } else {
+        Err(BridgeError::ConfigError(format!(
+            "instance name {:?} must be 1 to {} letters, digits, '_' or '-'",
+            instance, MAX_INSTANCE_LEN
+        )))
+    }
+}
+
+impl MatterConfig {
+    /// Name of this bridge instance (`virtual-matter-bridge[-<instance>]`).
+    ///
+    /// Used for the storage directory and the instance lock socket.
+    pub fn instance_name(&self) -> String {
+        match &self.instance {
+            Some(instance) => format!("{}-{}", APP_NAME, instance),
+            None => APP_NAME.to_string(),
+        }
+    }
+
+    /// Storage directory of this instance (`~/.config/<instance name>`).
+    pub fn persist_dir(&self) -> PathBuf {
+        dirs::home_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join(".config")
+            .join(self.instance_name())
+    }
+
+    /// Check the instance name and the BasicInformation strings against their spec
+    /// maximum lengths.
+    pub fn validate(&self) -> Result<(), BridgeError> {
+        if let Some(instance) = &self.instance {
+            validate_instance(instance)?;
+        }
+        let fields = [
+            (
+                "DEVICE_NAME",
+                Some(&self.device_name),
+                MAX_BASIC_INFO_NAME_LEN,
+            ),
+            (
+                "MATTER_VENDOR_NAME",
+                Some(&self.vendor_name),
+                MAX_BASIC_INFO_NAME_LEN,
+            ),
+            (
+                "MATTER_PRODUCT_URL",
+                Some(&self.product_url),
+                MAX_PRODUCT_URL_LEN,
+            ),
+            (
+                "MATTER_SERIAL_NUMBER",
+                self.serial_number.as_ref(),
+                MAX_BASIC_INFO_NAME_LEN,
+            ),
+            (
+                "MATTER_UNIQUE_ID",
+                self.unique_id.as_ref(),
+                MAX_BASIC_INFO_NAME_LEN,
+            ),
+        ];
+        for (name, value, max) in fields {
+            if let Some(value) = value
+                && value.len() > max
+            {
+                return Err(BridgeError::ConfigError(format!(
+                    "{} is {} bytes long, at most {} are allowed",
+                    name,
+                    value.len(),
+                    max
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Device attestation credential files (DER or PEM).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttestationConfig {
+    /// Device Attestation Certificate
+    pub dac_cert: PathBuf,
+    /// DAC private key (SEC1, PKCS#8 or raw 32 bytes)
+    pub dac_key: PathBuf,
+    /// Product Attestation Intermediate certificate
+    pub pai_cert: PathBuf,
+    /// Certification Declaration
+    pub cert_declaration: PathBuf,
+}
+
+/// Environment variables of the attestation files (DAC, DAC key, PAI, CD)
+const ATTESTATION_VARS: [&str; 4] = [
+    "MATTER_DAC_CERT",
+    "MATTER_DAC_KEY",
+    "MATTER_PAI_CERT",
+    "MATTER_CD",
+];
+
+impl AttestationConfig {
+    /// Build the config from the values of [`ATTESTATION_VARS`].
+    ///
+    /// All four files or none must be given; a partial set is an error rather than a
+    /// silent fallback to the test credentials.
+    fn from_vars(vars: [Option<String>; 4]) -> Result<Option<Self>, BridgeError> {
+        match vars {
+            [
+                Some(dac_cert),
+                Some(dac_key),
+                Some(pai_cert),
+                Some(cert_declaration),
+            ] => Ok(Some(Self {
+                dac_cert: dac_cert.into(),
+                dac_key: dac_key.into(),
+                pai_cert: pai_cert.into(),
+                cert_declaration: cert_declaration.into(),
+            })),
+            [None, None, None, None] => Ok(None),
+            _ => {
+                let missing: Vec<&str> = ATTESTATION_VARS
+                    .iter()
+                    .zip(&vars)
+                    .filter(|(_, value)| value.is_none())
+                    .map(|(name, _)| *name)
+                    .collect();
+                Err(BridgeError::ConfigError(format!(
+                    "incomplete device attestation files, {} not set",
+                    missing.join(", ")
+                )))
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -108,12 +361,23 @@ impl Default for Config {
                 password: Some("password".to_string()),
             },
             matter: MatterConfig {
+                instance: None,
+                port: DEFAULT_MATTER_PORT,
                 vendor_id: 0xFFF1,
                 product_id: 0x8001,
                 device_name: "Virtual Matter Bridge".to_string(),
-                discriminator: 3840,
-                passcode: 20202021,
+                vendor_name: "timlisemer".to_string(),
+                product_url: "https://github.com/timlisemer/virtual_matter_bridge".to_string(),
+                serial_number: None,
+                unique_id: None,
+                discriminator: None,
+                passcode: None,
                 server_url: None,
+                master_switch: true,
+                attestation: None,
+                local_bindings: None,
+                transforms: None,
+                derived: None,
             },
             webrtc: WebRtcConfig {
                 stun_servers: vec!["stun:stun.l.google.com:19302".to_string()],
@@ -124,16 +388,26 @@ impl Default for Config {
             mqtt: MqttConfig {
                 broker_host: "10.0.0.2".to_string(),
                 broker_port: 1883,
-                client_id: "virtual-matter-bridge".to_string(),
+                client_id: APP_NAME.to_string(),
                 username: None,
                 password: None,
+                rename_devices: false,
+                tls: None,
+                persistent_session: true,
+                qos: MqttQosConfig::default(),
+                mirror: false,
+                mirror_prefix: None,
+                remotes: None,
             },
         }
     }
 }
 
 impl Config {
-    pub fn from_env() -> Self {
+    /// Load the configuration from environment variables.
+    ///
+    /// Fails on settings that cannot be used as given (e.g., incomplete attestation files).
+    pub fn from_env() -> Result<Self, BridgeError> {
         let mut config = Self::default();
 
         if let Ok(url) = std::env::var("RTSP_URL") {
@@ -145,22 +419,76 @@ impl Config {
         if let Ok(password) = std::env::var("RTSP_PASSWORD") {
             config.rtsp.password = Some(password);
         }
+        if let Ok(instance) = std::env::var("MATTER_INSTANCE")
+            && !instance.is_empty()
+        {
+            config.matter.instance = Some(instance);
+        }
+        if let Ok(port) = std::env::var("MATTER_PORT")
+            && let Ok(p) = port.parse()
+        {
+            config.matter.port = p;
+        }
         if let Ok(name) = std::env::var("DEVICE_NAME") {
             config.matter.device_name = name;
         }
+        if let Ok(vendor_id) = std::env::var("MATTER_VENDOR_ID")
+            && let Some(v) = parse_id(&vendor_id)
+        {
+            config.matter.vendor_id = v;
+        }
+        if let Ok(product_id) = std::env::var("MATTER_PRODUCT_ID")
+            && let Some(p) = parse_id(&product_id)
+        {
+            config.matter.product_id = p;
+        }
+        if let Ok(vendor_name) = std::env::var("MATTER_VENDOR_NAME") {
+            config.matter.vendor_name = vendor_name;
+        }
+        if let Ok(product_url) = std::env::var("MATTER_PRODUCT_URL") {
+            config.matter.product_url = product_url;
+        }
+        if let Ok(serial_number) = std::env::var("MATTER_SERIAL_NUMBER") {
+            config.matter.serial_number = Some(serial_number);
+        }
+        if let Ok(unique_id) = std::env::var("MATTER_UNIQUE_ID") {
+            config.matter.unique_id = Some(unique_id);
+        }
         if let Ok(discriminator) = std::env::var("MATTER_DISCRIMINATOR")
             && let Ok(d) = discriminator.parse()
         {
-            config.matter.discriminator = d;
+            config.matter.discriminator = Some(d);
         }
         if let Ok(passcode) = std::env::var("MATTER_PASSCODE")
             && let Ok(p) = passcode.parse()
         {
-            config.matter.passcode = p;
+            config.matter.passcode = Some(p);
         }
         if let Ok(server_url) = std::env::var("MATTER_SERVER_URL") {
             config.matter.server_url = Some(server_url);
         }
+        if let Ok(master_switch) = std::env::var("MATTER_MASTER_SWITCH") {
+            config.matter.master_switch =
+                !(master_switch == "0" || master_switch.to_lowercase() == "false");
+        }
+        config.matter.attestation = AttestationConfig::from_vars(
+            ATTESTATION_VARS.map(|name| std::env::var(name).ok().filter(|value| !value.is_empty())),
+        )?;
+        if let Ok(path) = std::env::var("MATTER_LOCAL_BINDINGS")
+            && !path.is_empty()
+        {
+            config.matter.local_bindings = Some(path.into());
+        }
+        if let Ok(path) = std::env::var("MATTER_TRANSFORMS")
+            && !path.is_empty()
+        {
+            config.matter.transforms = Some(path.into());
+        }
+        if let Ok(path) = std::env::var("MATTER_DERIVED")
+            && !path.is_empty()
+        {
+            config.matter.derived = Some(path.into());
+        }
 
         // MQTT configuration
         if let Ok(host) = std::env::var("MQTT_BROKER_HOST") {
@@ -173,6 +501,9 @@ impl Config {
         }
         if let Ok(client_id) = std::env::var("MQTT_CLIENT_ID") {
             config.mqtt.client_id = client_id;
+        } else if config.matter.instance.is_some() {
+            // Brokers disconnect clients with duplicate IDs
+            config.mqtt.client_id = config.matter.instance_name();
         }
         if let Ok(username) = std::env::var("MQTT_USERNAME") {
             config.mqtt.username = Some(username);
@@ -180,7 +511,85 @@ impl Config {
         if let Ok(password) = std::env::var("MQTT_PASSWORD") {
             config.mqtt.password = Some(password);
         }
+        if let Ok(rename) = std::env::var("MQTT_RENAME_DEVICES") {
+            config.mqtt.rename_devices = rename == "1" || rename.to_lowercase() == "true";
+        }
+        if let Ok(persistent) = std::env::var("MQTT_PERSISTENT_SESSION") {
+            config.mqtt.persistent_session =
+                !(persistent == "0" || persistent.to_lowercase() == "false");
+        }
+        for (var, level) in [
+            ("MQTT_QOS_STATE", &mut config.mqtt.qos.state),
+            ("MQTT_QOS_ACTION", &mut config.mqtt.qos.action),
+            ("MQTT_QOS_AVAILABILITY", &mut config.mqtt.qos.availability),
+            ("MQTT_QOS_COMMAND", &mut config.mqtt.qos.command),
+        ] {
+            if let Ok(value) = std::env::var(var)
+                && let Some(qos) = parse_qos(&value)
+            {
+                *level = qos;
+            }
+        }
+        if let Ok(mirror) = std::env::var("MQTT_MIRROR") {
+            config.mqtt.mirror = mirror == "1" || mirror.to_lowercase() == "true";
+        }
+        if let Ok(prefix) = std::env::var("MQTT_MIRROR_PREFIX")
+            && !prefix.is_empty()
+        {
+            config.mqtt.mirror_prefix = Some(prefix.trim_matches('/').to_string());
+        }
+        if let Ok(path) = std::env::var("MQTT_REMOTES")
+            && !path.is_empty()
+        {
+            config.mqtt.remotes = Some(path.into());
+        }
+        let tls_enabled =
+            std::env::var("MQTT_TLS").is_ok_and(|tls| tls == "1" || tls.to_lowercase() == "true");
+        let ca_cert = std::env::var("MQTT_CA_CERT").ok().map(PathBuf::from);
+        if tls_enabled || ca_cert.is_some() {
+            config.mqtt.tls = Some(MqttTlsConfig {
+                ca_cert,
+                client_cert: std::env::var("MQTT_CLIENT_CERT").ok().map(PathBuf::from),
+                client_key: std::env::var("MQTT_CLIENT_KEY").ok().map(PathBuf::from),
+            });
+            if std::env::var("MQTT_BROKER_PORT").is_err() {
+                config.mqtt.broker_port = DEFAULT_MQTTS_PORT;
+            }
+        }
+
+        config.matter.validate()?;
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_instance() {
+        let longest = "a".repeat(MAX_INSTANCE_LEN);
+        let too_long = "a".repeat(MAX_INSTANCE_LEN + 1);
+        for valid in ["kitchen", "Bridge_2", "a-b", "x", longest.as_str()] {
+            assert!(validate_instance(valid).is_ok(), "{}", valid);
+        }
+        for invalid in [
+            "",
+            "../etc",
+            "a/b",
+            "a+b",
+            "a#b",
+            "a b",
+            "küche",
+            too_long.as_str(),
+        ] {
+            assert!(validate_instance(invalid).is_err(), "{}", invalid);
+        }
 
-        config
+        let mut config = Config::default().matter;
+        config.instance = Some("home/..".to_string());
+        assert!(config.validate().is_err());
+        config.instance = Some("home".to_string());
+        assert!(config.validate().is_ok());
     }
 }
@@ -0,0 +1,582 @@
+//! Local control plane served on the instance socket.
+//!
+//! The Unix socket bound by [`InstanceLock`](crate::instance_lock::InstanceLock) accepts
+//! connections speaking newline-delimited JSON-RPC 2.0: one request object per line,
+//! answered by one response object per line. Only local users with access to the
+//! socket file can connect.
+//!
+//! Methods:
+//! - `list_devices`: all devices with their endpoints and current values
+//! - `get` `{endpoint}`: one endpoint with its current values
+//! - `set` `{endpoint, value, kind?}`: set a sensor value, switch or button action
+//! - `toggle` `{endpoint, kind?}`: toggle a switch
+//! - `open_commissioning_window` `{timeout_secs?, basic?}`: open a commissioning window
+//!   (enhanced window with a one-time passcode for multi-admin sharing, or a basic one
+//!   with the per-install passcode with `basic`)
+//! - `revoke_commissioning_window`: close an open commissioning window
+//! - `list_fabrics`: fabrics the bridge is commissioned into, with alias and last session
+//! - `set_fabric_alias` `{fabric_index, alias?}`: set or clear the local alias of a fabric
+//! - `remove_fabric` `{fabric_index}`: remove a fabric with its ACLs and subscriptions
+//! - `factory_reset`: remove the bridge from python-matter-server (when configured) and
+//!   remove all fabrics, then reopen commissioning
+//! - `shutdown`: shut the bridge down gracefully
+//!
+//! `vmbctl` is the command line client.
+
+use crate::matter::comm_data::{
+    MAX_COMM_WINDOW_TIMEOUT_SECS, MIN_COMM_WINDOW_TIMEOUT_SECS, is_valid_comm_window_timeout,
+};
+use crate::matter::control::{BridgeControl, ControlError, StackCommand};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::Notify;
+
+/// JSON-RPC protocol version
+const JSONRPC_VERSION: &str = "2.0";
+
+/// How long to wait for the Matter stack to answer a request
+const STACK_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// JSON-RPC error codes
+pub mod error_codes {
+    /// Request is not valid JSON
+    pub const PARSE_ERROR: i32 = -32700;
+    /// Request is not a valid request object
+    pub const INVALID_REQUEST: i32 = -32600;
+    /// Unknown method
+    pub const METHOD_NOT_FOUND: i32 = -32601;
+    /// Missing or invalid parameters
+    pub const INVALID_PARAMS: i32 = -32602;
+    /// The bridge failed to perform the operation
+    pub const SERVER_ERROR: i32 = -32000;
+}
+
+/// JSON-RPC request object.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RpcRequest {
+    pub jsonrpc: String,
+    #[serde(default)]
+    pub id: Value,
+    pub method: String,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub params: Value,
+}
+
+/// JSON-RPC error object.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Error)]
+#[error("{message} ({code})")]
+pub struct RpcError {
+    pub code: i32,
+    pub message: String,
+}
+
+impl RpcError {
+    fn new(code: i32, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    fn invalid_params(message: impl Into<String>) -> Self {
+        Self::new(error_codes::INVALID_PARAMS, message)
+    }
+}
+
+impl From<ControlError> for RpcError {
+    fn from(e: ControlError) -> Self {
+        let code = match e {
+            ControlError::StackUnavailable | ControlError::Matter(_) => error_codes::SERVER_ERROR,
+            _ => error_codes::INVALID_PARAMS,
+        };
+        Self::new(code, e.to_string())
+    }
+}
+
+/// JSON-RPC response object.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RpcResponse {
+    pub jsonrpc: String,
+    pub id: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+}
+
+impl RpcResponse {
+    fn new(id: Value, result: Result<Value, RpcError>) -> Self {
+        let (result, error) = match result {
+            Ok(value) => (Some(value), None),
+            Err(e) => (None, Some(e)),
+        };
+        Self {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id,
+            result,
+            error,
+        }
+    }
+}
+
+/// Parameters addressing a value on an endpoint.
+#[derive(Debug, Deserialize)]
+struct EndpointParams {
+    endpoint: u16,
+    #[serde(default)]
+    kind: Option<String>,
+    #[serde(default)]
+    value: Value,
+}
+
+/// Parameters of the fabric methods.
+#[derive(Debug, Deserialize)]
+struct FabricParams {
+    fabric_index: u8,
+    #[serde(default)]
+    alias: Option<String>,
+}
+
+/// Parameters of `open_commissioning_window`.
+#[derive(Debug, Default, Deserialize)]
+struct CommWindowParams {
+    #[serde(default)]
+    timeout_secs: Option<u16>,
+    /// Open a basic window with the per-install commissioning data instead of an
+    /// enhanced window with a one-time passcode
+    #[serde(default)]
+    basic: bool,
+}
+
+fn parse_params<T: for<'de> Deserialize<'de>>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::invalid_params(e.to_string()))
+}
+
+/// python-matter-server the bridge is commissioned into.
+#[derive(Debug, Clone)]
+struct MatterServer {
+    url: String,
+}
+
+/// Control plane server of one bridge instance.
+#[derive(Clone)]
+pub struct ControlServer {
+    control: BridgeControl,
+    /// Notified when a client requests a shutdown
+    shutdown: Arc<Notify>,
+    /// Controller to clean up on factory reset
+    matter_server: Option<MatterServer>,
+}
+
+impl ControlServer {
+    /// Create a server for `control`; `shutdown` is notified by the `shutdown` method.
+    pub fn new(control: BridgeControl, shutdown: Arc<Notify>) -> Self {
+        Self {
+            control,
+            shutdown,
+            matter_server: None,
+        }
+    }
+
+    /// Remove this bridge's nodes (matched by its UniqueID) from the python-matter-server
+    /// at `url` on factory reset.
+    pub fn with_matter_server(mut self, url: impl Into<String>) -> Self {
+        self.matter_server = Some(MatterServer { url: url.into() });
+        self
+    }
+
+    /// Accept and serve connections on `listener` (the instance lock socket).
+    pub async fn run(self, listener: std::os::unix::net::UnixListener) -> io::Result<()> {
+        listener.set_nonblocking(true)?;
+        let listener = UnixListener::from_std(listener)?;
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let server = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = server.serve_connection(stream).await {
+                    debug!("[Control] Connection closed with error: {}", e);
+                }
+            });
+        }
+    }
+
+    /// Serve requests of one client until it disconnects.
+    async fn serve_connection(&self, stream: UnixStream) -> io::Result<()> {
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let response = self.handle_line(&line).await;
+            let mut encoded = serde_json::to_vec(&response)?;
+            encoded.push(b'\n');
+            writer.write_all(&encoded).await?;
+        }
+        Ok(())
+    }
+
+    /// Handle one request line and build its response.
+    pub async fn handle_line(&self, line: &str) -> RpcResponse {
+        let value: Value = match serde_json::from_str(line) {
+            Ok(value) => value,
+            Err(e) => {
+                return RpcResponse::new(
+                    Value::Null,
+                    Err(RpcError::new(error_codes::PARSE_ERROR, e.to_string())),
+                );
+            }
+        };
+        let id = value.get("id").cloned().unwrap_or(Value::Null);
+        let request = match serde_json::from_value::<RpcRequest>(value) {
+            Ok(request) if request.jsonrpc == JSONRPC_VERSION => request,
+            Ok(_) => {
+                return RpcResponse::new(
+                    id,
+                    Err(RpcError::new(
+                        error_codes::INVALID_REQUEST,
+                        "unsupported jsonrpc version",
+                    )),
+                );
+            }
+            Err(e) => {
+                return RpcResponse::new(
+                    id,
+                    Err(RpcError::new(error_codes::INVALID_REQUEST, e.to_string())),
+                );
+            }
+        };
+        let result = self.dispatch(&request.method, request.params).await;
+        if let Err(e) = &result {
+            debug!("[Control] {} failed: {}", request.method, e);
+        }
+        RpcResponse::new(id, result)
+    }
+
+    /// Execute one method.
+    async fn dispatch(&self, method: &str, params: Value) -> Result<Value, RpcError> {
+        match method {
+            "list_devices" => Ok(self.list_devices()),
+            "get" => {
+                let params: EndpointParams = parse_params(params)?;
+                Ok(self.control.endpoint(params.endpoint)?.snapshot())
+            }
+            "set" => {
+                let params: EndpointParams = parse_params(params)?;
+                let endpoint = self.control.endpoint(params.endpoint)?;
+                let point = endpoint.point(params.kind.as_deref())?;
+                let value = point.set(&params.value)?;
+                info!(
+                    "[Control] Set {} of endpoint {} ({}) to {}",
+                    point.kind(),
+                    endpoint.endpoint_id,
+                    endpoint.label,
+                    value
+                );
+                Ok(value)
+            }
+            "toggle" => {
+                let params: EndpointParams = parse_params(params)?;
+                let endpoint = self.control.endpoint(params.endpoint)?;
+                let point = match params.kind.as_deref() {
+                    Some(kind) => endpoint.point(Some(kind))?,
+                    // Toggle the switch even on endpoints carrying other values
+                    None => match endpoint.points.iter().find(|point| point.is_switch()) {
+                        Some(point) => point,
+                        None => endpoint.point(None)?,
+                    },
+                };
+                let value = point.toggle()?;
+                info!(
+                    "[Control] Toggled endpoint {} ({}) to {}",
+                    endpoint.endpoint_id, endpoint.label, value
+                );
+                Ok(value)
+            }
+            "open_commissioning_window" => {
+                let params: CommWindowParams = if params.is_null() {
+                    CommWindowParams::default()
+                } else {
+                    parse_params(params)?
+                };
+                let timeout_secs = params.timeout_secs.unwrap_or(MAX_COMM_WINDOW_TIMEOUT_SECS);
+                if !is_valid_comm_window_timeout(timeout_secs) {
+                    return Err(RpcError::invalid_params(format!(
+                        "timeout_secs must be between {} and {}",
+                        MIN_COMM_WINDOW_TIMEOUT_SECS, MAX_COMM_WINDOW_TIMEOUT_SECS
+                    )));
+                }
+                self.stack_request(StackCommand::OpenCommissioningWindow {
+                    timeout_secs,
+                    enhanced: !params.basic,
+                })
+                .await
+            }
+            "revoke_commissioning_window" => {
+                self.stack_request(StackCommand::RevokeCommissioningWindow)
+                    .await
+            }
+            "list_fabrics" => self.stack_request(StackCommand::ListFabrics).await,
+            "set_fabric_alias" => {
+                let params: FabricParams = parse_params(params)?;
+                self.stack_request(StackCommand::SetFabricAlias {
+                    fabric_index: params.fabric_index,
+                    alias: params.alias,
+                })
+                .await
+            }
+            "remove_fabric" => {
+                let params: FabricParams = parse_params(params)?;
+                warn!(
+                    "[Control] Removing fabric {} via control socket",
+                    params.fabric_index
+                );
+                self.stack_request(StackCommand::RemoveFabric {
+                    fabric_index: params.fabric_index,
+                })
+                .await
+            }
+            "factory_reset" => self.factory_reset().await,
+            "diagnostics" => self.stack_request(StackCommand::Diagnostics).await,
+            "shutdown" => {
+                warn!("[Control] Shutdown requested via control socket");
+                self.shutdown.notify_one();
+                Ok(json!(true))
+            }
+            _ => Err(RpcError::new(
+                error_codes::METHOD_NOT_FOUND,
+                format!("unknown method '{}'", method),
+            )),
+        }
+    }
+
+    /// Group the registered endpoints by device (in endpoint order).
+    fn list_devices(&self) -> Value {
+        let mut devices: Vec<(String, Vec<Value>)> = Vec::new();
+        for endpoint in self.control.endpoints() {
+            let snapshot = endpoint.snapshot();
+            match devices
+                .iter_mut()
+                .find(|(name, _)| *name == endpoint.device)
+            {
+                Some((_, endpoints)) => endpoints.push(snapshot),
+                None => devices.push((endpoint.device, vec![snapshot])),
+            }
+        }
+        Value::Array(
+            devices
+                .into_iter()
+                .map(|(device, endpoints)| json!({ "device": device, "endpoints": endpoints }))
+                .collect(),
+        )
+    }
+
+    /// Remove the bridge from the controller, then reset the Matter stack.
+    ///
+    /// The controller is cleaned up first, while it can still reach the bridge. A
+    /// failure there is reported in the result but does not stop the local reset.
+    async fn factory_reset(&self) -> Result<Value, RpcError> {
+        warn!("[Control] Factory reset requested via control socket");
+        let controller = match (&self.matter_server, self.control.unique_id()) {
+            (Some(server), Some(unique_id)) => {
+                match crate::commissioning::remove_bridge_nodes(&server.url, unique_id).await {
+                    Ok(removed) => json!({ "removed_nodes": removed }),
+                    Err(e) => {
+                        warn!(
+                            "[Control] Failed to remove bridge nodes from controller: {}",
+                            e
+                        );
+                        json!({ "error": e.to_string() })
+                    }
+                }
+            }
+            (Some(_), None) => {
+                warn!("[Control] Bridge identity unknown, not removing nodes from controller");
+                json!({ "error": "bridge identity not known yet" })
+            }
+            (None, _) => Value::Null,
+        };
+        let mut result = self.stack_request(StackCommand::FactoryReset).await?;
+        result["controller"] = controller;
+        Ok(result)
+    }
+
+    /// Forward a request to the Matter stack thread.
+    async fn stack_request(&self, command: StackCommand) -> Result<Value, RpcError> {
+        match tokio::time::timeout(STACK_REQUEST_TIMEOUT, self.control.request(command)).await {
+            Ok(result) => Ok(result?),
+            Err(_) => Err(ControlError::StackUnavailable.into()),
+        }
+    }
+}
+
+/// Error types for control plane clients.
+#[derive(Debug, Error)]
+pub enum ControlClientError {
+    /// The bridge is not running or the socket is not accessible.
+    #[error("failed to connect to {path}: {source}")]
+    Connect { path: String, source: io::Error },
+
+    /// I/O error while talking to the bridge.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    /// The bridge sent an invalid response.
+    #[error("invalid response: {0}")]
+    InvalidResponse(String),
+
+    /// The bridge answered with an error.
+    #[error(transparent)]
+    Rpc(#[from] RpcError),
+}
+
+/// Call a control plane method on the bridge listening at `socket_path`.
+pub async fn call(
+    socket_path: &Path,
+    method: &str,
+    params: Value,
+) -> Result<Value, ControlClientError> {
+    let stream =
+        UnixStream::connect(socket_path)
+            .await
+            .map_err(|source| ControlClientError::Connect {
+                path: socket_path.display().to_string(),
+                source,
+            })?;
+    let (reader, mut writer) = stream.into_split();
+
+    let request = RpcRequest {
+        jsonrpc: JSONRPC_VERSION.to_string(),
+        id: json!(1),
+        method: method.to_string(),
+        params,
+    };
+    let mut encoded = serde_json::to_vec(&request)
+        .map_err(|e| ControlClientError::InvalidResponse(e.to_string()))?;
+    encoded.push(b'\n');
+    writer.write_all(&encoded).await?;
+
+    let line = BufReader::new(reader)
+        .lines()
+        .next_line()
+        .await?
+        .ok_or_else(|| ControlClientError::InvalidResponse("connection closed".to_string()))?;
+    let response: RpcResponse = serde_json::from_str(&line)
+        .map_err(|e| ControlClientError::InvalidResponse(e.to_string()))?;
+    match (response.result, response.error) {
+        (_, Some(e)) => Err(e.into()),
+        (Some(result), None) => Ok(result),
+        (None, None) => Ok(Value::Null),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matter::clusters::TemperatureSensor;
+    use crate::matter::control::{ControlEndpoint, ControlPoint};
+
+    fn server() -> (ControlServer, Arc<Notify>) {
+        let control = BridgeControl::new();
+        control.register(
+            ControlEndpoint::new(5, "Climate", "Temperature").with_point(
+                ControlPoint::Temperature(Arc::new(TemperatureSensor::new(20.0))),
+            ),
+        );
+        let shutdown = Arc::new(Notify::new());
+        (ControlServer::new(control, shutdown.clone()), shutdown)
+    }
+
+    fn error_code(response: &RpcResponse) -> Option<i32> {
+        response.error.as_ref().map(|e| e.code)
+    }
+
+    #[tokio::test]
+    async fn test_invalid_requests() {
+        let (server, _) = server();
+        let response = server.handle_line("not json").await;
+        assert_eq!(error_code(&response), Some(error_codes::PARSE_ERROR));
+
+        let response = server
+            .handle_line(r#"{"jsonrpc":"2.0","id":7,"method":"nope"}"#)
+            .await;
+        assert_eq!(response.id, json!(7));
+        assert_eq!(error_code(&response), Some(error_codes::METHOD_NOT_FOUND));
+
+        let response = server
+            .handle_line(r#"{"jsonrpc":"1.0","id":1,"method":"list_devices"}"#)
+            .await;
+        assert_eq!(error_code(&response), Some(error_codes::INVALID_REQUEST));
+    }
+
+    #[tokio::test]
+    async fn test_set_and_get_value() {
+        let (server, _) = server();
+        let response = server
+            .handle_line(
+                r#"{"jsonrpc":"2.0","id":1,"method":"set","params":{"endpoint":5,"value":21.5}}"#,
+            )
+            .await;
+        assert_eq!(response.result, Some(json!(21.5)));
+
+        let response = server
+            .handle_line(r#"{"jsonrpc":"2.0","id":2,"method":"get","params":{"endpoint":5}}"#)
+            .await;
+        let result = response.result.unwrap();
+        assert_eq!(result["values"]["temperature"], json!(21.5));
+
+        // A temperature is not a switch, and unknown endpoints are rejected
+        let response = server
+            .handle_line(r#"{"jsonrpc":"2.0","id":3,"method":"toggle","params":{"endpoint":5}}"#)
+            .await;
+        assert_eq!(error_code(&response), Some(error_codes::INVALID_PARAMS));
+        let response = server
+            .handle_line(r#"{"jsonrpc":"2.0","id":4,"method":"get","params":{"endpoint":9}}"#)
+            .await;
+        assert_eq!(error_code(&response), Some(error_codes::INVALID_PARAMS));
+    }
+
+    #[tokio::test]
+    async fn test_comm_window_timeout_validated() {
+        let (server, _) = server();
+        let response = server
+            .handle_line(r#"{"jsonrpc":"2.0","id":1,"method":"open_commissioning_window","params":{"timeout_secs":30}}"#)
+            .await;
+        assert_eq!(error_code(&response), Some(error_codes::INVALID_PARAMS));
+    }
+
+    #[tokio::test]
+    async fn test_fabric_params_validated() {
+        let (server, _) = server();
+        let response = server
+            .handle_line(r#"{"jsonrpc":"2.0","id":1,"method":"remove_fabric"}"#)
+            .await;
+        assert_eq!(error_code(&response), Some(error_codes::INVALID_PARAMS));
+
+        let response = server
+            .handle_line(r#"{"jsonrpc":"2.0","id":2,"method":"set_fabric_alias","params":{"fabric_index":300}}"#)
+            .await;
+        assert_eq!(error_code(&response), Some(error_codes::INVALID_PARAMS));
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_notifies() {
+        let (server, shutdown) = server();
+        let response = server
+            .handle_line(r#"{"jsonrpc":"2.0","id":1,"method":"shutdown"}"#)
+            .await;
+        assert_eq!(response.result, Some(json!(true)));
+        // The permit is stored until main waits for it
+        tokio::time::timeout(Duration::from_secs(1), shutdown.notified())
+            .await
+            .unwrap();
+    }
+}
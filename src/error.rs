@@ -41,6 +41,12 @@ pub enum BridgeError {
     #[error("Doorbell press simulation failed: {0}")]
     DoorbellSimulationFailed(String),
 
+    #[error("Device attestation error: {0}")]
+    AttestationError(String),
+
+    #[error("Invalid configuration: {0}")]
+    ConfigError(String),
+
     #[error(transparent)]
     IoError(#[from] std::io::Error),
 
@@ -143,13 +143,13 @@ impl CameraInput {
         self.config.matter.product_id
     }
 
-    /// Get Matter discriminator.
-    pub fn discriminator(&self) -> u16 {
+    /// Get the configured Matter discriminator (None = generated per install).
+    pub fn discriminator(&self) -> Option<u16> {
         self.config.matter.discriminator
     }
 
-    /// Get Matter passcode.
-    pub fn passcode(&self) -> u32 {
+    /// Get the configured Matter passcode (None = generated per install).
+    pub fn passcode(&self) -> Option<u32> {
         self.config.matter.passcode
     }
 
@@ -0,0 +1,408 @@
+//! Declarative mapping of zigbee2mqtt button actions onto Matter GenericSwitch events.
+//!
+//! Remotes report presses as action strings on `zigbee2mqtt/<device>/action`
+//! (`single_plus`, `on`, `brightness_move_up`, ...). An [`ActionMap`] maps each action
+//! to a named button of the device and the event to emit on it, so any remote can be
+//! bridged by describing its actions instead of writing a new device handler:
+//!
+//! ```json
+//! {
+//!     "on": { "button": "top", "event": "single" },
+//!     "brightness_move_up": { "button": "top", "event": "long" },
+//!     "brightness_stop": { "button": "top", "event": "release" }
+//! }
+//! ```
+//!
+//! Remotes are listed in a [`RemoteList`] file that names each zigbee2mqtt device and
+//! the action map file describing it, so new remotes need no code at all.
+
+use crate::matter::clusters::GenericSwitchState;
+use serde::Deserialize;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Errors loading an action map.
+#[derive(Debug, Error)]
+pub enum ActionMapError {
+    #[error("failed to read {0}: {1}")]
+    Read(PathBuf, std::io::Error),
+
+    #[error("invalid action map: {0}")]
+    Parse(#[from] serde_json::Error),
+
+    #[error("invalid remote list: {0}")]
+    RemoteList(serde_json::Error),
+
+    #[error("remote {remote}: action map uses unknown button {button:?}")]
+    UnknownButton { remote: String, button: String },
+}
+
+/// Default remote list file name in the storage directory
+pub const REMOTES_FILE: &str = "remotes.json";
+
+/// GenericSwitch event emitted for an action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ButtonEvent {
+    /// Short press (InitialPress + ShortRelease + MultiPressComplete, count 1)
+    Single,
+    /// Double press (MultiPressComplete, count 2)
+    Double,
+    /// Triple press (MultiPressComplete, count 3)
+    Triple,
+    /// Press and hold (InitialPress + LongPress)
+    #[serde(alias = "hold")]
+    Long,
+    /// Release after a hold (LongRelease)
+    Release,
+}
+
+impl ButtonEvent {
+    /// Emit this event on a button.
+    pub fn apply(self, button: &GenericSwitchState) {
+        match self {
+            Self::Single => button.single_press(),
+            Self::Double => button.multi_press(2),
+            Self::Triple => button.multi_press(3),
+            Self::Long => button.hold_start(),
+            Self::Release => button.hold_release(),
+        }
+    }
+
+    /// Name of the event for logging.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Single => "single press",
+            Self::Double => "double press",
+            Self::Triple => "triple press",
+            Self::Long => "hold start",
+            Self::Release => "release",
+        }
+    }
+}
+
+/// Button and event an action maps to.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ActionTarget {
+    /// Name of the button on the device
+    pub button: String,
+    /// Event to emit on the button
+    pub event: ButtonEvent,
+}
+
+/// Map from action strings to button events.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct ActionMap {
+    actions: HashMap<String, ActionTarget>,
+}
+
+impl ActionMap {
+    /// Create an empty map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Map an action to an event on a button.
+    pub fn with_action(
+        mut self,
+        action: impl Into<String>,
+        button: impl Into<String>,
+        event: ButtonEvent,
+    ) -> Self {
+        self.actions.insert(
+            action.into(),
+            ActionTarget {
+                button: button.into(),
+                event,
+            },
+        );
+        self
+    }
+
+    /// Parse a map from JSON (an object of action to `{"button", "event"}`).
+    pub fn from_json(json: &str) -> Result<Self, ActionMapError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Load a map from a JSON file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ActionMapError> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| ActionMapError::Read(path.to_path_buf(), e))?;
+        Self::from_json(&json)
+    }
+
+    /// Actions of the Aqara W100 on its `plus`, `minus` and `center` buttons.
+    ///
+    /// Actions without a button suffix belong to the center button.
+    pub fn w100() -> Self {
+        let mut map = Self::new();
+        for button in ["plus", "minus", "center"] {
+            for (prefix, event) in [
+                ("single", ButtonEvent::Single),
+                ("double", ButtonEvent::Double),
+                ("hold", ButtonEvent::Long),
+                ("release", ButtonEvent::Release),
+            ] {
+                map = map.with_action(format!("{}_{}", prefix, button), button, event);
+                if button == "center" {
+                    map = map.with_action(prefix, button, event);
+                }
+            }
+        }
+        map
+    }
+
+    /// Look up the target of an action.
+    pub fn get(&self, action: &str) -> Option<&ActionTarget> {
+        self.actions.get(action)
+    }
+
+    /// Number of mapped actions.
+    pub fn len(&self) -> usize {
+        self.actions.len()
+    }
+
+    /// Whether no actions are mapped.
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+
+    /// Names of the buttons the map emits events on (sorted).
+    pub fn buttons(&self) -> BTreeSet<&str> {
+        self.actions
+            .values()
+            .map(|target| target.button.as_str())
+            .collect()
+    }
+}
+
+/// A remote as listed in the remote list file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RemoteEntry {
+    friendly_name: String,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    vendor: Option<String>,
+    #[serde(default)]
+    product: Option<String>,
+    #[serde(default)]
+    buttons: Option<Vec<String>>,
+    actions: PathBuf,
+}
+
+/// A configured remote with its loaded action map.
+#[derive(Debug, Clone)]
+pub struct RemoteDefinition {
+    /// Friendly name in zigbee2mqtt
+    pub friendly_name: String,
+    /// Device name in Matter (defaults to the friendly name)
+    pub name: String,
+    /// Vendor shown in Matter
+    pub vendor: Option<String>,
+    /// Product shown in Matter
+    pub product: Option<String>,
+    /// Buttons in endpoint order
+    pub buttons: Vec<String>,
+    /// Maps actions onto the buttons
+    pub actions: ActionMap,
+}
+
+/// Remotes bridged from zigbee2mqtt, read from a JSON list:
+///
+/// ```json
+/// [
+///     { "friendly_name": "Hallway Remote", "actions": "ikea_styrbar.json" },
+///     { "friendly_name": "Dimmer", "name": "Bedroom Dimmer", "vendor": "Philips",
+///       "buttons": ["on", "up", "down", "off"], "actions": "/etc/vmb/hue_dimmer.json" }
+/// ]
+/// ```
+///
+/// Relative action map paths are resolved against the directory of the list. Without
+/// `buttons`, the buttons of the action map are used in alphabetical order.
+#[derive(Debug, Clone, Default)]
+pub struct RemoteList {
+    remotes: Vec<RemoteDefinition>,
+}
+
+impl RemoteList {
+    /// Load a remote list and its action maps; a missing list means no remotes.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ActionMapError> {
+        let path = path.as_ref();
+        let json = match std::fs::read_to_string(path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(ActionMapError::Read(path.to_path_buf(), e)),
+        };
+        let base = path.parent().unwrap_or_else(|| Path::new("."));
+        Self::from_json(&json, |actions| ActionMap::load(base.join(actions)))
+    }
+
+    /// Parse a remote list, loading each action map with `load_actions`.
+    fn from_json(
+        json: &str,
+        load_actions: impl Fn(&Path) -> Result<ActionMap, ActionMapError>,
+    ) -> Result<Self, ActionMapError> {
+        let entries: Vec<RemoteEntry> =
+            serde_json::from_str(json).map_err(ActionMapError::RemoteList)?;
+        let mut remotes = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let actions = load_actions(&entry.actions)?;
+            let buttons = match entry.buttons {
+                Some(buttons) => {
+                    if let Some(button) = actions
+                        .buttons()
+                        .into_iter()
+                        .find(|button| !buttons.iter().any(|name| name == button))
+                    {
+                        return Err(ActionMapError::UnknownButton {
+                            remote: entry.friendly_name,
+                            button: button.to_string(),
+                        });
+                    }
+                    buttons
+                }
+                None => actions.buttons().into_iter().map(String::from).collect(),
+            };
+            remotes.push(RemoteDefinition {
+                name: entry.name.unwrap_or_else(|| entry.friendly_name.clone()),
+                friendly_name: entry.friendly_name,
+                vendor: entry.vendor,
+                product: entry.product,
+                buttons,
+                actions,
+            });
+        }
+        Ok(Self { remotes })
+    }
+
+    /// The configured remotes in list order.
+    pub fn remotes(&self) -> &[RemoteDefinition] {
+        &self.remotes
+    }
+
+    /// Number of configured remotes.
+    pub fn len(&self) -> usize {
+        self.remotes.len()
+    }
+
+    /// Whether no remotes are configured.
+    pub fn is_empty(&self) -> bool {
+        self.remotes.is_empty()
+    }
+}
+
+/// Named buttons of a device and the action map driving them.
+#[derive(Default)]
+pub struct ButtonSet {
+    buttons: HashMap<String, Arc<GenericSwitchState>>,
+    actions: ActionMap,
+}
+
+impl ButtonSet {
+    /// Add a named button.
+    pub fn insert(&mut self, name: impl Into<String>, button: Arc<GenericSwitchState>) {
+        self.buttons.insert(name.into(), button);
+    }
+
+    /// Replace the action map.
+    pub fn set_actions(&mut self, actions: ActionMap) {
+        self.actions = actions;
+    }
+
+    /// Emit the event an action maps to.
+    ///
+    /// Returns the button name and event, or None when the action is not mapped
+    /// (or its button is not configured).
+    pub fn dispatch(&self, action: &str) -> Option<(&str, ButtonEvent)> {
+        let target = self.actions.get(action)?;
+        let button = self.buttons.get(&target.button)?;
+        target.event.apply(button);
+        Some((&target.button, target.event))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action_map_from_json() {
+        let map = ActionMap::from_json(
+            r#"{
+                "on": { "button": "top", "event": "single" },
+                "brightness_move_up": { "button": "top", "event": "hold" },
+                "off_triple": { "button": "bottom", "event": "triple" }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(map.len(), 3);
+        assert_eq!(
+            map.get("brightness_move_up"),
+            Some(&ActionTarget {
+                button: "top".to_string(),
+                event: ButtonEvent::Long
+            })
+        );
+        assert_eq!(map.get("off_triple").unwrap().event, ButtonEvent::Triple);
+        assert!(map.get("off").is_none());
+        assert!(
+            ActionMap::from_json(r#"{"on": {"button": "top", "event": "quadruple"}}"#).is_err()
+        );
+
+        let w100 = ActionMap::w100();
+        assert_eq!(w100.get("double_minus").unwrap().button, "minus");
+        assert_eq!(w100.get("hold").unwrap().button, "center");
+        assert_eq!(w100.len(), 16);
+    }
+
+    #[test]
+    fn test_remote_list() {
+        let load_actions = |path: &Path| {
+            assert_eq!(path, Path::new("styrbar.json"));
+            Ok(ActionMap::new()
+                .with_action("on", "top", ButtonEvent::Single)
+                .with_action("off", "bottom", ButtonEvent::Single))
+        };
+        let list = RemoteList::from_json(
+            r#"[
+                { "friendly_name": "Hallway", "actions": "styrbar.json" },
+                { "friendly_name": "Desk", "name": "Desk Remote", "vendor": "IKEA",
+                  "buttons": ["top", "bottom", "left"], "actions": "styrbar.json" }
+            ]"#,
+            load_actions,
+        )
+        .unwrap();
+        assert_eq!(list.len(), 2);
+        let hallway = &list.remotes()[0];
+        assert_eq!(hallway.name, "Hallway");
+        assert_eq!(hallway.buttons, ["bottom", "top"]);
+        let desk = &list.remotes()[1];
+        assert_eq!(desk.name, "Desk Remote");
+        assert_eq!(desk.vendor.as_deref(), Some("IKEA"));
+        assert_eq!(desk.buttons, ["top", "bottom", "left"]);
+
+        // Every mapped button must be listed
+        assert!(matches!(
+            RemoteList::from_json(
+                r#"[{ "friendly_name": "Desk", "buttons": ["top"], "actions": "styrbar.json" }]"#,
+                load_actions,
+            ),
+            Err(ActionMapError::UnknownButton { button, .. }) if button == "bottom"
+        ));
+        assert!(matches!(
+            RemoteList::from_json(r#"[{ "friendly_name": "Desk" }]"#, load_actions),
+            Err(ActionMapError::RemoteList(_))
+        ));
+        assert!(
+            RemoteList::load("/nonexistent/remotes.json")
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
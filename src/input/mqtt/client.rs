@@ -1,11 +1,28 @@
 //! MQTT client wrapper for zigbee2mqtt communication.
 
-use crate::config::MqttConfig;
+use crate::config::{MqttConfig, MqttTlsConfig};
 use log::{debug, error, info, warn};
-use rumqttc::{AsyncClient, Event, EventLoop, MqttOptions, Packet, QoS};
+use parking_lot::Mutex;
+use rand::Rng;
+use rumqttc::{
+    AsyncClient, Event, EventLoop, MqttOptions, Outgoing, Packet, QoS, TlsConfiguration, Transport,
+};
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Duration;
+use thiserror::Error;
 use tokio::sync::{mpsc, oneshot};
 
+/// Delay before the first reconnect attempt
+const RECONNECT_DELAY_MIN: Duration = Duration::from_secs(1);
+
+/// Upper bound of the reconnect delay
+const RECONNECT_DELAY_MAX: Duration = Duration::from_secs(60);
+
+/// How long the broker may take to acknowledge a published message
+pub const DELIVERY_TIMEOUT: Duration = Duration::from_secs(5);
+
 /// Message received from MQTT broker.
 #[derive(Debug, Clone)]
 pub struct MqttMessage {
@@ -13,27 +30,264 @@ pub struct MqttMessage {
     pub payload: String,
 }
 
+/// Errors creating the MQTT client.
+#[derive(Debug, Error)]
+pub enum MqttClientError {
+    #[error("failed to read {0}: {1}")]
+    ReadFile(PathBuf, std::io::Error),
+
+    #[error("client certificate and key must be configured together")]
+    IncompleteClientAuth,
+
+    #[error("client certificate authentication requires a CA certificate")]
+    MissingCaCert,
+}
+
+/// Errors publishing a message with delivery confirmation.
+#[derive(Debug, Clone, Error)]
+pub enum PublishError {
+    #[error("MQTT client stopped")]
+    Closed,
+
+    #[error("broker did not acknowledge the message within {0:?}")]
+    Timeout(Duration),
+}
+
+/// Publishes waiting for the broker to acknowledge them.
+///
+/// The event loop reports publishes in the order they were queued (`Outgoing::Publish`)
+/// and acknowledges them by packet ID (`PubAck`, or `PubComp` for QoS 2). Waiters are
+/// queued in the same order, so the n-th reported publish belongs to the n-th waiter.
+/// Retransmissions after a reconnect keep their packet ID and are not counted again.
+#[derive(Default)]
+struct DeliveryTracker {
+    state: Mutex<TrackerState>,
+    /// Keeps registering a waiter and queueing its publish atomic
+    order: tokio::sync::Mutex<()>,
+}
+
+#[derive(Default)]
+struct TrackerState {
+    /// Publishes queued but not yet written, in order
+    queued: VecDeque<oneshot::Sender<()>>,
+    /// Written QoS 1/2 publishes waiting for their acknowledgement
+    inflight: HashMap<u16, oneshot::Sender<()>>,
+}
+
+impl DeliveryTracker {
+    /// Register the next publish.
+    fn queue(&self) -> oneshot::Receiver<()> {
+        let (tx, rx) = oneshot::channel();
+        self.state.lock().queued.push_back(tx);
+        rx
+    }
+
+    /// Drop the last registered publish (it could not be queued).
+    fn unqueue_last(&self) {
+        self.state.lock().queued.pop_back();
+    }
+
+    /// A publish was written to the connection.
+    fn sent(&self, pkid: u16) {
+        let mut state = self.state.lock();
+        if pkid != 0 && state.inflight.contains_key(&pkid) {
+            return; // Retransmission
+        }
+        let Some(waiter) = state.queued.pop_front() else {
+            return; // Not published through MqttPublisher
+        };
+        if pkid == 0 {
+            // QoS 0 is never acknowledged
+            let _ = waiter.send(());
+        } else {
+            state.inflight.insert(pkid, waiter);
+        }
+    }
+
+    /// The broker acknowledged a publish.
+    fn acked(&self, pkid: u16) {
+        if let Some(waiter) = self.state.lock().inflight.remove(&pkid) {
+            let _ = waiter.send(());
+        }
+    }
+}
+
+/// A queued publish.
+pub struct Delivery {
+    ack: oneshot::Receiver<()>,
+}
+
+impl Delivery {
+    /// Wait until the broker acknowledged the message (QoS 0: until it was written).
+    pub async fn confirmed(self) -> Result<(), PublishError> {
+        match tokio::time::timeout(DELIVERY_TIMEOUT, self.ack).await {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(_)) => Err(PublishError::Closed),
+            Err(_) => Err(PublishError::Timeout(DELIVERY_TIMEOUT)),
+        }
+    }
+}
+
+/// Publishing handle that reports delivery of each message.
+///
+/// All publishes of a client should go through its publisher, so deliveries are matched
+/// to the right message.
+#[derive(Clone)]
+pub struct MqttPublisher {
+    client: AsyncClient,
+    tracker: Arc<DeliveryTracker>,
+}
+
+impl MqttPublisher {
+    /// Queue a message (not retained) and return its delivery.
+    pub async fn send(
+        &self,
+        topic: &str,
+        qos: QoS,
+        payload: impl Into<Vec<u8>>,
+    ) -> Result<Delivery, PublishError> {
+        self.queue(topic, qos, false, payload).await
+    }
+
+    /// Queue a retained message and return its delivery.
+    pub async fn send_retained(
+        &self,
+        topic: &str,
+        qos: QoS,
+        payload: impl Into<Vec<u8>>,
+    ) -> Result<Delivery, PublishError> {
+        self.queue(topic, qos, true, payload).await
+    }
+
+    async fn queue(
+        &self,
+        topic: &str,
+        qos: QoS,
+        retain: bool,
+        payload: impl Into<Vec<u8>>,
+    ) -> Result<Delivery, PublishError> {
+        let _order = self.tracker.order.lock().await;
+        let ack = self.tracker.queue();
+        if let Err(e) = self.client.publish(topic, qos, retain, payload).await {
+            self.tracker.unqueue_last();
+            debug!("Failed to queue publish to {}: {}", topic, e);
+            return Err(PublishError::Closed);
+        }
+        Ok(Delivery { ack })
+    }
+
+    /// Publish a message and wait until the broker acknowledged it.
+    pub async fn publish(
+        &self,
+        topic: &str,
+        qos: QoS,
+        payload: impl Into<Vec<u8>>,
+    ) -> Result<(), PublishError> {
+        self.send(topic, qos, payload).await?.confirmed().await
+    }
+}
+
+/// Exponential reconnect backoff with jitter.
+///
+/// The delay doubles with each failed attempt up to [`RECONNECT_DELAY_MAX`]. A random
+/// delay between half and all of it is used, so several bridges do not reconnect to a
+/// restarted broker in lockstep.
+#[derive(Debug, Default)]
+struct Backoff {
+    attempt: u32,
+}
+
+impl Backoff {
+    /// Delay of the current attempt before jitter.
+    fn base_delay(&self) -> Duration {
+        RECONNECT_DELAY_MIN
+            .saturating_mul(1 << self.attempt.min(16))
+            .min(RECONNECT_DELAY_MAX)
+    }
+
+    /// Delay before the next attempt.
+    fn next_delay(&mut self) -> Duration {
+        let base = self.base_delay();
+        self.attempt = self.attempt.saturating_add(1);
+        let half = base / 2;
+        half + half.mul_f64(rand::thread_rng().gen_range(0.0..=1.0))
+    }
+
+    /// Start over after a successful connection.
+    fn reset(&mut self) {
+        self.attempt = 0;
+    }
+}
+
+/// Read a certificate or key file.
+fn read_file(path: &Path) -> Result<Vec<u8>, MqttClientError> {
+    std::fs::read(path).map_err(|e| MqttClientError::ReadFile(path.to_path_buf(), e))
+}
+
+/// Build the TLS transport from the configured certificate files.
+fn tls_transport(tls: &MqttTlsConfig) -> Result<Transport, MqttClientError> {
+    let client_auth = match (&tls.client_cert, &tls.client_key) {
+        (Some(cert), Some(key)) => Some((read_file(cert)?, read_file(key)?)),
+        (None, None) => None,
+        _ => return Err(MqttClientError::IncompleteClientAuth),
+    };
+    let config = match &tls.ca_cert {
+        Some(ca) => TlsConfiguration::Simple {
+            ca: read_file(ca)?,
+            alpn: None,
+            client_auth,
+        },
+        // rumqttc only supports client authentication with an explicit CA
+        None if client_auth.is_some() => return Err(MqttClientError::MissingCaCert),
+        None => TlsConfiguration::default(),
+    };
+    Ok(Transport::tls_with_config(config))
+}
+
 /// MQTT client for zigbee2mqtt communication.
 pub struct MqttClient {
     client: AsyncClient,
     event_loop: EventLoop,
+    tracker: Arc<DeliveryTracker>,
 }
 
 impl MqttClient {
     /// Create a new MQTT client from configuration.
-    pub fn new(config: &MqttConfig) -> Self {
+    pub fn new(config: &MqttConfig) -> Result<Self, MqttClientError> {
+        Ok(Self::from_options(Self::options(config)?))
+    }
+
+    /// Build the connection options from configuration.
+    ///
+    /// Use with [`MqttClient::from_options`] to add e.g. a last will.
+    pub fn options(config: &MqttConfig) -> Result<MqttOptions, MqttClientError> {
         let mut options =
             MqttOptions::new(&config.client_id, &config.broker_host, config.broker_port);
         options.set_keep_alive(Duration::from_secs(30));
+        // A persistent session keeps subscriptions and queues QoS 1/2 messages while offline
+        options.set_clean_session(!config.persistent_session);
 
         // Set credentials if provided
         if let (Some(username), Some(password)) = (&config.username, &config.password) {
             options.set_credentials(username, password);
         }
 
+        if let Some(tls) = &config.tls {
+            options.set_transport(tls_transport(tls)?);
+        }
+
+        Ok(options)
+    }
+
+    /// Create a new MQTT client from connection options.
+    pub fn from_options(options: MqttOptions) -> Self {
         let (client, event_loop) = AsyncClient::new(options, 100);
 
-        Self { client, event_loop }
+        Self {
+            client,
+            event_loop,
+            tracker: Arc::default(),
+        }
     }
 
     /// Subscribe to a topic.
@@ -43,25 +297,36 @@ impl MqttClient {
     }
 
     /// Publish a message to a topic.
-    pub async fn publish(&self, topic: &str, payload: &str) -> Result<(), rumqttc::ClientError> {
+    pub async fn publish(&self, topic: &str, payload: &str) -> Result<(), PublishError> {
         debug!("Publishing to {}: {}", topic, payload);
-        self.client
-            .publish(topic, QoS::AtMostOnce, false, payload.as_bytes())
+        self.publisher()
+            .send(topic, QoS::AtMostOnce, payload.as_bytes())
             .await
+            .map(|_| ())
+    }
+
+    /// Get a publisher that reports delivery of each message.
+    pub fn publisher(&self) -> MqttPublisher {
+        MqttPublisher {
+            client: self.client.clone(),
+            tracker: self.tracker.clone(),
+        }
     }
 
     /// Run the MQTT event loop and forward messages to the provided channel.
     ///
-    /// Optionally signals when connected via the `connected_tx` oneshot.
-    /// This method runs indefinitely, processing MQTT events and sending
-    /// received messages through the channel.
+    /// Optionally signals every (re)connection via `connected_tx`, so the caller can
+    /// restore its subscriptions (the broker drops them unless the session is persistent).
+    /// Connection errors are retried with exponential backoff. This method runs
+    /// indefinitely, processing MQTT events and sending received messages through
+    /// the channel.
     pub async fn run(
         mut self,
         tx: mpsc::Sender<MqttMessage>,
-        connected_tx: Option<oneshot::Sender<()>>,
+        connected_tx: Option<mpsc::UnboundedSender<()>>,
     ) {
         info!("Starting MQTT event loop");
-        let mut connected_tx = connected_tx;
+        let mut backoff = Backoff::default();
 
         loop {
             match self.event_loop.poll().await {
@@ -69,8 +334,9 @@ impl MqttClient {
                     match &event {
                         Event::Incoming(Packet::ConnAck(_)) => {
                             info!("[MQTT] Connected to broker");
+                            backoff.reset();
                             // Signal that we're connected
-                            if let Some(tx) = connected_tx.take() {
+                            if let Some(tx) = &connected_tx {
                                 let _ = tx.send(());
                             }
                         }
@@ -92,13 +358,21 @@ impl MqttClient {
                                 break;
                             }
                         }
+                        Event::Outgoing(Outgoing::Publish(pkid)) => self.tracker.sent(*pkid),
+                        Event::Incoming(Packet::PubAck(ack)) => self.tracker.acked(ack.pkid),
+                        Event::Incoming(Packet::PubComp(comp)) => self.tracker.acked(comp.pkid),
                         _ => {}
                     }
                 }
                 Err(e) => {
-                    error!("MQTT connection error: {:?}", e);
                     // Wait before reconnecting
-                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    let delay = backoff.next_delay();
+                    error!(
+                        "MQTT connection error: {:?}, reconnecting in {:.1}s",
+                        e,
+                        delay.as_secs_f32()
+                    );
+                    tokio::time::sleep(delay).await;
                 }
             }
         }
@@ -109,3 +383,46 @@ impl MqttClient {
         self.client.clone()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delivery_tracker() {
+        let tracker = DeliveryTracker::default();
+        let mut qos0 = tracker.queue();
+        let mut qos1 = tracker.queue();
+        let mut failed = tracker.queue();
+        tracker.unqueue_last();
+
+        tracker.sent(0);
+        assert!(qos0.try_recv().is_ok());
+
+        tracker.sent(7);
+        assert!(qos1.try_recv().is_err());
+        // Retransmission after a reconnect does not consume a waiter
+        let mut next = tracker.queue();
+        tracker.sent(7);
+        assert!(next.try_recv().is_err());
+
+        tracker.acked(7);
+        assert!(qos1.try_recv().is_ok());
+        assert_eq!(failed.try_recv(), Err(oneshot::error::TryRecvError::Closed));
+    }
+
+    #[test]
+    fn test_backoff_grows_with_jitter() {
+        let mut backoff = Backoff::default();
+        let mut expected = RECONNECT_DELAY_MIN;
+        for _ in 0..10 {
+            let delay = backoff.next_delay();
+            assert!(delay >= expected / 2 && delay <= expected);
+            expected = (expected * 2).min(RECONNECT_DELAY_MAX);
+        }
+        assert_eq!(backoff.base_delay(), RECONNECT_DELAY_MAX);
+
+        backoff.reset();
+        assert_eq!(backoff.base_delay(), RECONNECT_DELAY_MIN);
+    }
+}
@@ -1,16 +1,24 @@
 //! MQTT Integration orchestrator for clean device management.
 //!
 //! Provides a high-level API for integrating MQTT devices without exposing
-//! MQTT internals to main.rs. Supports multiple W100 devices.
-
-use super::client::{MqttClient, MqttMessage};
-use crate::config::MqttConfig;
-use crate::matter::clusters::{GenericSwitchState, HumiditySensor, TemperatureSensor};
+//! MQTT internals to main.rs. Supports multiple W100 devices and remotes.
+
+use super::actions::{ActionMap, ButtonSet};
+use super::client::{MqttClient, MqttMessage, MqttPublisher};
+use super::switch::MqttSwitch;
+use crate::config::{MqttConfig, MqttQosConfig};
+use crate::matter::JsonStore;
+use crate::matter::clusters::{
+    GenericSwitchState, HumiditySensor, NodeLabelListener, PowerSource, TemperatureSensor,
+};
+use crate::matter::endpoints::DeviceAvailability;
 use log::{info, warn};
-use rumqttc::QoS;
+use rumqttc::{AsyncClient, QoS};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::sync::{mpsc, oneshot};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
 use tokio::task::JoinHandle;
 
 /// Configuration for a W100 climate sensor.
@@ -21,10 +29,14 @@ pub struct W100Config {
     pub temperature_sensor: Arc<TemperatureSensor>,
     /// Shared humidity sensor (also used by Matter)
     pub humidity_sensor: Arc<HumiditySensor>,
-    /// Shared button states for Plus/Minus/Center buttons (also used by Matter)
-    pub button_plus: Option<Arc<GenericSwitchState>>,
-    pub button_minus: Option<Arc<GenericSwitchState>>,
-    pub button_center: Option<Arc<GenericSwitchState>>,
+    /// Shared button states by name (also used by Matter)
+    pub buttons: Vec<(String, Arc<GenericSwitchState>)>,
+    /// Maps actions onto the buttons (defaults to [`ActionMap::w100`])
+    pub actions: ActionMap,
+    /// Shared availability handle (drives the Matter Reachable attribute)
+    pub availability: Option<Arc<DeviceAvailability>>,
+    /// Shared battery power source (also used by Matter)
+    pub power_source: Option<Arc<PowerSource>>,
 }
 
 impl W100Config {
@@ -38,37 +50,198 @@ impl W100Config {
             friendly_name: friendly_name.into(),
             temperature_sensor,
             humidity_sensor,
-            button_plus: None,
-            button_minus: None,
-            button_center: None,
+            buttons: Vec::new(),
+            actions: ActionMap::w100(),
+            availability: None,
+            power_source: None,
         }
     }
 
     /// Add button state handlers for Matter GenericSwitch integration.
+    ///
+    /// The buttons are named `plus`, `minus` and `center` in the action map.
     pub fn with_buttons(
-        mut self,
+        self,
         plus: Arc<GenericSwitchState>,
         minus: Arc<GenericSwitchState>,
         center: Arc<GenericSwitchState>,
     ) -> Self {
-        self.button_plus = Some(plus);
-        self.button_minus = Some(minus);
-        self.button_center = Some(center);
+        self.with_button("plus", plus)
+            .with_button("minus", minus)
+            .with_button("center", center)
+    }
+
+    /// Add a named button state for Matter GenericSwitch integration.
+    pub fn with_button(mut self, name: impl Into<String>, button: Arc<GenericSwitchState>) -> Self {
+        self.buttons.push((name.into(), button));
+        self
+    }
+
+    /// Replace the action map (e.g. for a W100 with a different firmware).
+    pub fn with_action_map(mut self, actions: ActionMap) -> Self {
+        self.actions = actions;
+        self
+    }
+
+    /// Report availability (zigbee2mqtt availability topic and last-seen) to Matter.
+    pub fn with_availability(mut self, availability: Arc<DeviceAvailability>) -> Self {
+        self.availability = Some(availability);
+        self
+    }
+
+    /// Report the battery level to a Matter PowerSource cluster.
+    pub fn with_power_source(mut self, power_source: Arc<PowerSource>) -> Self {
+        self.power_source = Some(power_source);
+        self
+    }
+}
+
+/// Configuration for a zigbee2mqtt remote (button device).
+///
+/// Any remote whose actions can be described by an [`ActionMap`] (IKEA, Hue dimmer,
+/// Aqara Opple, ...) is bridged through the same code path as the W100 buttons.
+pub struct RemoteConfig {
+    /// Friendly name in zigbee2mqtt
+    pub friendly_name: String,
+    /// Shared button states by name (also used by Matter)
+    pub buttons: Vec<(String, Arc<GenericSwitchState>)>,
+    /// Maps actions onto the buttons
+    pub actions: ActionMap,
+    /// Shared availability handle (drives the Matter Reachable attribute)
+    pub availability: Option<Arc<DeviceAvailability>>,
+    /// Shared battery power source (also used by Matter)
+    pub power_source: Option<Arc<PowerSource>>,
+}
+
+impl RemoteConfig {
+    /// Create a new remote configuration.
+    pub fn new(friendly_name: impl Into<String>, actions: ActionMap) -> Self {
+        Self {
+            friendly_name: friendly_name.into(),
+            buttons: Vec::new(),
+            actions,
+            availability: None,
+            power_source: None,
+        }
+    }
+
+    /// Add a named button state for Matter GenericSwitch integration.
+    pub fn with_button(mut self, name: impl Into<String>, button: Arc<GenericSwitchState>) -> Self {
+        self.buttons.push((name.into(), button));
         self
     }
+
+    /// Report availability (zigbee2mqtt availability topic and last-seen) to Matter.
+    pub fn with_availability(mut self, availability: Arc<DeviceAvailability>) -> Self {
+        self.availability = Some(availability);
+        self
+    }
+
+    /// Report the battery level to a Matter PowerSource cluster.
+    pub fn with_power_source(mut self, power_source: Arc<PowerSource>) -> Self {
+        self.power_source = Some(power_source);
+        self
+    }
+}
+
+/// zigbee2mqtt bridge request topic for renaming a device
+const RENAME_TOPIC: &str = "zigbee2mqtt/bridge/request/device/rename";
+
+/// zigbee2mqtt bridge response topic for device renames
+const RENAME_RESPONSE_TOPIC: &str = "zigbee2mqtt/bridge/response/device/rename";
+
+/// How long a rename waits for the response of zigbee2mqtt
+const RENAME_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// File with the zigbee2mqtt friendly names of renamed devices (see
+/// [`MqttIntegration::with_friendly_names`])
+pub const FRIENDLY_NAMES_FILE: &str = "zigbee2mqtt_names.json";
+
+/// zigbee2mqtt bridge state topic (carries the bridge's LWT)
+const BRIDGE_STATE_TOPIC: &str = "zigbee2mqtt/bridge/state";
+
+/// Parse a zigbee2mqtt availability/state payload.
+///
+/// Accepts both the JSON form (`{"state":"online"}`) and the legacy plain form (`online`).
+/// Returns None for unrecognized payloads.
+fn parse_availability(payload: &str) -> Option<bool> {
+    #[derive(serde::Deserialize)]
+    struct Availability {
+        state: String,
+    }
+
+    let payload = payload.trim();
+    let state = match serde_json::from_str::<Availability>(payload) {
+        Ok(availability) => availability.state,
+        Err(_) => payload.to_string(),
+    };
+    match state.as_str() {
+        "online" => Some(true),
+        "offline" => Some(false),
+        _ => None,
+    }
+}
+
+/// QoS per topic class.
+#[derive(Debug, Clone, Copy)]
+struct TopicQos {
+    state: QoS,
+    action: QoS,
+    availability: QoS,
+    command: QoS,
+}
+
+impl TopicQos {
+    fn new(config: &MqttQosConfig) -> Self {
+        let level = |level| rumqttc::qos(level).unwrap_or(QoS::AtLeastOnce);
+        Self {
+            state: level(config.state),
+            action: level(config.action),
+            availability: level(config.availability),
+            command: level(config.command),
+        }
+    }
+}
+
+/// A pending rename request from a Matter NodeLabel write.
+struct RenameRequest {
+    /// Friendly name the device was configured with (stable identifier)
+    configured_name: String,
+    /// New friendly name
+    new_name: String,
 }
 
-/// Internal W100 device state for the integration.
-struct W100Device {
+/// A rename sent to zigbee2mqtt, waiting for its response.
+struct PendingRename {
+    configured_name: String,
+    new_name: String,
+    sent: Instant,
+}
+
+/// Response of zigbee2mqtt to a bridge request.
+#[derive(serde::Deserialize)]
+struct BridgeResponse {
+    status: String,
+    #[serde(default)]
+    error: Option<String>,
+    #[serde(default)]
+    transaction: Option<String>,
+}
+
+/// Internal state of a zigbee2mqtt sensor or remote (W100 or generic remote).
+struct ZigbeeDevice {
+    /// Friendly name the device was configured with
+    configured_name: String,
+    /// Current friendly name in zigbee2mqtt (changes on rename, persisted)
     friendly_name: String,
-    temperature_sensor: Arc<TemperatureSensor>,
-    humidity_sensor: Arc<HumiditySensor>,
-    button_plus: Option<Arc<GenericSwitchState>>,
-    button_minus: Option<Arc<GenericSwitchState>>,
-    button_center: Option<Arc<GenericSwitchState>>,
+    temperature_sensor: Option<Arc<TemperatureSensor>>,
+    humidity_sensor: Option<Arc<HumiditySensor>>,
+    buttons: ButtonSet,
+    availability: Option<Arc<DeviceAvailability>>,
+    power_source: Option<Arc<PowerSource>>,
 }
 
-impl W100Device {
+impl ZigbeeDevice {
     fn state_topic(&self) -> String {
         format!("zigbee2mqtt/{}", self.friendly_name)
     }
@@ -77,8 +250,16 @@ impl W100Device {
         format!("zigbee2mqtt/{}/action", self.friendly_name)
     }
 
-    fn subscribe_topics(&self) -> Vec<String> {
-        vec![self.state_topic(), self.action_topic()]
+    fn availability_topic(&self) -> String {
+        format!("zigbee2mqtt/{}/availability", self.friendly_name)
+    }
+
+    fn subscribe_topics(&self, qos: &TopicQos) -> Vec<(String, QoS)> {
+        vec![
+            (self.state_topic(), qos.state),
+            (self.action_topic(), qos.action),
+            (self.availability_topic(), qos.availability),
+        ]
     }
 
     /// Process a message and update sensors if applicable.
@@ -86,51 +267,110 @@ impl W100Device {
     fn process_message(&self, topic: &str, payload: &str) -> bool {
         let state_topic = self.state_topic();
         let action_topic = self.action_topic();
+        let availability_topic = self.availability_topic();
 
         if topic == state_topic {
+            self.mark_seen();
             self.process_state_message(payload);
             true
         } else if topic == action_topic {
+            self.mark_seen();
             self.process_action_message(payload);
             true
+        } else if topic == availability_topic {
+            self.process_availability_message(payload);
+            true
         } else {
             false
         }
     }
 
+    /// Record that the device sent a message (resets its staleness timer).
+    fn mark_seen(&self) {
+        if let Some(availability) = &self.availability {
+            availability.mark_seen();
+        }
+    }
+
+    /// Update availability, logging transitions.
+    fn set_available(&self, available: bool) {
+        if let Some(availability) = &self.availability
+            && availability.is_available() != available
+        {
+            availability.set_available(available);
+            info!(
+                "[MQTT] {} is now {}",
+                self.friendly_name,
+                if available { "online" } else { "offline" }
+            );
+        }
+    }
+
+    fn process_availability_message(&self, payload: &str) {
+        match parse_availability(payload) {
+            Some(available) => self.set_available(available),
+            None => warn!(
+                "[MQTT] Unknown {} availability: {}",
+                self.friendly_name, payload
+            ),
+        }
+    }
+
     fn process_state_message(&self, payload: &str) {
         #[derive(serde::Deserialize)]
-        struct W100State {
+        struct DeviceState {
             #[serde(default)]
             temperature: Option<f32>,
             #[serde(default)]
             humidity: Option<f32>,
+            #[serde(default)]
+            battery: Option<f32>,
             // Note: 'action' field is intentionally not parsed here.
             // Button actions are processed via the dedicated /action topic.
         }
 
-        match serde_json::from_str::<W100State>(payload) {
+        match serde_json::from_str::<DeviceState>(payload) {
             Ok(state) => {
-                if let Some(temp) = state.temperature {
-                    let old_temp = self.temperature_sensor.get_celsius();
-                    self.temperature_sensor.set_celsius(temp);
-                    if (temp - old_temp).abs() > 0.1 {
+                if let Some(temp) = state.temperature
+                    && let Some(temperature_sensor) = &self.temperature_sensor
+                {
+                    // Log what is reported, after calibration/smoothing/deadband
+                    let old_value = temperature_sensor.get_centidegrees();
+                    temperature_sensor.set_raw_celsius(temp);
+                    if temperature_sensor.get_centidegrees() != old_value {
                         info!(
-                            "[MQTT] {} temperature updated: {:.1}°C",
-                            self.friendly_name, temp
+                            "[MQTT] {} temperature updated: {:.2}°C (raw {:.2})",
+                            self.friendly_name,
+                            temperature_sensor.get_celsius(),
+                            temp
                         );
                     }
                 }
-                if let Some(humidity) = state.humidity {
-                    let old_humidity = self.humidity_sensor.get_percent();
-                    self.humidity_sensor.set_percent(humidity);
-                    if (humidity - old_humidity).abs() > 0.5 {
+                if let Some(humidity) = state.humidity
+                    && let Some(humidity_sensor) = &self.humidity_sensor
+                {
+                    let old_value = humidity_sensor.get_centipercent();
+                    humidity_sensor.set_raw_percent(humidity);
+                    if humidity_sensor.get_centipercent() != old_value {
                         info!(
-                            "[MQTT] {} humidity updated: {:.1}%",
-                            self.friendly_name, humidity
+                            "[MQTT] {} humidity updated: {:.2}% (raw {:.2})",
+                            self.friendly_name,
+                            humidity_sensor.get_percent(),
+                            humidity
                         );
                     }
                 }
+                if let Some(battery) = state.battery
+                    && let Some(power_source) = &self.power_source
+                {
+                    if power_source.get_battery_percent() != Some(battery) {
+                        info!(
+                            "[MQTT] {} battery updated: {:.0}%",
+                            self.friendly_name, battery
+                        );
+                    }
+                    power_source.set_battery_percent(battery);
+                }
                 // Note: Button actions are also included in state messages, but we process
                 // them via the dedicated /action topic to avoid duplicate processing.
             }
@@ -142,124 +382,127 @@ impl W100Device {
 
     fn process_action_message(&self, payload: &str) {
         let action = payload.trim();
+        if action.is_empty() {
+            return; // zigbee2mqtt clears the action after each event
+        }
         info!("[MQTT] {} button action: {}", self.friendly_name, action);
 
-        // Map W100 actions to GenericSwitch events
-        match action {
-            // Single press
-            "single_plus" => {
-                if let Some(btn) = &self.button_plus {
-                    btn.single_press();
-                    info!("[Matter] Button Plus: single press event emitted");
-                }
-            }
-            "single_minus" => {
-                if let Some(btn) = &self.button_minus {
-                    btn.single_press();
-                    info!("[Matter] Button Minus: single press event emitted");
-                }
-            }
-            "single_center" | "single" => {
-                if let Some(btn) = &self.button_center {
-                    btn.single_press();
-                    info!("[Matter] Button Center: single press event emitted");
-                }
-            }
-            // Double press
-            "double_plus" => {
-                if let Some(btn) = &self.button_plus {
-                    btn.double_press();
-                    info!("[Matter] Button Plus: double press event emitted");
-                }
-            }
-            "double_minus" => {
-                if let Some(btn) = &self.button_minus {
-                    btn.double_press();
-                    info!("[Matter] Button Minus: double press event emitted");
-                }
-            }
-            "double_center" | "double" => {
-                if let Some(btn) = &self.button_center {
-                    btn.double_press();
-                    info!("[Matter] Button Center: double press event emitted");
-                }
-            }
-            // Hold (long press)
-            "hold_plus" => {
-                if let Some(btn) = &self.button_plus {
-                    btn.hold_start();
-                    info!("[Matter] Button Plus: hold start event emitted");
-                }
-            }
-            "hold_minus" => {
-                if let Some(btn) = &self.button_minus {
-                    btn.hold_start();
-                    info!("[Matter] Button Minus: hold start event emitted");
-                }
-            }
-            "hold_center" | "hold" => {
-                if let Some(btn) = &self.button_center {
-                    btn.hold_start();
-                    info!("[Matter] Button Center: hold start event emitted");
-                }
-            }
-            // Release (after hold)
-            "release_plus" => {
-                if let Some(btn) = &self.button_plus {
-                    btn.hold_release();
-                    info!("[Matter] Button Plus: release event emitted");
-                }
-            }
-            "release_minus" => {
-                if let Some(btn) = &self.button_minus {
-                    btn.hold_release();
-                    info!("[Matter] Button Minus: release event emitted");
-                }
-            }
-            "release_center" | "release" => {
-                if let Some(btn) = &self.button_center {
-                    btn.hold_release();
-                    info!("[Matter] Button Center: release event emitted");
-                }
-            }
-            _ => {
-                warn!("[MQTT] Unknown W100 action: {}", action);
-            }
+        // Map the action to a GenericSwitch event via the device's action map
+        match self.buttons.dispatch(action) {
+            Some((button, event)) => info!(
+                "[Matter] {} button {}: {} event emitted",
+                self.friendly_name,
+                button,
+                event.name()
+            ),
+            None => warn!("[MQTT] Unmapped {} action: {}", self.friendly_name, action),
         }
     }
 }
 
+fn button_set(buttons: Vec<(String, Arc<GenericSwitchState>)>, actions: ActionMap) -> ButtonSet {
+    let mut set = ButtonSet::default();
+    for (name, button) in buttons {
+        set.insert(name, button);
+    }
+    set.set_actions(actions);
+    set
+}
+
 /// MQTT Integration orchestrator.
 ///
 /// Manages MQTT client and device subscriptions, keeping MQTT internals
 /// out of main.rs.
 pub struct MqttIntegration {
     config: MqttConfig,
-    w100_devices: Vec<W100Device>,
+    qos: TopicQos,
+    devices: Vec<ZigbeeDevice>,
+    switches: Vec<Arc<MqttSwitch>>,
+    rename_tx: mpsc::UnboundedSender<RenameRequest>,
+    rename_rx: mpsc::UnboundedReceiver<RenameRequest>,
+    /// Current friendly names of renamed devices, by configured name
+    friendly_names: Option<JsonStore<BTreeMap<String, String>>>,
+    /// Renames waiting for the response of zigbee2mqtt, by transaction
+    pending_renames: HashMap<String, PendingRename>,
+    next_transaction: u64,
 }
 
 impl MqttIntegration {
     /// Create a new MQTT integration with the given broker config.
     pub fn new(config: MqttConfig) -> Self {
+        let (rename_tx, rename_rx) = mpsc::unbounded_channel();
         Self {
+            qos: TopicQos::new(&config.qos),
             config,
-            w100_devices: Vec::new(),
+            devices: Vec::new(),
+            switches: Vec::new(),
+            rename_tx,
+            rename_rx,
+            friendly_names: None,
+            pending_renames: HashMap::new(),
+            next_transaction: 0,
         }
     }
 
+    /// Persist the zigbee2mqtt friendly names of renamed devices in `path`.
+    ///
+    /// Without it, a device renamed via its NodeLabel is looked up under its configured
+    /// name again after a restart.
+    pub fn with_friendly_names(mut self, path: impl Into<PathBuf>) -> Self {
+        self.friendly_names = Some(JsonStore::load(path));
+        self
+    }
+
     /// Add a W100 climate sensor to the integration.
     pub fn with_w100(mut self, config: W100Config) -> Self {
-        self.w100_devices.push(W100Device {
+        self.devices.push(ZigbeeDevice {
+            configured_name: config.friendly_name.clone(),
+            friendly_name: config.friendly_name,
+            temperature_sensor: Some(config.temperature_sensor),
+            humidity_sensor: Some(config.humidity_sensor),
+            buttons: button_set(config.buttons, config.actions),
+            availability: config.availability,
+            power_source: config.power_source,
+        });
+        self
+    }
+
+    /// Add a zigbee2mqtt remote whose actions are mapped onto GenericSwitch buttons.
+    pub fn with_remote(mut self, config: RemoteConfig) -> Self {
+        self.devices.push(ZigbeeDevice {
+            configured_name: config.friendly_name.clone(),
             friendly_name: config.friendly_name,
-            temperature_sensor: config.temperature_sensor,
-            humidity_sensor: config.humidity_sensor,
-            button_plus: config.button_plus,
-            button_minus: config.button_minus,
-            button_center: config.button_center,
+            temperature_sensor: None,
+            humidity_sensor: None,
+            buttons: button_set(config.buttons, config.actions),
+            availability: config.availability,
+            power_source: config.power_source,
         });
         self
     }
 
+    /// Add a zigbee2mqtt switch (also used as the handler of a Matter switch endpoint).
+    pub fn with_switch(mut self, switch: Arc<MqttSwitch>) -> Self {
+        self.switches.push(switch);
+        self
+    }
+
+    /// Create a NodeLabel listener that renames a device in zigbee2mqtt.
+    ///
+    /// Attach the returned listener to a [`VirtualDevice`](crate::matter::VirtualDevice)
+    /// to propagate Matter renames to the zigbee2mqtt friendly name. `friendly_name`
+    /// is the name the device was configured with via [`W100Config::new`].
+    pub fn label_listener(&self, friendly_name: impl Into<String>) -> NodeLabelListener {
+        let configured_name = friendly_name.into();
+        let tx = self.rename_tx.clone();
+        Arc::new(move |label: &str| {
+            let _ = tx.send(RenameRequest {
+                configured_name: configured_name.clone(),
+                new_name: label.to_string(),
+            });
+        })
+    }
+
     /// Start the MQTT integration.
     ///
     /// Spawns a background task that connects to the broker, subscribes to
@@ -271,94 +514,364 @@ impl MqttIntegration {
         })
     }
 
-    async fn run(self) {
-        if self.w100_devices.is_empty() {
+    async fn run(mut self) {
+        if self.devices.is_empty() && self.switches.is_empty() {
             info!("[MQTT] No devices configured, skipping MQTT integration");
             return;
         }
 
+        if let Some(names) = &self.friendly_names {
+            for device in &mut self.devices {
+                if let Some(name) = names.read(|names| names.get(&device.configured_name).cloned())
+                {
+                    info!(
+                        "[MQTT] {} is named {} in zigbee2mqtt",
+                        device.configured_name, name
+                    );
+                    device.friendly_name = name;
+                }
+            }
+        }
+
         info!(
             "[MQTT] Connecting to {}:{}",
             self.config.broker_host, self.config.broker_port
         );
 
-        let mqtt_client = MqttClient::new(&self.config);
+        let mqtt_client = match MqttClient::new(&self.config) {
+            Ok(client) => client,
+            Err(e) => {
+                warn!("[MQTT] Failed to create client: {}", e);
+                return;
+            }
+        };
 
-        // Get client for subscribing/publishing (AsyncClient is Send+Sync)
+        // Get client for subscribing (AsyncClient is Send+Sync)
         let subscribe_client = mqtt_client.client();
+        // Publishes go through the publisher, which reports their delivery
+        let publisher = mqtt_client.publisher();
+        for switch in &self.switches {
+            switch.attach(publisher.clone(), self.qos.command);
+        }
 
         // Channel for MQTT messages
         let (msg_tx, mut msg_rx) = mpsc::channel::<MqttMessage>(64);
 
-        // Channel to signal when connected
-        let (connected_tx, connected_rx) = oneshot::channel();
+        // Channel signalled on every (re)connection
+        let (connected_tx, mut connected_rx) = mpsc::unbounded_channel();
 
-        // Start MQTT event loop FIRST (so it can establish connection)
+        // Start MQTT event loop (connects and reconnects with backoff)
         let mqtt_loop = tokio::spawn(async move {
             mqtt_client.run(msg_tx, Some(connected_tx)).await;
         });
 
-        // Wait for connection (with timeout)
-        match tokio::time::timeout(Duration::from_secs(10), connected_rx).await {
-            Ok(Ok(())) => {
-                info!("[MQTT] Connection established, subscribing to topics");
-            }
-            Ok(Err(_)) => {
-                warn!("[MQTT] Connection signal channel dropped");
-                return;
-            }
-            Err(_) => {
-                warn!("[MQTT] Connection timeout after 10 seconds");
-                mqtt_loop.abort();
-                return;
+        info!(
+            "[MQTT] Integration started with {} device(s) and {} switch(es)",
+            self.devices.len(),
+            self.switches.len()
+        );
+
+        // Process incoming messages and rename requests
+        loop {
+            tokio::select! {
+                Some(()) = connected_rx.recv() => {
+                    self.restore_session(&subscribe_client, &publisher).await;
+                }
+                msg = msg_rx.recv() => {
+                    let Some(msg) = msg else { break };
+                    if msg.topic == BRIDGE_STATE_TOPIC {
+                        self.process_bridge_state(&publisher, &msg.payload).await;
+                        continue;
+                    }
+                    if msg.topic == RENAME_RESPONSE_TOPIC {
+                        self.process_rename_response(&subscribe_client, &msg.payload).await;
+                        continue;
+                    }
+                    if let Some(switch) = self
+                        .switches
+                        .iter()
+                        .find(|switch| switch.state_topic() == msg.topic)
+                    {
+                        switch.process_state_message(&msg.payload);
+                        continue;
+                    }
+                    for device in &self.devices {
+                        if device.process_message(&msg.topic, &msg.payload) {
+                            break; // Message was handled by this device
+                        }
+                    }
+                }
+                Some(request) = self.rename_rx.recv() => {
+                    self.rename_device(&publisher, request).await;
+                }
             }
         }
 
-        // NOW subscribe to all device topics (after connection is established)
-        for device in &self.w100_devices {
-            for topic in device.subscribe_topics() {
-                if let Err(e) = subscribe_client.subscribe(&topic, QoS::AtMostOnce).await {
+        mqtt_loop.abort();
+    }
+
+    /// Subscribe to all topics and refresh device state after a (re)connect.
+    ///
+    /// Without a persistent session the broker forgets our subscriptions whenever the
+    /// connection drops. Values that changed while disconnected are requested again.
+    async fn restore_session(&self, client: &AsyncClient, publisher: &MqttPublisher) {
+        info!("[MQTT] Connection established, subscribing to topics");
+
+        if let Err(e) = client
+            .subscribe(BRIDGE_STATE_TOPIC, self.qos.availability)
+            .await
+        {
+            warn!(
+                "[MQTT] Failed to subscribe to {}: {:?}",
+                BRIDGE_STATE_TOPIC, e
+            );
+        }
+        if let Err(e) = client
+            .subscribe(RENAME_RESPONSE_TOPIC, self.qos.command)
+            .await
+        {
+            warn!(
+                "[MQTT] Failed to subscribe to {}: {:?}",
+                RENAME_RESPONSE_TOPIC, e
+            );
+        }
+        for device in &self.devices {
+            for (topic, qos) in device.subscribe_topics(&self.qos) {
+                if let Err(e) = client.subscribe(&topic, qos).await {
                     warn!("[MQTT] Failed to subscribe to {}: {:?}", topic, e);
                 }
             }
         }
+        for switch in &self.switches {
+            let topic = switch.state_topic();
+            if let Err(e) = client.subscribe(&topic, self.qos.state).await {
+                warn!("[MQTT] Failed to subscribe to {}: {:?}", topic, e);
+            }
+        }
 
         // Small delay to ensure subscriptions are processed before requesting state
         tokio::time::sleep(Duration::from_millis(100)).await;
 
         // Request current state from all devices (W100 is battery-powered and sleeps)
-        for device in &self.w100_devices {
-            let get_topic = format!("zigbee2mqtt/{}/get", device.friendly_name);
-            if let Err(e) = subscribe_client
-                .publish(&get_topic, QoS::AtMostOnce, false, r#"{"state":""}"#)
+        self.request_state(publisher).await;
+    }
+
+    /// Request the current state of all devices.
+    async fn request_state(&self, publisher: &MqttPublisher) {
+        let names = self
+            .devices
+            .iter()
+            .map(|device| device.friendly_name.as_str())
+            .chain(self.switches.iter().map(|switch| switch.friendly_name()));
+        for name in names {
+            let get_topic = format!("zigbee2mqtt/{}/get", name);
+            if let Err(e) = publisher
+                .send(&get_topic, self.qos.command, r#"{"state":""}"#)
                 .await
             {
-                warn!(
-                    "[MQTT] Failed to request state for {}: {:?}",
-                    device.friendly_name, e
-                );
+                warn!("[MQTT] Failed to request state for {}: {}", name, e);
             } else {
-                info!(
-                    "[MQTT] Requested initial state for {}",
-                    device.friendly_name
+                info!("[MQTT] Requested state for {}", name);
+            }
+        }
+    }
+
+    /// Handle the zigbee2mqtt bridge state (online, or offline via its LWT).
+    ///
+    /// When the bridge goes offline, no device can be reached. When it comes back,
+    /// devices become available again on their next availability or state message.
+    async fn process_bridge_state(&self, publisher: &MqttPublisher, payload: &str) {
+        match parse_availability(payload) {
+            Some(true) => {
+                info!("[MQTT] zigbee2mqtt bridge is online");
+                self.request_state(publisher).await;
+            }
+            Some(false) => {
+                warn!("[MQTT] zigbee2mqtt bridge is offline, marking devices unreachable");
+                for device in &self.devices {
+                    device.set_available(false);
+                }
+            }
+            None => warn!("[MQTT] Unknown bridge state: {}", payload),
+        }
+    }
+
+    /// Ask zigbee2mqtt to rename a device.
+    ///
+    /// Subscriptions move to the new topics once zigbee2mqtt confirmed the rename (see
+    /// [`process_rename_response`](Self::process_rename_response)).
+    async fn rename_device(&mut self, publisher: &MqttPublisher, request: RenameRequest) {
+        self.expire_renames();
+
+        let Some(device) = self
+            .devices
+            .iter()
+            .find(|d| d.configured_name == request.configured_name)
+        else {
+            warn!(
+                "[MQTT] Rename requested for unknown device '{}'",
+                request.configured_name
+            );
+            return;
+        };
+
+        if device.friendly_name == request.new_name || request.new_name.is_empty() {
+            return;
+        }
+
+        self.next_transaction += 1;
+        let transaction = format!("vmb-rename-{}", self.next_transaction);
+        let payload = serde_json::json!({
+            "from": device.friendly_name,
+            "to": request.new_name,
+            "transaction": transaction,
+        })
+        .to_string();
+        // Not waiting for the broker acknowledgement here: this runs in the message loop,
+        // which has to keep draining incoming messages. The bridge response confirms it.
+        if let Err(e) = publisher
+            .send(RENAME_TOPIC, self.qos.command, payload)
+            .await
+        {
+            warn!(
+                "[MQTT] Failed to rename {} to {}: {}",
+                device.friendly_name, request.new_name, e
+            );
+            return;
+        }
+
+        info!(
+            "[MQTT] Requested rename of {} to {}",
+            device.friendly_name, request.new_name
+        );
+        self.pending_renames.insert(
+            transaction,
+            PendingRename {
+                configured_name: request.configured_name,
+                new_name: request.new_name,
+                sent: Instant::now(),
+            },
+        );
+    }
+
+    /// Drop renames zigbee2mqtt did not answer in time.
+    fn expire_renames(&mut self) {
+        self.pending_renames.retain(|_, pending| {
+            let waiting = pending.sent.elapsed() < RENAME_TIMEOUT;
+            if !waiting {
+                warn!(
+                    "[MQTT] No response to rename of {} to {}",
+                    pending.configured_name, pending.new_name
                 );
             }
+            waiting
+        });
+    }
+
+    /// Handle the response of zigbee2mqtt to a rename request.
+    ///
+    /// On success, subscriptions move to the new topics and the new name is persisted.
+    /// Responses to renames of other clients are ignored.
+    async fn process_rename_response(&mut self, client: &AsyncClient, payload: &str) {
+        self.expire_renames();
+
+        let response: BridgeResponse = match serde_json::from_str(payload) {
+            Ok(response) => response,
+            Err(e) => {
+                warn!("[MQTT] Invalid rename response: {}", e);
+                return;
+            }
+        };
+        let Some(pending) = response
+            .transaction
+            .and_then(|transaction| self.pending_renames.remove(&transaction))
+        else {
+            return;
+        };
+        if response.status != "ok" {
+            warn!(
+                "[MQTT] zigbee2mqtt refused to rename {} to {}: {}",
+                pending.configured_name,
+                pending.new_name,
+                response.error.as_deref().unwrap_or(&response.status)
+            );
+            return;
+        }
+
+        let Some(device) = self
+            .devices
+            .iter_mut()
+            .find(|d| d.configured_name == pending.configured_name)
+        else {
+            return;
+        };
+
+        for (topic, _) in device.subscribe_topics(&self.qos) {
+            if let Err(e) = client.unsubscribe(&topic).await {
+                warn!("[MQTT] Failed to unsubscribe from {}: {:?}", topic, e);
+            }
         }
 
         info!(
-            "[MQTT] Integration started with {} W100 device(s)",
-            self.w100_devices.len()
+            "[MQTT] Renamed {} to {}",
+            device.friendly_name, pending.new_name
         );
+        device.friendly_name = pending.new_name;
 
-        // Process incoming messages
-        while let Some(msg) = msg_rx.recv().await {
-            for device in &self.w100_devices {
-                if device.process_message(&msg.topic, &msg.payload) {
-                    break; // Message was handled by this device
-                }
+        for (topic, qos) in device.subscribe_topics(&self.qos) {
+            if let Err(e) = client.subscribe(&topic, qos).await {
+                warn!("[MQTT] Failed to subscribe to {}: {:?}", topic, e);
             }
         }
 
-        mqtt_loop.abort();
+        if let Some(names) = &self.friendly_names {
+            names.update(|names| {
+                if device.friendly_name == device.configured_name {
+                    names.remove(&device.configured_name);
+                } else {
+                    names.insert(device.configured_name.clone(), device.friendly_name.clone());
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_availability_json() {
+        assert_eq!(parse_availability(r#"{"state":"online"}"#), Some(true));
+        assert_eq!(parse_availability(r#"{"state":"offline"}"#), Some(false));
+    }
+
+    #[test]
+    fn test_parse_availability_plain() {
+        assert_eq!(parse_availability("online"), Some(true));
+        assert_eq!(parse_availability("offline\n"), Some(false));
+    }
+
+    #[test]
+    fn test_parse_availability_unknown() {
+        assert_eq!(parse_availability(r#"{"state":"unknown"}"#), None);
+        assert_eq!(parse_availability(""), None);
+    }
+
+    #[test]
+    fn test_parse_rename_response() {
+        let ok: BridgeResponse = serde_json::from_str(
+            r#"{"data":{"from":"a","to":"b","homeassistant_rename":false},"status":"ok","transaction":"vmb-rename-1"}"#,
+        )
+        .unwrap();
+        assert_eq!(ok.status, "ok");
+        assert_eq!(ok.transaction.as_deref(), Some("vmb-rename-1"));
+
+        let error: BridgeResponse = serde_json::from_str(
+            r#"{"data":{},"status":"error","error":"Device 'a' does not exist"}"#,
+        )
+        .unwrap();
+        assert_eq!(error.error.as_deref(), Some("Device 'a' does not exist"));
+        assert_eq!(error.transaction, None);
     }
 }
@@ -0,0 +1,309 @@
+//! MQTT mirror of the bridge's own endpoints.
+//!
+//! Publishes the values of every registered endpoint as a retained JSON object under
+//! `<prefix>/<device>/<id>-<endpoint>` whenever they change (sensor update, Matter command,
+//! control plane), so tools like Node-RED can follow bridge-native devices. The endpoint
+//! ID keeps endpoints with the same label apart. Messages to
+//! `<prefix>/<device>/<id>-<endpoint>/set` are applied through the same handlers Matter
+//! commands use. `<prefix>/availability` is `online` while connected and set to
+//! `offline` by the broker (last will) when the bridge goes away.
+//!
+//! `/set` accepts a JSON object of values by kind (`{"on_off": true}`), or a bare value
+//! for endpoints with a single value (`true`, `ON`, `21.5`, `single_press`). `toggle`
+//! toggles a switch.
+
+use super::client::{MqttClient, MqttMessage, MqttPublisher};
+use crate::config::MqttConfig;
+use crate::matter::BridgeControl;
+use crate::matter::control::ControlEndpoint;
+use log::{debug, info, warn};
+use rumqttc::{AsyncClient, LastWill, QoS};
+use serde_json::Value;
+use std::collections::HashMap;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// Replace characters that are not allowed in a topic level.
+fn topic_segment(name: &str) -> String {
+    let segment: String = name
+        .trim()
+        .chars()
+        .map(|c| if matches!(c, '/' | '+' | '#') { '_' } else { c })
+        .collect();
+    if segment.is_empty() {
+        "_".to_string()
+    } else {
+        segment
+    }
+}
+
+/// Topic level of an endpoint: `<id>-<label>`, unique even if labels repeat.
+fn endpoint_segment(endpoint_id: u16, label: &str) -> String {
+    format!("{}-{}", endpoint_id, topic_segment(label))
+}
+
+/// Endpoint ID of a `<prefix>/<device>/<id>-<label>/set` topic.
+fn set_topic_endpoint_id(topic: &str) -> Option<u16> {
+    let segment = topic.strip_suffix("/set")?.rsplit('/').next()?;
+    segment.split_once('-')?.0.parse().ok()
+}
+
+/// A value to apply from a `/set` message.
+#[derive(Debug, PartialEq)]
+enum SetValue {
+    Toggle,
+    Value(Value),
+}
+
+impl SetValue {
+    fn from_json(value: Value) -> Self {
+        match value.as_str().map(str::to_lowercase).as_deref() {
+            Some("toggle") => Self::Toggle,
+            Some("on") => Self::Value(Value::Bool(true)),
+            Some("off") => Self::Value(Value::Bool(false)),
+            _ => Self::Value(value),
+        }
+    }
+}
+
+/// Parse a `/set` payload into values by kind (None = the endpoint's only value).
+///
+/// Payloads that are not JSON are taken as a plain string (`ON`, `toggle`).
+fn parse_set_payload(payload: &str) -> Vec<(Option<String>, SetValue)> {
+    let payload = payload.trim();
+    match serde_json::from_str::<Value>(payload) {
+        Ok(Value::Object(values)) => values
+            .into_iter()
+            .map(|(kind, value)| (Some(kind), SetValue::from_json(value)))
+            .collect(),
+        Ok(value) => vec![(None, SetValue::from_json(value))],
+        Err(_) => vec![(None, SetValue::from_json(Value::from(payload)))],
+    }
+}
+
+/// Mirrors the bridge's endpoints to MQTT.
+pub struct MqttMirror {
+    config: MqttConfig,
+    prefix: String,
+    control: BridgeControl,
+    qos: QoS,
+    availability_qos: QoS,
+    command_qos: QoS,
+    /// Last published payload per endpoint (unchanged values are not republished)
+    published: HashMap<u16, String>,
+}
+
+impl MqttMirror {
+    /// Create a mirror publishing under `prefix` (e.g. the instance name).
+    pub fn new(config: MqttConfig, prefix: impl Into<String>, control: BridgeControl) -> Self {
+        let level = |level| rumqttc::qos(level).unwrap_or(QoS::AtLeastOnce);
+        Self {
+            qos: level(config.qos.state),
+            availability_qos: level(config.qos.availability),
+            command_qos: level(config.qos.command),
+            config,
+            prefix: prefix.into(),
+            control,
+            published: HashMap::new(),
+        }
+    }
+
+    /// Start the mirror in a background task.
+    ///
+    /// Returns a JoinHandle that can be used to abort the task on shutdown.
+    pub fn start(self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            self.run().await;
+        })
+    }
+
+    fn availability_topic(&self) -> String {
+        format!("{}/availability", self.prefix)
+    }
+
+    fn endpoint_topic(&self, endpoint: &ControlEndpoint) -> String {
+        format!(
+            "{}/{}/{}",
+            self.prefix,
+            topic_segment(&endpoint.device),
+            endpoint_segment(endpoint.endpoint_id, &endpoint.label)
+        )
+    }
+
+    async fn run(mut self) {
+        // Own client ID, so the mirror does not take over the integration's session
+        let mut config = self.config.clone();
+        config.client_id = format!("{}-mirror", config.client_id);
+        let mut options = match MqttClient::options(&config) {
+            Ok(options) => options,
+            Err(e) => {
+                warn!("[MQTT] Failed to create mirror client: {}", e);
+                return;
+            }
+        };
+        options.set_last_will(LastWill::new(
+            self.availability_topic(),
+            "offline",
+            self.availability_qos,
+            true,
+        ));
+        let mqtt_client = MqttClient::from_options(options);
+        let subscribe_client = mqtt_client.client();
+        let publisher = mqtt_client.publisher();
+
+        // Subscribe before connecting, so no change is missed
+        let mut changes = self.control.subscribe_changes();
+
+        let (msg_tx, mut msg_rx) = mpsc::channel::<MqttMessage>(64);
+        let (connected_tx, mut connected_rx) = mpsc::unbounded_channel();
+        let mqtt_loop = tokio::spawn(async move {
+            mqtt_client.run(msg_tx, Some(connected_tx)).await;
+        });
+
+        info!("[MQTT] Mirroring bridge endpoints under {}/", self.prefix);
+
+        loop {
+            tokio::select! {
+                Some(()) = connected_rx.recv() => {
+                    self.on_connect(&subscribe_client, &publisher).await;
+                }
+                msg = msg_rx.recv() => {
+                    let Some(msg) = msg else { break };
+                    self.process_set(&msg);
+                }
+                change = changes.recv() => match change {
+                    Ok(endpoint_id) => {
+                        if let Ok(endpoint) = self.control.endpoint(endpoint_id) {
+                            self.publish_endpoint(&publisher, &endpoint).await;
+                        }
+                    }
+                    Err(RecvError::Lagged(_)) => self.publish_all(&publisher).await,
+                    Err(RecvError::Closed) => break,
+                },
+            }
+        }
+
+        mqtt_loop.abort();
+    }
+
+    /// Announce availability, subscribe to `/set` topics and publish all endpoints.
+    async fn on_connect(&mut self, client: &AsyncClient, publisher: &MqttPublisher) {
+        let topic = self.availability_topic();
+        if let Err(e) = publisher
+            .send_retained(&topic, self.availability_qos, "online")
+            .await
+        {
+            warn!("[MQTT] Failed to publish {}: {}", topic, e);
+        }
+
+        let topic = format!("{}/+/+/set", self.prefix);
+        if let Err(e) = client.subscribe(&topic, self.command_qos).await {
+            warn!("[MQTT] Failed to subscribe to {}: {:?}", topic, e);
+        }
+
+        // Values may have changed while disconnected
+        self.published.clear();
+        self.publish_all(publisher).await;
+    }
+
+    async fn publish_all(&mut self, publisher: &MqttPublisher) {
+        for endpoint in self.control.endpoints() {
+            self.publish_endpoint(publisher, &endpoint).await;
+        }
+    }
+
+    /// Publish the values of an endpoint (retained) unless they did not change.
+    async fn publish_endpoint(&mut self, publisher: &MqttPublisher, endpoint: &ControlEndpoint) {
+        if endpoint.points.is_empty() {
+            return;
+        }
+        let payload = endpoint.snapshot()["values"].to_string();
+        if self.published.get(&endpoint.endpoint_id) == Some(&payload) {
+            return;
+        }
+
+        let topic = self.endpoint_topic(endpoint);
+        debug!("[MQTT] Mirror {}: {}", topic, payload);
+        match publisher
+            .send_retained(&topic, self.qos, payload.as_bytes())
+            .await
+        {
+            Ok(_) => {
+                self.published.insert(endpoint.endpoint_id, payload);
+            }
+            Err(e) => warn!("[MQTT] Failed to publish {}: {}", topic, e),
+        }
+    }
+
+    /// Apply a `/set` message to the endpoint it addresses.
+    ///
+    /// The change notification of the endpoint publishes the new values.
+    fn process_set(&self, msg: &MqttMessage) {
+        // The whole topic must match, so a stale device or label is not applied
+        let Some(endpoint) = set_topic_endpoint_id(&msg.topic)
+            .and_then(|endpoint_id| self.control.endpoint(endpoint_id).ok())
+            .filter(|endpoint| format!("{}/set", self.endpoint_topic(endpoint)) == msg.topic)
+        else {
+            warn!("[MQTT] No bridge endpoint for {}", msg.topic);
+            return;
+        };
+
+        for (kind, value) in parse_set_payload(&msg.payload) {
+            let result = endpoint
+                .point(kind.as_deref())
+                .and_then(|point| match &value {
+                    SetValue::Toggle => point.toggle(),
+                    SetValue::Value(value) => point.set(value),
+                });
+            match result {
+                Ok(value) => info!("[MQTT] {} set to {}", msg.topic, value),
+                Err(e) => warn!("[MQTT] {}: {}", msg.topic, e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_set_payload() {
+        assert_eq!(
+            parse_set_payload(r#"{"on_off": true, "temperature": 21.5}"#),
+            [
+                (Some("on_off".to_string()), SetValue::Value(json!(true))),
+                (
+                    Some("temperature".to_string()),
+                    SetValue::Value(json!(21.5))
+                )
+            ]
+        );
+        assert_eq!(
+            parse_set_payload("ON"),
+            [(None, SetValue::Value(json!(true)))]
+        );
+        assert_eq!(parse_set_payload(r#""toggle""#), [(None, SetValue::Toggle)]);
+        assert_eq!(
+            parse_set_payload("single_press"),
+            [(None, SetValue::Value(json!("single_press")))]
+        );
+        assert_eq!(topic_segment("Door/Window #1"), "Door_Window _1");
+    }
+
+    #[test]
+    fn test_endpoint_topic_ids() {
+        // Same label, different endpoints
+        assert_eq!(endpoint_segment(3, "Light"), "3-Light");
+        assert_eq!(endpoint_segment(4, "Light"), "4-Light");
+        assert_eq!(set_topic_endpoint_id("vmb/Kitchen/4-Light/set"), Some(4));
+        assert_eq!(
+            set_topic_endpoint_id("vmb/Kitchen/12-Top-Left/set"),
+            Some(12)
+        );
+        assert_eq!(set_topic_endpoint_id("vmb/Kitchen/Light/set"), None);
+        assert_eq!(set_topic_endpoint_id("vmb/Kitchen/4-Light"), None);
+    }
+}
@@ -3,15 +3,24 @@
 //! This module provides MQTT client functionality to communicate with zigbee2mqtt
 //! and translate Zigbee device data into the Virtual Matter Bridge.
 
+mod actions;
 mod client;
 mod integration;
+mod mirror;
+mod switch;
 mod w100;
 
 // Main API - clean integration for use in main.rs
-pub use integration::{MqttIntegration, W100Config};
+#[allow(unused_imports)]
+pub use actions::{ActionMap, ActionMapError, ButtonEvent};
+pub use actions::{REMOTES_FILE, RemoteList};
+pub use integration::{FRIENDLY_NAMES_FILE, MqttIntegration, RemoteConfig, W100Config};
+pub use mirror::MqttMirror;
+#[allow(unused_imports)]
+pub use switch::MqttSwitch;
 
 // Legacy exports for test binary and reference
 #[allow(unused_imports)]
-pub use client::MqttClient;
+pub use client::{MqttClient, MqttClientError};
 #[allow(unused_imports)]
 pub use w100::{W100Action, W100Device, W100State};
@@ -0,0 +1,172 @@
+//! zigbee2mqtt switch (smart plug, relay) exposed as a Matter OnOff endpoint.
+//!
+//! The switch follows the device state topic (`zigbee2mqtt/<device>`) and sends
+//! Matter commands to `zigbee2mqtt/<device>/set`. Commands are confirmed by the broker
+//! (at the configured command QoS), so an unacknowledged command fails the Matter invoke.
+
+use super::client::MqttPublisher;
+use crate::matter::endpoints::{CommandError, CommandFuture, EndpointHandler};
+use log::{info, warn};
+use parking_lot::RwLock;
+use rumqttc::QoS;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Publishing side of a switch, attached when the MQTT integration starts.
+#[derive(Clone)]
+struct SwitchLink {
+    publisher: MqttPublisher,
+    runtime: tokio::runtime::Handle,
+    qos: QoS,
+}
+
+/// Parse the `state` of a zigbee2mqtt switch state message.
+fn parse_switch_state(payload: &str) -> Option<bool> {
+    #[derive(serde::Deserialize)]
+    struct SwitchState {
+        state: Option<String>,
+    }
+
+    let state = serde_json::from_str::<SwitchState>(payload).ok()?.state?;
+    match state.to_uppercase().as_str() {
+        "ON" => Some(true),
+        "OFF" => Some(false),
+        _ => None,
+    }
+}
+
+/// A zigbee2mqtt switch.
+///
+/// Use it as the handler of a switch endpoint and add it to the integration with
+/// [`MqttIntegration::with_switch`](super::MqttIntegration::with_switch).
+pub struct MqttSwitch {
+    friendly_name: String,
+    state: Arc<AtomicBool>,
+    pusher: RwLock<Option<Arc<dyn Fn(bool) + Send + Sync>>>,
+    link: RwLock<Option<SwitchLink>>,
+}
+
+impl MqttSwitch {
+    /// Create a switch for a zigbee2mqtt device (off until its first state report).
+    pub fn new(friendly_name: impl Into<String>) -> Arc<Self> {
+        Arc::new(Self {
+            friendly_name: friendly_name.into(),
+            state: Arc::new(AtomicBool::new(false)),
+            pusher: RwLock::new(None),
+            link: RwLock::new(None),
+        })
+    }
+
+    /// Friendly name in zigbee2mqtt.
+    pub fn friendly_name(&self) -> &str {
+        &self.friendly_name
+    }
+
+    pub(super) fn state_topic(&self) -> String {
+        format!("zigbee2mqtt/{}", self.friendly_name)
+    }
+
+    fn set_topic(&self) -> String {
+        format!("zigbee2mqtt/{}/set", self.friendly_name)
+    }
+
+    /// Publish commands through the integration's client.
+    pub(super) fn attach(&self, publisher: MqttPublisher, qos: QoS) {
+        *self.link.write() = Some(SwitchLink {
+            publisher,
+            runtime: tokio::runtime::Handle::current(),
+            qos,
+        });
+    }
+
+    /// Update the state from a device state message.
+    pub(super) fn process_state_message(&self, payload: &str) {
+        let Some(on) = parse_switch_state(payload) else {
+            return; // No state in this report
+        };
+        if self.state.swap(on, Ordering::SeqCst) != on {
+            info!(
+                "[MQTT] {} is now {}",
+                self.friendly_name,
+                if on { "on" } else { "off" }
+            );
+            if let Some(pusher) = self.pusher.read().as_ref() {
+                pusher(on);
+            }
+        }
+    }
+
+    /// Send a command and return a future resolving once the broker acknowledged it.
+    fn send(&self, value: bool) -> CommandFuture {
+        let Some(link) = self.link.read().clone() else {
+            return Box::pin(std::future::ready(Err(CommandError(format!(
+                "{} is not connected to MQTT",
+                self.friendly_name
+            )))));
+        };
+        let topic = self.set_topic();
+        let payload = serde_json::json!({ "state": if value { "ON" } else { "OFF" } }).to_string();
+        // Publishing needs the Tokio runtime, the Matter stack runs on its own executor
+        let task = link.runtime.spawn(async move {
+            link.publisher
+                .publish(&topic, link.qos, payload)
+                .await
+                .map_err(|e| CommandError(format!("{}: {}", topic, e)))
+        });
+        let state = self.state.clone();
+        Box::pin(async move {
+            task.await
+                .map_err(|e| CommandError(format!("publish task failed: {}", e)))??;
+            state.store(value, Ordering::SeqCst);
+            Ok(())
+        })
+    }
+}
+
+impl EndpointHandler for MqttSwitch {
+    fn on_command(&self, value: bool) {
+        let name = self.friendly_name.clone();
+        let command = self.send(value);
+        if let Some(link) = self.link.read().as_ref() {
+            link.runtime.spawn(async move {
+                if let Err(e) = command.await {
+                    warn!("[MQTT] Command to {} failed: {}", name, e);
+                }
+            });
+        } else {
+            warn!("[MQTT] {} is not connected, command dropped", name);
+        }
+    }
+
+    fn get_state(&self) -> bool {
+        self.state.load(Ordering::SeqCst)
+    }
+
+    fn set_state_pusher(&self, pusher: Arc<dyn Fn(bool) + Send + Sync>) {
+        *self.pusher.write() = Some(pusher);
+    }
+
+    fn confirms_commands(&self) -> bool {
+        true
+    }
+
+    fn command(&self, value: bool) -> CommandFuture {
+        self.send(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_switch_state() {
+        assert_eq!(
+            parse_switch_state(r#"{"state":"ON","linkquality":80}"#),
+            Some(true)
+        );
+        assert_eq!(parse_switch_state(r#"{"state":"off"}"#), Some(false));
+        assert_eq!(parse_switch_state(r#"{"linkquality":80}"#), None);
+        assert_eq!(parse_switch_state("ON"), None);
+    }
+}
@@ -1,12 +1,16 @@
 //! Single instance lock using Unix socket.
 //!
-//! Prevents multiple instances of the bridge from running simultaneously.
+//! Prevents the same bridge instance from running twice. Each instance name
+//! (see `MatterConfig::instance_name`) has its own socket, so differently
+//! named instances can run side by side.
 //! Uses a Unix socket which is automatically cleaned up by the OS when the
-//! process dies, avoiding stale lock files.
+//! process dies, avoiding stale lock files. The same socket serves the local
+//! control plane (see [`crate::control`]).
 
+use std::ffi::OsString;
 use std::io;
 use std::os::unix::net::UnixListener;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
 /// Error types for instance lock operations.
@@ -27,17 +31,17 @@ pub enum InstanceLockError {
 /// file is removed. If the process crashes, the OS automatically removes
 /// the socket, preventing stale locks.
 pub struct InstanceLock {
-    _listener: UnixListener,
+    listener: UnixListener,
     path: PathBuf,
 }
 
 impl InstanceLock {
-    /// Attempt to acquire the instance lock.
+    /// Attempt to acquire the lock for the instance `name`.
     ///
-    /// Returns `Ok(InstanceLock)` if this is the only instance running.
+    /// Returns `Ok(InstanceLock)` if this is the only instance with that name running.
     /// Returns `Err(InstanceLockError::AlreadyRunning)` if another instance holds the lock.
-    pub fn acquire() -> Result<Self, InstanceLockError> {
-        let path = Self::socket_path();
+    pub fn acquire(name: &str) -> Result<Self, InstanceLockError> {
+        let path = Self::socket_path(&Self::runtime_dir(), name);
 
         // Remove stale socket if it exists but no process holds it
         // This handles the case where the process was SIGKILL'd and
@@ -58,10 +62,7 @@ impl InstanceLock {
 
         // Try to bind the socket
         match UnixListener::bind(&path) {
-            Ok(listener) => Ok(Self {
-                _listener: listener,
-                path,
-            }),
+            Ok(listener) => Ok(Self { listener, path }),
             Err(e) if e.kind() == io::ErrorKind::AddrInUse => {
                 // Race condition: another instance bound between our check and bind
                 Err(InstanceLockError::AlreadyRunning)
@@ -70,17 +71,33 @@ impl InstanceLock {
         }
     }
 
-    /// Get the path to the socket file.
-    pub fn socket_path() -> PathBuf {
-        // Use XDG_RUNTIME_DIR if available (auto-cleaned on logout)
-        // Fallback to /tmp
-        std::env::var("XDG_RUNTIME_DIR")
-            .map(PathBuf::from)
-            .unwrap_or_else(|_| PathBuf::from("/tmp"))
-            .join("virtual-matter-bridge.sock")
+    /// Get a handle to the bound socket, e.g. to serve the control plane on it.
+    ///
+    /// The lock itself keeps its own handle, so it stays held while the returned
+    /// listener is in use.
+    pub fn listener(&self) -> io::Result<UnixListener> {
+        self.listener.try_clone()
+    }
+
+    /// Directory of the socket files: `XDG_RUNTIME_DIR` (auto-cleaned on logout) or `/tmp`.
+    pub fn runtime_dir() -> PathBuf {
+        runtime_dir_from(std::env::var_os("XDG_RUNTIME_DIR"))
+    }
+
+    /// Get the path to the socket file of the instance `name` in `runtime_dir`.
+    pub fn socket_path(runtime_dir: &Path, name: &str) -> PathBuf {
+        runtime_dir.join(format!("{}.sock", name))
     }
 }
 
+/// Runtime directory from the value of `XDG_RUNTIME_DIR`.
+fn runtime_dir_from(xdg_runtime_dir: Option<OsString>) -> PathBuf {
+    xdg_runtime_dir
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+}
+
 impl Drop for InstanceLock {
     fn drop(&mut self) {
         // Clean up the socket file on normal exit
@@ -93,20 +110,24 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_socket_path_uses_xdg_runtime_dir() {
-        std::env::set_var("XDG_RUNTIME_DIR", "/run/user/1000");
-        let path = InstanceLock::socket_path();
+    fn test_socket_path() {
+        let path = InstanceLock::socket_path(Path::new("/run/user/1000"), "virtual-matter-bridge");
         assert_eq!(
             path,
             PathBuf::from("/run/user/1000/virtual-matter-bridge.sock")
         );
-        std::env::remove_var("XDG_RUNTIME_DIR");
     }
 
     #[test]
-    fn test_socket_path_fallback_to_tmp() {
-        std::env::remove_var("XDG_RUNTIME_DIR");
-        let path = InstanceLock::socket_path();
-        assert_eq!(path, PathBuf::from("/tmp/virtual-matter-bridge.sock"));
+    fn test_runtime_dir_fallback_to_tmp() {
+        assert_eq!(
+            runtime_dir_from(Some("/run/user/1000".into())),
+            PathBuf::from("/run/user/1000")
+        );
+        assert_eq!(runtime_dir_from(None), PathBuf::from("/tmp"));
+        assert_eq!(
+            runtime_dir_from(Some(OsString::new())),
+            PathBuf::from("/tmp")
+        );
     }
 }
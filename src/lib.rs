@@ -9,7 +9,9 @@
 
 pub mod commissioning;
 pub mod config;
+pub mod control;
 pub mod error;
 pub mod input;
 pub mod instance_lock;
 pub mod matter;
+pub mod onboarding;
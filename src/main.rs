@@ -8,19 +8,26 @@
 
 mod commissioning;
 mod config;
+mod control;
 mod error;
 mod input;
 mod instance_lock;
 mod matter;
+mod onboarding;
 
 use crate::config::Config;
+use crate::control::ControlServer;
 use crate::input::camera::CameraInput;
-use crate::input::mqtt::{MqttIntegration, W100Config};
+use crate::input::mqtt::{
+    FRIENDLY_NAMES_FILE, MqttIntegration, MqttMirror, REMOTES_FILE, RemoteConfig, RemoteList,
+    W100Config,
+};
 use crate::instance_lock::{InstanceLock, InstanceLockError};
 use crate::matter::clusters::{
-    BridgedDeviceInfo, GenericSwitchState, HumiditySensor, TemperatureSensor,
+    BridgedDeviceInfo, GenericSwitchState, HumiditySensor, PowerSource, TemperatureSensor,
 };
-use crate::matter::endpoints::EndpointHandler;
+use crate::matter::endpoints::derived::{DERIVED_FILE, DerivedConfig};
+use crate::matter::endpoints::{DeviceAvailability, EndpointHandler};
 use crate::matter::{EndpointConfig, VirtualDevice};
 use log::info;
 use parking_lot::RwLock as SyncRwLock;
@@ -126,18 +133,34 @@ async fn main() {
 
     init_logger();
 
-    // Acquire instance lock - exit if another instance is already running
-    let _instance_lock = match InstanceLock::acquire() {
+    // Load configuration (the instance name selects lock, port and storage)
+    let config = match Config::from_env() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let instance_name = config.matter.instance_name();
+
+    // Acquire instance lock - exit if this instance is already running
+    let instance_lock = match InstanceLock::acquire(&instance_name) {
         Ok(lock) => {
             info!(
                 "Instance lock acquired: {}",
-                InstanceLock::socket_path().display()
+                InstanceLock::socket_path(&InstanceLock::runtime_dir(), &instance_name).display()
             );
             lock
         }
         Err(InstanceLockError::AlreadyRunning) => {
-            eprintln!("Error: Another instance of virtual-matter-bridge is already running");
-            eprintln!("Socket: {}", InstanceLock::socket_path().display());
+            eprintln!(
+                "Error: Another instance of {} is already running",
+                instance_name
+            );
+            eprintln!(
+                "Socket: {}",
+                InstanceLock::socket_path(&InstanceLock::runtime_dir(), &instance_name).display()
+            );
             std::process::exit(1);
         }
         Err(e) => {
@@ -146,20 +169,20 @@ async fn main() {
         }
     };
 
-    info!("Starting Virtual Matter Bridge");
+    info!("Starting Virtual Matter Bridge ({})", instance_name);
 
-    // Load configuration
-    let config = Config::from_env();
     info!("Configuration loaded:");
     info!("  Device Name: {}", config.matter.device_name);
     info!("  RTSP URL: {}", config.rtsp.url);
     info!("  Vendor ID: 0x{:04X}", config.matter.vendor_id);
     info!("  Product ID: 0x{:04X}", config.matter.product_id);
-    info!("  Discriminator: {}", config.matter.discriminator);
+    info!("  Matter port: {}", config.matter.port);
+    info!("  Storage: {}", config.matter.persist_dir().display());
 
     // Clone config parts before moving to camera input
     let matter_config = config.matter.clone();
     let mqtt_config = config.mqtt.clone();
+    let mirror_config = config.mqtt.clone();
 
     // Create the camera input (handles RTSP/WebRTC)
     let camera = Arc::new(SyncRwLock::new(CameraInput::new(config)));
@@ -175,13 +198,116 @@ async fn main() {
     // Create W100 climate sensors (will be updated by MQTT)
     let w100_temperature = Arc::new(TemperatureSensor::new(20.0)); // Default 20°C
     let w100_humidity = Arc::new(HumiditySensor::new(50.0)); // Default 50%
+    let w100_battery = Arc::new(PowerSource::new()); // Unknown until first report
     // W100 button states (Plus, Minus, Center buttons)
     let w100_button_plus = Arc::new(GenericSwitchState::new());
     let w100_button_minus = Arc::new(GenericSwitchState::new());
     let w100_button_center = Arc::new(GenericSwitchState::new());
 
+    // W100 availability: zigbee2mqtt availability topic, or offline after 2h without reports
+    let w100_availability =
+        Arc::new(DeviceAvailability::new().with_stale_timeout(Duration::from_secs(2 * 60 * 60)));
+
+    // MQTT integration for W100 climate sensor (started once devices are defined)
+    let rename_devices = mqtt_config.rename_devices;
+    let remotes_path = mqtt_config
+        .remotes
+        .clone()
+        .unwrap_or_else(|| matter_config.persist_dir().join(REMOTES_FILE));
+    let mut mqtt_integration = MqttIntegration::new(mqtt_config)
+        .with_friendly_names(matter_config.persist_dir().join(FRIENDLY_NAMES_FILE))
+        .with_w100(
+            W100Config::new(
+                "Tim-Thermometer",
+                w100_temperature.clone(),
+                w100_humidity.clone(),
+            )
+            .with_buttons(
+                w100_button_plus.clone(),
+                w100_button_minus.clone(),
+                w100_button_center.clone(),
+            )
+            .with_availability(w100_availability.clone())
+            .with_power_source(w100_battery.clone()),
+        );
+
+    // W100 Climate Sensor (Aqara TH-S04D) via MQTT/zigbee2mqtt
+    let mut w100_device = VirtualDevice::new("Tim Thermometer")
+        .with_device_info(
+            BridgedDeviceInfo::new("Tim Thermometer")
+                .with_vendor("Aqara")
+                .with_product("Climate Sensor W100"),
+        )
+        // A thermometer has nothing to power on/off - expose a pure Bridged Node
+        .without_device_switch()
+        .with_availability(w100_availability.clone())
+        // Temperature, humidity and battery on one endpoint, like the physical sensor
+        .with_endpoint(
+            EndpointConfig::new("Climate")
+                .with_temperature_sensor(w100_temperature.clone())
+                .with_humidity_sensor(w100_humidity.clone())
+                .with_power_source(w100_battery.clone()),
+        )
+        .with_endpoint(EndpointConfig::generic_switch(
+            "Button Plus",
+            w100_button_plus.clone(),
+        ))
+        .with_endpoint(EndpointConfig::generic_switch(
+            "Button Minus",
+            w100_button_minus.clone(),
+        ))
+        .with_endpoint(EndpointConfig::generic_switch(
+            "Button Center",
+            w100_button_center.clone(),
+        ));
+    if rename_devices {
+        // Renaming the device in a controller also renames it in zigbee2mqtt
+        w100_device =
+            w100_device.with_label_listener(mqtt_integration.label_listener("Tim-Thermometer"));
+    }
+
+    // Remotes from the remote list, each button a GenericSwitch endpoint
+    let remotes = RemoteList::load(&remotes_path).unwrap_or_else(|e| {
+        log::error!("Ignoring remote list: {}", e);
+        RemoteList::default()
+    });
+    if !remotes.is_empty() {
+        info!("Loaded {} remotes", remotes.len());
+    }
+    let mut remote_devices = Vec::with_capacity(remotes.len());
+    for remote in remotes.remotes() {
+        let availability = Arc::new(DeviceAvailability::new());
+        let mut info = BridgedDeviceInfo::new(&remote.name);
+        if let Some(vendor) = &remote.vendor {
+            info = info.with_vendor(vendor);
+        }
+        if let Some(product) = &remote.product {
+            info = info.with_product(product);
+        }
+        let mut device = VirtualDevice::new(&remote.name)
+            .with_device_info(info)
+            .without_device_switch()
+            .with_availability(availability.clone());
+        let mut remote_config = RemoteConfig::new(&remote.friendly_name, remote.actions.clone())
+            .with_availability(availability);
+        for button in &remote.buttons {
+            let state = Arc::new(GenericSwitchState::new());
+            device = device.with_endpoint(EndpointConfig::generic_switch(
+                format!("Button {}", button),
+                state.clone(),
+            ));
+            remote_config = remote_config.with_button(button, state);
+        }
+        if rename_devices {
+            device =
+                device.with_label_listener(mqtt_integration.label_listener(&remote.friendly_name));
+        }
+        mqtt_integration = mqtt_integration.with_remote(remote_config);
+        remote_devices.push(device);
+    }
+
     // Define our virtual devices using the new API
-    let virtual_devices = vec![
+    let mut virtual_devices = vec![
         // Door sensor (parent) with contact sensor endpoint (child)
         VirtualDevice::new("Door").with_endpoint(EndpointConfig::contact_sensor(
             "Door Sensor",
@@ -205,37 +331,29 @@ async fn main() {
             "Camera",
             doorbell_handler.clone(),
         )),
-        // W100 Climate Sensor (Aqara TH-S04D) via MQTT/zigbee2mqtt
-        VirtualDevice::new("Tim Thermometer")
-            .with_device_info(
-                BridgedDeviceInfo::new("Tim Thermometer")
-                    .with_vendor("Aqara")
-                    .with_product("Climate Sensor W100"),
-            )
-            .with_endpoint(EndpointConfig::temperature_sensor(
-                "Temperature",
-                w100_temperature.clone(),
-            ))
-            .with_endpoint(EndpointConfig::humidity_sensor(
-                "Humidity",
-                w100_humidity.clone(),
-            ))
-            .with_endpoint(EndpointConfig::generic_switch(
-                "Button Plus",
-                w100_button_plus.clone(),
-            ))
-            .with_endpoint(EndpointConfig::generic_switch(
-                "Button Minus",
-                w100_button_minus.clone(),
-            ))
-            .with_endpoint(EndpointConfig::generic_switch(
-                "Button Center",
-                w100_button_center.clone(),
-            )),
+        w100_device,
     ];
+    virtual_devices.extend(remote_devices);
+
+    // Derived endpoints, computed from other endpoints (evaluated once the stack runs)
+    let derived_path = matter_config
+        .derived
+        .clone()
+        .unwrap_or_else(|| matter_config.persist_dir().join(DERIVED_FILE));
+    let derived = DerivedConfig::load(&derived_path).unwrap_or_else(|e| {
+        log::error!("Ignoring derived endpoints: {}", e);
+        DerivedConfig::default()
+    });
+    if !derived.is_empty() {
+        info!("Loaded {} derived endpoints", derived.len());
+    }
+    let (derived_devices, derived_endpoints) = derived.into_parts();
+    virtual_devices.extend(derived_devices);
 
-    // Get the bridge master on/off switch from camera input
-    let virtual_bridge_onoff = camera.read().device_power();
+    // Get the bridge master on/off switch from camera input (unless disabled)
+    let virtual_bridge_onoff = matter_config
+        .master_switch
+        .then(|| camera.read().device_power());
 
     // Initialize the camera input
     let camera_for_init = camera.clone();
@@ -264,20 +382,48 @@ async fn main() {
     });
 
     // Start MQTT integration for W100 climate sensor (self-contained!)
-    let mqtt_task = MqttIntegration::new(mqtt_config)
-        .with_w100(
-            W100Config::new(
-                "Tim-Thermometer",
-                w100_temperature.clone(),
-                w100_humidity.clone(),
-            )
-            .with_buttons(
-                w100_button_plus.clone(),
-                w100_button_minus.clone(),
-                w100_button_center.clone(),
-            ),
-        )
-        .start();
+    let mqtt_task = mqtt_integration.start();
+
+    // Shutdown handle of this bridge instance
+    let matter_shutdown = matter::ShutdownHandle::new();
+    let matter_shutdown_for_stack = matter_shutdown.clone();
+
+    // Local control plane served on the instance lock socket
+    let bridge_control = matter::BridgeControl::new();
+    let bridge_control_for_stack = bridge_control.clone();
+
+    // Subscribes before the stack registers the endpoints the expressions read
+    let derived_task =
+        (!derived_endpoints.is_empty()).then(|| derived_endpoints.start(&bridge_control));
+
+    // Optional MQTT mirror of all bridge endpoints (state, availability and `/set`)
+    let mirror_task = if mirror_config.mirror {
+        let prefix = mirror_config
+            .mirror_prefix
+            .clone()
+            .unwrap_or_else(|| matter_config.instance_name());
+        Some(MqttMirror::new(mirror_config, prefix, bridge_control.clone()).start())
+    } else {
+        None
+    };
+    let control_shutdown = Arc::new(tokio::sync::Notify::new());
+    let control_task = match instance_lock.listener() {
+        Ok(listener) => {
+            let mut server = ControlServer::new(bridge_control, control_shutdown.clone());
+            if let Some(ref server_url) = matter_config.server_url {
+                server = server.with_matter_server(server_url.clone());
+            }
+            Some(tokio::spawn(async move {
+                if let Err(e) = server.run(listener).await {
+                    log::error!("Control socket error: {}", e);
+                }
+            }))
+        }
+        Err(e) => {
+            log::warn!("Control socket unavailable: {}", e);
+            None
+        }
+    };
 
     // Start Matter stack in a separate thread
     // Matter uses blocking I/O internally with embassy, so we run it on a dedicated thread
@@ -289,6 +435,8 @@ async fn main() {
                 &matter_config,
                 virtual_bridge_onoff,
                 virtual_devices,
+                matter_shutdown_for_stack,
+                bridge_control_for_stack,
             )) {
                 log::error!("Matter stack error: {:?}", e);
             }
@@ -297,22 +445,36 @@ async fn main() {
 
     info!("Matter stack started on dedicated thread");
 
-    // Wait for shutdown signal
-    match signal::ctrl_c().await {
-        Ok(()) => {
-            info!("Received shutdown signal, initiating graceful shutdown...");
-        }
-        Err(e) => {
-            log::error!("Failed to listen for shutdown signal: {}", e);
+    // Wait for shutdown signal (Ctrl+C or `vmbctl shutdown`)
+    tokio::select! {
+        result = signal::ctrl_c() => match result {
+            Ok(()) => {
+                info!("Received shutdown signal, initiating graceful shutdown...");
+            }
+            Err(e) => {
+                log::error!("Failed to listen for shutdown signal: {}", e);
+            }
+        },
+        _ = control_shutdown.notified() => {
+            info!("Shutdown requested via control socket, initiating graceful shutdown...");
         }
     }
 
     // Signal Matter stack to shut down
-    matter::signal_shutdown();
+    matter_shutdown.signal();
 
     // Abort async tasks
     sensor_task.abort();
     mqtt_task.abort();
+    if let Some(mirror_task) = mirror_task {
+        mirror_task.abort();
+    }
+    if let Some(derived_task) = derived_task {
+        derived_task.abort();
+    }
+    if let Some(control_task) = control_task {
+        control_task.abort();
+    }
 
     // Wait for Matter thread to finish (with timeout)
     info!("Waiting for Matter stack to shut down...");
@@ -0,0 +1,142 @@
+//! Persistent storage for the Binding cluster.
+//!
+//! Controllers write Binding targets (node/group, endpoint, cluster) to the Binding
+//! cluster of button endpoints. The targets are fabric-scoped and stored in a small
+//! JSON file next to `matter.bin`, keyed by `"<device>/<endpoint>"` like the NodeLabels,
+//! so they are independent of endpoint IDs.
+
+use super::json_store::JsonStore;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// A Binding cluster target (TargetStruct).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BindingTarget {
+    /// Fabric the entry belongs to
+    pub fabric_index: u8,
+    /// Target node (unicast)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node: Option<u64>,
+    /// Target group (groupcast)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<u16>,
+    /// Target endpoint (unicast only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<u16>,
+    /// Target cluster (all clusters when absent)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cluster: Option<u32>,
+}
+
+impl BindingTarget {
+    /// Whether the entry is valid: either a node or a group, and an endpoint only
+    /// together with a node.
+    pub fn is_valid(&self) -> bool {
+        match (self.node, self.group) {
+            (Some(_), None) => true,
+            (None, Some(_)) => self.endpoint.is_none(),
+            _ => false,
+        }
+    }
+}
+
+/// JSON-backed Binding table of all endpoints.
+pub struct BindingTableStore {
+    tables: JsonStore<BTreeMap<String, Vec<BindingTarget>>>,
+}
+
+impl BindingTableStore {
+    /// Load the store from `path` (empty if the file does not exist).
+    pub fn load(path: impl Into<PathBuf>) -> Self {
+        Self {
+            tables: JsonStore::load(path),
+        }
+    }
+
+    /// Get the Binding targets of an endpoint (all fabrics).
+    pub fn get(&self, key: &str) -> Vec<BindingTarget> {
+        self.tables
+            .read(|tables| tables.get(key).cloned().unwrap_or_default())
+    }
+
+    /// Replace the targets of one fabric on an endpoint.
+    pub fn replace(&self, key: &str, fabric_index: u8, targets: Vec<BindingTarget>) {
+        self.update(|tables| {
+            let table = tables.entry(key.to_string()).or_default();
+            table.retain(|target| target.fabric_index != fabric_index);
+            table.extend(targets);
+        });
+    }
+
+    /// Append a target to an endpoint.
+    pub fn append(&self, key: &str, target: BindingTarget) {
+        self.update(|tables| tables.entry(key.to_string()).or_default().push(target));
+    }
+
+    /// Remove all targets of a fabric (fabric removed).
+    pub fn remove_fabric(&self, fabric_index: u8) {
+        self.update(|tables| {
+            for table in tables.values_mut() {
+                table.retain(|target| target.fabric_index != fabric_index);
+            }
+        });
+    }
+
+    /// Forget all targets (factory reset).
+    pub fn clear(&self) -> std::io::Result<()> {
+        self.tables.clear()
+    }
+
+    fn update(&self, f: impl FnOnce(&mut BTreeMap<String, Vec<BindingTarget>>)) {
+        self.tables.update(|tables| {
+            f(tables);
+            tables.retain(|_, table| !table.is_empty());
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node_target(fabric_index: u8, node: u64) -> BindingTarget {
+        BindingTarget {
+            fabric_index,
+            node: Some(node),
+            group: None,
+            endpoint: Some(1),
+            cluster: Some(0x0006),
+        }
+    }
+
+    #[test]
+    fn test_fabric_scoped_replace() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bindings.json");
+
+        let store = BindingTableStore::load(&path);
+        store.replace("W100/Plus", 1, vec![node_target(1, 10), node_target(1, 11)]);
+        store.append("W100/Plus", node_target(2, 20));
+        store.replace("W100/Plus", 1, vec![node_target(1, 12)]);
+        assert_eq!(
+            store.get("W100/Plus"),
+            [node_target(2, 20), node_target(1, 12)]
+        );
+
+        store.remove_fabric(2);
+        let reloaded = BindingTableStore::load(&path);
+        assert_eq!(reloaded.get("W100/Plus"), [node_target(1, 12)]);
+        assert!(reloaded.get("W100/Minus").is_empty());
+
+        let group = BindingTarget {
+            fabric_index: 1,
+            node: None,
+            group: Some(3),
+            endpoint: Some(1),
+            cluster: None,
+        };
+        assert!(node_target(1, 10).is_valid());
+        assert!(!group.is_valid());
+    }
+}
@@ -0,0 +1,194 @@
+//! Binding cluster handler (0x001E).
+//!
+//! Controllers write the targets a switch should control to the Binding cluster of its
+//! endpoint (e.g. "the OnOff cluster on endpoint 1 of node 0x1234"). Entries are
+//! fabric-scoped: each fabric reads and writes only its own entries, and they are
+//! persisted via [`BindingTableStore`].
+//!
+//! The bridge stores and serves the table so controllers can configure it, but does
+//! not open CASE sessions to the targets itself. Button-to-endpoint control inside the
+//! bridge is handled by the local bindings (see `local_bindings`).
+
+use super::super::binding_table::{BindingTableStore, BindingTarget};
+use rs_matter::dm::{
+    Access, Attribute, Cluster, Dataver, Handler, NonBlockingHandler, Quality, ReadContext,
+    ReadReply, Reply, WriteContext,
+};
+use rs_matter::error::{Error, ErrorCode};
+use rs_matter::tlv::{TLVElement, TLVTag, TLVWrite};
+use rs_matter::{attribute_enum, attributes, with};
+use std::sync::Arc;
+use strum::FromRepr;
+
+/// Matter Cluster ID for Binding
+pub const CLUSTER_ID: u32 = 0x001E;
+
+/// Cluster revision
+pub const CLUSTER_REVISION: u16 = 1;
+
+/// Context tag of the fabric index in fabric-scoped structs
+const FABRIC_INDEX_TAG: u8 = 0xFE;
+
+/// Attribute IDs for the Binding cluster
+#[derive(Clone, Copy, Debug, Eq, PartialEq, FromRepr)]
+#[repr(u32)]
+pub enum BindingAttribute {
+    /// List of binding targets (TargetStruct, fabric-scoped)
+    Binding = 0x0000,
+}
+
+attribute_enum!(BindingAttribute);
+
+/// Cluster metadata definition for Binding
+pub const CLUSTER: Cluster<'static> = Cluster {
+    id: CLUSTER_ID,
+    revision: CLUSTER_REVISION,
+    feature_map: 0,
+    attributes: attributes!(Attribute::new(
+        BindingAttribute::Binding as _,
+        Access::RWFVM,
+        Quality::NONE
+    ),),
+    commands: &[],
+    with_attrs: with!(all),
+    with_cmds: with!(all),
+};
+
+/// Handler for the Binding cluster of one endpoint.
+pub struct BindingHandler {
+    dataver: Dataver,
+    store: Arc<BindingTableStore>,
+    /// Store key of the endpoint (`"<device>/<endpoint>"`)
+    key: String,
+}
+
+impl BindingHandler {
+    /// Cluster definition for use in the data model
+    pub const CLUSTER: Cluster<'static> = CLUSTER;
+
+    /// Create a handler serving the entries stored under `key`.
+    pub fn new(dataver: Dataver, store: Arc<BindingTableStore>, key: impl Into<String>) -> Self {
+        Self {
+            dataver,
+            store,
+            key: key.into(),
+        }
+    }
+
+    fn read_impl(&self, ctx: impl ReadContext, reply: impl ReadReply) -> Result<(), Error> {
+        let attr = ctx.attr();
+
+        let Some(mut writer) = reply.with_dataver(self.dataver.get())? else {
+            return Ok(()); // No update needed
+        };
+
+        // Global attributes
+        if attr.is_system() {
+            return CLUSTER.read(attr, writer);
+        }
+
+        let tag = writer.tag();
+        {
+            let mut tw = writer.writer();
+
+            match attr.attr_id.try_into()? {
+                BindingAttribute::Binding => {
+                    // For list reads with list_index, return ConstraintError
+                    if attr.list_index.as_ref().is_some_and(|li| li.is_some()) {
+                        return Err(ErrorCode::ConstraintError.into());
+                    }
+                    tw.start_array(tag)?;
+                    for target in self.store.get(&self.key) {
+                        if attr.fab_filter && target.fabric_index != attr.fab_idx {
+                            continue;
+                        }
+                        tw.start_struct(&TLVTag::Anonymous)?;
+                        if let Some(node) = target.node {
+                            tw.u64(&TLVTag::Context(1), node)?;
+                        }
+                        if let Some(group) = target.group {
+                            tw.u16(&TLVTag::Context(2), group)?;
+                        }
+                        if let Some(endpoint) = target.endpoint {
+                            tw.u16(&TLVTag::Context(3), endpoint)?;
+                        }
+                        if let Some(cluster) = target.cluster {
+                            tw.u32(&TLVTag::Context(4), cluster)?;
+                        }
+                        tw.u8(&TLVTag::Context(FABRIC_INDEX_TAG), target.fabric_index)?;
+                        tw.end_container()?;
+                    }
+                    tw.end_container()?;
+                }
+            }
+        }
+
+        writer.complete()
+    }
+
+    fn write_impl(&self, ctx: impl WriteContext) -> Result<(), Error> {
+        let attr = ctx.attr();
+        attr.check_dataver(self.dataver.get())?;
+
+        match attr.attr_id.try_into()? {
+            BindingAttribute::Binding => {
+                let data = ctx.data();
+                match &attr.list_index {
+                    // Whole list: replace the entries of the writing fabric
+                    None => {
+                        let targets = data
+                            .array()?
+                            .iter()
+                            .map(|entry| parse_target(&entry?, attr.fab_idx))
+                            .collect::<Result<Vec<_>, _>>()?;
+                        self.store.replace(&self.key, attr.fab_idx, targets);
+                    }
+                    // Null index: append one entry
+                    Some(index) if index.is_none() => {
+                        let target = parse_target(&data, attr.fab_idx)?;
+                        self.store.append(&self.key, target);
+                    }
+                    // Modifying single entries is not supported for lists of structs
+                    Some(_) => return Err(ErrorCode::ConstraintError.into()),
+                }
+                log::info!(
+                    "Binding of {} set by fabric {}: {:?}",
+                    self.key,
+                    attr.fab_idx,
+                    self.store.get(&self.key)
+                );
+                self.dataver.changed();
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Parse a TargetStruct written by fabric `fabric_index`.
+fn parse_target(entry: &TLVElement<'_>, fabric_index: u8) -> Result<BindingTarget, Error> {
+    // All fields are optional, so every field is looked up from the start of the struct
+    let field = |tag: u8| entry.structure().and_then(|mut seq| seq.scan_ctx(tag)).ok();
+    let target = BindingTarget {
+        fabric_index,
+        node: field(1).map(|e| e.u64()).transpose()?,
+        group: field(2).map(|e| e.u16()).transpose()?,
+        endpoint: field(3).map(|e| e.u16()).transpose()?,
+        cluster: field(4).map(|e| e.u32()).transpose()?,
+    };
+    if !target.is_valid() {
+        return Err(ErrorCode::ConstraintError.into());
+    }
+    Ok(target)
+}
+
+impl Handler for BindingHandler {
+    fn read(&self, ctx: impl ReadContext, reply: impl ReadReply) -> Result<(), Error> {
+        self.read_impl(ctx, reply)
+    }
+
+    fn write(&self, ctx: impl WriteContext) -> Result<(), Error> {
+        self.write_impl(ctx)
+    }
+}
+
+impl NonBlockingHandler for BindingHandler {}
@@ -2,16 +2,31 @@
 //!
 //! Provides device identification and endpoint names for Matter bridges.
 //! Controllers like Home Assistant read these attributes to display bridged device info.
+//!
+//! NodeLabel is writable: controllers can rename a bridged device, the new label is
+//! persisted via [`NodeLabelStore`] and optionally forwarded to the input source.
+//!
+//! Reachable is driven by [`ReachableState`], which combines the parent device switch
+//! with source availability and emits the ReachableChanged event (0x03) on transitions.
 
+use super::super::endpoints::ClusterNotifier;
+use super::super::endpoints::endpoints_helpers::{NotifiableSensor, Sensor};
+use super::super::node_labels::NodeLabelStore;
+use super::sync_dataver_with_sensor;
+use log::warn;
+use parking_lot::{Mutex, RwLock};
 use rs_matter::dm::{
     Access, Attribute, Cluster, Dataver, Handler, NonBlockingHandler, ReadContext, ReadReply,
     Reply, WriteContext,
 };
+use rs_matter::dm::{EventNumberGenerator, EventSource, MAX_PENDING_EVENTS, PendingEvent};
 use rs_matter::error::{Error, ErrorCode};
+use rs_matter::im::EventPriority;
 use rs_matter::tlv::TLVWrite;
 use rs_matter::{attribute_enum, attributes, with};
 use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU16, AtomicU32, Ordering};
+use std::time::Instant;
 use strum::FromRepr;
 
 /// Matter Cluster ID for BridgedDeviceBasicInformation
@@ -20,6 +35,42 @@ pub const CLUSTER_ID: u32 = 0x0039;
 /// Cluster revision
 const CLUSTER_REVISION: u16 = 4;
 
+/// Event IDs for the BridgedDeviceBasicInformation cluster
+pub mod events {
+    /// ReachableChanged event - Reachable attribute changed
+    pub const REACHABLE_CHANGED: u32 = 0x03;
+}
+
+/// Maximum NodeLabel length in bytes (per Matter spec)
+pub const MAX_NODE_LABEL_LEN: usize = 32;
+
+/// Maximum VendorName, ProductName and SerialNumber length in bytes (per Matter spec)
+pub const MAX_INFO_STRING_LEN: usize = 32;
+
+/// Truncate a configured string to `max` bytes (at a character boundary).
+fn truncate_info(field: &str, value: String, max: usize) -> String {
+    if value.len() <= max {
+        return value;
+    }
+    let end = (0..=max)
+        .rev()
+        .find(|&end| value.is_char_boundary(end))
+        .unwrap_or(0);
+    warn!(
+        "{} '{}' is longer than {} bytes, truncated to '{}'",
+        field,
+        value,
+        max,
+        &value[..end]
+    );
+    value[..end].to_string()
+}
+
+/// Callback invoked when a controller writes a new NodeLabel.
+///
+/// Used to propagate renames to the underlying source (e.g., zigbee2mqtt friendly name).
+pub type NodeLabelListener = Arc<dyn Fn(&str) + Send + Sync>;
+
 /// Attribute IDs for the BridgedDeviceBasicInformation cluster
 #[derive(Clone, Copy, Debug, Eq, PartialEq, FromRepr)]
 #[repr(u32)]
@@ -60,10 +111,10 @@ const CLUSTER: Cluster<'static> = Cluster {
             Access::RV,
             rs_matter::dm::Quality::NONE
         ),
-        // NodeLabel: optional, read-only string
+        // NodeLabel: optional, writable string (persisted)
         Attribute::new(
             BridgedDeviceBasicInfoAttribute::NodeLabel as _,
-            Access::RV,
+            Access::RWVM,
             rs_matter::dm::Quality::NONE
         ),
         // HardwareVersion: optional, read-only u16
@@ -99,30 +150,31 @@ const CLUSTER: Cluster<'static> = Cluster {
 /// Device information for bridged devices.
 ///
 /// REUSABLE across ALL bridged devices in the platform.
-/// Use the builder pattern to set optional fields.
+/// Use the builder pattern to set optional fields. Strings longer than the spec allows
+/// are truncated when set.
 #[derive(Clone, Debug)]
 pub struct BridgedDeviceInfo {
     /// Vendor name (e.g., "Aqara")
-    pub vendor_name: Option<&'static str>,
+    pub vendor_name: Option<String>,
     /// Product name (e.g., "Climate Sensor W100")
-    pub product_name: Option<&'static str>,
+    pub product_name: Option<String>,
     /// Node label - user-friendly name
-    pub node_label: &'static str,
+    pub node_label: String,
     /// Hardware version
     pub hardware_version: Option<u16>,
     /// Software version
     pub software_version: Option<u32>,
     /// Serial number (e.g., IEEE address)
-    pub serial_number: Option<&'static str>,
+    pub serial_number: Option<String>,
 }
 
 impl BridgedDeviceInfo {
     /// Create device info with just a node label.
-    pub fn new(node_label: &'static str) -> Self {
+    pub fn new(node_label: impl Into<String>) -> Self {
         Self {
             vendor_name: None,
             product_name: None,
-            node_label,
+            node_label: truncate_info("NodeLabel", node_label.into(), MAX_NODE_LABEL_LEN),
             hardware_version: None,
             software_version: None,
             serial_number: None,
@@ -130,14 +182,22 @@ impl BridgedDeviceInfo {
     }
 
     /// Set the vendor name (e.g., "Aqara").
-    pub fn with_vendor(mut self, name: &'static str) -> Self {
-        self.vendor_name = Some(name);
+    pub fn with_vendor(mut self, name: impl Into<String>) -> Self {
+        self.vendor_name = Some(truncate_info(
+            "VendorName",
+            name.into(),
+            MAX_INFO_STRING_LEN,
+        ));
         self
     }
 
     /// Set the product name (e.g., "Climate Sensor W100").
-    pub fn with_product(mut self, name: &'static str) -> Self {
-        self.product_name = Some(name);
+    pub fn with_product(mut self, name: impl Into<String>) -> Self {
+        self.product_name = Some(truncate_info(
+            "ProductName",
+            name.into(),
+            MAX_INFO_STRING_LEN,
+        ));
         self
     }
 
@@ -154,23 +214,178 @@ impl BridgedDeviceInfo {
     }
 
     /// Set the serial number (e.g., IEEE address).
-    pub fn with_serial_number(mut self, serial: &'static str) -> Self {
-        self.serial_number = Some(serial);
+    pub fn with_serial_number(mut self, serial: impl Into<String>) -> Self {
+        self.serial_number = Some(truncate_info(
+            "SerialNumber",
+            serial.into(),
+            MAX_INFO_STRING_LEN,
+        ));
         self
     }
 }
 
+/// Encode the ReachableChanged event payload.
+///
+/// TLV: anonymous struct { 0: ReachableNewValue (bool) }
+pub fn encode_reachable_changed(reachable: bool) -> heapless::Vec<u8, 16> {
+    let mut payload = heapless::Vec::new();
+    let bool_control = if reachable { 0x29 } else { 0x28 };
+    let _ = payload.extend_from_slice(&[0x15, bool_control, 0x00, 0x18]);
+    payload
+}
+
+/// Reachability of a bridged endpoint.
+///
+/// An endpoint is reachable when it is both *enabled* (its parent DeviceSwitch is on)
+/// and *available* (the input source reports the device as online). Every transition
+/// of the combined value bumps the version and queues a ReachableChanged event.
+pub struct ReachableState {
+    /// Controlled by the parent DeviceSwitch cascade
+    enabled: AtomicBool,
+    /// Controlled by the input source (availability topic, staleness timeout)
+    available: AtomicBool,
+    /// Last reported combined value
+    reachable: AtomicBool,
+    /// Version counter for change detection
+    version: AtomicU32,
+    /// Event number generator (sequential, never resets)
+    event_number: EventNumberGenerator,
+    /// Pending ReachableChanged events
+    pending_events: Mutex<heapless::Vec<PendingEvent, MAX_PENDING_EVENTS>>,
+    /// Time when the state was created (for elapsed timestamps)
+    start_time: Instant,
+    /// Endpoint ID (set when wired to Matter stack)
+    endpoint_id: AtomicU16,
+    /// Notifier for Matter subscription updates
+    notifier: RwLock<Option<ClusterNotifier>>,
+}
+
+impl ReachableState {
+    /// Create a new reachable state (enabled and available).
+    pub fn new() -> Self {
+        Self {
+            enabled: AtomicBool::new(true),
+            available: AtomicBool::new(true),
+            reachable: AtomicBool::new(true),
+            version: AtomicU32::new(0),
+            event_number: EventNumberGenerator::new(),
+            pending_events: Mutex::new(heapless::Vec::new()),
+            start_time: Instant::now(),
+            endpoint_id: AtomicU16::new(0),
+            notifier: RwLock::new(None),
+        }
+    }
+
+    /// Set the endpoint ID (called when wiring to Matter stack).
+    pub fn set_endpoint_id(&self, endpoint_id: u16) {
+        self.endpoint_id.store(endpoint_id, Ordering::SeqCst);
+    }
+
+    /// Get the current Reachable value.
+    pub fn is_reachable(&self) -> bool {
+        self.reachable.load(Ordering::SeqCst)
+    }
+
+    /// Get whether the input source reports the device as available.
+    pub fn is_available(&self) -> bool {
+        self.available.load(Ordering::SeqCst)
+    }
+
+    /// Enable or disable the endpoint (parent DeviceSwitch cascade).
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::SeqCst);
+        self.update();
+    }
+
+    /// Mark the device as available or unavailable (input source).
+    pub fn set_available(&self, available: bool) {
+        self.available.store(available, Ordering::SeqCst);
+        self.update();
+    }
+
+    /// Recompute Reachable and emit ReachableChanged if it changed.
+    fn update(&self) {
+        let reachable =
+            self.enabled.load(Ordering::SeqCst) && self.available.load(Ordering::SeqCst);
+        if self.reachable.swap(reachable, Ordering::SeqCst) == reachable {
+            return;
+        }
+
+        self.version.fetch_add(1, Ordering::SeqCst);
+
+        let payload = encode_reachable_changed(reachable);
+        let event = PendingEvent::with_payload(
+            self.endpoint_id.load(Ordering::SeqCst),
+            CLUSTER_ID,
+            events::REACHABLE_CHANGED,
+            self.event_number.next(),
+            EventPriority::Info,
+            self.start_time.elapsed().as_millis() as u64,
+            &payload,
+        );
+        self.pending_events.lock().push(event).ok();
+
+        if let Some(notifier) = self.notifier.read().as_ref() {
+            notifier.notify();
+        }
+    }
+}
+
+impl Default for ReachableState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sensor for ReachableState {
+    fn version(&self) -> u32 {
+        self.version.load(Ordering::SeqCst)
+    }
+}
+
+impl NotifiableSensor for ReachableState {
+    fn set_notifier(&self, notifier: ClusterNotifier) {
+        *self.notifier.write() = Some(notifier);
+    }
+}
+
+impl EventSource for ReachableState {
+    fn take_pending_events(&self) -> heapless::Vec<PendingEvent, MAX_PENDING_EVENTS> {
+        let mut events = self.pending_events.lock();
+        core::mem::take(&mut *events)
+    }
+
+    fn has_pending_events(&self) -> bool {
+        !self.pending_events.lock().is_empty()
+    }
+}
+
+/// Persistence binding for a writable NodeLabel.
+struct LabelPersistence {
+    store: Arc<NodeLabelStore>,
+    key: String,
+}
+
 /// Handler for BridgedDeviceBasicInformation cluster.
 ///
 /// Provides device identification via VendorName, ProductName, and other attributes.
 /// Controllers like Home Assistant read these to display bridged device info.
-#[derive(Clone, Debug)]
 pub struct BridgedHandler {
     dataver: Dataver,
     /// Device information
     info: BridgedDeviceInfo,
-    /// Dynamic reachable state (shared with parent DeviceSwitch)
-    reachable: Arc<AtomicBool>,
+    /// Current NodeLabel (writable by controllers)
+    node_label: RwLock<String>,
+    /// Dynamic reachable state (shared with parent DeviceSwitch and input source)
+    reachable: Arc<ReachableState>,
+    /// Last seen reachable version (for dataver sync)
+    last_reachable_version: AtomicU32,
+    /// Where to persist NodeLabel writes
+    persistence: Option<LabelPersistence>,
+    /// Callback for propagating NodeLabel writes to the input source
+    label_listener: Option<NodeLabelListener>,
+    /// Notifier for Matter subscription updates
+    notifier: RwLock<Option<ClusterNotifier>>,
 }
 
 impl BridgedHandler {
@@ -178,42 +393,111 @@ impl BridgedHandler {
     pub const CLUSTER: Cluster<'static> = CLUSTER;
 
     /// Create a new handler with device info and reachable state.
-    pub fn new(dataver: Dataver, info: BridgedDeviceInfo, reachable: Arc<AtomicBool>) -> Self {
+    pub fn new(dataver: Dataver, info: BridgedDeviceInfo, reachable: Arc<ReachableState>) -> Self {
         Self {
             dataver,
+            node_label: RwLock::new(info.node_label.clone()),
             info,
+            last_reachable_version: AtomicU32::new(reachable.version()),
             reachable,
+            persistence: None,
+            label_listener: None,
+            notifier: RwLock::new(None),
         }
     }
 
     /// Create a new handler that is always reachable (for parent endpoints).
     pub fn new_always_reachable(dataver: Dataver, info: BridgedDeviceInfo) -> Self {
-        Self {
-            dataver,
-            info,
-            reachable: Arc::new(AtomicBool::new(true)),
-        }
+        Self::new(dataver, info, Arc::new(ReachableState::new()))
     }
 
     /// Create a new handler with just a name (backwards compatible).
-    pub fn new_with_name(dataver: Dataver, name: &'static str, reachable: Arc<AtomicBool>) -> Self {
-        Self {
-            dataver,
-            info: BridgedDeviceInfo::new(name),
-            reachable,
-        }
+    pub fn new_with_name(
+        dataver: Dataver,
+        name: impl Into<String>,
+        reachable: Arc<ReachableState>,
+    ) -> Self {
+        Self::new(dataver, BridgedDeviceInfo::new(name), reachable)
     }
 
     /// Create a new handler with just a name, always reachable (backwards compatible).
-    pub fn new_with_name_always_reachable(dataver: Dataver, name: &'static str) -> Self {
-        Self {
-            dataver,
-            info: BridgedDeviceInfo::new(name),
-            reachable: Arc::new(AtomicBool::new(true)),
+    pub fn new_with_name_always_reachable(dataver: Dataver, name: impl Into<String>) -> Self {
+        Self::new_always_reachable(dataver, BridgedDeviceInfo::new(name))
+    }
+
+    /// Persist NodeLabel writes under `key` in the given store.
+    ///
+    /// If the store already holds a label for `key`, it replaces the configured label.
+    pub fn with_label_store(mut self, store: Arc<NodeLabelStore>, key: impl Into<String>) -> Self {
+        let key = key.into();
+        if let Some(label) = store.get(&key) {
+            *self.node_label.write() = label;
+        }
+        self.persistence = Some(LabelPersistence { store, key });
+        self
+    }
+
+    /// Register a callback that is invoked after a controller renames this node.
+    pub fn with_label_listener(mut self, listener: NodeLabelListener) -> Self {
+        self.label_listener = Some(listener);
+        self
+    }
+
+    /// Set a notifier for Matter subscription updates.
+    pub fn set_notifier(&self, notifier: ClusterNotifier) {
+        *self.notifier.write() = Some(notifier);
+    }
+
+    /// Get the shared reachable state.
+    pub fn reachable(&self) -> &Arc<ReachableState> {
+        &self.reachable
+    }
+
+    /// Get the current NodeLabel.
+    pub fn node_label(&self) -> String {
+        self.node_label.read().clone()
+    }
+
+    /// Update the NodeLabel, persist it and notify listeners.
+    ///
+    /// Returns `ConstraintError` if the label exceeds [`MAX_NODE_LABEL_LEN`] bytes.
+    pub fn set_node_label(&self, label: &str) -> Result<(), Error> {
+        if label.len() > MAX_NODE_LABEL_LEN {
+            return Err(ErrorCode::ConstraintError.into());
+        }
+
+        {
+            let mut current = self.node_label.write();
+            if *current == label {
+                return Ok(());
+            }
+            *current = label.to_string();
+        }
+
+        self.dataver.changed();
+        log::info!("[Matter] NodeLabel changed to '{}'", label);
+
+        if let Some(persistence) = &self.persistence {
+            persistence.store.set(&persistence.key, label);
+        }
+        if let Some(listener) = &self.label_listener {
+            listener(label);
+        }
+        if let Some(notifier) = self.notifier.read().as_ref() {
+            notifier.notify();
         }
+
+        Ok(())
     }
 
     fn read_impl(&self, ctx: impl ReadContext, reply: impl ReadReply) -> Result<(), Error> {
+        // Bump dataver when Reachable changed since the last read
+        sync_dataver_with_sensor(
+            &*self.reachable,
+            &self.last_reachable_version,
+            &self.dataver,
+        );
+
         let attr = ctx.attr();
 
         let Some(mut writer) = reply.with_dataver(self.dataver.get())? else {
@@ -231,21 +515,21 @@ impl BridgedHandler {
 
             match attr.attr_id.try_into()? {
                 BridgedDeviceBasicInfoAttribute::VendorName => {
-                    if let Some(vendor) = self.info.vendor_name {
+                    if let Some(vendor) = &self.info.vendor_name {
                         tw.utf8(tag, vendor)?;
                     } else {
                         tw.utf8(tag, "")?;
                     }
                 }
                 BridgedDeviceBasicInfoAttribute::ProductName => {
-                    if let Some(product) = self.info.product_name {
+                    if let Some(product) = &self.info.product_name {
                         tw.utf8(tag, product)?;
                     } else {
                         tw.utf8(tag, "")?;
                     }
                 }
                 BridgedDeviceBasicInfoAttribute::NodeLabel => {
-                    tw.utf8(tag, self.info.node_label)?;
+                    tw.utf8(tag, &self.node_label.read())?;
                 }
                 BridgedDeviceBasicInfoAttribute::HardwareVersion => {
                     tw.u16(tag, self.info.hardware_version.unwrap_or(0))?;
@@ -254,14 +538,14 @@ impl BridgedHandler {
                     tw.u32(tag, self.info.software_version.unwrap_or(0))?;
                 }
                 BridgedDeviceBasicInfoAttribute::SerialNumber => {
-                    if let Some(serial) = self.info.serial_number {
+                    if let Some(serial) = &self.info.serial_number {
                         tw.utf8(tag, serial)?;
                     } else {
                         tw.utf8(tag, "")?;
                     }
                 }
                 BridgedDeviceBasicInfoAttribute::Reachable => {
-                    tw.bool(tag, self.reachable.load(Ordering::SeqCst))?;
+                    tw.bool(tag, self.reachable.is_reachable())?;
                 }
             }
         }
@@ -269,9 +553,18 @@ impl BridgedHandler {
         writer.complete()
     }
 
-    fn write_impl(&self, _ctx: impl WriteContext) -> Result<(), Error> {
-        // Cluster is read-only
-        Err(ErrorCode::UnsupportedAccess.into())
+    fn write_impl(&self, ctx: impl WriteContext) -> Result<(), Error> {
+        let attr = ctx.attr();
+        attr.check_dataver(self.dataver.get())?;
+
+        match attr.attr_id.try_into()? {
+            BridgedDeviceBasicInfoAttribute::NodeLabel => {
+                let data = ctx.data();
+                self.set_node_label(data.utf8()?)
+            }
+            // All other attributes are read-only
+            _ => Err(ErrorCode::UnsupportedAccess.into()),
+        }
     }
 }
 
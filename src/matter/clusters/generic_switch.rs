@@ -6,17 +6,28 @@
 //! ## Features Supported
 //! - Momentary Switch (MS) - Button that returns to default position when released
 //! - Momentary Switch Release (MSR) - Generates events on button release
+//! - Momentary Switch Long Press (MSL) - Long press detection
 //! - Momentary Switch Multi Press (MSM) - Multi-press detection
+//! - Latching Switch (LS) - Rocker or rotary switch with 2 or more positions
+//!   (exclusive with the momentary features, see [`LATCHING_CLUSTER`])
 //!
 //! ## Events
+//! - SwitchLatched (0x00) - Latching switch moved to a new position
 //! - InitialPress (0x01) - Button pressed down
+//! - LongPress (0x02) - Button held past the long press threshold
 //! - ShortRelease (0x03) - Button released after short press
+//! - LongRelease (0x04) - Button released after a long press
+//! - MultiPressOngoing (0x05) - Another press of a running multi-press sequence
 //! - MultiPressComplete (0x06) - Multi-press sequence completed
+//!
+//! Sources that only know raw press/release edges derive these events with
+//! [`SwitchGestures`](super::switch_gestures::SwitchGestures).
 
 use crate::matter::endpoints::ClusterNotifier;
 use parking_lot::{Mutex, RwLock};
 use rs_matter::dm::clusters::generic_switch::{
-    encode_initial_press, encode_multi_press_complete, encode_short_release, events,
+    encode_initial_press, encode_long_press, encode_long_release, encode_multi_press_complete,
+    encode_multi_press_ongoing, encode_short_release, encode_switch_latched, events,
 };
 use rs_matter::dm::{
     Access, Attribute, Cluster, Dataver, Handler, NonBlockingHandler, Quality, ReadContext,
@@ -38,6 +49,9 @@ pub const CLUSTER_ID: u32 = 0x003B;
 /// Cluster revision
 pub const CLUSTER_REVISION: u16 = 2;
 
+/// Default MultiPressMax (double and triple presses)
+pub const DEFAULT_MULTI_PRESS_MAX: u8 = 3;
+
 /// Feature flags for GenericSwitch
 pub mod features {
     /// Latching Switch feature (LS)
@@ -56,9 +70,9 @@ pub mod features {
 #[derive(Clone, Copy, Debug, Eq, PartialEq, FromRepr)]
 #[repr(u32)]
 pub enum GenericSwitchAttribute {
-    /// Number of switch positions (always 2 for momentary)
+    /// Number of switch positions (2 for momentary, 2 or more for latching)
     NumberOfPositions = 0x0000,
-    /// Current switch position (0 = released, 1 = pressed)
+    /// Current switch position (momentary: 0 = released, 1 = pressed)
     CurrentPosition = 0x0001,
     /// Maximum number of presses for multi-press
     MultiPressMax = 0x0002,
@@ -66,13 +80,14 @@ pub enum GenericSwitchAttribute {
 
 attribute_enum!(GenericSwitchAttribute);
 
-/// Cluster metadata definition for GenericSwitch with MS+MSR+MSM features
+/// Cluster metadata definition for GenericSwitch with MS+MSR+MSL+MSM features
 pub const CLUSTER: Cluster<'static> = Cluster {
     id: CLUSTER_ID,
     revision: CLUSTER_REVISION,
-    // MS (0x02) + MSR (0x04) + MSM (0x10) = 0x16
+    // MS (0x02) + MSR (0x04) + MSL (0x08) + MSM (0x10) = 0x1E
     feature_map: features::MOMENTARY_SWITCH
         | features::MOMENTARY_SWITCH_RELEASE
+        | features::MOMENTARY_SWITCH_LONG_PRESS
         | features::MOMENTARY_SWITCH_MULTI_PRESS,
     attributes: attributes!(
         Attribute::new(
@@ -96,6 +111,47 @@ pub const CLUSTER: Cluster<'static> = Cluster {
     with_cmds: with!(all),
 };
 
+/// Cluster metadata definition for a latching GenericSwitch (LS feature)
+pub const LATCHING_CLUSTER: Cluster<'static> = Cluster {
+    id: CLUSTER_ID,
+    revision: CLUSTER_REVISION,
+    // LS (0x01), mutually exclusive with the momentary features
+    feature_map: features::LATCHING_SWITCH,
+    attributes: attributes!(
+        Attribute::new(
+            GenericSwitchAttribute::NumberOfPositions as _,
+            Access::RV,
+            Quality::FIXED
+        ),
+        Attribute::new(
+            GenericSwitchAttribute::CurrentPosition as _,
+            Access::RV,
+            Quality::NONE
+        ),
+    ),
+    commands: &[],
+    with_attrs: with!(all),
+    with_cmds: with!(all),
+};
+
+/// GenericSwitch event, as passed to event listeners.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwitchEvent {
+    /// Latching switch moved to a position
+    SwitchLatched(u8),
+    InitialPress,
+    LongPress,
+    ShortRelease,
+    LongRelease,
+    /// Press count of a running sequence
+    MultiPressOngoing(u8),
+    /// Total press count of a finished sequence
+    MultiPressComplete(u8),
+}
+
+/// Callback invoked for every event recorded on a switch.
+pub type SwitchEventListener = Arc<dyn Fn(SwitchEvent) + Send + Sync>;
+
 /// GenericSwitch state that can be shared and updated from external sources.
 pub struct GenericSwitchState {
     /// Current position (0 = released, 1 = pressed)
@@ -110,11 +166,31 @@ pub struct GenericSwitchState {
     endpoint_id: AtomicU8,
     /// Notifier for Matter subscription updates
     notifier: RwLock<Option<ClusterNotifier>>,
+    /// Maximum multi-press count (MultiPressMax attribute)
+    multi_press_max: AtomicU8,
+    /// Whether the switch stays in its position (rocker, rotary dial)
+    latching: bool,
+    /// Number of positions (NumberOfPositions attribute)
+    number_of_positions: u8,
+    /// Local listeners (e.g. bindings), called after the event is queued
+    listeners: RwLock<Vec<SwitchEventListener>>,
 }
 
 impl GenericSwitchState {
-    /// Create a new GenericSwitch state.
+    /// Create a new momentary GenericSwitch state (button).
     pub fn new() -> Self {
+        Self::with_mode(false, 2)
+    }
+
+    /// Create a latching switch with `number_of_positions` positions (at least 2).
+    ///
+    /// The source drives the position with [`set_position`](Self::set_position); the
+    /// switch starts in position 0.
+    pub fn latching(number_of_positions: u8) -> Self {
+        Self::with_mode(true, number_of_positions.max(2))
+    }
+
+    fn with_mode(latching: bool, number_of_positions: u8) -> Self {
         Self {
             current_position: AtomicU8::new(0),
             event_number: EventNumberGenerator::new(),
@@ -122,9 +198,23 @@ impl GenericSwitchState {
             start_time: Instant::now(),
             endpoint_id: AtomicU8::new(0),
             notifier: RwLock::new(None),
+            multi_press_max: AtomicU8::new(DEFAULT_MULTI_PRESS_MAX),
+            latching,
+            number_of_positions,
+            listeners: RwLock::new(Vec::new()),
         }
     }
 
+    /// Whether this is a latching switch.
+    pub fn is_latching(&self) -> bool {
+        self.latching
+    }
+
+    /// Number of positions of the switch.
+    pub fn number_of_positions(&self) -> u8 {
+        self.number_of_positions
+    }
+
     /// Set a notifier for Matter subscription updates.
     ///
     /// When events are recorded, the notifier will signal the Matter stack
@@ -133,6 +223,11 @@ impl GenericSwitchState {
         *self.notifier.write() = Some(notifier);
     }
 
+    /// Add a listener called for every event recorded on this switch.
+    pub fn add_event_listener(&self, listener: SwitchEventListener) {
+        self.listeners.write().push(listener);
+    }
+
     /// Notify the Matter stack that an event occurred.
     fn notify(&self) {
         if let Some(notifier) = self.notifier.read().as_ref() {
@@ -160,26 +255,37 @@ impl GenericSwitchState {
         self.endpoint_id.load(Ordering::SeqCst) as u16
     }
 
-    /// Record an InitialPress event (button pressed down).
-    pub fn press(&self) {
-        self.current_position.store(1, Ordering::SeqCst);
-
-        let payload: heapless::Vec<u8, 16> = encode_initial_press(1);
-        let event = PendingEvent::with_payload(
+    /// Queue an event with an encoded payload and notify subscribers.
+    fn record(&self, event: SwitchEvent, event_id: u32, payload: &[u8]) {
+        let pending = PendingEvent::with_payload(
             self.get_endpoint_id(),
             CLUSTER_ID,
-            events::INITIAL_PRESS,
+            event_id,
             self.event_number.next(),
             EventPriority::Info,
             self.elapsed_ms(),
-            &payload,
+            payload,
         );
 
         let mut events = self.pending_events.lock();
-        events.push(event).ok();
+        events.push(pending).ok();
+        drop(events);
 
         // Notify subscription system that an event occurred
         self.notify();
+
+        let listeners = self.listeners.read().clone();
+        for listener in listeners {
+            listener(event);
+        }
+    }
+
+    /// Record an InitialPress event (button pressed down).
+    pub fn press(&self) {
+        self.current_position.store(1, Ordering::SeqCst);
+
+        let payload: heapless::Vec<u8, 16> = encode_initial_press(1);
+        self.record(SwitchEvent::InitialPress, events::INITIAL_PRESS, &payload);
     }
 
     /// Record a ShortRelease event (button released after short press).
@@ -187,59 +293,100 @@ impl GenericSwitchState {
         let prev_position = self.current_position.swap(0, Ordering::SeqCst);
 
         let payload: heapless::Vec<u8, 16> = encode_short_release(prev_position);
-        let event = PendingEvent::with_payload(
-            self.get_endpoint_id(),
-            CLUSTER_ID,
-            events::SHORT_RELEASE,
-            self.event_number.next(),
-            EventPriority::Info,
-            self.elapsed_ms(),
-            &payload,
-        );
+        self.record(SwitchEvent::ShortRelease, events::SHORT_RELEASE, &payload);
+    }
 
-        let mut events = self.pending_events.lock();
-        events.push(event).ok();
+    /// Record a LongPress event (button still pressed after the long press threshold).
+    pub fn long_press(&self) {
+        self.current_position.store(1, Ordering::SeqCst);
 
-        // Notify subscription system that an event occurred
-        self.notify();
+        let payload: heapless::Vec<u8, 16> = encode_long_press(1);
+        self.record(SwitchEvent::LongPress, events::LONG_PRESS, &payload);
+    }
+
+    /// Record a LongRelease event (button released after a long press).
+    pub fn long_release(&self) {
+        let prev_position = self.current_position.swap(0, Ordering::SeqCst);
+
+        let payload: heapless::Vec<u8, 16> = encode_long_release(prev_position);
+        self.record(SwitchEvent::LongRelease, events::LONG_RELEASE, &payload);
     }
 
-    /// Record a single press (InitialPress + ShortRelease).
+    /// Record a MultiPressOngoing event (press `count` of a running sequence, count >= 2).
+    pub fn multi_press_ongoing(&self, count: u8) {
+        let payload: heapless::Vec<u8, 16> = encode_multi_press_ongoing(1, count);
+        self.record(
+            SwitchEvent::MultiPressOngoing(count),
+            events::MULTI_PRESS_ONGOING,
+            &payload,
+        );
+    }
+
+    /// Record a single press (InitialPress + ShortRelease + MultiPressComplete with count=1).
     pub fn single_press(&self) {
         self.press();
         self.release();
+        self.multi_press(1);
     }
 
     /// Record a double press (MultiPressComplete with count=2).
     pub fn double_press(&self) {
+        self.multi_press(2);
+    }
+
+    /// Record a completed multi-press sequence (MultiPressComplete with `count`).
+    ///
+    /// `count` should not exceed [`multi_press_max`](Self::multi_press_max); 0 reports a
+    /// sequence that exceeded it.
+    pub fn multi_press(&self, count: u8) {
         self.current_position.store(0, Ordering::SeqCst);
 
-        let payload: heapless::Vec<u8, 16> = encode_multi_press_complete(1, 2);
-        let event = PendingEvent::with_payload(
-            self.get_endpoint_id(),
-            CLUSTER_ID,
+        let payload: heapless::Vec<u8, 16> = encode_multi_press_complete(1, count);
+        self.record(
+            SwitchEvent::MultiPressComplete(count),
             events::MULTI_PRESS_COMPLETE,
-            self.event_number.next(),
-            EventPriority::Info,
-            self.elapsed_ms(),
             &payload,
         );
+    }
 
-        let mut events = self.pending_events.lock();
-        events.push(event).ok();
-
-        // Notify subscription system that an event occurred
-        self.notify();
+    /// Move a latching switch to `position` and record a SwitchLatched event.
+    ///
+    /// Returns false when the position is out of range. Setting the current position
+    /// again records nothing.
+    pub fn set_position(&self, position: u8) -> bool {
+        if position >= self.number_of_positions {
+            return false;
+        }
+        if self.current_position.swap(position, Ordering::SeqCst) != position {
+            let payload: heapless::Vec<u8, 16> = encode_switch_latched(position);
+            self.record(
+                SwitchEvent::SwitchLatched(position),
+                events::SWITCH_LATCHED,
+                &payload,
+            );
+        }
+        true
     }
 
-    /// Record a hold start (InitialPress, kept pressed).
+    /// Record a hold start (InitialPress + LongPress, kept pressed).
     pub fn hold_start(&self) {
         self.press();
+        self.long_press();
     }
 
-    /// Record a hold release (ShortRelease after hold).
+    /// Record a hold release (LongRelease after hold).
     pub fn hold_release(&self) {
-        self.release();
+        self.long_release();
+    }
+
+    /// Maximum number of presses in a multi-press sequence (MultiPressMax attribute).
+    pub fn multi_press_max(&self) -> u8 {
+        self.multi_press_max.load(Ordering::SeqCst)
+    }
+
+    /// Set the MultiPressMax attribute (at least 2, before the endpoint is served).
+    pub fn set_multi_press_max(&self, max: u8) {
+        self.multi_press_max.store(max.max(2), Ordering::SeqCst);
     }
 }
 
@@ -268,23 +415,26 @@ impl EventSource for GenericSwitchState {
 pub struct GenericSwitchHandler {
     dataver: Dataver,
     state: Arc<GenericSwitchState>,
-    /// Number of positions (always 2 for momentary switch)
-    num_positions: u8,
-    /// Maximum multi-press count
-    multi_press_max: u8,
 }
 
 impl GenericSwitchHandler {
     /// Cluster definition for use in the data model
     pub const CLUSTER: Cluster<'static> = CLUSTER;
 
+    /// Cluster definition of latching switches
+    pub const LATCHING_CLUSTER: Cluster<'static> = LATCHING_CLUSTER;
+
     /// Create a new handler with a shared state.
     pub fn new(dataver: Dataver, state: Arc<GenericSwitchState>) -> Self {
-        Self {
-            dataver,
-            state,
-            num_positions: 2,
-            multi_press_max: 2,
+        Self { dataver, state }
+    }
+
+    /// Cluster definition matching the switch type of the state.
+    fn cluster(&self) -> Cluster<'static> {
+        if self.state.is_latching() {
+            LATCHING_CLUSTER
+        } else {
+            CLUSTER
         }
     }
 
@@ -302,7 +452,7 @@ impl GenericSwitchHandler {
 
         // Global attributes
         if attr.is_system() {
-            return CLUSTER.read(attr, writer);
+            return self.cluster().read(attr, writer);
         }
 
         let tag = writer.tag();
@@ -311,13 +461,13 @@ impl GenericSwitchHandler {
 
             match attr.attr_id.try_into()? {
                 GenericSwitchAttribute::NumberOfPositions => {
-                    tw.u8(tag, self.num_positions)?;
+                    tw.u8(tag, self.state.number_of_positions())?;
                 }
                 GenericSwitchAttribute::CurrentPosition => {
                     tw.u8(tag, self.state.current_position())?;
                 }
                 GenericSwitchAttribute::MultiPressMax => {
-                    tw.u8(tag, self.multi_press_max)?;
+                    tw.u8(tag, self.state.multi_press_max())?;
                 }
             }
         }
@@ -342,3 +492,49 @@ impl Handler for GenericSwitchHandler {
 }
 
 impl NonBlockingHandler for GenericSwitchHandler {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Latching switch that collects its recorded events.
+    fn latching_switch(positions: u8) -> (GenericSwitchState, Arc<Mutex<Vec<SwitchEvent>>>) {
+        let state = GenericSwitchState::latching(positions);
+        let recorded = Arc::new(Mutex::new(Vec::new()));
+        let sink = recorded.clone();
+        state.add_event_listener(Arc::new(move |event| sink.lock().push(event)));
+        (state, recorded)
+    }
+
+    #[test]
+    fn test_latching_positions() {
+        assert_eq!(GenericSwitchState::latching(0).number_of_positions(), 2);
+        assert_eq!(GenericSwitchState::latching(1).number_of_positions(), 2);
+        assert_eq!(GenericSwitchState::latching(3).number_of_positions(), 3);
+        assert!(GenericSwitchState::latching(3).is_latching());
+        assert!(!GenericSwitchState::new().is_latching());
+    }
+
+    #[test]
+    fn test_set_position() {
+        let (state, recorded) = latching_switch(3);
+        assert_eq!(state.current_position(), 0);
+
+        // Positions at or above NumberOfPositions are rejected without an event
+        assert!(!state.set_position(3));
+        assert!(!state.set_position(u8::MAX));
+        assert_eq!(state.current_position(), 0);
+        assert!(!state.has_pending_events());
+
+        assert!(state.set_position(2));
+        assert_eq!(state.current_position(), 2);
+        // Setting the same position again is accepted but records nothing
+        assert!(state.set_position(2));
+        assert!(state.set_position(1));
+        assert_eq!(
+            *recorded.lock(),
+            [SwitchEvent::SwitchLatched(2), SwitchEvent::SwitchLatched(1)]
+        );
+        assert_eq!(state.take_pending_events().len(), 2);
+    }
+}
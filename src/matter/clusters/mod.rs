@@ -11,22 +11,36 @@ use super::endpoints::endpoints_helpers::Sensor;
 use rs_matter::dm::Dataver;
 use std::sync::atomic::{AtomicU32, Ordering};
 
+pub mod binding;
 pub mod boolean_state;
 pub mod bridged_device_basic_info;
 pub mod camera_av_stream_mgmt;
 pub mod generic_switch;
+pub mod network_diagnostics;
 pub mod occupancy_sensing;
+pub mod power_source;
 pub mod relative_humidity;
+pub mod software_diagnostics;
+pub mod switch_gestures;
 pub mod temperature_measurement;
 pub mod time_sync;
 pub mod webrtc_transport_provider;
 
 // Re-export for convenience
+pub use binding::BindingHandler;
 pub use boolean_state::BooleanStateHandler;
-pub use bridged_device_basic_info::{BridgedDeviceInfo, BridgedHandler};
-pub use generic_switch::{GenericSwitchHandler, GenericSwitchState};
+pub use bridged_device_basic_info::{
+    BridgedDeviceInfo, BridgedHandler, NodeLabelListener, ReachableState,
+};
+pub use generic_switch::{
+    GenericSwitchHandler, GenericSwitchState, SwitchEvent, SwitchEventListener,
+};
+pub use network_diagnostics::NetworkDiagnosticsHandler;
 pub use occupancy_sensing::OccupancySensingHandler;
+pub use power_source::{PowerSource, PowerSourceHandler};
 pub use relative_humidity::{HumiditySensor, RelativeHumidityHandler};
+pub use software_diagnostics::SoftwareDiagnosticsHandler;
+pub use switch_gestures::{GestureConfig, SwitchGestures};
 pub use temperature_measurement::{TemperatureMeasurementHandler, TemperatureSensor};
 pub use time_sync::TimeSyncHandler;
 // TODO: Re-export when handlers are wired in stack.rs
@@ -0,0 +1,454 @@
+//! Ethernet / WiFi Network Diagnostics cluster handler for rs-matter.
+//!
+//! Serves the network diagnostics cluster for the interface the bridge uses: Ethernet
+//! Network Diagnostics for wired interfaces and WiFi Network Diagnostics for wireless
+//! ones. Values are read from `/sys/class/net/<interface>` and `/proc/net/wireless`.
+//!
+//! The kernel counters cannot be reset, so ResetCounts records a baseline that is
+//! subtracted from later reads.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use parking_lot::Mutex;
+use rs_matter::dm::{
+    Access, Attribute, Cluster, Command, Dataver, Handler, InvokeContext, InvokeReply,
+    NonBlockingHandler, Quality, ReadContext, ReadReply, Reply, WriteContext,
+};
+use rs_matter::error::{Error, ErrorCode};
+use rs_matter::tlv::TLVWrite;
+use rs_matter::{attribute_enum, attributes, command_enum, commands, with};
+use strum::FromRepr;
+
+use super::super::netif::is_wireless;
+
+/// Matter Cluster ID for WiFi Network Diagnostics
+pub const WIFI_CLUSTER_ID: u32 = 0x0036;
+
+/// Matter Cluster ID for Ethernet Network Diagnostics
+pub const ETHERNET_CLUSTER_ID: u32 = 0x0037;
+
+/// Cluster revision
+pub const CLUSTER_REVISION: u16 = 1;
+
+/// Ethernet feature map: Packet Counts (PKTCNT) and Error Counts (ERRCNT)
+pub const ETHERNET_FEATURES: u32 = 0x01 | 0x02;
+
+/// Kernel network interface directory
+const SYS_CLASS_NET: &str = "/sys/class/net";
+
+/// Kernel wireless statistics
+const PROC_NET_WIRELESS: &str = "/proc/net/wireless";
+
+/// Attribute IDs for the Ethernet Network Diagnostics cluster
+#[derive(Clone, Copy, Debug, Eq, PartialEq, FromRepr)]
+#[repr(u32)]
+pub enum EthernetAttribute {
+    /// Link speed (PHYRateEnum, nullable)
+    PhyRate = 0x00,
+    /// Full duplex link (nullable)
+    FullDuplex = 0x01,
+    /// Received packets
+    PacketRxCount = 0x02,
+    /// Transmitted packets
+    PacketTxCount = 0x03,
+    /// Transmit errors
+    TxErrCount = 0x04,
+    /// Collisions
+    CollisionCount = 0x05,
+    /// Dropped packets due to receive overruns
+    OverrunCount = 0x06,
+    /// Carrier detected (nullable)
+    CarrierDetect = 0x07,
+    /// Minutes since the counters were reset
+    TimeSinceReset = 0x08,
+}
+
+attribute_enum!(EthernetAttribute);
+
+/// Attribute IDs for the WiFi Network Diagnostics cluster
+#[derive(Clone, Copy, Debug, Eq, PartialEq, FromRepr)]
+#[repr(u32)]
+pub enum WiFiAttribute {
+    /// BSSID of the access point (nullable)
+    Bssid = 0x00,
+    /// Security type (nullable)
+    SecurityType = 0x01,
+    /// WiFi version (nullable)
+    WiFiVersion = 0x02,
+    /// Channel number (nullable)
+    ChannelNumber = 0x03,
+    /// Signal level in dBm (nullable)
+    Rssi = 0x04,
+}
+
+attribute_enum!(WiFiAttribute);
+
+/// Command IDs for the Ethernet Network Diagnostics cluster
+#[derive(Clone, Copy, Debug, Eq, PartialEq, FromRepr)]
+#[repr(u32)]
+pub enum EthernetCommand {
+    /// Reset the packet and error counters
+    ResetCounts = 0x00,
+}
+
+command_enum!(EthernetCommand);
+
+/// Ethernet Network Diagnostics cluster metadata
+pub const ETHERNET_CLUSTER: Cluster<'static> = Cluster {
+    id: ETHERNET_CLUSTER_ID,
+    revision: CLUSTER_REVISION,
+    feature_map: ETHERNET_FEATURES,
+    attributes: attributes!(
+        Attribute::new(
+            EthernetAttribute::PhyRate as _,
+            Access::RV,
+            Quality::NULLABLE
+        ),
+        Attribute::new(
+            EthernetAttribute::FullDuplex as _,
+            Access::RV,
+            Quality::NULLABLE
+        ),
+        Attribute::new(
+            EthernetAttribute::PacketRxCount as _,
+            Access::RV,
+            Quality::NONE
+        ),
+        Attribute::new(
+            EthernetAttribute::PacketTxCount as _,
+            Access::RV,
+            Quality::NONE
+        ),
+        Attribute::new(
+            EthernetAttribute::TxErrCount as _,
+            Access::RV,
+            Quality::NONE
+        ),
+        Attribute::new(
+            EthernetAttribute::CollisionCount as _,
+            Access::RV,
+            Quality::NONE
+        ),
+        Attribute::new(
+            EthernetAttribute::OverrunCount as _,
+            Access::RV,
+            Quality::NONE
+        ),
+        Attribute::new(
+            EthernetAttribute::CarrierDetect as _,
+            Access::RV,
+            Quality::NULLABLE
+        ),
+        Attribute::new(
+            EthernetAttribute::TimeSinceReset as _,
+            Access::RV,
+            Quality::NONE
+        ),
+    ),
+    commands: commands!(Command::new(
+        EthernetCommand::ResetCounts as _,
+        None,
+        Access::WO
+    ),),
+    with_attrs: with!(all),
+    with_cmds: with!(all),
+};
+
+/// WiFi Network Diagnostics cluster metadata (no optional features)
+pub const WIFI_CLUSTER: Cluster<'static> = Cluster {
+    id: WIFI_CLUSTER_ID,
+    revision: CLUSTER_REVISION,
+    feature_map: 0,
+    attributes: attributes!(
+        Attribute::new(WiFiAttribute::Bssid as _, Access::RV, Quality::NULLABLE),
+        Attribute::new(
+            WiFiAttribute::SecurityType as _,
+            Access::RV,
+            Quality::NULLABLE
+        ),
+        Attribute::new(
+            WiFiAttribute::WiFiVersion as _,
+            Access::RV,
+            Quality::NULLABLE
+        ),
+        Attribute::new(
+            WiFiAttribute::ChannelNumber as _,
+            Access::RV,
+            Quality::NULLABLE
+        ),
+        Attribute::new(WiFiAttribute::Rssi as _, Access::RV, Quality::NULLABLE),
+    ),
+    commands: commands!(),
+    with_attrs: with!(all),
+    with_cmds: with!(all),
+};
+
+/// Packet and error counters of an interface.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InterfaceCounters {
+    pub rx_packets: u64,
+    pub tx_packets: u64,
+    pub tx_errors: u64,
+    pub collisions: u64,
+    pub rx_over_errors: u64,
+}
+
+impl InterfaceCounters {
+    /// Read the counters from a `/sys/class/net/<interface>` directory.
+    pub fn read(dir: &Path) -> Self {
+        let counter = |name: &str| read_value(&dir.join("statistics").join(name)).unwrap_or(0);
+        Self {
+            rx_packets: counter("rx_packets"),
+            tx_packets: counter("tx_packets"),
+            tx_errors: counter("tx_errors"),
+            collisions: counter("collisions"),
+            rx_over_errors: counter("rx_over_errors"),
+        }
+    }
+
+    /// Counters relative to `baseline` (a counter that wrapped restarts from zero).
+    pub fn since(&self, baseline: &Self) -> Self {
+        let delta = |current: u64, base: u64| current.checked_sub(base).unwrap_or(current);
+        Self {
+            rx_packets: delta(self.rx_packets, baseline.rx_packets),
+            tx_packets: delta(self.tx_packets, baseline.tx_packets),
+            tx_errors: delta(self.tx_errors, baseline.tx_errors),
+            collisions: delta(self.collisions, baseline.collisions),
+            rx_over_errors: delta(self.rx_over_errors, baseline.rx_over_errors),
+        }
+    }
+}
+
+/// Read a single sysfs value (`None` if missing or not available, e.g. `speed` on a
+/// link that is down).
+fn read_value<T: std::str::FromStr>(path: &Path) -> Option<T> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Map a link speed in Mb/s to the Matter PHYRateEnum.
+pub fn phy_rate(speed_mbps: u64) -> Option<u8> {
+    match speed_mbps {
+        10 => Some(0),
+        100 => Some(1),
+        1_000 => Some(2),
+        2_500 => Some(3),
+        5_000 => Some(4),
+        10_000 => Some(5),
+        40_000 => Some(6),
+        100_000 => Some(7),
+        200_000 => Some(8),
+        400_000 => Some(9),
+        _ => None,
+    }
+}
+
+/// Parse the signal level of `interface` from `/proc/net/wireless`.
+pub fn parse_wireless_rssi(contents: &str, interface: &str) -> Option<i8> {
+    contents.lines().find_map(|line| {
+        let rest = line
+            .trim_start()
+            .strip_prefix(interface)?
+            .strip_prefix(':')?;
+        // status, link quality, signal level, ...
+        let level = rest.split_whitespace().nth(2)?;
+        let level: f32 = level.trim_end_matches('.').parse().ok()?;
+        (-128.0..=0.0).contains(&level).then_some(level as i8)
+    })
+}
+
+/// Get the network diagnostics cluster matching an interface.
+pub fn cluster_for(interface: &str) -> Cluster<'static> {
+    if is_wireless(interface) {
+        WIFI_CLUSTER
+    } else {
+        ETHERNET_CLUSTER
+    }
+}
+
+/// Counter baseline set at start and by ResetCounts.
+struct Baseline {
+    counters: InterfaceCounters,
+    at: Instant,
+}
+
+/// Handler that serves the network diagnostics cluster of one interface.
+pub struct NetworkDiagnosticsHandler {
+    dataver: Dataver,
+    interface: &'static str,
+    sys_dir: PathBuf,
+    cluster: Cluster<'static>,
+    baseline: Mutex<Baseline>,
+}
+
+impl NetworkDiagnosticsHandler {
+    /// Create a new handler for `interface`.
+    pub fn new(dataver: Dataver, interface: &'static str) -> Self {
+        let sys_dir = Path::new(SYS_CLASS_NET).join(interface);
+        Self {
+            dataver,
+            interface,
+            baseline: Mutex::new(Baseline {
+                counters: InterfaceCounters::read(&sys_dir),
+                at: Instant::now(),
+            }),
+            sys_dir,
+            cluster: cluster_for(interface),
+        }
+    }
+
+    /// Cluster definition served by this handler (Ethernet or WiFi)
+    pub fn cluster(&self) -> &Cluster<'static> {
+        &self.cluster
+    }
+
+    fn read_impl(&self, ctx: impl ReadContext, reply: impl ReadReply) -> Result<(), Error> {
+        let attr = ctx.attr();
+
+        let Some(mut writer) = reply.with_dataver(self.dataver.get())? else {
+            return Ok(()); // No update needed
+        };
+
+        // Global attributes
+        if attr.is_system() {
+            return self.cluster.read(attr, writer);
+        }
+
+        let tag = writer.tag();
+        {
+            let mut tw = writer.writer();
+
+            if self.cluster.id == WIFI_CLUSTER_ID {
+                match attr.attr_id.try_into()? {
+                    WiFiAttribute::Rssi => {
+                        let rssi = fs::read_to_string(PROC_NET_WIRELESS)
+                            .ok()
+                            .and_then(|contents| parse_wireless_rssi(&contents, self.interface));
+                        match rssi {
+                            Some(rssi) => tw.i8(tag, rssi)?,
+                            None => tw.null(tag)?,
+                        }
+                    }
+                    WiFiAttribute::Bssid
+                    | WiFiAttribute::SecurityType
+                    | WiFiAttribute::WiFiVersion
+                    | WiFiAttribute::ChannelNumber => {
+                        tw.null(tag)?;
+                    }
+                }
+            } else {
+                let attribute: EthernetAttribute = attr.attr_id.try_into()?;
+                let baseline = self.baseline.lock();
+                let counters = InterfaceCounters::read(&self.sys_dir).since(&baseline.counters);
+                match attribute {
+                    EthernetAttribute::PhyRate => {
+                        match read_value(&self.sys_dir.join("speed")).and_then(phy_rate) {
+                            Some(rate) => tw.u8(tag, rate)?,
+                            None => tw.null(tag)?,
+                        }
+                    }
+                    EthernetAttribute::FullDuplex => {
+                        match read_value::<String>(&self.sys_dir.join("duplex")).as_deref() {
+                            Some("full") => tw.bool(tag, true)?,
+                            Some("half") => tw.bool(tag, false)?,
+                            _ => tw.null(tag)?,
+                        }
+                    }
+                    EthernetAttribute::PacketRxCount => tw.u64(tag, counters.rx_packets)?,
+                    EthernetAttribute::PacketTxCount => tw.u64(tag, counters.tx_packets)?,
+                    EthernetAttribute::TxErrCount => tw.u64(tag, counters.tx_errors)?,
+                    EthernetAttribute::CollisionCount => tw.u64(tag, counters.collisions)?,
+                    EthernetAttribute::OverrunCount => tw.u64(tag, counters.rx_over_errors)?,
+                    EthernetAttribute::CarrierDetect => {
+                        match read_value::<u8>(&self.sys_dir.join("carrier")) {
+                            Some(carrier) => tw.bool(tag, carrier != 0)?,
+                            None => tw.null(tag)?,
+                        }
+                    }
+                    EthernetAttribute::TimeSinceReset => {
+                        tw.u64(tag, baseline.at.elapsed().as_secs() / 60)?;
+                    }
+                }
+            }
+        }
+
+        writer.complete()
+    }
+
+    fn write_impl(&self, _ctx: impl WriteContext) -> Result<(), Error> {
+        Err(ErrorCode::UnsupportedAccess.into())
+    }
+
+    fn invoke_impl(&self, ctx: impl InvokeContext, _reply: impl InvokeReply) -> Result<(), Error> {
+        if self.cluster.id != ETHERNET_CLUSTER_ID {
+            return Err(ErrorCode::CommandNotFound.into());
+        }
+        match ctx.cmd().cmd_id.try_into()? {
+            EthernetCommand::ResetCounts => {
+                *self.baseline.lock() = Baseline {
+                    counters: InterfaceCounters::read(&self.sys_dir),
+                    at: Instant::now(),
+                };
+                self.dataver.changed();
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Handler for NetworkDiagnosticsHandler {
+    fn read(&self, ctx: impl ReadContext, reply: impl ReadReply) -> Result<(), Error> {
+        self.read_impl(ctx, reply)
+    }
+
+    fn write(&self, ctx: impl WriteContext) -> Result<(), Error> {
+        self.write_impl(ctx)
+    }
+
+    fn invoke(&self, ctx: impl InvokeContext, reply: impl InvokeReply) -> Result<(), Error> {
+        self.invoke_impl(ctx, reply)
+    }
+}
+
+impl NonBlockingHandler for NetworkDiagnosticsHandler {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counters_since_baseline() {
+        let dir = tempfile::tempdir().unwrap();
+        let stats = dir.path().join("statistics");
+        fs::create_dir_all(&stats).unwrap();
+        fs::write(stats.join("rx_packets"), "1000\n").unwrap();
+        fs::write(stats.join("tx_packets"), "500\n").unwrap();
+        fs::write(stats.join("tx_errors"), "2\n").unwrap();
+
+        let baseline = InterfaceCounters::read(dir.path());
+        assert_eq!(baseline.rx_packets, 1000);
+        // Missing counters read as zero
+        assert_eq!(baseline.collisions, 0);
+
+        fs::write(stats.join("rx_packets"), "1500\n").unwrap();
+        fs::write(stats.join("tx_packets"), "20\n").unwrap(); // wrapped
+        let counters = InterfaceCounters::read(dir.path()).since(&baseline);
+        assert_eq!(counters.rx_packets, 500);
+        assert_eq!(counters.tx_packets, 20);
+        assert_eq!(counters.tx_errors, 0);
+    }
+
+    #[test]
+    fn test_phy_rate_and_rssi() {
+        assert_eq!(phy_rate(1000), Some(2));
+        assert_eq!(phy_rate(2500), Some(3));
+        assert_eq!(phy_rate(42), None);
+
+        let wireless = "Inter-| sta-|   Quality        |   Discarded packets               | Missed | WE\n \
+                        face | tus | link level noise |  nwid  crypt   frag  retry   misc | beacon | 22\n \
+                        wlan0: 0000   70.  -40.  -256        0      0      0      0      0        0\n";
+        assert_eq!(parse_wireless_rssi(wireless, "wlan0"), Some(-40));
+        assert_eq!(parse_wireless_rssi(wireless, "wlan1"), None);
+    }
+}
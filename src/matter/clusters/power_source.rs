@@ -0,0 +1,342 @@
+//! PowerSource cluster handler.
+//!
+//! The PowerSource cluster (0x002F) describes how an endpoint is powered.
+//! Only the Battery (BAT) feature is supported, for battery-powered bridged sensors.
+//!
+//! Battery level is reported in half-percent units (value * 2).
+//! For example: 87.5% is reported as 175.
+
+use crate::matter::endpoints::ClusterNotifier;
+use crate::matter::runtime_state::StateHandle;
+use parking_lot::RwLock;
+use rs_matter::dm::{
+    Access, Attribute, Cluster, Dataver, Handler, NonBlockingHandler, Quality, ReadContext,
+    ReadReply, Reply, WriteContext,
+};
+use rs_matter::error::{Error, ErrorCode};
+use rs_matter::tlv::{TLVTag, TLVWrite};
+use rs_matter::{attribute_enum, attributes, with};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU8, AtomicU16, AtomicU32, Ordering};
+use strum::FromRepr;
+
+/// Matter Cluster ID for PowerSource
+pub const CLUSTER_ID: u32 = 0x002F;
+
+/// Cluster revision
+pub const CLUSTER_REVISION: u16 = 2;
+
+/// Name of the persisted reading in the runtime state store
+const READING_NAME: &str = "battery";
+
+/// Feature flags for PowerSource
+pub mod features {
+    /// Wired feature (WIRED)
+    pub const WIRED: u32 = 0x01;
+    /// Battery feature (BAT)
+    pub const BATTERY: u32 = 0x02;
+    /// Rechargeable feature (RECHG)
+    pub const RECHARGEABLE: u32 = 0x04;
+    /// Replaceable feature (REPLC)
+    pub const REPLACEABLE: u32 = 0x08;
+}
+
+/// Raw value for an unknown battery level (null)
+const PERCENT_UNKNOWN: u8 = 0xFF;
+
+/// Battery level below which BatChargeLevel reports Warning (percent)
+const WARNING_PERCENT: f32 = 20.0;
+
+/// Battery level below which BatChargeLevel reports Critical (percent)
+const CRITICAL_PERCENT: f32 = 10.0;
+
+/// Attribute IDs for the PowerSource cluster
+#[derive(Clone, Copy, Debug, Eq, PartialEq, FromRepr)]
+#[repr(u32)]
+pub enum PowerSourceAttribute {
+    /// Status of the power source (PowerSourceStatusEnum)
+    Status = 0x0000,
+    /// Relative order among power sources (lower = preferred)
+    Order = 0x0001,
+    /// User-facing description
+    Description = 0x0002,
+    /// Remaining battery in half-percent units (nullable)
+    BatPercentRemaining = 0x000C,
+    /// Battery charge level (BatChargeLevelEnum)
+    BatChargeLevel = 0x000E,
+    /// Whether the battery needs replacing
+    BatReplacementNeeded = 0x000F,
+    /// Battery replaceability (BatReplaceabilityEnum)
+    BatReplaceability = 0x0010,
+    /// Endpoints powered by this source
+    EndpointList = 0x001F,
+}
+
+attribute_enum!(PowerSourceAttribute);
+
+/// Attributes derived from the battery percentage (changed by `set_battery_percent`)
+pub const BATTERY_ATTRIBUTES: &[u32] = &[
+    PowerSourceAttribute::BatPercentRemaining as u32,
+    PowerSourceAttribute::BatChargeLevel as u32,
+    PowerSourceAttribute::BatReplacementNeeded as u32,
+];
+
+/// PowerSourceStatusEnum: source is in use
+const STATUS_ACTIVE: u8 = 1;
+
+/// BatReplaceabilityEnum: replaceability not specified
+const REPLACEABILITY_UNSPECIFIED: u8 = 0;
+
+/// Cluster metadata definition (BAT feature)
+pub const CLUSTER: Cluster<'static> = Cluster {
+    id: CLUSTER_ID,
+    revision: CLUSTER_REVISION,
+    feature_map: features::BATTERY,
+    attributes: attributes!(
+        Attribute::new(PowerSourceAttribute::Status as _, Access::RV, Quality::NONE),
+        Attribute::new(PowerSourceAttribute::Order as _, Access::RV, Quality::NONE),
+        Attribute::new(
+            PowerSourceAttribute::Description as _,
+            Access::RV,
+            Quality::FIXED
+        ),
+        Attribute::new(
+            PowerSourceAttribute::BatPercentRemaining as _,
+            Access::RV,
+            Quality::NULLABLE
+        ),
+        Attribute::new(
+            PowerSourceAttribute::BatChargeLevel as _,
+            Access::RV,
+            Quality::NONE
+        ),
+        Attribute::new(
+            PowerSourceAttribute::BatReplacementNeeded as _,
+            Access::RV,
+            Quality::NONE
+        ),
+        Attribute::new(
+            PowerSourceAttribute::BatReplaceability as _,
+            Access::RV,
+            Quality::FIXED
+        ),
+        Attribute::new(
+            PowerSourceAttribute::EndpointList as _,
+            Access::RV,
+            Quality::NONE
+        ),
+    ),
+    commands: &[],
+    with_attrs: with!(all),
+    with_cmds: with!(all),
+};
+
+/// Battery state that can be updated from external sources.
+pub struct PowerSource {
+    /// Remaining battery in half-percent units (0xFF = unknown)
+    half_percent: AtomicU8,
+    /// Endpoint ID (set when wired to Matter stack, reported in EndpointList)
+    endpoint_id: AtomicU16,
+    /// Version counter for change detection
+    version: AtomicU32,
+    /// Notifier for Matter subscription updates
+    notifier: RwLock<Option<ClusterNotifier>>,
+    /// Persists the last reading across restarts
+    state_handle: RwLock<Option<StateHandle>>,
+}
+
+impl PowerSource {
+    /// Create a new battery power source with unknown level.
+    pub fn new() -> Self {
+        Self {
+            half_percent: AtomicU8::new(PERCENT_UNKNOWN),
+            endpoint_id: AtomicU16::new(0),
+            version: AtomicU32::new(0),
+            notifier: RwLock::new(None),
+            state_handle: RwLock::new(None),
+        }
+    }
+
+    /// Set a notifier for Matter subscription updates.
+    pub fn set_notifier(&self, notifier: ClusterNotifier) {
+        *self.notifier.write() = Some(notifier);
+    }
+
+    /// Set the endpoint ID (called when wiring to Matter stack).
+    pub fn set_endpoint_id(&self, endpoint_id: u16) {
+        self.endpoint_id.store(endpoint_id, Ordering::SeqCst);
+    }
+
+    /// Get the endpoint ID.
+    pub fn endpoint_id(&self) -> u16 {
+        self.endpoint_id.load(Ordering::SeqCst)
+    }
+
+    /// Get the remaining battery in percent, if known.
+    pub fn get_battery_percent(&self) -> Option<f32> {
+        self.get_half_percent().map(|half| half as f32 / 2.0)
+    }
+
+    /// Get the remaining battery in half-percent units (raw Matter value).
+    pub fn get_half_percent(&self) -> Option<u8> {
+        match self.half_percent.load(Ordering::SeqCst) {
+            PERCENT_UNKNOWN => None,
+            half => Some(half),
+        }
+    }
+
+    /// Restore the last persisted battery level and persist future readings.
+    ///
+    /// The restored value is reported until the source sends a new reading.
+    pub fn restore_state(&self, handle: StateHandle) {
+        if let Some(value) = handle.reading(READING_NAME) {
+            self.set_battery_percent(value);
+        }
+        *self.state_handle.write() = Some(handle);
+    }
+
+    /// Set the remaining battery in percent (clamped to 0-100).
+    pub fn set_battery_percent(&self, percent: f32) {
+        let half = (percent.clamp(0.0, 100.0) * 2.0).round() as u8;
+        if self.half_percent.swap(half, Ordering::SeqCst) == half {
+            return;
+        }
+        self.version.fetch_add(1, Ordering::SeqCst);
+        // Notify subscribers of the change
+        if let Some(notifier) = self.notifier.read().as_ref() {
+            notifier.notify();
+        }
+        if let Some(handle) = self.state_handle.read().as_ref() {
+            handle.save_reading(READING_NAME, half as f32 / 2.0);
+        }
+    }
+
+    /// Get the BatChargeLevelEnum value (0 = OK, 1 = Warning, 2 = Critical).
+    pub fn charge_level(&self) -> u8 {
+        match self.get_battery_percent() {
+            Some(percent) if percent < CRITICAL_PERCENT => 2,
+            Some(percent) if percent < WARNING_PERCENT => 1,
+            _ => 0,
+        }
+    }
+
+    /// Whether the battery should be replaced (level is critical).
+    pub fn replacement_needed(&self) -> bool {
+        self.charge_level() == 2
+    }
+
+    /// Get the current version (incremented on each change).
+    pub fn version(&self) -> u32 {
+        self.version.load(Ordering::SeqCst)
+    }
+}
+
+impl Default for PowerSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Handler that serves a PowerSource cluster.
+pub struct PowerSourceHandler {
+    dataver: Dataver,
+    source: Arc<PowerSource>,
+    last_source_version: AtomicU32,
+    /// User-facing description (e.g., "Battery")
+    description: &'static str,
+}
+
+impl PowerSourceHandler {
+    /// Cluster definition for use in the data model
+    pub const CLUSTER: Cluster<'static> = CLUSTER;
+
+    /// Create a new handler with a power source reference.
+    pub fn new(dataver: Dataver, source: Arc<PowerSource>) -> Self {
+        Self {
+            dataver,
+            source,
+            last_source_version: AtomicU32::new(0),
+            description: "Battery",
+        }
+    }
+
+    /// Sync dataver with source version for subscription updates.
+    fn sync_dataver(&self) {
+        let source_version = self.source.version();
+        let last = self.last_source_version.load(Ordering::SeqCst);
+        if source_version != last {
+            self.last_source_version
+                .store(source_version, Ordering::SeqCst);
+            self.dataver.changed();
+        }
+    }
+
+    fn read_impl(&self, ctx: impl ReadContext, reply: impl ReadReply) -> Result<(), Error> {
+        self.sync_dataver();
+
+        let attr = ctx.attr();
+
+        let Some(mut writer) = reply.with_dataver(self.dataver.get())? else {
+            return Ok(());
+        };
+
+        // Global attributes
+        if attr.is_system() {
+            return CLUSTER.read(attr, writer);
+        }
+
+        let tag = writer.tag();
+        {
+            let mut tw = writer.writer();
+
+            match attr.attr_id.try_into()? {
+                PowerSourceAttribute::Status => {
+                    tw.u8(tag, STATUS_ACTIVE)?;
+                }
+                PowerSourceAttribute::Order => {
+                    tw.u8(tag, 0)?;
+                }
+                PowerSourceAttribute::Description => {
+                    tw.utf8(tag, self.description)?;
+                }
+                PowerSourceAttribute::BatPercentRemaining => match self.source.get_half_percent() {
+                    Some(half) => tw.u8(tag, half)?,
+                    None => tw.null(tag)?,
+                },
+                PowerSourceAttribute::BatChargeLevel => {
+                    tw.u8(tag, self.source.charge_level())?;
+                }
+                PowerSourceAttribute::BatReplacementNeeded => {
+                    tw.bool(tag, self.source.replacement_needed())?;
+                }
+                PowerSourceAttribute::BatReplaceability => {
+                    tw.u8(tag, REPLACEABILITY_UNSPECIFIED)?;
+                }
+                PowerSourceAttribute::EndpointList => {
+                    tw.start_array(tag)?;
+                    tw.u16(&TLVTag::Anonymous, self.source.endpoint_id())?;
+                    tw.end_container()?;
+                }
+            }
+        }
+
+        writer.complete()
+    }
+
+    fn write_impl(&self, _ctx: impl WriteContext) -> Result<(), Error> {
+        // Cluster is read-only
+        Err(ErrorCode::UnsupportedAccess.into())
+    }
+}
+
+impl Handler for PowerSourceHandler {
+    fn read(&self, ctx: impl ReadContext, reply: impl ReadReply) -> Result<(), Error> {
+        self.read_impl(ctx, reply)
+    }
+
+    fn write(&self, ctx: impl WriteContext) -> Result<(), Error> {
+        self.write_impl(ctx)
+    }
+}
+
+impl NonBlockingHandler for PowerSourceHandler {}
@@ -6,7 +6,9 @@
 //! For example: 55.5% is reported as 5550.
 
 use crate::matter::endpoints::ClusterNotifier;
-use parking_lot::RwLock;
+use crate::matter::endpoints::endpoints_helpers::transform::ValueTransform;
+use crate::matter::runtime_state::StateHandle;
+use parking_lot::{Mutex, RwLock};
 use rs_matter::dm::{
     Access, Attribute, Cluster, Dataver, Handler, NonBlockingHandler, Quality, ReadContext,
     ReadReply, Reply, WriteContext,
@@ -24,6 +26,21 @@ pub const CLUSTER_ID: u32 = 0x0405;
 /// Cluster revision
 pub const CLUSTER_REVISION: u16 = 3;
 
+/// Name of the persisted reading in the runtime state store
+const READING_NAME: &str = "humidity";
+
+/// Minimum measurable humidity in centi-percent (0%)
+const MIN_MEASURED_VALUE: u16 = 0;
+
+/// Maximum measurable humidity in centi-percent (100%)
+const MAX_MEASURED_VALUE: u16 = 10000;
+
+/// Measurable range in percent (MinMeasuredValue, MaxMeasuredValue)
+pub const MEASURED_RANGE: (f64, f64) = (
+    MIN_MEASURED_VALUE as f64 / 100.0,
+    MAX_MEASURED_VALUE as f64 / 100.0,
+);
+
 /// Attribute IDs for the RelativeHumidityMeasurement cluster
 #[derive(Clone, Copy, Debug, Eq, PartialEq, FromRepr)]
 #[repr(u32)]
@@ -80,6 +97,10 @@ pub struct HumiditySensor {
     version: AtomicU32,
     /// Notifier for Matter subscription updates
     notifier: RwLock<Option<ClusterNotifier>>,
+    /// Persists the last reading across restarts
+    state_handle: RwLock<Option<StateHandle>>,
+    /// Calibration/smoothing applied to readings from the source
+    transform: Mutex<Option<ValueTransform>>,
 }
 
 impl HumiditySensor {
@@ -92,6 +113,8 @@ impl HumiditySensor {
             value: AtomicU16::new((initial_percent * 100.0) as u16),
             version: AtomicU32::new(0),
             notifier: RwLock::new(None),
+            state_handle: RwLock::new(None),
+            transform: Mutex::new(None),
         }
     }
 
@@ -113,8 +136,47 @@ impl HumiditySensor {
         self.value.load(Ordering::SeqCst)
     }
 
-    /// Set the humidity in percent.
+    /// Restore the last persisted humidity and persist future readings.
+    ///
+    /// The restored value is reported until the source sends a new reading, and seeds
+    /// the deadband of the transform (set the transform first).
+    pub fn restore_state(&self, handle: StateHandle) {
+        if let Some(value) = handle.reading(READING_NAME) {
+            self.store(value);
+            if let Some(transform) = self.transform.lock().as_mut() {
+                transform.seed(value as f64);
+            }
+        }
+        *self.state_handle.write() = Some(handle);
+    }
+
+    /// Transform future source readings (calibration, smoothing, deadband).
+    pub fn set_transform(&self, transform: ValueTransform) {
+        *self.transform.lock() = Some(transform);
+    }
+
+    /// Set the humidity in percent (no transform: control plane, derived values).
     pub fn set_percent(&self, percent: f32) {
+        self.store(percent);
+    }
+
+    /// Report a reading from the sensor source.
+    ///
+    /// The reading goes through the transform, if any, and is dropped when the
+    /// transform filters it out (e.g. within the deadband).
+    pub fn set_raw_percent(&self, percent: f32) {
+        let percent = match self.transform.lock().as_mut() {
+            Some(transform) => match transform.apply(percent as f64, MEASURED_RANGE) {
+                Some(value) => value as f32,
+                None => return,
+            },
+            None => percent,
+        };
+        self.store(percent);
+    }
+
+    /// Report a (transformed) humidity in percent.
+    fn store(&self, percent: f32) {
         let centipercent = (percent * 100.0) as u16;
         self.value.store(centipercent, Ordering::SeqCst);
         self.version.fetch_add(1, Ordering::SeqCst);
@@ -122,6 +184,9 @@ impl HumiditySensor {
         if let Some(notifier) = self.notifier.read().as_ref() {
             notifier.notify();
         }
+        if let Some(handle) = self.state_handle.read().as_ref() {
+            handle.save_reading(READING_NAME, percent);
+        }
     }
 
     /// Get the current version (incremented on each change).
@@ -153,8 +218,8 @@ impl RelativeHumidityHandler {
             dataver,
             sensor,
             last_sensor_version: AtomicU32::new(0),
-            min_value: 0,     // 0%
-            max_value: 10000, // 100%
+            min_value: MIN_MEASURED_VALUE,
+            max_value: MAX_MEASURED_VALUE,
         }
     }
 
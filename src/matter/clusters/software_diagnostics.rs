@@ -0,0 +1,268 @@
+//! Software Diagnostics cluster handler for rs-matter.
+//!
+//! Reports the threads of the bridge process and its memory use, read from `/proc`.
+//! The "heap" attributes describe the resident memory of the process (used) and the
+//! memory available on the host (free). The high watermark is the highest resident
+//! memory seen since start or the last ResetWatermarks command.
+
+use std::fs;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use rs_matter::dm::{
+    Access, Attribute, Cluster, Command, Dataver, Handler, InvokeContext, InvokeReply,
+    NonBlockingHandler, Quality, ReadContext, ReadReply, Reply, WriteContext,
+};
+use rs_matter::error::{Error, ErrorCode};
+use rs_matter::tlv::{TLVTag, TLVWrite};
+use rs_matter::{attribute_enum, attributes, command_enum, commands, with};
+use strum::FromRepr;
+
+/// Matter Cluster ID for Software Diagnostics
+pub const CLUSTER_ID: u32 = 0x0034;
+
+/// Cluster revision
+pub const CLUSTER_REVISION: u16 = 1;
+
+/// Feature map: Watermarks (WTRMRK)
+pub const FEATURE_WATERMARKS: u32 = 0x01;
+
+/// Attribute IDs for the Software Diagnostics cluster
+#[derive(Clone, Copy, Debug, Eq, PartialEq, FromRepr)]
+#[repr(u32)]
+pub enum SoftwareDiagnosticsAttribute {
+    /// List of ThreadMetricsStruct
+    ThreadMetrics = 0x00,
+    /// Free memory in bytes
+    CurrentHeapFree = 0x01,
+    /// Used memory in bytes
+    CurrentHeapUsed = 0x02,
+    /// Highest used memory in bytes since the last reset
+    CurrentHeapHighWatermark = 0x03,
+}
+
+attribute_enum!(SoftwareDiagnosticsAttribute);
+
+/// Command IDs for the Software Diagnostics cluster
+#[derive(Clone, Copy, Debug, Eq, PartialEq, FromRepr)]
+#[repr(u32)]
+pub enum SoftwareDiagnosticsCommand {
+    /// Reset the high watermark to the current usage
+    ResetWatermarks = 0x00,
+}
+
+command_enum!(SoftwareDiagnosticsCommand);
+
+/// Cluster metadata definition
+pub const CLUSTER: Cluster<'static> = Cluster {
+    id: CLUSTER_ID,
+    revision: CLUSTER_REVISION,
+    feature_map: FEATURE_WATERMARKS,
+    attributes: attributes!(
+        Attribute::new(
+            SoftwareDiagnosticsAttribute::ThreadMetrics as _,
+            Access::RV,
+            Quality::A
+        ),
+        Attribute::new(
+            SoftwareDiagnosticsAttribute::CurrentHeapFree as _,
+            Access::RV,
+            Quality::NONE
+        ),
+        Attribute::new(
+            SoftwareDiagnosticsAttribute::CurrentHeapUsed as _,
+            Access::RV,
+            Quality::NONE
+        ),
+        Attribute::new(
+            SoftwareDiagnosticsAttribute::CurrentHeapHighWatermark as _,
+            Access::RV,
+            Quality::NONE
+        ),
+    ),
+    commands: commands!(Command::new(
+        SoftwareDiagnosticsCommand::ResetWatermarks as _,
+        None,
+        Access::WO
+    ),),
+    with_attrs: with!(all),
+    with_cmds: with!(all),
+};
+
+/// A thread of the bridge process.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThreadInfo {
+    /// Kernel thread ID
+    pub id: u64,
+    /// Thread name (`comm`)
+    pub name: String,
+}
+
+/// Memory use of the bridge process in bytes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+    /// Resident memory of the process
+    pub used: u64,
+    /// Memory available on the host
+    pub free: u64,
+}
+
+/// Parse a `<key>: <value> kB` line from `/proc/self/status` or `/proc/meminfo` into bytes.
+pub fn parse_kb_field(contents: &str, key: &str) -> Option<u64> {
+    contents.lines().find_map(|line| {
+        let value = line.strip_prefix(key)?.strip_prefix(':')?;
+        let kb = value
+            .trim()
+            .trim_end_matches("kB")
+            .trim()
+            .parse::<u64>()
+            .ok()?;
+        Some(kb * 1024)
+    })
+}
+
+/// Read the memory use of the process.
+pub fn memory_stats() -> MemoryStats {
+    let status = fs::read_to_string("/proc/self/status").unwrap_or_default();
+    let meminfo = fs::read_to_string("/proc/meminfo").unwrap_or_default();
+    MemoryStats {
+        used: parse_kb_field(&status, "VmRSS").unwrap_or(0),
+        free: parse_kb_field(&meminfo, "MemAvailable").unwrap_or(0),
+    }
+}
+
+/// Read the threads of the process (sorted by ID).
+pub fn threads() -> Vec<ThreadInfo> {
+    let Ok(tasks) = fs::read_dir("/proc/self/task") else {
+        return Vec::new();
+    };
+    let mut threads: Vec<ThreadInfo> = tasks
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let id = entry.file_name().to_str()?.parse().ok()?;
+            let name = fs::read_to_string(entry.path().join("comm")).unwrap_or_default();
+            Some(ThreadInfo {
+                id,
+                name: name.trim().to_string(),
+            })
+        })
+        .collect();
+    threads.sort_by_key(|thread| thread.id);
+    threads
+}
+
+/// Handler that serves the Software Diagnostics cluster.
+pub struct SoftwareDiagnosticsHandler {
+    dataver: Dataver,
+    high_watermark: AtomicU64,
+}
+
+impl SoftwareDiagnosticsHandler {
+    /// Cluster definition for use in the data model
+    pub const CLUSTER: Cluster<'static> = CLUSTER;
+
+    /// Create a new handler
+    pub const fn new(dataver: Dataver) -> Self {
+        Self {
+            dataver,
+            high_watermark: AtomicU64::new(0),
+        }
+    }
+
+    /// Read the memory use and update the high watermark.
+    pub fn sample(&self) -> (MemoryStats, u64) {
+        let stats = memory_stats();
+        let previous = self.high_watermark.fetch_max(stats.used, Ordering::SeqCst);
+        (stats, previous.max(stats.used))
+    }
+
+    fn read_impl(&self, ctx: impl ReadContext, reply: impl ReadReply) -> Result<(), Error> {
+        let attr = ctx.attr();
+
+        let Some(mut writer) = reply.with_dataver(self.dataver.get())? else {
+            return Ok(()); // No update needed
+        };
+
+        // Global attributes
+        if attr.is_system() {
+            return CLUSTER.read(attr, writer);
+        }
+
+        let tag = writer.tag();
+        {
+            let mut tw = writer.writer();
+
+            match attr.attr_id.try_into()? {
+                SoftwareDiagnosticsAttribute::ThreadMetrics => {
+                    if attr.list_index.as_ref().is_some_and(|li| li.is_some()) {
+                        return Err(ErrorCode::ConstraintError.into());
+                    }
+                    tw.start_array(tag)?;
+                    for thread in threads() {
+                        tw.start_struct(&TLVTag::Anonymous)?;
+                        tw.u64(&TLVTag::Context(0), thread.id)?;
+                        tw.utf8(&TLVTag::Context(1), &thread.name)?;
+                        tw.end_container()?;
+                    }
+                    tw.end_container()?;
+                }
+                SoftwareDiagnosticsAttribute::CurrentHeapFree => {
+                    tw.u64(tag, self.sample().0.free)?;
+                }
+                SoftwareDiagnosticsAttribute::CurrentHeapUsed => {
+                    tw.u64(tag, self.sample().0.used)?;
+                }
+                SoftwareDiagnosticsAttribute::CurrentHeapHighWatermark => {
+                    tw.u64(tag, self.sample().1)?;
+                }
+            }
+        }
+
+        writer.complete()
+    }
+
+    fn write_impl(&self, _ctx: impl WriteContext) -> Result<(), Error> {
+        Err(ErrorCode::UnsupportedAccess.into())
+    }
+
+    fn invoke_impl(&self, ctx: impl InvokeContext, _reply: impl InvokeReply) -> Result<(), Error> {
+        match ctx.cmd().cmd_id.try_into()? {
+            SoftwareDiagnosticsCommand::ResetWatermarks => {
+                self.high_watermark
+                    .store(memory_stats().used, Ordering::SeqCst);
+                self.dataver.changed();
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Handler for SoftwareDiagnosticsHandler {
+    fn read(&self, ctx: impl ReadContext, reply: impl ReadReply) -> Result<(), Error> {
+        self.read_impl(ctx, reply)
+    }
+
+    fn write(&self, ctx: impl WriteContext) -> Result<(), Error> {
+        self.write_impl(ctx)
+    }
+
+    fn invoke(&self, ctx: impl InvokeContext, reply: impl InvokeReply) -> Result<(), Error> {
+        self.invoke_impl(ctx, reply)
+    }
+}
+
+impl NonBlockingHandler for SoftwareDiagnosticsHandler {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_kb_field() {
+        let status = "Name:\tvirtual-matter\nVmHWM:\t   20480 kB\nVmRSS:\t   10240 kB\n";
+        assert_eq!(parse_kb_field(status, "VmRSS"), Some(10240 * 1024));
+        assert_eq!(parse_kb_field(status, "VmHWM"), Some(20480 * 1024));
+        assert_eq!(parse_kb_field(status, "VmSwap"), None);
+        // Keys are matched exactly, not by prefix
+        assert_eq!(parse_kb_field(status, "Vm"), None);
+    }
+}
@@ -0,0 +1,310 @@
+//! Gesture detection for GenericSwitch sources that only report raw edges.
+//!
+//! A UDP button or a GPIO input knows when the button goes down and up, not whether
+//! that was a double press or a long press. [`SwitchGestures`] turns those edges into
+//! the GenericSwitch event sequence using timing thresholds:
+//!
+//! - press: InitialPress, plus MultiPressOngoing from the second press of a sequence
+//! - held for `long_press` (first press only): LongPress, then LongRelease on release
+//! - short release: ShortRelease, then MultiPressComplete once no further press follows
+//!   within `multi_press_window` (count 0 when the sequence exceeded `multi_press_max`)
+
+use super::generic_switch::{DEFAULT_MULTI_PRESS_MAX, GenericSwitchState};
+use log::warn;
+use parking_lot::Mutex;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+/// Timing thresholds of the gesture detector.
+#[derive(Debug, Clone, Copy)]
+pub struct GestureConfig {
+    /// Hold time after which a press becomes a long press
+    pub long_press: Duration,
+    /// Time after a release in which another press continues the sequence
+    pub multi_press_window: Duration,
+    /// Highest press count reported (advertised as MultiPressMax, at least 2)
+    pub multi_press_max: u8,
+}
+
+impl Default for GestureConfig {
+    fn default() -> Self {
+        Self {
+            long_press: Duration::from_millis(1000),
+            multi_press_window: Duration::from_millis(400),
+            multi_press_max: DEFAULT_MULTI_PRESS_MAX,
+        }
+    }
+}
+
+/// GenericSwitch event derived from raw edges.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gesture {
+    InitialPress,
+    LongPress,
+    ShortRelease,
+    LongRelease,
+    /// Press count of a running sequence (2 or more)
+    MultiPressOngoing(u8),
+    /// Total press count of a finished sequence (0 = more than MultiPressMax)
+    MultiPressComplete(u8),
+}
+
+impl Gesture {
+    /// Record this event on a switch.
+    pub fn apply(self, state: &GenericSwitchState) {
+        match self {
+            Self::InitialPress => state.press(),
+            Self::LongPress => state.long_press(),
+            Self::ShortRelease => state.release(),
+            Self::LongRelease => state.long_release(),
+            Self::MultiPressOngoing(count) => state.multi_press_ongoing(count),
+            Self::MultiPressComplete(count) => state.multi_press(count),
+        }
+    }
+}
+
+/// Position in the gesture state machine.
+#[derive(Debug, Clone, Copy)]
+enum Phase {
+    Idle,
+    /// Press `count` of the sequence is down
+    Pressed {
+        count: u8,
+        since: Instant,
+    },
+    /// The first press was held past the long press threshold
+    LongPressed,
+    /// Released after `count` short presses, waiting for the next one
+    Released {
+        count: u8,
+        since: Instant,
+    },
+}
+
+/// Edge-to-gesture state machine (time is passed in by the caller).
+#[derive(Debug)]
+pub struct GestureDetector {
+    config: GestureConfig,
+    phase: Phase,
+}
+
+impl GestureDetector {
+    /// Create an idle detector.
+    pub fn new(config: GestureConfig) -> Self {
+        Self {
+            config: GestureConfig {
+                multi_press_max: config.multi_press_max.max(2),
+                ..config
+            },
+            phase: Phase::Idle,
+        }
+    }
+
+    /// The button went down.
+    pub fn press(&mut self, now: Instant) -> Vec<Gesture> {
+        let mut gestures = self.poll(now);
+        match self.phase {
+            Phase::Idle => {
+                gestures.push(Gesture::InitialPress);
+                self.phase = Phase::Pressed {
+                    count: 1,
+                    since: now,
+                };
+            }
+            Phase::Released { count, .. } => {
+                let count = count.saturating_add(1);
+                gestures.push(Gesture::InitialPress);
+                if count <= self.config.multi_press_max {
+                    gestures.push(Gesture::MultiPressOngoing(count));
+                }
+                self.phase = Phase::Pressed { count, since: now };
+            }
+            // Repeated edge, the button is already down
+            Phase::Pressed { .. } | Phase::LongPressed => {}
+        }
+        gestures
+    }
+
+    /// The button went up.
+    pub fn release(&mut self, now: Instant) -> Vec<Gesture> {
+        let mut gestures = self.poll(now);
+        match self.phase {
+            Phase::Pressed { count, .. } => {
+                gestures.push(Gesture::ShortRelease);
+                self.phase = Phase::Released { count, since: now };
+            }
+            Phase::LongPressed => {
+                gestures.push(Gesture::LongRelease);
+                self.phase = Phase::Idle;
+            }
+            // Repeated edge, the button is already up
+            Phase::Idle | Phase::Released { .. } => {}
+        }
+        gestures
+    }
+
+    /// Emit the gestures whose threshold passed by `now`.
+    pub fn poll(&mut self, now: Instant) -> Vec<Gesture> {
+        let Some(deadline) = self.deadline() else {
+            return Vec::new();
+        };
+        if now < deadline {
+            return Vec::new();
+        }
+        match self.phase {
+            Phase::Pressed { .. } => {
+                self.phase = Phase::LongPressed;
+                vec![Gesture::LongPress]
+            }
+            Phase::Released { count, .. } => {
+                self.phase = Phase::Idle;
+                let count = if count > self.config.multi_press_max {
+                    0
+                } else {
+                    count
+                };
+                vec![Gesture::MultiPressComplete(count)]
+            }
+            Phase::Idle | Phase::LongPressed => Vec::new(),
+        }
+    }
+
+    /// When the next threshold passes (None while nothing is pending).
+    pub fn deadline(&self) -> Option<Instant> {
+        match self.phase {
+            // Only the first press of a sequence can become a long press
+            Phase::Pressed { count: 1, since } => Some(since + self.config.long_press),
+            Phase::Released { since, .. } => Some(since + self.config.multi_press_window),
+            Phase::Idle | Phase::Pressed { .. } | Phase::LongPressed => None,
+        }
+    }
+}
+
+/// Derives GenericSwitch events from raw press/release edges.
+///
+/// Feed edges with [`press`](Self::press) and [`release`](Self::release) from a Tokio
+/// runtime; the first edge starts the task for the time-based events. Events are
+/// recorded while the detector is locked, so edges and timeouts cannot reorder them.
+pub struct SwitchGestures {
+    state: Arc<GenericSwitchState>,
+    detector: Mutex<GestureDetector>,
+    wake: Notify,
+    timer: OnceLock<JoinHandle<()>>,
+}
+
+impl SwitchGestures {
+    /// Create a detector for a switch and advertise its MultiPressMax.
+    pub fn new(state: Arc<GenericSwitchState>, config: GestureConfig) -> Arc<Self> {
+        let detector = GestureDetector::new(config);
+        state.set_multi_press_max(detector.config.multi_press_max);
+        Arc::new(Self {
+            state,
+            detector: Mutex::new(detector),
+            wake: Notify::new(),
+            timer: OnceLock::new(),
+        })
+    }
+
+    /// The button went down.
+    pub fn press(self: &Arc<Self>) {
+        self.start_timer();
+        let mut detector = self.detector.lock();
+        self.emit(detector.press(Instant::now()));
+    }
+
+    /// The button went up.
+    pub fn release(self: &Arc<Self>) {
+        self.start_timer();
+        let mut detector = self.detector.lock();
+        self.emit(detector.release(Instant::now()));
+    }
+
+    /// Record gestures (called with the detector locked).
+    fn emit(&self, gestures: Vec<Gesture>) {
+        for gesture in gestures {
+            gesture.apply(&self.state);
+        }
+        // The deadline may have moved
+        self.wake.notify_one();
+    }
+
+    /// Start the timer task for long presses and completed sequences (once).
+    fn start_timer(self: &Arc<Self>) {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            warn!("Button edges outside a Tokio runtime, long and multi presses are not reported");
+            return;
+        };
+        self.timer.get_or_init(|| {
+            let gestures = self.clone();
+            runtime.spawn(async move {
+                loop {
+                    let deadline = gestures.detector.lock().deadline();
+                    match deadline {
+                        Some(deadline) => {
+                            tokio::select! {
+                                _ = tokio::time::sleep_until(deadline.into()) => {
+                                    let mut detector = gestures.detector.lock();
+                                    let due = detector.poll(Instant::now());
+                                    for gesture in due {
+                                        gesture.apply(&gestures.state);
+                                    }
+                                }
+                                _ = gestures.wake.notified() => {}
+                            }
+                        }
+                        None => gestures.wake.notified().await,
+                    }
+                }
+            })
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gesture_detector() {
+        let mut detector = GestureDetector::new(GestureConfig::default());
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        // Triple press
+        assert_eq!(detector.press(at(0)), [Gesture::InitialPress]);
+        assert_eq!(detector.release(at(100)), [Gesture::ShortRelease]);
+        assert_eq!(
+            detector.press(at(300)),
+            [Gesture::InitialPress, Gesture::MultiPressOngoing(2)]
+        );
+        assert_eq!(detector.release(at(400)), [Gesture::ShortRelease]);
+        detector.press(at(600));
+        detector.release(at(700));
+        assert_eq!(detector.deadline(), Some(at(1100)));
+        assert!(detector.poll(at(1000)).is_empty());
+        assert_eq!(detector.poll(at(1100)), [Gesture::MultiPressComplete(3)]);
+
+        // Long press
+        detector.press(at(2000));
+        assert_eq!(detector.poll(at(3000)), [Gesture::LongPress]);
+        assert_eq!(detector.release(at(3500)), [Gesture::LongRelease]);
+        assert_eq!(detector.deadline(), None);
+
+        // More presses than MultiPressMax
+        for i in 0..4 {
+            detector.press(at(5000 + i * 200));
+            detector.release(at(5100 + i * 200));
+        }
+        assert_eq!(detector.poll(at(6100)), [Gesture::MultiPressComplete(0)]);
+
+        // Overdue sequence completes before the next press starts a new one
+        detector.press(at(7000));
+        detector.release(at(7100));
+        assert_eq!(
+            detector.press(at(8000)),
+            [Gesture::MultiPressComplete(1), Gesture::InitialPress]
+        );
+    }
+}
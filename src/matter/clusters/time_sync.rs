@@ -1,55 +1,94 @@
-//! Minimal Time Synchronization cluster handler for rs-matter.
+//! Time Synchronization cluster handler for rs-matter.
 //!
 //! Some controllers (e.g., Home Assistant) probe the Time Synchronization
-//! cluster on the root endpoint. Previously we returned `UnsupportedCluster`
-//! which surfaced as errors in the logs. This handler answers the basic reads
-//! with sensible defaults so the controller can proceed without errors.
+//! cluster on the root endpoint, and some set the node's time and time zone
+//! after commissioning. This handler serves the cluster with the Time Zone
+//! feature: SetUTCTime, SetTimeZone and SetDSTOffset are applied to the
+//! persisted [`TimeConfigStore`], and LocalTime is computed from it.
 
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::Arc;
 
 use rs_matter::dm::{
-    Access, Attribute, Cluster, Dataver, Handler, NonBlockingHandler, ReadContext, ReadReply,
-    Reply, WriteContext,
+    Access, Attribute, Cluster, Command, Dataver, Handler, InvokeContext, InvokeReply,
+    NonBlockingHandler, ReadContext, ReadReply, Reply, WriteContext,
 };
 use rs_matter::error::{Error, ErrorCode};
-use rs_matter::tlv::TLVWrite;
-use rs_matter::{attribute_enum, attributes, with};
+use rs_matter::tlv::{TLVElement, TLVTag, TLVWrite};
+use rs_matter::{attribute_enum, attributes, command_enum, commands, with};
 use strum::FromRepr;
 
+use super::super::time_config::{
+    DST_OFFSET_LIST_MAX_SIZE, DstOffsetEntry, TIME_ZONE_LIST_MAX_SIZE, TimeConfigError,
+    TimeConfigStore, TimeZoneEntry,
+};
+
 /// Matter Cluster ID for Time Synchronization (see spec)
 pub const CLUSTER_ID: u32 = 0x0046;
 
 /// Cluster revision
-pub const CLUSTER_REVISION: u16 = 1;
+pub const CLUSTER_REVISION: u16 = 2;
+
+/// Feature map: Time Zone (TZ)
+pub const FEATURE_TIME_ZONE: u32 = 0x01;
+
+/// TimeZoneDatabaseEnum: None (DST offsets must be provided by the controller)
+const TIME_ZONE_DATABASE_NONE: u8 = 2;
 
 /// Attribute IDs for the Time Synchronization cluster
 #[derive(Clone, Copy, Debug, Eq, PartialEq, FromRepr)]
 #[repr(u32)]
 pub enum TimeSyncAttribute {
-    /// UTC time in microseconds since Unix epoch
+    /// UTC time in Matter epoch microseconds
     UtcTime = 0x00,
     /// Current time granularity (enum)
     Granularity = 0x01,
     /// Current time source (enum)
     TimeSource = 0x02,
-    /// DST offset table (list)
+    /// Time zone list
+    TimeZone = 0x05,
+    /// DST offset list
     DstOffset = 0x06,
-    /// Local time in microseconds since Unix epoch
+    /// Local time in Matter epoch microseconds
     LocalTime = 0x07,
+    /// Time zone database availability (enum)
+    TimeZoneDatabase = 0x08,
+    /// Maximum number of time zone entries
+    TimeZoneListMaxSize = 0x0A,
+    /// Maximum number of DST offset entries
+    DstOffsetListMaxSize = 0x0B,
 }
 
 attribute_enum!(TimeSyncAttribute);
 
+/// Command IDs for the Time Synchronization cluster
+#[derive(Clone, Copy, Debug, Eq, PartialEq, FromRepr)]
+#[repr(u32)]
+pub enum TimeSyncCommand {
+    /// Set the UTC time with granularity and source
+    SetUtcTime = 0x00,
+    /// Set the time zone list
+    SetTimeZone = 0x02,
+    /// Set the DST offset list
+    SetDstOffset = 0x04,
+}
+
+command_enum!(TimeSyncCommand);
+
+/// Response command IDs
+pub mod response_commands {
+    pub const SET_TIME_ZONE_RESPONSE: u32 = 0x03;
+}
+
 /// Cluster metadata definition
 pub const CLUSTER: Cluster<'static> = Cluster {
     id: CLUSTER_ID,
     revision: CLUSTER_REVISION,
-    feature_map: 0,
+    feature_map: FEATURE_TIME_ZONE,
     attributes: attributes!(
         Attribute::new(
             TimeSyncAttribute::UtcTime as _,
             Access::RV,
-            rs_matter::dm::Quality::NONE
+            rs_matter::dm::Quality::NULLABLE
         ),
         Attribute::new(
             TimeSyncAttribute::Granularity as _,
@@ -61,34 +100,71 @@ pub const CLUSTER: Cluster<'static> = Cluster {
             Access::RV,
             rs_matter::dm::Quality::NONE
         ),
+        Attribute::new(
+            TimeSyncAttribute::TimeZone as _,
+            Access::RV,
+            rs_matter::dm::Quality::A
+        ),
         Attribute::new(
             TimeSyncAttribute::DstOffset as _,
             Access::RV,
-            rs_matter::dm::Quality::A // DstOffset is actually a list
+            rs_matter::dm::Quality::A
         ),
         Attribute::new(
             TimeSyncAttribute::LocalTime as _,
             Access::RV,
-            rs_matter::dm::Quality::NONE
+            rs_matter::dm::Quality::NULLABLE
+        ),
+        Attribute::new(
+            TimeSyncAttribute::TimeZoneDatabase as _,
+            Access::RV,
+            rs_matter::dm::Quality::FIXED
+        ),
+        Attribute::new(
+            TimeSyncAttribute::TimeZoneListMaxSize as _,
+            Access::RV,
+            rs_matter::dm::Quality::FIXED
+        ),
+        Attribute::new(
+            TimeSyncAttribute::DstOffsetListMaxSize as _,
+            Access::RV,
+            rs_matter::dm::Quality::FIXED
+        ),
+    ),
+    commands: commands!(
+        Command::new(
+            TimeSyncCommand::SetUtcTime as _,
+            None,
+            Access::WRITE.union(Access::NEED_ADMIN)
+        ),
+        Command::new(
+            TimeSyncCommand::SetTimeZone as _,
+            Some(response_commands::SET_TIME_ZONE_RESPONSE),
+            Access::WRITE.union(Access::NEED_MANAGE)
+        ),
+        Command::new(
+            TimeSyncCommand::SetDstOffset as _,
+            None,
+            Access::WRITE.union(Access::NEED_MANAGE)
         ),
     ),
-    commands: &[],
     with_attrs: with!(all),
     with_cmds: with!(all),
 };
 
-/// Handler that serves a minimal, read-only Time Synchronization cluster.
+/// Handler that serves the Time Synchronization cluster.
 pub struct TimeSyncHandler {
     dataver: Dataver,
+    store: Arc<TimeConfigStore>,
 }
 
 impl TimeSyncHandler {
     /// Cluster definition for use in the data model
     pub const CLUSTER: Cluster<'static> = CLUSTER;
 
-    /// Create a new handler
-    pub const fn new(dataver: Dataver) -> Self {
-        Self { dataver }
+    /// Create a new handler backed by `store`
+    pub const fn new(dataver: Dataver, store: Arc<TimeConfigStore>) -> Self {
+        Self { dataver, store }
     }
 
     fn read_impl(&self, ctx: impl ReadContext, reply: impl ReadReply) -> Result<(), Error> {
@@ -109,28 +185,59 @@ impl TimeSyncHandler {
 
             match attr.attr_id.try_into()? {
                 TimeSyncAttribute::UtcTime => {
-                    tw.i64(tag, Self::epoch_micros()?)?;
+                    tw.u64(tag, self.store.utc_now())?;
                 }
                 TimeSyncAttribute::Granularity => {
-                    // 2 = Seconds granularity (conservative, but avoids overstating accuracy)
-                    tw.u8(tag, 2)?;
+                    tw.u8(tag, self.store.granularity())?;
                 }
                 TimeSyncAttribute::TimeSource => {
-                    // 0 = Unknown/None (per spec enum), since we don't track a specific source yet
-                    tw.u8(tag, 0)?;
+                    tw.u8(tag, self.store.time_source())?;
+                }
+                TimeSyncAttribute::TimeZone => {
+                    // For list reads with list_index, return ConstraintError
+                    if attr.list_index.as_ref().is_some_and(|li| li.is_some()) {
+                        return Err(ErrorCode::ConstraintError.into());
+                    }
+                    tw.start_array(tag)?;
+                    for zone in self.store.time_zones() {
+                        tw.start_struct(&TLVTag::Anonymous)?;
+                        tw.i32(&TLVTag::Context(0), zone.offset)?;
+                        tw.u64(&TLVTag::Context(1), zone.valid_at)?;
+                        if let Some(name) = &zone.name {
+                            tw.utf8(&TLVTag::Context(2), name)?;
+                        }
+                        tw.end_container()?;
+                    }
+                    tw.end_container()?;
                 }
                 TimeSyncAttribute::DstOffset => {
-                    // No DST offsets configured - empty list
-                    // For list reads with list_index, return ConstraintError (empty list)
                     if attr.list_index.as_ref().is_some_and(|li| li.is_some()) {
                         return Err(ErrorCode::ConstraintError.into());
                     }
-                    // Otherwise write empty array
                     tw.start_array(tag)?;
+                    for dst in self.store.dst_offsets() {
+                        tw.start_struct(&TLVTag::Anonymous)?;
+                        tw.i32(&TLVTag::Context(0), dst.offset)?;
+                        tw.u64(&TLVTag::Context(1), dst.valid_starting)?;
+                        match dst.valid_until {
+                            Some(until) => tw.u64(&TLVTag::Context(2), until)?,
+                            None => tw.null(&TLVTag::Context(2))?,
+                        }
+                        tw.end_container()?;
+                    }
                     tw.end_container()?;
                 }
                 TimeSyncAttribute::LocalTime => {
-                    tw.i64(tag, Self::epoch_micros()?)?;
+                    tw.u64(tag, self.store.local_now())?;
+                }
+                TimeSyncAttribute::TimeZoneDatabase => {
+                    tw.u8(tag, TIME_ZONE_DATABASE_NONE)?;
+                }
+                TimeSyncAttribute::TimeZoneListMaxSize => {
+                    tw.u8(tag, TIME_ZONE_LIST_MAX_SIZE as u8)?;
+                }
+                TimeSyncAttribute::DstOffsetListMaxSize => {
+                    tw.u8(tag, DST_OFFSET_LIST_MAX_SIZE as u8)?;
                 }
             }
         }
@@ -139,15 +246,108 @@ impl TimeSyncHandler {
     }
 
     fn write_impl(&self, _ctx: impl WriteContext) -> Result<(), Error> {
-        // Cluster is read-only in this implementation
+        // All attributes are changed via commands
         Err(ErrorCode::UnsupportedAccess.into())
     }
 
-    fn epoch_micros() -> Result<i64, Error> {
-        let duration = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map_err(|_| Error::new(ErrorCode::Failure))?;
-        Ok(duration.as_micros().try_into().unwrap_or(i64::MAX))
+    fn invoke_impl(&self, ctx: impl InvokeContext, reply: impl InvokeReply) -> Result<(), Error> {
+        let cmd = ctx.cmd();
+        let data = ctx.data();
+
+        match cmd.cmd_id.try_into()? {
+            TimeSyncCommand::SetUtcTime => {
+                let mut seq = data.structure()?;
+                let utc = seq.scan_ctx(0)?.u64()?;
+                let granularity = seq.scan_ctx(1)?.u8()?;
+                // TimeSource (context 2) - optional
+                let time_source = seq.scan_ctx(2).ok().and_then(|e| e.u8().ok());
+
+                self.store
+                    .set_utc_time(utc, granularity, time_source)
+                    .map_err(config_error)?;
+                log::info!(
+                    "Time set by controller (granularity {}, source {})",
+                    granularity,
+                    self.store.time_source()
+                );
+                self.dataver.changed();
+                Ok(())
+            }
+            TimeSyncCommand::SetTimeZone => {
+                let mut seq = data.structure()?;
+                let time_zones = parse_list(&seq.scan_ctx(0)?, |entry| {
+                    let mut zone = entry.structure()?;
+                    Ok(TimeZoneEntry {
+                        offset: zone.scan_ctx(0)?.i32()?,
+                        valid_at: zone.scan_ctx(1)?.u64()?,
+                        // Name (context 2) - optional
+                        name: zone
+                            .scan_ctx(2)
+                            .ok()
+                            .and_then(|e| e.utf8().ok())
+                            .map(str::to_string),
+                    })
+                })?;
+
+                let dst_required = self
+                    .store
+                    .set_time_zones(time_zones)
+                    .map_err(config_error)?;
+                log::info!("Time zone set by controller: {:?}", self.store.time_zones());
+                self.dataver.changed();
+
+                // Send response
+                let mut writer = reply.with_command(response_commands::SET_TIME_ZONE_RESPONSE)?;
+                let tag = writer.tag();
+                {
+                    let mut tw = writer.writer();
+                    tw.start_struct(tag)?;
+                    tw.bool(&TLVTag::Context(0), dst_required)?;
+                    tw.end_container()?;
+                }
+                writer.complete()
+            }
+            TimeSyncCommand::SetDstOffset => {
+                let mut seq = data.structure()?;
+                let dst_offsets = parse_list(&seq.scan_ctx(0)?, |entry| {
+                    let mut dst = entry.structure()?;
+                    Ok(DstOffsetEntry {
+                        offset: dst.scan_ctx(0)?.i32()?,
+                        valid_starting: dst.scan_ctx(1)?.u64()?,
+                        // ValidUntil (context 2) - nullable
+                        valid_until: dst.scan_ctx(2).ok().and_then(|e| e.u64().ok()),
+                    })
+                })?;
+
+                self.store
+                    .set_dst_offsets(dst_offsets)
+                    .map_err(config_error)?;
+                log::info!(
+                    "DST offsets set by controller: {:?}",
+                    self.store.dst_offsets()
+                );
+                self.dataver.changed();
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Parse a TLV array with `f` applied to each element.
+fn parse_list<T>(
+    list: &TLVElement<'_>,
+    f: impl Fn(&TLVElement<'_>) -> Result<T, Error>,
+) -> Result<Vec<T>, Error> {
+    list.array()?.iter().map(|entry| f(&entry?)).collect()
+}
+
+/// Map a rejected configuration to an interaction model status.
+fn config_error(e: TimeConfigError) -> Error {
+    log::warn!("Time Synchronization command rejected: {}", e);
+    match e {
+        TimeConfigError::TimeNotAccepted => ErrorCode::Failure.into(),
+        TimeConfigError::ListTooLong(..) => ErrorCode::ResourceExhausted.into(),
+        TimeConfigError::InvalidEntry(_) => ErrorCode::ConstraintError.into(),
     }
 }
 
@@ -159,6 +359,10 @@ impl Handler for TimeSyncHandler {
     fn write(&self, ctx: impl WriteContext) -> Result<(), Error> {
         self.write_impl(ctx)
     }
+
+    fn invoke(&self, ctx: impl InvokeContext, reply: impl InvokeReply) -> Result<(), Error> {
+        self.invoke_impl(ctx, reply)
+    }
 }
 
 impl NonBlockingHandler for TimeSyncHandler {}
@@ -0,0 +1,335 @@
+//! Commissioning data (discriminator and setup passcode).
+//!
+//! Instead of the shared rs-matter test credentials, every install gets its own
+//! random, spec-valid passcode and discriminator on first run. They are stored next to
+//! `matter.bin` so the pairing code stays stable across restarts. Values configured
+//! via `MATTER_DISCRIMINATOR`/`MATTER_PASSCODE` take precedence.
+//!
+//! The PAKE (SPAKE2+) verifier is derived from the passcode with a fresh salt each
+//! time a commissioning window is opened. Additional windows for multi-admin sharing
+//! are Enhanced Commissioning Method windows with one-time commissioning data (see
+//! [`CommissioningData::generate`]) and a [`PakeVerifier`] derived here, so the
+//! per-install passcode is never handed to another ecosystem.
+
+use super::json_store::write_json_atomic;
+use crate::config::MatterConfig;
+use hmac::{Hmac, Mac};
+use log::{error, info, warn};
+use p256::elliptic_curve::bigint::U256;
+use p256::elliptic_curve::ops::Reduce;
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use p256::{ProjectivePoint, Scalar};
+use rand::Rng;
+use rs_matter::BasicCommData;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::fs;
+use std::path::Path;
+
+/// Largest valid discriminator (12 bits)
+pub const MAX_DISCRIMINATOR: u16 = 0x0FFF;
+
+/// Largest valid setup passcode
+pub const MAX_PASSCODE: u32 = 99_999_998;
+
+/// Shortest commissioning window allowed by the specification (seconds)
+pub const MIN_COMM_WINDOW_TIMEOUT_SECS: u16 = 180;
+
+/// Longest commissioning window allowed by the specification (seconds)
+pub const MAX_COMM_WINDOW_TIMEOUT_SECS: u16 = 900;
+
+/// Passcodes forbidden by the Matter specification (trivially guessable)
+const INVALID_PASSCODES: [u32; 12] = [
+    0, 11111111, 22222222, 33333333, 44444444, 55555555, 66666666, 77777777, 88888888, 99999999,
+    12345678, 87654321,
+];
+
+/// Well-known test passcode (rs-matter/CHIP test devices) - never generated
+const TEST_PASSCODE: u32 = 20202021;
+
+/// Well-known test discriminator (rs-matter/CHIP test devices) - never generated
+const TEST_DISCRIMINATOR: u16 = 3840;
+
+/// PBKDF2 iterations of generated PAKE verifiers (the specification allows 1000..=100000)
+const PAKE_ITERATIONS: u32 = 1000;
+
+/// Salt length of generated PAKE verifiers (the specification allows 16..=32 bytes)
+const PAKE_SALT_LEN: usize = 32;
+
+/// Length of a serialized SPAKE2+ verifier (`w0 || L`)
+pub const PAKE_VERIFIER_LEN: usize = 97;
+
+/// Check whether a setup passcode is allowed by the Matter specification.
+pub fn is_valid_passcode(passcode: u32) -> bool {
+    (1..=MAX_PASSCODE).contains(&passcode) && !INVALID_PASSCODES.contains(&passcode)
+}
+
+/// Check whether a commissioning window timeout is allowed by the Matter specification.
+pub fn is_valid_comm_window_timeout(timeout_secs: u16) -> bool {
+    (MIN_COMM_WINDOW_TIMEOUT_SECS..=MAX_COMM_WINDOW_TIMEOUT_SECS).contains(&timeout_secs)
+}
+
+/// Discriminator and setup passcode used for commissioning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommissioningData {
+    /// 12-bit discriminator (advertised via mDNS)
+    pub discriminator: u16,
+    /// Setup passcode (PIN code)
+    pub passcode: u32,
+}
+
+impl CommissioningData {
+    /// Generate a random, spec-valid discriminator and passcode.
+    ///
+    /// The well-known test values (3840/20202021) are never generated.
+    pub fn generate() -> Self {
+        let mut rng = rand::thread_rng();
+        let passcode = loop {
+            let passcode = rng.gen_range(1..=MAX_PASSCODE);
+            if is_valid_passcode(passcode) && passcode != TEST_PASSCODE {
+                break passcode;
+            }
+        };
+        let discriminator = loop {
+            let discriminator = rng.gen_range(0..=MAX_DISCRIMINATOR);
+            if discriminator != TEST_DISCRIMINATOR {
+                break discriminator;
+            }
+        };
+        Self {
+            discriminator,
+            passcode,
+        }
+    }
+
+    /// Check whether both values are within the ranges allowed by the specification.
+    pub fn is_valid(&self) -> bool {
+        self.discriminator <= MAX_DISCRIMINATOR && is_valid_passcode(self.passcode)
+    }
+
+    /// Load persisted commissioning data from `path`.
+    ///
+    /// Returns None if the file is missing, unreadable or contains invalid values.
+    pub fn load(path: &Path) -> Option<Self> {
+        let contents = fs::read_to_string(path).ok()?;
+        match serde_json::from_str::<Self>(&contents) {
+            Ok(data) if data.is_valid() => Some(data),
+            Ok(_) => {
+                warn!("Ignoring invalid commissioning data in {:?}", path);
+                None
+            }
+            Err(e) => {
+                warn!("Ignoring unreadable commissioning data {:?}: {}", path, e);
+                None
+            }
+        }
+    }
+
+    /// Persist the commissioning data to `path`.
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        write_json_atomic(path, self)
+    }
+
+    /// Resolve the commissioning data for this install.
+    ///
+    /// Configured values win; anything not configured comes from `path`, which is
+    /// created with freshly generated values on first run.
+    pub fn resolve(config: &MatterConfig, path: &Path) -> Self {
+        let persisted = Self::load(path).unwrap_or_else(|| {
+            let generated = Self::generate();
+            match generated.save(path) {
+                Ok(()) => info!("Generated per-install commissioning data in {:?}", path),
+                Err(e) => error!("Failed to persist commissioning data to {:?}: {}", path, e),
+            }
+            generated
+        });
+
+        let discriminator = match config.discriminator {
+            Some(discriminator) if discriminator <= MAX_DISCRIMINATOR => discriminator,
+            Some(discriminator) => {
+                warn!(
+                    "Configured discriminator {} exceeds {}, using {}",
+                    discriminator, MAX_DISCRIMINATOR, persisted.discriminator
+                );
+                persisted.discriminator
+            }
+            None => persisted.discriminator,
+        };
+
+        let passcode = match config.passcode {
+            Some(passcode) if is_valid_passcode(passcode) => {
+                if passcode == TEST_PASSCODE {
+                    warn!(
+                        "Configured passcode is the well-known test passcode {}",
+                        passcode
+                    );
+                }
+                passcode
+            }
+            Some(passcode) => {
+                warn!(
+                    "Configured passcode {} is not allowed by the Matter specification, using the per-install passcode",
+                    passcode
+                );
+                persisted.passcode
+            }
+            None => persisted.passcode,
+        };
+
+        Self {
+            discriminator,
+            passcode,
+        }
+    }
+
+    /// Convert to the rs-matter commissioning data.
+    pub fn basic_comm_data(&self) -> BasicCommData {
+        BasicCommData {
+            password: self.passcode,
+            discriminator: self.discriminator,
+        }
+    }
+}
+
+/// SPAKE2+ verifier of a passcode, as sent in an AdministratorCommissioning
+/// OpenCommissioningWindow command (Enhanced Commissioning Method).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PakeVerifier {
+    /// `w0` (32-byte scalar) followed by `L` (uncompressed P-256 point)
+    pub verifier: [u8; PAKE_VERIFIER_LEN],
+    /// PBKDF2 salt
+    pub salt: Vec<u8>,
+    /// PBKDF2 iterations
+    pub iterations: u32,
+}
+
+impl PakeVerifier {
+    /// Derive the verifier of `passcode` with a fresh random salt.
+    pub fn generate(passcode: u32) -> Self {
+        let mut salt = vec![0u8; PAKE_SALT_LEN];
+        rand::thread_rng().fill(&mut salt[..]);
+        Self::derive(passcode, &salt, PAKE_ITERATIONS)
+    }
+
+    /// Derive the verifier of `passcode` (PBKDF2-HMAC-SHA256, SPAKE2+ on P-256).
+    pub fn derive(passcode: u32, salt: &[u8], iterations: u32) -> Self {
+        // w0s || w1s, 40 bytes each
+        let mut ws = [0u8; 80];
+        pbkdf2_hmac_sha256(&passcode.to_le_bytes(), salt, iterations, &mut ws);
+        let w0 = reduce_scalar(&ws[..40]);
+        let w1 = reduce_scalar(&ws[40..]);
+        let l = (ProjectivePoint::GENERATOR * w1)
+            .to_affine()
+            .to_encoded_point(false);
+
+        let mut verifier = [0u8; PAKE_VERIFIER_LEN];
+        verifier[..32].copy_from_slice(&w0.to_bytes());
+        verifier[32..].copy_from_slice(l.as_bytes());
+        Self {
+            verifier,
+            salt: salt.to_vec(),
+            iterations,
+        }
+    }
+}
+
+/// Reduce a 40-byte big-endian number modulo the P-256 group order.
+fn reduce_scalar(bytes: &[u8]) -> Scalar {
+    let (high, low) = bytes.split_at(bytes.len() - 32);
+    let mut high_bytes = [0u8; 8];
+    high_bytes.copy_from_slice(high);
+    // high * 2^256 + low, with 2^256 mod n = (2^256 - 1 mod n) + 1
+    let two_256 = <Scalar as Reduce<U256>>::reduce(U256::MAX) + Scalar::ONE;
+    Scalar::from(u64::from_be_bytes(high_bytes)) * two_256
+        + <Scalar as Reduce<U256>>::reduce(U256::from_be_slice(low))
+}
+
+/// PBKDF2 with HMAC-SHA256 (RFC 8018), filling `out`.
+fn pbkdf2_hmac_sha256(password: &[u8], salt: &[u8], iterations: u32, out: &mut [u8]) {
+    let prf = Hmac::<Sha256>::new_from_slice(password).expect("HMAC accepts keys of any length");
+    for (index, chunk) in out.chunks_mut(32).enumerate() {
+        let mut mac = prf.clone();
+        mac.update(salt);
+        mac.update(&(index as u32 + 1).to_be_bytes());
+        let mut u = mac.finalize().into_bytes();
+        let mut block = u;
+        for _ in 1..iterations {
+            let mut mac = prf.clone();
+            mac.update(&u);
+            u = mac.finalize().into_bytes();
+            block.iter_mut().zip(&u).for_each(|(b, u)| *b ^= u);
+        }
+        chunk.copy_from_slice(&block[..chunk.len()]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine;
+
+    #[test]
+    fn test_invalid_passcodes() {
+        assert!(!is_valid_passcode(0));
+        assert!(!is_valid_passcode(11111111));
+        assert!(!is_valid_passcode(12345678));
+        assert!(!is_valid_passcode(87654321));
+        assert!(!is_valid_passcode(99999999));
+        assert!(!is_valid_passcode(100_000_000));
+        assert!(is_valid_passcode(20202021));
+        assert!(is_valid_passcode(1));
+    }
+
+    #[test]
+    fn test_comm_window_timeout() {
+        assert!(!is_valid_comm_window_timeout(0));
+        assert!(!is_valid_comm_window_timeout(179));
+        assert!(is_valid_comm_window_timeout(180));
+        assert!(is_valid_comm_window_timeout(900));
+        assert!(!is_valid_comm_window_timeout(901));
+    }
+
+    #[test]
+    fn test_generate_is_valid() {
+        for _ in 0..1000 {
+            let data = CommissioningData::generate();
+            assert!(data.is_valid());
+            assert_ne!(data.passcode, TEST_PASSCODE);
+            assert_ne!(data.discriminator, TEST_DISCRIMINATOR);
+        }
+    }
+
+    #[test]
+    fn test_pake_verifier() {
+        // Test verifier of the CHIP SDK (passcode 20202021, salt "SPAKE2P Key Salt")
+        let expected = base64::engine::general_purpose::STANDARD
+            .decode(
+                "uWFwqugDNGiEck/po7KHwwMwwqZgN10XuyBajPGuyzUEV/iree4lOrao5GuwnlQ65CJzbeUB49s31EH+NEkg0JVI5MGCQGMMT/SRPFNRODm3wH/MBiehuFc6FJ/NH6Rmzw==",
+            )
+            .unwrap();
+        let derived = PakeVerifier::derive(TEST_PASSCODE, b"SPAKE2P Key Salt", 1000);
+        assert_eq!(derived.verifier.as_slice(), expected.as_slice());
+
+        let generated = PakeVerifier::generate(TEST_PASSCODE);
+        assert_eq!(generated.salt.len(), PAKE_SALT_LEN);
+        assert_eq!(generated.iterations, PAKE_ITERATIONS);
+        assert_ne!(generated.verifier, derived.verifier);
+        assert_eq!(
+            PakeVerifier::derive(TEST_PASSCODE, &generated.salt, PAKE_ITERATIONS),
+            generated
+        );
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("commissioning.json");
+
+        let data = CommissioningData::generate();
+        data.save(&path).unwrap();
+        assert_eq!(CommissioningData::load(&path), Some(data));
+
+        fs::write(&path, r#"{"discriminator":4096,"passcode":12345678}"#).unwrap();
+        assert_eq!(CommissioningData::load(&path), None);
+    }
+}
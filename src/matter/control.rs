@@ -0,0 +1,432 @@
+//! Runtime control of a running bridge instance.
+//!
+//! [`BridgeControl`] is shared between the Matter stack and the local control plane
+//! (see [`crate::control`]). The stack registers every endpoint it wires up, so values
+//! can be read and set from outside Matter. Operations that need the `Matter`
+//! instance itself (commissioning window, fabric management) are queued to the stack
+//! thread and answered from there.
+
+use super::clusters::{
+    GenericSwitchState, HumiditySensor, PowerSource, SwitchGestures, TemperatureSensor,
+};
+use super::endpoints::controls::{DeviceSwitch, Switch};
+use super::handler_bridge::{SensorBridge, SwitchBridge};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::channel::Channel;
+use parking_lot::RwLock;
+use rs_matter::dm::clusters::on_off::OnOffHooks;
+use serde::Serialize;
+use serde_json::{Value, json};
+use std::sync::{Arc, OnceLock};
+use thiserror::Error;
+use tokio::sync::{broadcast, oneshot};
+
+/// Number of stack requests that can be queued before senders wait
+const REQUEST_QUEUE_SIZE: usize = 4;
+
+/// Number of endpoint changes buffered per change subscriber
+const CHANGE_QUEUE_SIZE: usize = 64;
+
+/// Error types for control operations.
+#[derive(Debug, Error)]
+pub enum ControlError {
+    /// No endpoint with this ID is registered.
+    #[error("unknown endpoint {0}")]
+    UnknownEndpoint(u16),
+
+    /// The endpoint has no value of the requested kind.
+    #[error("endpoint {endpoint} has no {kind} value")]
+    UnknownPoint { endpoint: u16, kind: String },
+
+    /// The endpoint has several settable values and none was selected.
+    #[error("endpoint {0} has several values, select one with \"kind\"")]
+    AmbiguousPoint(u16),
+
+    /// The endpoint has no values to read or set.
+    #[error("{0} has no values")]
+    NoPoints(String),
+
+    /// Toggle was requested for a value that is not a switch.
+    #[error("{0} is not a switch")]
+    NotASwitch(String),
+
+    /// The value has the wrong type or is out of range.
+    #[error("invalid value: {0}")]
+    InvalidValue(String),
+
+    /// The Matter stack is not running (anymore).
+    #[error("Matter stack is not running")]
+    StackUnavailable,
+
+    /// No fabric with this index is commissioned.
+    #[error("unknown fabric {0}")]
+    UnknownFabric(u8),
+
+    /// The Matter stack rejected the operation.
+    #[error("Matter error: {0}")]
+    Matter(String),
+}
+
+/// A readable (and possibly settable) value on an endpoint.
+#[derive(Clone)]
+pub enum ControlPoint {
+    /// BooleanState (contact sensor)
+    Contact(Arc<SensorBridge>),
+    /// OccupancySensing (motion sensor)
+    Occupancy(Arc<SensorBridge>),
+    /// OnOff of a child switch or light
+    OnOff(Arc<SwitchBridge>),
+    /// Device-level OnOff of a parent endpoint (cascades to the children)
+    DeviceSwitch(Arc<DeviceSwitch>),
+    /// Bridge master OnOff (cascades to all device switches)
+    MasterSwitch(Arc<Switch>),
+    /// TemperatureMeasurement in degrees Celsius
+    Temperature(Arc<TemperatureSensor>),
+    /// RelativeHumidityMeasurement in percent
+    Humidity(Arc<HumiditySensor>),
+    /// PowerSource battery level in percent
+    Battery(Arc<PowerSource>),
+    /// GenericSwitch (button), set with an action name (latching: with a position).
+    /// Raw `press`/`release` edges go through the gesture detector, if any.
+    Button(Arc<GenericSwitchState>, Option<Arc<SwitchGestures>>),
+}
+
+impl ControlPoint {
+    /// Name of this value kind in the control API.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Contact(_) => "contact",
+            Self::Occupancy(_) => "occupancy",
+            Self::OnOff(_) | Self::DeviceSwitch(_) | Self::MasterSwitch(_) => "on_off",
+            Self::Temperature(_) => "temperature",
+            Self::Humidity(_) => "humidity",
+            Self::Battery(_) => "battery",
+            Self::Button(..) => "button",
+        }
+    }
+
+    /// Whether this value can be toggled.
+    pub fn is_switch(&self) -> bool {
+        matches!(
+            self,
+            Self::OnOff(_) | Self::DeviceSwitch(_) | Self::MasterSwitch(_)
+        )
+    }
+
+    /// Get the current value as JSON (null when unknown).
+    pub fn value(&self) -> Value {
+        match self {
+            Self::Contact(bridge) | Self::Occupancy(bridge) => json!(bridge.get()),
+            Self::OnOff(bridge) => json!(bridge.get()),
+            Self::DeviceSwitch(switch) => json!(switch.get()),
+            Self::MasterSwitch(switch) => json!(switch.get()),
+            Self::Temperature(sensor) => json!(sensor.get_celsius()),
+            Self::Humidity(sensor) => json!(sensor.get_percent()),
+            Self::Battery(source) => json!(source.get_battery_percent()),
+            Self::Button(state, _) => json!(state.current_position()),
+        }
+    }
+
+    /// Set the value from JSON and return the resulting value.
+    ///
+    /// Switches and binary sensors take a bool, measurements a number, and buttons
+    /// one of `press`, `release`, `single_press`, `double_press`, `hold_start` or
+    /// `hold_release`. Latching switches take their new position.
+    pub fn set(&self, value: &Value) -> Result<Value, ControlError> {
+        match self {
+            Self::Contact(bridge) | Self::Occupancy(bridge) => bridge.set(as_bool(value)?),
+            Self::OnOff(bridge) => bridge.set(as_bool(value)?),
+            // Same path as a Matter On/Off command (cascades to the children)
+            Self::DeviceSwitch(switch) => switch.set_on_off(as_bool(value)?),
+            Self::MasterSwitch(switch) => switch.set_on_off(as_bool(value)?),
+            Self::Temperature(sensor) => sensor.set_celsius(as_number(value)?),
+            Self::Humidity(sensor) => sensor.set_percent(as_number(value)?),
+            Self::Battery(source) => source.set_battery_percent(as_number(value)?),
+            Self::Button(state, _) if state.is_latching() => {
+                let position = value
+                    .as_u64()
+                    .and_then(|position| u8::try_from(position).ok())
+                    .filter(|&position| state.set_position(position));
+                if position.is_none() {
+                    return Err(ControlError::InvalidValue(format!(
+                        "expected a position below {}, got {}",
+                        state.number_of_positions(),
+                        value
+                    )));
+                }
+            }
+            Self::Button(state, gestures) => match (value.as_str(), gestures) {
+                (Some("press"), Some(gestures)) => gestures.press(),
+                (Some("release"), Some(gestures)) => gestures.release(),
+                (Some("press"), None) => state.press(),
+                (Some("release"), None) => state.release(),
+                (Some("single_press"), _) => state.single_press(),
+                (Some("double_press"), _) => state.double_press(),
+                (Some("hold_start"), _) => state.hold_start(),
+                (Some("hold_release"), _) => state.hold_release(),
+                _ => {
+                    return Err(ControlError::InvalidValue(format!(
+                        "expected a button action, got {}",
+                        value
+                    )));
+                }
+            },
+        }
+        Ok(self.value())
+    }
+
+    /// Toggle a switch and return the new value.
+    pub fn toggle(&self) -> Result<Value, ControlError> {
+        match self.value().as_bool() {
+            Some(on) if self.is_switch() => self.set(&json!(!on)),
+            _ => Err(ControlError::NotASwitch(self.kind().to_string())),
+        }
+    }
+}
+
+fn as_bool(value: &Value) -> Result<bool, ControlError> {
+    value
+        .as_bool()
+        .ok_or_else(|| ControlError::InvalidValue(format!("expected a bool, got {}", value)))
+}
+
+fn as_number(value: &Value) -> Result<f32, ControlError> {
+    value
+        .as_f64()
+        .map(|number| number as f32)
+        .ok_or_else(|| ControlError::InvalidValue(format!("expected a number, got {}", value)))
+}
+
+/// An endpoint registered by the Matter stack.
+#[derive(Clone)]
+pub struct ControlEndpoint {
+    /// Matter endpoint ID
+    pub endpoint_id: u16,
+    /// Label of the virtual device the endpoint belongs to
+    pub device: String,
+    /// Label of the endpoint
+    pub label: String,
+    /// Values on this endpoint (in cluster order)
+    pub points: Vec<ControlPoint>,
+}
+
+impl ControlEndpoint {
+    /// Create an endpoint entry without values.
+    pub fn new(endpoint_id: u16, device: impl Into<String>, label: impl Into<String>) -> Self {
+        Self {
+            endpoint_id,
+            device: device.into(),
+            label: label.into(),
+            points: Vec::new(),
+        }
+    }
+
+    /// Add a value to this endpoint.
+    pub fn with_point(mut self, point: ControlPoint) -> Self {
+        self.points.push(point);
+        self
+    }
+
+    /// Select a value by kind, or the only value when no kind is given.
+    pub fn point(&self, kind: Option<&str>) -> Result<&ControlPoint, ControlError> {
+        match kind {
+            Some(kind) => self
+                .points
+                .iter()
+                .find(|point| point.kind() == kind)
+                .ok_or_else(|| ControlError::UnknownPoint {
+                    endpoint: self.endpoint_id,
+                    kind: kind.to_string(),
+                }),
+            None => match self.points.as_slice() {
+                [point] => Ok(point),
+                [] => Err(ControlError::NoPoints(format!(
+                    "endpoint {}",
+                    self.endpoint_id
+                ))),
+                _ => Err(ControlError::AmbiguousPoint(self.endpoint_id)),
+            },
+        }
+    }
+
+    /// Snapshot of this endpoint with its current values as JSON.
+    pub fn snapshot(&self) -> Value {
+        let values: serde_json::Map<String, Value> = self
+            .points
+            .iter()
+            .map(|point| (point.kind().to_string(), point.value()))
+            .collect();
+        json!({
+            "endpoint_id": self.endpoint_id,
+            "device": self.device,
+            "label": self.label,
+            "values": values,
+        })
+    }
+}
+
+/// A fabric the bridge is commissioned into.
+#[derive(Debug, Clone, Serialize)]
+pub struct FabricInfo {
+    /// Local fabric index
+    pub fabric_index: u8,
+    /// Fabric ID assigned by the commissioner
+    pub fabric_id: u64,
+    /// Node ID of the bridge in this fabric
+    pub node_id: u64,
+    /// Vendor ID of the commissioner
+    pub vendor_id: u16,
+    /// Label set by the commissioner
+    pub label: String,
+    /// Local alias set via the control plane
+    pub alias: Option<String>,
+    /// Last time a session of this fabric was seen (Unix seconds)
+    pub last_session: Option<u64>,
+}
+
+/// Operations served by the Matter stack thread.
+#[derive(Debug, Clone)]
+pub enum StackCommand {
+    /// Open a commissioning window for `timeout_secs` seconds.
+    ///
+    /// Enhanced windows (Enhanced Commissioning Method) use one-time commissioning data
+    /// and a PAKE verifier derived from it; basic windows use the per-install data.
+    OpenCommissioningWindow { timeout_secs: u16, enhanced: bool },
+    /// Close an open commissioning window
+    RevokeCommissioningWindow,
+    /// List the commissioned fabrics
+    ListFabrics,
+    /// Set or clear the local alias of a fabric
+    SetFabricAlias {
+        fabric_index: u8,
+        alias: Option<String>,
+    },
+    /// Remove a fabric with its ACLs, sessions and subscriptions
+    RemoveFabric { fabric_index: u8 },
+    /// Remove all fabrics and controller-assigned state, then reopen commissioning
+    FactoryReset,
+    /// Report node diagnostics (boot reason, reboot count, uptime, memory)
+    Diagnostics,
+}
+
+/// A queued stack command with its reply channel.
+pub struct StackRequest {
+    /// The operation to perform
+    pub command: StackCommand,
+    /// Receives the result (JSON on success)
+    pub reply: oneshot::Sender<Result<Value, ControlError>>,
+}
+
+/// Control handle of one bridge instance.
+///
+/// Cheap to clone. The Matter stack fills the endpoint registry, reports value changes
+/// and serves queued stack requests; the control plane reads the registry and submits
+/// requests.
+#[derive(Clone)]
+pub struct BridgeControl {
+    endpoints: Arc<RwLock<Vec<ControlEndpoint>>>,
+    requests: Arc<Channel<CriticalSectionRawMutex, StackRequest, REQUEST_QUEUE_SIZE>>,
+    changes: broadcast::Sender<u16>,
+    /// UniqueID of the root BasicInformation (set once the stack starts)
+    unique_id: Arc<OnceLock<String>>,
+}
+
+impl BridgeControl {
+    /// Create a new, empty control handle.
+    pub fn new() -> Self {
+        Self {
+            endpoints: Arc::new(RwLock::new(Vec::new())),
+            requests: Arc::new(Channel::new()),
+            changes: broadcast::channel(CHANGE_QUEUE_SIZE).0,
+            unique_id: Arc::new(OnceLock::new()),
+        }
+    }
+
+    /// Publish the UniqueID the bridge reports (called by the Matter stack at startup).
+    pub fn set_unique_id(&self, unique_id: &str) {
+        let _ = self.unique_id.set(unique_id.to_string());
+    }
+
+    /// UniqueID the bridge reports in BasicInformation (None until the stack started).
+    pub fn unique_id(&self) -> Option<&str> {
+        self.unique_id.get().map(String::as_str)
+    }
+
+    /// Register an endpoint (called by the Matter stack while wiring handlers).
+    ///
+    /// Also reported as a change, so subscribers learn about the endpoint.
+    pub fn register(&self, endpoint: ControlEndpoint) {
+        let endpoint_id = endpoint.endpoint_id;
+        self.endpoints.write().push(endpoint);
+        self.endpoint_changed(endpoint_id);
+    }
+
+    /// Report that values of an endpoint changed (called by the Matter stack).
+    pub fn endpoint_changed(&self, endpoint_id: u16) {
+        // No subscribers is fine
+        let _ = self.changes.send(endpoint_id);
+    }
+
+    /// Subscribe to endpoint changes (IDs of endpoints whose values changed).
+    ///
+    /// A subscriber that falls behind receives `Lagged` and should re-read all endpoints.
+    pub fn subscribe_changes(&self) -> broadcast::Receiver<u16> {
+        self.changes.subscribe()
+    }
+
+    /// Get all registered endpoints (in endpoint ID order).
+    pub fn endpoints(&self) -> Vec<ControlEndpoint> {
+        let mut endpoints = self.endpoints.read().clone();
+        endpoints.sort_by_key(|endpoint| endpoint.endpoint_id);
+        endpoints
+    }
+
+    /// Get a registered endpoint by ID.
+    pub fn endpoint(&self, endpoint_id: u16) -> Result<ControlEndpoint, ControlError> {
+        self.endpoints
+            .read()
+            .iter()
+            .find(|endpoint| endpoint.endpoint_id == endpoint_id)
+            .cloned()
+            .ok_or(ControlError::UnknownEndpoint(endpoint_id))
+    }
+
+    /// Queue a command to the Matter stack thread and wait for its result.
+    pub async fn request(&self, command: StackCommand) -> Result<Value, ControlError> {
+        let (reply, result) = oneshot::channel();
+        self.requests.send(StackRequest { command, reply }).await;
+        result.await.unwrap_or(Err(ControlError::StackUnavailable))
+    }
+
+    /// Wait for the next queued stack command (called by the Matter stack).
+    pub(crate) async fn next_request(&self) -> StackRequest {
+        self.requests.receive().await
+    }
+}
+
+impl Default for BridgeControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_latching_button() {
+        let state = Arc::new(GenericSwitchState::latching(2));
+        let point = ControlPoint::Button(state.clone(), None);
+
+        assert_eq!(point.set(&json!(1)).unwrap(), json!(1));
+        for invalid in [json!(2), json!(-1), json!(1.5), json!("press")] {
+            assert!(
+                matches!(point.set(&invalid), Err(ControlError::InvalidValue(_))),
+                "{}",
+                invalid
+            );
+        }
+        assert_eq!(state.current_position(), 1);
+    }
+}
@@ -11,7 +11,7 @@ pub struct HardCodedDevAtt;
 // These certificates use test Vendor ID 0xFFF1 and Product ID 0x8000
 
 // Certification Declaration (CD) - Signed statement about device compliance
-const CD: &[u8] = &[
+pub(super) const CD: &[u8] = &[
     0x30, 0x81, 0xe9, 0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x02, 0xa0, 0x81,
     0xdb, 0x30, 0x81, 0xd8, 0x02, 0x01, 0x03, 0x31, 0x0d, 0x30, 0x0b, 0x06, 0x09, 0x60, 0x86, 0x48,
     0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x30, 0x45, 0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d,
@@ -1,24 +1,126 @@
 //! Device information for Matter stack.
 //!
-//! Custom device details replacing rs-matter's test defaults.
+//! The root BasicInformation is built at runtime from `MatterConfig`. The serial
+//! number and UniqueID default to values generated once per install, so several
+//! bridges in one home do not report identical identities.
 
+use super::json_store::write_json_atomic;
+use crate::config::MatterConfig;
+use log::{error, info, warn};
+use rand::Rng;
 use rs_matter::dm::clusters::basic_info::BasicInfoConfig;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
 
-/// Device details for Virtual Matter Bridge.
+/// Hardware version reported in BasicInformation
+const HW_VER: u16 = 1;
+
+/// Per-install device identity (serial number and UniqueID).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceIdentity {
+    pub serial_number: String,
+    pub unique_id: String,
+}
+
+impl DeviceIdentity {
+    /// Generate a random identity (`VMB-XXXXXXXX` serial, 32 hex digit UniqueID).
+    pub fn generate() -> Self {
+        let mut rng = rand::thread_rng();
+        Self {
+            serial_number: format!("VMB-{:08X}", rng.r#gen::<u32>()),
+            unique_id: format!("{:032X}", rng.r#gen::<u128>()),
+        }
+    }
+
+    /// Resolve the identity for this install.
+    ///
+    /// Configured values win; anything not configured comes from `path`, which is
+    /// created with freshly generated values on first run.
+    pub fn resolve(config: &MatterConfig, path: &Path) -> Self {
+        let persisted = fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| match serde_json::from_str::<Self>(&contents) {
+                Ok(identity) => Some(identity),
+                Err(e) => {
+                    warn!("Ignoring invalid device identity {:?}: {}", path, e);
+                    None
+                }
+            })
+            .unwrap_or_else(|| {
+                let generated = Self::generate();
+                match write_json_atomic(path, &generated) {
+                    Ok(()) => info!("Generated per-install device identity in {:?}", path),
+                    Err(e) => error!("Failed to persist device identity to {:?}: {}", path, e),
+                }
+                generated
+            });
+
+        Self {
+            serial_number: config
+                .serial_number
+                .clone()
+                .unwrap_or(persisted.serial_number),
+            unique_id: config.unique_id.clone().unwrap_or(persisted.unique_id),
+        }
+    }
+}
+
+/// Encode the crate version as the BasicInformation SoftwareVersion.
 ///
-/// Replaces `TEST_DEV_DET` with proper naming:
-/// - Product name: "Virtual Matter Bridge" (shown as device name in Home Assistant)
-/// - Vendor name: "timlisemer" (shown as "by timlisemer")
-pub const DEV_INFO: BasicInfoConfig<'static> = BasicInfoConfig {
-    vid: 0xFFF1,
-    pid: 0x8001,
-    hw_ver: 1,
-    hw_ver_str: "1",
-    sw_ver: 1,
-    sw_ver_str: "1.0",
-    serial_no: "VMB-001",
-    device_name: "VirtualMatterBridge",
-    product_name: "Virtual Matter Bridge",
-    vendor_name: "timlisemer",
-    ..BasicInfoConfig::new()
-};
+/// `major.minor.patch` becomes `major << 16 | minor << 8 | patch`.
+pub fn software_version() -> u32 {
+    let part = |s: &str| s.parse::<u32>().unwrap_or(0) & 0xFF;
+    part(env!("CARGO_PKG_VERSION_MAJOR")) << 16
+        | part(env!("CARGO_PKG_VERSION_MINOR")) << 8
+        | part(env!("CARGO_PKG_VERSION_PATCH"))
+}
+
+/// Build the root BasicInformation from configuration.
+///
+/// The strings are leaked: the config lives for the whole process, like the Matter stack.
+pub fn build_basic_info(
+    config: &MatterConfig,
+    identity: &DeviceIdentity,
+) -> &'static BasicInfoConfig<'static> {
+    let leak = |s: &str| -> &'static str { s.to_string().leak() };
+
+    Box::leak(Box::new(BasicInfoConfig {
+        vid: config.vendor_id,
+        pid: config.product_id,
+        hw_ver: HW_VER,
+        hw_ver_str: "1",
+        sw_ver: software_version(),
+        sw_ver_str: env!("CARGO_PKG_VERSION"),
+        serial_no: leak(&identity.serial_number),
+        device_name: leak(&config.device_name),
+        product_name: leak(&config.device_name),
+        vendor_name: leak(&config.vendor_name),
+        product_url: leak(&config.product_url),
+        unique_id: leak(&identity.unique_id),
+        ..BasicInfoConfig::new()
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_software_version() {
+        let expected = env!("CARGO_PKG_VERSION_MAJOR").parse::<u32>().unwrap() << 16
+            | env!("CARGO_PKG_VERSION_MINOR").parse::<u32>().unwrap() << 8
+            | env!("CARGO_PKG_VERSION_PATCH").parse::<u32>().unwrap();
+        assert_eq!(software_version(), expected);
+    }
+
+    #[test]
+    fn test_generated_identities_differ() {
+        let a = DeviceIdentity::generate();
+        let b = DeviceIdentity::generate();
+        assert!(a.serial_number.starts_with("VMB-"));
+        assert_eq!(a.serial_number.len(), 12);
+        assert_eq!(a.unique_id.len(), 32);
+        assert_ne!(a, b);
+    }
+}
@@ -166,3 +166,18 @@ pub const DEV_TYPE_GENERIC_SWITCH: DeviceType = DeviceType {
     dtype: 0x000F,
     drev: 2,
 };
+
+/// Matter Power Source device type (utility)
+///
+/// Device Type ID: 0x0011 (17 decimal)
+/// Device Type Revision: 1
+///
+/// Required clusters:
+/// - PowerSource (0x002F)
+/// - Descriptor (standard)
+///
+/// Added alongside the functional device type on battery-powered endpoints.
+pub const DEV_TYPE_POWER_SOURCE: DeviceType = DeviceType {
+    dtype: 0x0011,
+    drev: 1,
+};
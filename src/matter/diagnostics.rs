@@ -0,0 +1,265 @@
+//! Node diagnostics for the GeneralDiagnostics cluster.
+//!
+//! Every start of the bridge counts as a reboot of the Matter node. The reboot count,
+//! the accumulated operational time and whether the last run shut down cleanly are
+//! stored in a small JSON file next to `matter.bin`, so the boot reason can be told
+//! apart on the next start: software update, host reboot, graceful restart or crash.
+
+use super::json_store::write_json_atomic;
+use log::{error, info, warn};
+use parking_lot::Mutex;
+use rs_matter::dm::clusters::gen_diag::{BootReasonEnum, GenDiag};
+use rs_matter::error::{Error, ErrorCode};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::Instant;
+
+/// Kernel boot ID (changes on every host boot)
+const HOST_BOOT_ID_PATH: &str = "/proc/sys/kernel/random/boot_id";
+
+/// How often the accumulated operational time is written at most
+const CHECKPOINT_INTERVAL_SECS: u64 = 600;
+
+/// Persisted diagnostics state.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct DiagnosticsState {
+    reboot_count: u16,
+    /// Operational time of all runs up to the last checkpoint
+    operational_secs: u64,
+    /// Whether the last run shut down gracefully
+    clean_shutdown: bool,
+    /// Software version of the last run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sw_version: Option<String>,
+    /// Host boot ID of the last run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    host_boot_id: Option<String>,
+}
+
+/// Why the bridge (re)started.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BootReason {
+    /// First start, nothing known about a previous run
+    Unspecified,
+    /// The host was rebooted
+    PowerOnReboot,
+    /// The previous run ended without a graceful shutdown (crash or kill)
+    SoftwareWatchdogReset,
+    /// A different software version ran before
+    SoftwareUpdateCompleted,
+    /// The previous run shut down gracefully
+    SoftwareReset,
+}
+
+impl BootReason {
+    /// Determine the boot reason from the state of the previous run.
+    fn determine(
+        previous: Option<&DiagnosticsState>,
+        sw_version: &str,
+        host_boot_id: Option<&str>,
+    ) -> Self {
+        let Some(previous) = previous else {
+            return Self::Unspecified;
+        };
+        if previous.sw_version.as_deref() != Some(sw_version) {
+            Self::SoftwareUpdateCompleted
+        } else if host_boot_id.is_some() && previous.host_boot_id.as_deref() != host_boot_id {
+            Self::PowerOnReboot
+        } else if previous.clean_shutdown {
+            Self::SoftwareReset
+        } else {
+            Self::SoftwareWatchdogReset
+        }
+    }
+
+    /// Name of the boot reason (for logs and the control plane).
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Unspecified => "unspecified",
+            Self::PowerOnReboot => "power_on_reboot",
+            Self::SoftwareWatchdogReset => "unclean_shutdown",
+            Self::SoftwareUpdateCompleted => "software_update",
+            Self::SoftwareReset => "restart",
+        }
+    }
+
+    fn to_matter(self) -> BootReasonEnum {
+        match self {
+            Self::Unspecified => BootReasonEnum::Unspecified,
+            Self::PowerOnReboot => BootReasonEnum::PowerOnReboot,
+            Self::SoftwareWatchdogReset => BootReasonEnum::SoftwareWatchdogReset,
+            Self::SoftwareUpdateCompleted => BootReasonEnum::SoftwareUpdateCompleted,
+            Self::SoftwareReset => BootReasonEnum::SoftwareReset,
+        }
+    }
+}
+
+/// Persisted node diagnostics, served by the GeneralDiagnostics cluster.
+pub struct NodeDiagnostics {
+    path: PathBuf,
+    started: Instant,
+    boot_reason: BootReason,
+    state: Mutex<DiagnosticsState>,
+    /// Operational time of previous runs
+    previous_operational_secs: u64,
+}
+
+impl NodeDiagnostics {
+    /// Record a start of the bridge running `sw_version`.
+    ///
+    /// Determines the boot reason, increments the reboot count and marks the run as
+    /// not shut down cleanly until [`shutdown`](Self::shutdown) is called.
+    pub fn start(path: impl Into<PathBuf>, sw_version: &str) -> Self {
+        let host_boot_id = fs::read_to_string(HOST_BOOT_ID_PATH)
+            .ok()
+            .map(|id| id.trim().to_string());
+        Self::start_with_boot_id(path, sw_version, host_boot_id)
+    }
+
+    fn start_with_boot_id(
+        path: impl Into<PathBuf>,
+        sw_version: &str,
+        host_boot_id: Option<String>,
+    ) -> Self {
+        let path = path.into();
+        let previous: Option<DiagnosticsState> = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|e| warn!("Ignoring invalid diagnostics file {:?}: {}", path, e))
+                .ok(),
+            Err(_) => None,
+        };
+        let boot_reason =
+            BootReason::determine(previous.as_ref(), sw_version, host_boot_id.as_deref());
+        let previous = previous.unwrap_or_default();
+
+        let state = DiagnosticsState {
+            reboot_count: previous.reboot_count.saturating_add(1),
+            operational_secs: previous.operational_secs,
+            clean_shutdown: false,
+            sw_version: Some(sw_version.to_string()),
+            host_boot_id,
+        };
+        info!(
+            "Boot #{} (reason: {}, {} operational hours)",
+            state.reboot_count,
+            boot_reason.as_str(),
+            state.operational_secs / 3600
+        );
+
+        let diagnostics = Self {
+            path,
+            started: Instant::now(),
+            boot_reason,
+            previous_operational_secs: previous.operational_secs,
+            state: Mutex::new(state),
+        };
+        diagnostics.persist(&diagnostics.state.lock());
+        diagnostics
+    }
+
+    /// Number of starts of the bridge.
+    pub fn reboot_count(&self) -> u16 {
+        self.state.lock().reboot_count
+    }
+
+    /// Why the bridge started.
+    pub fn boot_reason(&self) -> BootReason {
+        self.boot_reason
+    }
+
+    /// Seconds since the bridge started.
+    pub fn uptime_secs(&self) -> u64 {
+        self.started.elapsed().as_secs()
+    }
+
+    /// Operational hours of all runs.
+    pub fn total_operational_hours(&self) -> u32 {
+        ((self.previous_operational_secs + self.uptime_secs()) / 3600) as u32
+    }
+
+    /// Persist the accumulated operational time (called periodically).
+    pub fn checkpoint(&self) {
+        let operational_secs = self.previous_operational_secs + self.uptime_secs();
+        let mut state = self.state.lock();
+        if operational_secs >= state.operational_secs + CHECKPOINT_INTERVAL_SECS {
+            state.operational_secs = operational_secs;
+            self.persist(&state);
+        }
+    }
+
+    /// Record a graceful shutdown.
+    pub fn shutdown(&self) {
+        let mut state = self.state.lock();
+        state.operational_secs = self.previous_operational_secs + self.uptime_secs();
+        state.clean_shutdown = true;
+        self.persist(&state);
+    }
+
+    fn persist(&self, state: &DiagnosticsState) {
+        if let Err(e) = write_json_atomic(&self.path, state) {
+            error!("Failed to persist diagnostics to {:?}: {}", self.path, e);
+        }
+    }
+}
+
+impl GenDiag for NodeDiagnostics {
+    fn reboot_count(&self) -> Result<u16, Error> {
+        Ok(NodeDiagnostics::reboot_count(self))
+    }
+
+    fn boot_reason(&self) -> Result<BootReasonEnum, Error> {
+        Ok(self.boot_reason.to_matter())
+    }
+
+    fn up_time(&self) -> Result<u64, Error> {
+        Ok(self.uptime_secs())
+    }
+
+    fn total_operational_hours(&self) -> Result<u32, Error> {
+        Ok(NodeDiagnostics::total_operational_hours(self))
+    }
+
+    fn test_event_triggers_enabled(&self) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    fn test_event_trigger(&self, _key: &[u8], _trigger: u64) -> Result<(), Error> {
+        Err(ErrorCode::ConstraintError.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_boot_reasons() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("diagnostics.json");
+        let boot_id = || Some("host-1".to_string());
+
+        let first = NodeDiagnostics::start_with_boot_id(&path, "1.0.0", boot_id());
+        assert_eq!(first.boot_reason(), BootReason::Unspecified);
+        assert_eq!(first.reboot_count(), 1);
+
+        // No graceful shutdown recorded: the previous run crashed
+        let crashed = NodeDiagnostics::start_with_boot_id(&path, "1.0.0", boot_id());
+        assert_eq!(crashed.boot_reason(), BootReason::SoftwareWatchdogReset);
+        assert_eq!(crashed.reboot_count(), 2);
+        crashed.shutdown();
+
+        let restarted = NodeDiagnostics::start_with_boot_id(&path, "1.0.0", boot_id());
+        assert_eq!(restarted.boot_reason(), BootReason::SoftwareReset);
+        restarted.shutdown();
+
+        let rebooted =
+            NodeDiagnostics::start_with_boot_id(&path, "1.0.0", Some("host-2".to_string()));
+        assert_eq!(rebooted.boot_reason(), BootReason::PowerOnReboot);
+
+        let updated =
+            NodeDiagnostics::start_with_boot_id(&path, "1.1.0", Some("host-2".to_string()));
+        assert_eq!(updated.boot_reason(), BootReason::SoftwareUpdateCompleted);
+        assert_eq!(updated.reboot_count(), 5);
+    }
+}
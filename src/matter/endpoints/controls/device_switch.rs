@@ -5,7 +5,9 @@
 //! (turns off their switches and marks them unreachable).
 
 use super::helpers::BinarySwitchHelper;
+use crate::matter::clusters::ReachableState;
 use crate::matter::handler_bridge::SwitchBridge;
+use crate::matter::runtime_state::{StartUpOnOff, StateHandle, resolve_start_up};
 use parking_lot::RwLock;
 use rs_matter::dm::Cluster;
 use rs_matter::dm::clusters::decl::on_off as on_off_cluster;
@@ -14,7 +16,7 @@ use rs_matter::error::Error;
 use rs_matter::tlv::Nullable;
 use rs_matter::with;
 use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::atomic::{AtomicU8, Ordering};
 
 /// Device-level OnOff switch for parent endpoints.
 ///
@@ -33,8 +35,8 @@ pub struct DeviceSwitch {
     start_up_on_off: AtomicU8,
     /// Child switch bridges to cascade ON/OFF commands to
     child_switches: RwLock<Vec<Arc<SwitchBridge>>>,
-    /// Child reachable flags to update when this device is turned on/off
-    child_reachable: RwLock<Vec<Arc<AtomicBool>>>,
+    /// Child reachable states to enable/disable when this device is turned on/off
+    child_reachable: RwLock<Vec<Arc<ReachableState>>>,
 }
 
 impl DeviceSwitch {
@@ -63,8 +65,8 @@ impl DeviceSwitch {
         self.child_switches.write().push(switch);
     }
 
-    /// Add a child reachable flag that will be updated when this device turns on/off.
-    pub fn add_child_reachable(&self, reachable: Arc<AtomicBool>) {
+    /// Add a child reachable state that will be updated when this device turns on/off.
+    pub fn add_child_reachable(&self, reachable: Arc<ReachableState>) {
         self.child_reachable.write().push(reachable);
     }
 
@@ -80,17 +82,31 @@ impl DeviceSwitch {
                     switch.set(false);
                 }
                 for reachable in self.child_reachable.read().iter() {
-                    reachable.store(false, Ordering::SeqCst);
+                    reachable.set_enabled(false);
                 }
             } else {
                 // Turning ON: mark all children reachable (but don't change their state)
                 for reachable in self.child_reachable.read().iter() {
-                    reachable.store(true, Ordering::SeqCst);
+                    reachable.set_enabled(true);
                 }
             }
         }
     }
 
+    /// Restore the persisted device state and persist future changes.
+    ///
+    /// Applies StartUpOnOff (Off/On/Toggle, or the previous value when null).
+    pub fn restore_state(&self, handle: StateHandle) {
+        let state = handle.state();
+        self.start_up_on_off.store(
+            Self::encode_start_up(state.start_up_on_off.map(StartUpOnOff::to_matter)),
+            Ordering::SeqCst,
+        );
+        let on = resolve_start_up(state.start_up_on_off, state.on_off, self.get());
+        self.set_with_cascade(on);
+        self.helper.set_state_handle(handle);
+    }
+
     /// Called by virtual_bridge_onoff when it turns OFF - forces this device OFF.
     pub fn set_from_master(&self, on: bool) {
         if !on {
@@ -130,7 +146,10 @@ impl OnOffHooks for DeviceSwitch {
     /// Cluster definition with basic OnOff functionality.
     const CLUSTER: Cluster<'static> = on_off_cluster::FULL_CLUSTER
         .with_revision(6)
-        .with_attrs(with!(required; on_off_cluster::AttributeId::OnOff))
+        .with_attrs(with!(
+            required;
+            on_off_cluster::AttributeId::OnOff | on_off_cluster::AttributeId::StartUpOnOff
+        ))
         .with_cmds(with!(
             on_off_cluster::CommandId::Off
                 | on_off_cluster::CommandId::On
@@ -157,8 +176,12 @@ impl OnOffHooks for DeviceSwitch {
     }
 
     fn set_start_up_on_off(&self, value: Nullable<StartUpOnOffEnum>) -> Result<(), Error> {
+        let value = value.into_option();
         self.start_up_on_off
-            .store(Self::encode_start_up(value.into_option()), Ordering::SeqCst);
+            .store(Self::encode_start_up(value), Ordering::SeqCst);
+        if let Some(handle) = self.helper.state_handle() {
+            handle.save_start_up(value.map(StartUpOnOff::from_matter));
+        }
         Ok(())
     }
 
@@ -7,6 +7,7 @@
 //! the notification is pushed instantly to Home Assistant.
 
 use crate::matter::endpoints::endpoints_helpers::{ClusterNotifier, NotifiableSensor, Sensor};
+use crate::matter::runtime_state::StateHandle;
 use parking_lot::RwLock;
 use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 
@@ -21,6 +22,7 @@ pub struct BinarySwitchHelper {
     state: AtomicBool,
     version: AtomicU32,
     notifier: RwLock<Option<ClusterNotifier>>,
+    state_handle: RwLock<Option<StateHandle>>,
 }
 
 impl BinarySwitchHelper {
@@ -30,6 +32,27 @@ impl BinarySwitchHelper {
             state: AtomicBool::new(initial),
             version: AtomicU32::new(0),
             notifier: RwLock::new(None),
+            state_handle: RwLock::new(None),
+        }
+    }
+
+    /// Persist the switch state to the runtime state store from now on.
+    ///
+    /// The current value is written immediately.
+    pub fn set_state_handle(&self, handle: StateHandle) {
+        handle.save_on_off(self.get());
+        *self.state_handle.write() = Some(handle);
+    }
+
+    /// Get the runtime state handle, if persistence is enabled.
+    pub fn state_handle(&self) -> Option<StateHandle> {
+        self.state_handle.read().clone()
+    }
+
+    /// Persist the current value (if persistence is enabled).
+    fn persist(&self) {
+        if let Some(handle) = self.state_handle.read().as_ref() {
+            handle.save_on_off(self.get());
         }
     }
 
@@ -49,6 +72,7 @@ impl BinarySwitchHelper {
             if let Some(notifier) = self.notifier.read().as_ref() {
                 notifier.notify();
             }
+            self.persist();
         }
     }
 
@@ -62,6 +86,7 @@ impl BinarySwitchHelper {
         if let Some(notifier) = self.notifier.read().as_ref() {
             notifier.notify();
         }
+        self.persist();
         !old
     }
 }
@@ -4,6 +4,7 @@
 //! for state management. Used for Matter On/Off Light endpoints.
 
 use super::helpers::BinarySwitchHelper;
+use crate::matter::runtime_state::{StartUpOnOff, StateHandle, resolve_start_up};
 use rs_matter::dm::Cluster;
 use rs_matter::dm::clusters::decl::on_off as on_off_cluster;
 use rs_matter::dm::clusters::on_off::{EffectVariantEnum, OnOffHooks, StartUpOnOffEnum};
@@ -54,6 +55,20 @@ impl LightSwitch {
         self.helper.toggle()
     }
 
+    /// Restore the persisted light state and persist future changes.
+    ///
+    /// Applies StartUpOnOff (Off/On/Toggle, or the previous value when null).
+    pub fn restore_state(&self, handle: StateHandle) {
+        let state = handle.state();
+        self.start_up_on_off.store(
+            Self::encode_start_up(state.start_up_on_off.map(StartUpOnOff::to_matter)),
+            Ordering::SeqCst,
+        );
+        let on = resolve_start_up(state.start_up_on_off, state.on_off, self.get());
+        self.helper.set(on);
+        self.helper.set_state_handle(handle);
+    }
+
     /// Encode StartUpOnOffEnum to u8
     fn encode_start_up(value: Option<StartUpOnOffEnum>) -> u8 {
         match value {
@@ -86,7 +101,10 @@ impl OnOffHooks for LightSwitch {
     /// Cluster definition with basic OnOff functionality.
     const CLUSTER: Cluster<'static> = on_off_cluster::FULL_CLUSTER
         .with_revision(6)
-        .with_attrs(with!(required; on_off_cluster::AttributeId::OnOff))
+        .with_attrs(with!(
+            required;
+            on_off_cluster::AttributeId::OnOff | on_off_cluster::AttributeId::StartUpOnOff
+        ))
         .with_cmds(with!(
             on_off_cluster::CommandId::Off
                 | on_off_cluster::CommandId::On
@@ -113,8 +131,12 @@ impl OnOffHooks for LightSwitch {
     }
 
     fn set_start_up_on_off(&self, value: Nullable<StartUpOnOffEnum>) -> Result<(), Error> {
+        let value = value.into_option();
         self.start_up_on_off
-            .store(Self::encode_start_up(value.into_option()), Ordering::SeqCst);
+            .store(Self::encode_start_up(value), Ordering::SeqCst);
+        if let Some(handle) = self.helper.state_handle() {
+            handle.save_start_up(value.map(StartUpOnOff::from_matter));
+        }
         Ok(())
     }
 
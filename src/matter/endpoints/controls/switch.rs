@@ -5,6 +5,7 @@
 
 use super::device_switch::DeviceSwitch;
 use super::helpers::BinarySwitchHelper;
+use crate::matter::runtime_state::{StartUpOnOff, StateHandle, resolve_start_up};
 use parking_lot::RwLock;
 use rs_matter::dm::Cluster;
 use rs_matter::dm::clusters::decl::on_off as on_off_cluster;
@@ -62,6 +63,24 @@ impl Switch {
         self.helper.toggle()
     }
 
+    /// Restore the persisted switch state and persist future changes.
+    ///
+    /// Applies StartUpOnOff (Off/On/Toggle, or the previous value when null). An OFF
+    /// state cascades to the device switches, so call this after `add_cascade_target`.
+    pub fn restore_state(&self, handle: StateHandle) {
+        let state = handle.state();
+        self.start_up_on_off.store(
+            Self::encode_start_up(state.start_up_on_off.map(StartUpOnOff::to_matter)),
+            Ordering::SeqCst,
+        );
+        let on = resolve_start_up(state.start_up_on_off, state.on_off, self.get());
+        self.helper.set(on);
+        self.helper.set_state_handle(handle);
+        if !on {
+            self.cascade_off();
+        }
+    }
+
     /// Add a device switch that should be cascaded when this switch turns OFF.
     /// Used to implement virtual_bridge_onoff → parent DeviceSwitch cascade.
     pub fn add_cascade_target(&self, target: Arc<DeviceSwitch>) {
@@ -107,7 +126,10 @@ impl OnOffHooks for Switch {
     /// Cluster definition with basic OnOff functionality.
     const CLUSTER: Cluster<'static> = on_off_cluster::FULL_CLUSTER
         .with_revision(6)
-        .with_attrs(with!(required; on_off_cluster::AttributeId::OnOff))
+        .with_attrs(with!(
+            required;
+            on_off_cluster::AttributeId::OnOff | on_off_cluster::AttributeId::StartUpOnOff
+        ))
         .with_cmds(with!(
             on_off_cluster::CommandId::Off
                 | on_off_cluster::CommandId::On
@@ -139,8 +161,12 @@ impl OnOffHooks for Switch {
     }
 
     fn set_start_up_on_off(&self, value: Nullable<StartUpOnOffEnum>) -> Result<(), Error> {
+        let value = value.into_option();
         self.start_up_on_off
-            .store(Self::encode_start_up(value.into_option()), Ordering::SeqCst);
+            .store(Self::encode_start_up(value), Ordering::SeqCst);
+        if let Some(handle) = self.helper.state_handle() {
+            handle.save_start_up(value.map(StartUpOnOff::from_matter));
+        }
         Ok(())
     }
 
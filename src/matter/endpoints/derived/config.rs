@@ -0,0 +1,354 @@
+//! Derived endpoints read from a JSON file.
+//!
+//! Each entry adds an endpoint to a (new) bridged device and computes its value from
+//! other endpoints of the bridge:
+//!
+//! ```json
+//! [
+//!     { "device": "House", "label": "Any Window Open", "type": "contact",
+//!       "expression": "any(kitchen, bath)",
+//!       "inputs": { "kitchen": "Kitchen/Window", "bath": "Bath/Window" } },
+//!     { "device": "House", "label": "Dew Point", "type": "temperature",
+//!       "expression": "round(dew_point(t, rh), 1)",
+//!       "inputs": {
+//!           "t": { "endpoint": "Tim Thermometer/Climate", "kind": "temperature" },
+//!           "rh": { "endpoint": "Tim Thermometer/Climate", "kind": "humidity" }
+//!       } }
+//! ]
+//! ```
+//!
+//! Inputs are addressed like local bindings (`"<device>/<endpoint>"`, a label or an
+//! endpoint ID), with `kind` selecting the value of endpoints that have several. They
+//! are resolved through [`BridgeControl`] once the Matter stack registers the endpoint,
+//! so derived endpoints can also read each other.
+
+use super::{
+    DerivedBinarySensor, DerivedEndpoint, DerivedEndpoints, DerivedInput, DerivedOutput,
+    ExpressionError,
+};
+use crate::matter::clusters::{HumiditySensor, TemperatureSensor};
+use crate::matter::control::{BridgeControl, ControlPoint};
+use crate::matter::local_bindings::EndpointRef;
+use crate::matter::{EndpointConfig, VirtualDevice};
+use log::warn;
+use parking_lot::RwLock;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use thiserror::Error;
+
+/// Default derived endpoints file name in the storage directory
+pub const DERIVED_FILE: &str = "derived.json";
+
+/// Errors loading derived endpoints.
+#[derive(Debug, Error)]
+pub enum DerivedConfigError {
+    #[error("failed to read {0}: {1}")]
+    Read(PathBuf, std::io::Error),
+
+    #[error("invalid derived endpoints: {0}")]
+    Parse(#[from] serde_json::Error),
+
+    #[error("derived endpoint {0}: {1}")]
+    Expression(String, ExpressionError),
+}
+
+/// Endpoint type of a derived value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum OutputType {
+    Contact,
+    Occupancy,
+    Temperature,
+    Humidity,
+}
+
+/// Reference to a value of a bridge endpoint.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+enum InputRef {
+    /// The endpoint's only value
+    Endpoint(EndpointRef),
+    /// A value of an endpoint with several
+    Value { endpoint: EndpointRef, kind: String },
+}
+
+/// A derived endpoint as listed in the file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DerivedEntry {
+    device: String,
+    label: String,
+    #[serde(rename = "type")]
+    output: OutputType,
+    expression: String,
+    #[serde(default)]
+    inputs: BTreeMap<String, InputRef>,
+}
+
+/// Input read from a value of a bridge endpoint.
+///
+/// Resolved once the endpoint is registered; the version advances with every change
+/// notification of the endpoint.
+pub struct EndpointInput {
+    endpoint: EndpointRef,
+    kind: Option<String>,
+    point: RwLock<Option<(u16, ControlPoint)>>,
+    version: AtomicU32,
+    /// The endpoint was found but has no such value (reported once)
+    failed: AtomicBool,
+}
+
+impl EndpointInput {
+    /// Create an input for a value of an endpoint (`kind` = None for its only value).
+    pub fn new(endpoint: EndpointRef, kind: Option<String>) -> Self {
+        Self {
+            endpoint,
+            kind,
+            point: RwLock::new(None),
+            version: AtomicU32::new(0),
+            failed: AtomicBool::new(false),
+        }
+    }
+
+    /// Look the value up in the registry.
+    fn resolve(&self, control: &BridgeControl) -> Option<(u16, ControlPoint)> {
+        if self.failed.load(Ordering::Relaxed) {
+            return None;
+        }
+        let endpoint = control.endpoints().into_iter().find(|endpoint| {
+            self.endpoint
+                .matches(endpoint.endpoint_id, &endpoint.device, &endpoint.label)
+        })?;
+        match endpoint.point(self.kind.as_deref()) {
+            Ok(point) => Some((endpoint.endpoint_id, point.clone())),
+            Err(e) => {
+                warn!(
+                    "[Derived] Input {}/{}: {}",
+                    endpoint.device, endpoint.label, e
+                );
+                self.failed.store(true, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+}
+
+impl DerivedInput for EndpointInput {
+    fn version(&self) -> u32 {
+        self.version.load(Ordering::SeqCst)
+    }
+
+    fn value(&self) -> f64 {
+        match self.point.read().as_ref().map(|(_, point)| point.value()) {
+            Some(Value::Bool(value)) => {
+                if value {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Some(value) => value.as_f64().unwrap_or(f64::NAN),
+            None => f64::NAN,
+        }
+    }
+
+    fn is_ready(&self) -> bool {
+        self.point.read().is_some()
+    }
+
+    fn endpoint_changed(&self, control: &BridgeControl, endpoint_id: Option<u16>) {
+        let mut point = self.point.write();
+        match (point.as_ref(), endpoint_id) {
+            (Some((id, _)), Some(changed)) if *id != changed => return,
+            (Some(_), _) => {}
+            (None, _) => match self.resolve(control) {
+                Some(resolved) => *point = Some(resolved),
+                None => return,
+            },
+        }
+        self.version.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+/// Derived endpoints and the devices exposing them.
+#[derive(Default)]
+pub struct DerivedConfig {
+    devices: Vec<VirtualDevice>,
+    endpoints: DerivedEndpoints,
+}
+
+impl DerivedConfig {
+    /// Parse derived endpoints from JSON (a list of entries).
+    ///
+    /// Entries with the same `device` share one bridged device, in file order.
+    pub fn from_json(json: &str) -> Result<Self, DerivedConfigError> {
+        let entries: Vec<DerivedEntry> = serde_json::from_str(json)?;
+        let mut devices: Vec<(String, Vec<EndpointConfig>)> = Vec::new();
+        let mut endpoints = DerivedEndpoints::new();
+        for entry in entries {
+            let (output, endpoint) = match entry.output {
+                OutputType::Contact | OutputType::Occupancy => {
+                    let sensor = Arc::new(DerivedBinarySensor::new());
+                    let endpoint = if entry.output == OutputType::Contact {
+                        EndpointConfig::contact_sensor(&entry.label, sensor.clone())
+                    } else {
+                        EndpointConfig::occupancy_sensor(&entry.label, sensor.clone())
+                    };
+                    (DerivedOutput::Binary(sensor), endpoint)
+                }
+                OutputType::Temperature => {
+                    let sensor = Arc::new(TemperatureSensor::new(0.0));
+                    let endpoint = EndpointConfig::temperature_sensor(&entry.label, sensor.clone());
+                    (DerivedOutput::Temperature(sensor), endpoint)
+                }
+                OutputType::Humidity => {
+                    let sensor = Arc::new(HumiditySensor::new(0.0));
+                    let endpoint = EndpointConfig::humidity_sensor(&entry.label, sensor.clone());
+                    (DerivedOutput::Humidity(sensor), endpoint)
+                }
+            };
+
+            let name = format!("{}/{}", entry.device, entry.label);
+            let error = |e| DerivedConfigError::Expression(name.clone(), e);
+            let mut derived =
+                DerivedEndpoint::new(&name, &entry.expression, output).map_err(error)?;
+            for (input, reference) in entry.inputs {
+                let (endpoint, kind) = match reference {
+                    InputRef::Endpoint(endpoint) => (endpoint, None),
+                    InputRef::Value { endpoint, kind } => (endpoint, Some(kind)),
+                };
+                derived = derived.with_input(input, Arc::new(EndpointInput::new(endpoint, kind)));
+            }
+            endpoints = endpoints.with_endpoint(derived).map_err(error)?;
+
+            match devices
+                .iter_mut()
+                .find(|(device, _)| *device == entry.device)
+            {
+                Some((_, device_endpoints)) => device_endpoints.push(endpoint),
+                None => devices.push((entry.device, vec![endpoint])),
+            }
+        }
+
+        let devices = devices
+            .into_iter()
+            .map(|(name, device_endpoints)| {
+                // Computed values have nothing to power on/off
+                device_endpoints.into_iter().fold(
+                    VirtualDevice::new(name).without_device_switch(),
+                    VirtualDevice::with_endpoint,
+                )
+            })
+            .collect();
+        Ok(Self { devices, endpoints })
+    }
+
+    /// Load derived endpoints from a JSON file; a missing file means none.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, DerivedConfigError> {
+        let path = path.as_ref();
+        match std::fs::read_to_string(path) {
+            Ok(json) => Self::from_json(&json),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(DerivedConfigError::Read(path.to_path_buf(), e)),
+        }
+    }
+
+    /// Number of derived endpoints.
+    pub fn len(&self) -> usize {
+        self.endpoints.len()
+    }
+
+    /// Whether there are no derived endpoints.
+    pub fn is_empty(&self) -> bool {
+        self.endpoints.is_empty()
+    }
+
+    /// Split into the devices to add to the bridge and the endpoints to evaluate.
+    pub fn into_parts(self) -> (Vec<VirtualDevice>, DerivedEndpoints) {
+        (self.devices, self.endpoints)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matter::control::ControlEndpoint;
+
+    #[test]
+    fn test_derived_config() {
+        let config = DerivedConfig::from_json(
+            r#"[
+                { "device": "House", "label": "Any Open", "type": "contact",
+                  "expression": "any(kitchen, bath)",
+                  "inputs": { "kitchen": "Kitchen/Window", "bath": 5 } },
+                { "device": "House", "label": "Dew Point", "type": "temperature",
+                  "expression": "round(dew_point(t, rh), 1)",
+                  "inputs": {
+                      "t": { "endpoint": "Climate", "kind": "temperature" },
+                      "rh": { "endpoint": "Climate", "kind": "humidity" }
+                  } }
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(config.len(), 2);
+        let (devices, _) = config.into_parts();
+        assert_eq!(devices.len(), 1);
+
+        assert!(matches!(
+            DerivedConfig::from_json(
+                r#"[{ "device": "House", "label": "Any Open", "type": "contact",
+                      "expression": "any(kitchen, bath)", "inputs": { "kitchen": 4 } }]"#,
+            ),
+            Err(DerivedConfigError::Expression(name, ExpressionError::UnknownInput(input)))
+                if name == "House/Any Open" && input == "bath"
+        ));
+        assert!(matches!(
+            DerivedConfig::from_json(
+                r#"[{ "device": "House", "label": "Level", "type": "level", "expression": "1" }]"#,
+            ),
+            Err(DerivedConfigError::Parse(_))
+        ));
+        assert!(
+            DerivedConfig::load("/nonexistent/derived.json")
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_endpoint_input() {
+        let control = BridgeControl::new();
+        let temperature = Arc::new(TemperatureSensor::new(21.5));
+        let humidity = Arc::new(HumiditySensor::new(40.0));
+        let input = EndpointInput::new(
+            EndpointRef::Label("Tim/Climate".to_string()),
+            Some("temperature".to_string()),
+        );
+
+        // Not registered yet
+        input.endpoint_changed(&control, None);
+        assert!(!input.is_ready());
+
+        control.register(
+            ControlEndpoint::new(7, "Tim", "Climate")
+                .with_point(ControlPoint::Temperature(temperature.clone()))
+                .with_point(ControlPoint::Humidity(humidity)),
+        );
+        input.endpoint_changed(&control, Some(7));
+        assert!(input.is_ready());
+        assert_eq!(input.value(), 21.5);
+        let version = input.version();
+
+        // Only changes of its own endpoint advance the version
+        input.endpoint_changed(&control, Some(8));
+        assert_eq!(input.version(), version);
+        temperature.set_celsius(22.0);
+        input.endpoint_changed(&control, Some(7));
+        assert_eq!(input.version(), version + 1);
+        assert_eq!(input.value(), 22.0);
+    }
+}
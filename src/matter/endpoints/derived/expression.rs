@@ -0,0 +1,567 @@
+//! Expression language of derived endpoints.
+//!
+//! Expressions compute a number from named inputs; booleans are 1 (true) and 0 (false),
+//! and any non-zero value counts as true.
+//!
+//! - literals: `21.5`, `true`, `false`
+//! - inputs: `kitchen`, `living_room_temp` (letters, digits and `_`)
+//! - operators, lowest precedence first: `||`, `&&`, comparisons (`<`, `<=`, `>`, `>=`,
+//!   `==`, `!=`), `+` `-`, `*` `/` `%`, unary `-` and `!`
+//! - functions: `min`, `max`, `avg`, `sum`, `any`, `all`, `count` (non-zero arguments)
+//!   with any number of arguments, `abs(x)`, `round(x)`, `round(x, digits)`,
+//!   `clamp(x, lo, hi)`, `if(condition, then, else)` and `dew_point(celsius, percent)`
+
+use thiserror::Error;
+
+/// Errors parsing or evaluating an expression.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum ExpressionError {
+    #[error("syntax error at {position}: {message}")]
+    Syntax { position: usize, message: String },
+
+    #[error("unknown function {0}")]
+    UnknownFunction(String),
+
+    #[error("{function} takes {expected} arguments, got {got}")]
+    Arity {
+        function: &'static str,
+        expected: &'static str,
+        got: usize,
+    },
+
+    #[error("unknown input {0}")]
+    UnknownInput(String),
+}
+
+/// Built-in function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Function {
+    Min,
+    Max,
+    Avg,
+    Sum,
+    Any,
+    All,
+    Count,
+    Abs,
+    Round,
+    Clamp,
+    If,
+    DewPoint,
+}
+
+/// Functions by name.
+const FUNCTIONS: [(&str, Function); 12] = [
+    ("min", Function::Min),
+    ("max", Function::Max),
+    ("avg", Function::Avg),
+    ("sum", Function::Sum),
+    ("any", Function::Any),
+    ("all", Function::All),
+    ("count", Function::Count),
+    ("abs", Function::Abs),
+    ("round", Function::Round),
+    ("clamp", Function::Clamp),
+    ("if", Function::If),
+    ("dew_point", Function::DewPoint),
+];
+
+impl Function {
+    fn from_name(name: &str) -> Option<Self> {
+        FUNCTIONS
+            .iter()
+            .find(|(function, _)| *function == name)
+            .map(|(_, function)| *function)
+    }
+
+    fn name(self) -> &'static str {
+        FUNCTIONS
+            .iter()
+            .find(|(_, function)| *function == self)
+            .map_or("?", |(name, _)| name)
+    }
+
+    /// Check the number of arguments.
+    fn check_arity(self, got: usize) -> Result<(), ExpressionError> {
+        let (valid, expected) = match self {
+            Self::Min | Self::Max | Self::Avg | Self::Sum | Self::Any | Self::All | Self::Count => {
+                (got >= 1, "1 or more")
+            }
+            Self::Abs => (got == 1, "1"),
+            Self::Round => (got == 1 || got == 2, "1 or 2"),
+            Self::DewPoint => (got == 2, "2"),
+            Self::Clamp | Self::If => (got == 3, "3"),
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(ExpressionError::Arity {
+                function: self.name(),
+                expected,
+                got,
+            })
+        }
+    }
+}
+
+/// Numeric value of a boolean.
+fn truth(value: bool) -> f64 {
+    if value { 1.0 } else { 0.0 }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UnaryOp {
+    Neg,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinaryOp {
+    Or,
+    And,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl BinaryOp {
+    fn apply(self, a: f64, b: f64) -> f64 {
+        match self {
+            Self::Or => truth(a != 0.0 || b != 0.0),
+            Self::And => truth(a != 0.0 && b != 0.0),
+            Self::Lt => truth(a < b),
+            Self::Le => truth(a <= b),
+            Self::Gt => truth(a > b),
+            Self::Ge => truth(a >= b),
+            Self::Eq => truth(a == b),
+            Self::Ne => truth(a != b),
+            Self::Add => a + b,
+            Self::Sub => a - b,
+            Self::Mul => a * b,
+            Self::Div => a / b,
+            Self::Rem => a % b,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Number(f64),
+    Input(String),
+    Unary(UnaryOp, Box<Node>),
+    Binary(BinaryOp, Box<Node>, Box<Node>),
+    Call(Function, Vec<Node>),
+}
+
+/// A parsed expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression {
+    source: String,
+    root: Node,
+}
+
+impl Expression {
+    /// Parse an expression.
+    pub fn parse(source: &str) -> Result<Self, ExpressionError> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            next: 0,
+            end: source.len(),
+        };
+        let root = parser.or()?;
+        if let Some((position, token)) = parser.tokens.get(parser.next) {
+            return Err(ExpressionError::Syntax {
+                position: *position,
+                message: format!("unexpected {}", token),
+            });
+        }
+        Ok(Self {
+            source: source.to_string(),
+            root,
+        })
+    }
+
+    /// The expression text.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Names of all inputs the expression uses.
+    pub fn inputs(&self) -> Vec<&str> {
+        fn collect<'a>(node: &'a Node, names: &mut Vec<&'a str>) {
+            match node {
+                Node::Number(_) => {}
+                Node::Input(name) => {
+                    if !names.contains(&name.as_str()) {
+                        names.push(name);
+                    }
+                }
+                Node::Unary(_, operand) => collect(operand, names),
+                Node::Binary(_, a, b) => {
+                    collect(a, names);
+                    collect(b, names);
+                }
+                Node::Call(_, args) => args.iter().for_each(|arg| collect(arg, names)),
+            }
+        }
+        let mut names = Vec::new();
+        collect(&self.root, &mut names);
+        names
+    }
+
+    /// Evaluate the expression with input values from `input`.
+    pub fn eval(&self, input: &dyn Fn(&str) -> Option<f64>) -> Result<f64, ExpressionError> {
+        eval(&self.root, input)
+    }
+}
+
+fn eval(node: &Node, input: &dyn Fn(&str) -> Option<f64>) -> Result<f64, ExpressionError> {
+    Ok(match node {
+        Node::Number(value) => *value,
+        Node::Input(name) => {
+            input(name).ok_or_else(|| ExpressionError::UnknownInput(name.clone()))?
+        }
+        Node::Unary(UnaryOp::Neg, operand) => -eval(operand, input)?,
+        Node::Unary(UnaryOp::Not, operand) => truth(eval(operand, input)? == 0.0),
+        Node::Binary(op, a, b) => op.apply(eval(a, input)?, eval(b, input)?),
+        Node::Call(Function::If, args) => {
+            if eval(&args[0], input)? != 0.0 {
+                eval(&args[1], input)?
+            } else {
+                eval(&args[2], input)?
+            }
+        }
+        Node::Call(function, args) => {
+            let values = args
+                .iter()
+                .map(|arg| eval(arg, input))
+                .collect::<Result<Vec<_>, _>>()?;
+            call(*function, &values)
+        }
+    })
+}
+
+fn call(function: Function, values: &[f64]) -> f64 {
+    let non_zero = values.iter().filter(|v| **v != 0.0).count();
+    match function {
+        Function::Min => values.iter().copied().fold(f64::INFINITY, f64::min),
+        Function::Max => values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        Function::Sum => values.iter().sum(),
+        Function::Avg => values.iter().sum::<f64>() / values.len() as f64,
+        Function::Any => truth(non_zero > 0),
+        Function::All => truth(non_zero == values.len()),
+        Function::Count => non_zero as f64,
+        Function::Abs => values[0].abs(),
+        Function::Round => {
+            let scale = 10f64.powi(values.get(1).copied().unwrap_or(0.0) as i32);
+            (values[0] * scale).round() / scale
+        }
+        Function::Clamp => values[0].max(values[1]).min(values[2]),
+        Function::DewPoint => dew_point(values[0], values[1]),
+        // Evaluated lazily in eval()
+        Function::If => unreachable!("if is evaluated lazily"),
+    }
+}
+
+/// Dew point in °C from temperature (°C) and relative humidity (%), Magnus formula.
+pub fn dew_point(celsius: f64, percent: f64) -> f64 {
+    const B: f64 = 17.62;
+    const C: f64 = 243.12;
+    let gamma = (percent / 100.0).ln() + B * celsius / (C + celsius);
+    C * gamma / (B - gamma)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+    Comma,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Number(value) => write!(f, "number {}", value),
+            Self::Ident(name) => write!(f, "name {}", name),
+            Self::Op(op) => write!(f, "'{}'", op),
+            Self::LParen => write!(f, "'('"),
+            Self::RParen => write!(f, "')'"),
+            Self::Comma => write!(f, "','"),
+        }
+    }
+}
+
+/// Operators, longest first so `<=` is not read as `<`.
+const OPERATORS: [&str; 15] = [
+    "||", "&&", "<=", ">=", "==", "!=", "<", ">", "+", "-", "*", "/", "%", "!", "=",
+];
+
+fn tokenize(source: &str) -> Result<Vec<(usize, Token)>, ExpressionError> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+    while let Some(&(position, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut end = position;
+            while let Some(&(i, c)) = chars.peek()
+                && (c.is_ascii_digit() || c == '.')
+            {
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            let text = &source[position..end];
+            let value = text.parse().map_err(|_| ExpressionError::Syntax {
+                position,
+                message: format!("invalid number {}", text),
+            })?;
+            tokens.push((position, Token::Number(value)));
+        } else if c.is_alphabetic() || c == '_' {
+            let mut end = position;
+            while let Some(&(i, c)) = chars.peek()
+                && (c.is_alphanumeric() || c == '_')
+            {
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            tokens.push((position, Token::Ident(source[position..end].to_string())));
+        } else {
+            let token = match c {
+                '(' => Token::LParen,
+                ')' => Token::RParen,
+                ',' => Token::Comma,
+                _ => match OPERATORS
+                    .into_iter()
+                    .find(|op| source[position..].starts_with(op))
+                {
+                    // A single `=` is a typo of `==`
+                    Some("=") | None => {
+                        return Err(ExpressionError::Syntax {
+                            position,
+                            message: format!("unexpected character '{}'", c),
+                        });
+                    }
+                    Some(op) => Token::Op(op),
+                },
+            };
+            let len = match &token {
+                Token::Op(op) => op.len(),
+                _ => 1,
+            };
+            for _ in 0..len {
+                chars.next();
+            }
+            tokens.push((position, token));
+        }
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent parser, one method per precedence level.
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    next: usize,
+    /// Position reported for errors at the end of the input
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next).map(|(_, token)| token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens
+            .get(self.next)
+            .map_or(self.end, |(position, _)| *position)
+    }
+
+    fn error(&self, message: impl Into<String>) -> ExpressionError {
+        ExpressionError::Syntax {
+            position: self.position(),
+            message: message.into(),
+        }
+    }
+
+    /// Consume the next token if it is one of `ops`.
+    fn operator(&mut self, ops: &[(&str, BinaryOp)]) -> Option<BinaryOp> {
+        let Some(Token::Op(op)) = self.peek() else {
+            return None;
+        };
+        let (_, binary) = ops.iter().find(|(name, _)| name == op)?;
+        self.next += 1;
+        Some(*binary)
+    }
+
+    /// Parse a left-associative level of binary operators.
+    fn binary(
+        &mut self,
+        ops: &[(&str, BinaryOp)],
+        operand: fn(&mut Self) -> Result<Node, ExpressionError>,
+    ) -> Result<Node, ExpressionError> {
+        let mut node = operand(self)?;
+        while let Some(op) = self.operator(ops) {
+            node = Node::Binary(op, Box::new(node), Box::new(operand(self)?));
+        }
+        Ok(node)
+    }
+
+    fn or(&mut self) -> Result<Node, ExpressionError> {
+        self.binary(&[("||", BinaryOp::Or)], Self::and)
+    }
+
+    fn and(&mut self) -> Result<Node, ExpressionError> {
+        self.binary(&[("&&", BinaryOp::And)], Self::comparison)
+    }
+
+    fn comparison(&mut self) -> Result<Node, ExpressionError> {
+        let node = self.sum()?;
+        let ops = [
+            ("<", BinaryOp::Lt),
+            ("<=", BinaryOp::Le),
+            (">", BinaryOp::Gt),
+            (">=", BinaryOp::Ge),
+            ("==", BinaryOp::Eq),
+            ("!=", BinaryOp::Ne),
+        ];
+        match self.operator(&ops) {
+            Some(op) => Ok(Node::Binary(op, Box::new(node), Box::new(self.sum()?))),
+            None => Ok(node),
+        }
+    }
+
+    fn sum(&mut self) -> Result<Node, ExpressionError> {
+        self.binary(&[("+", BinaryOp::Add), ("-", BinaryOp::Sub)], Self::product)
+    }
+
+    fn product(&mut self) -> Result<Node, ExpressionError> {
+        self.binary(
+            &[
+                ("*", BinaryOp::Mul),
+                ("/", BinaryOp::Div),
+                ("%", BinaryOp::Rem),
+            ],
+            Self::unary,
+        )
+    }
+
+    fn unary(&mut self) -> Result<Node, ExpressionError> {
+        let op = match self.peek() {
+            Some(Token::Op("-")) => UnaryOp::Neg,
+            Some(Token::Op("!")) => UnaryOp::Not,
+            _ => return self.primary(),
+        };
+        self.next += 1;
+        Ok(Node::Unary(op, Box::new(self.unary()?)))
+    }
+
+    fn primary(&mut self) -> Result<Node, ExpressionError> {
+        let Some(token) = self.peek().cloned() else {
+            return Err(self.error("unexpected end of expression"));
+        };
+        self.next += 1;
+        match token {
+            Token::Number(value) => Ok(Node::Number(value)),
+            Token::Ident(name) if name == "true" => Ok(Node::Number(1.0)),
+            Token::Ident(name) if name == "false" => Ok(Node::Number(0.0)),
+            Token::Ident(name) if self.peek() == Some(&Token::LParen) => {
+                self.next += 1;
+                let function = Function::from_name(&name)
+                    .ok_or_else(|| ExpressionError::UnknownFunction(name.clone()))?;
+                let mut args = Vec::new();
+                if self.peek() != Some(&Token::RParen) {
+                    loop {
+                        args.push(self.or()?);
+                        if self.peek() != Some(&Token::Comma) {
+                            break;
+                        }
+                        self.next += 1;
+                    }
+                }
+                self.expect(Token::RParen)?;
+                function.check_arity(args.len())?;
+                Ok(Node::Call(function, args))
+            }
+            Token::Ident(name) => Ok(Node::Input(name)),
+            Token::LParen => {
+                let node = self.or()?;
+                self.expect(Token::RParen)?;
+                Ok(node)
+            }
+            token => {
+                self.next -= 1;
+                Err(self.error(format!("unexpected {}", token)))
+            }
+        }
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), ExpressionError> {
+        if self.peek() == Some(&expected) {
+            self.next += 1;
+            Ok(())
+        } else {
+            Err(self.error(format!("expected {}", expected)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval_with(source: &str, inputs: &[(&str, f64)]) -> Result<f64, ExpressionError> {
+        let lookup = |name: &str| {
+            inputs
+                .iter()
+                .find(|(input, _)| *input == name)
+                .map(|(_, value)| *value)
+        };
+        Expression::parse(source)?.eval(&lookup)
+    }
+
+    #[test]
+    fn test_expressions() {
+        let inputs = [("kitchen", 0.0), ("bath", 1.0), ("t", 20.0), ("rh", 50.0)];
+        assert_eq!(eval_with("kitchen || bath", &inputs), Ok(1.0));
+        assert_eq!(eval_with("any(kitchen, bath) && !bath", &inputs), Ok(0.0));
+        assert_eq!(eval_with("1 + 2 * 3 - -4 / 2", &inputs), Ok(9.0));
+        assert_eq!(eval_with("avg(t, 22, 24)", &inputs), Ok(22.0));
+        assert_eq!(eval_with("if(t >= 20, max(t, rh), 0)", &inputs), Ok(50.0));
+        assert_eq!(eval_with("round(dew_point(t, rh), 1)", &inputs), Ok(9.3));
+        assert_eq!(eval_with("count(kitchen, bath, true)", &inputs), Ok(2.0));
+
+        let expression = Expression::parse("avg(a, b) > a").unwrap();
+        assert_eq!(expression.inputs(), ["a", "b"]);
+        assert_eq!(
+            eval_with("missing + 1", &inputs),
+            Err(ExpressionError::UnknownInput("missing".to_string()))
+        );
+        assert!(matches!(
+            Expression::parse("t = 1"),
+            Err(ExpressionError::Syntax { position: 2, .. })
+        ));
+        assert!(matches!(
+            Expression::parse("(t + 1"),
+            Err(ExpressionError::Syntax { position: 6, .. })
+        ));
+        assert_eq!(
+            Expression::parse("median(t)"),
+            Err(ExpressionError::UnknownFunction("median".to_string()))
+        );
+        assert!(matches!(
+            Expression::parse("abs(t, rh)"),
+            Err(ExpressionError::Arity { got: 2, .. })
+        ));
+    }
+}
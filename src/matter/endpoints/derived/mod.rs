@@ -0,0 +1,433 @@
+//! Derived endpoints: values computed from other endpoints.
+//!
+//! A [`DerivedEndpoint`] evaluates an [`Expression`] over named inputs and writes the
+//! result to an output sensor, which is exposed like any other endpoint. Examples:
+//!
+//! ```ignore
+//! // "Any window open" contact sensor
+//! let any_open = Arc::new(DerivedBinarySensor::new());
+//! DerivedEndpoint::new("Any Window Open", "any(kitchen, bath)", DerivedOutput::Binary(any_open.clone()))?
+//!     .with_input("kitchen", kitchen_window.clone())
+//!     .with_input("bath", bath_window.clone());
+//! // EndpointConfig::contact_sensor("Any Window Open", any_open)
+//!
+//! // Dew point from a climate sensor
+//! DerivedEndpoint::new("Dew Point", "dew_point(t, rh)", DerivedOutput::Temperature(dew_point))?
+//!     .with_input("t", temperature.clone())
+//!     .with_input("rh", humidity.clone());
+//! ```
+//!
+//! Expressions are re-evaluated whenever the version of an input changes, checked on
+//! every change reported by the Matter stack. Inputs should therefore be bridge
+//! endpoints (or derived values); [`DerivedConfig`] reads the whole setup from a file
+//! and binds the inputs to endpoints by name.
+
+pub mod config;
+pub mod expression;
+
+pub use config::{DERIVED_FILE, DerivedConfig, DerivedConfigError, EndpointInput};
+pub use expression::{Expression, ExpressionError};
+
+use super::endpoints_helpers::Sensor;
+use super::handler::EndpointHandler;
+use super::sensors::helpers::BinarySensorHelper;
+use crate::matter::BridgeControl;
+use crate::matter::clusters::{HumiditySensor, TemperatureSensor};
+use log::{debug, warn};
+use parking_lot::{Mutex, RwLock};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+
+type StatePusher = Arc<dyn Fn(bool) + Send + Sync>;
+
+/// A value an expression can read.
+pub trait DerivedInput: Send + Sync {
+    /// Version of the value, incremented on every change.
+    fn version(&self) -> u32;
+
+    /// Current value (booleans are 1 or 0).
+    fn value(&self) -> f64;
+
+    /// Whether the value can be read yet (endpoint inputs wait for their endpoint).
+    fn is_ready(&self) -> bool {
+        true
+    }
+
+    /// Follow a change notification of the bridge (`None` = notifications were missed).
+    fn endpoint_changed(&self, _control: &BridgeControl, _endpoint_id: Option<u16>) {}
+}
+
+impl DerivedInput for BinarySensorHelper {
+    fn version(&self) -> u32 {
+        Sensor::version(self)
+    }
+
+    fn value(&self) -> f64 {
+        if self.get() { 1.0 } else { 0.0 }
+    }
+}
+
+impl DerivedInput for TemperatureSensor {
+    fn version(&self) -> u32 {
+        TemperatureSensor::version(self)
+    }
+
+    fn value(&self) -> f64 {
+        self.get_celsius() as f64
+    }
+}
+
+impl DerivedInput for HumiditySensor {
+    fn version(&self) -> u32 {
+        HumiditySensor::version(self)
+    }
+
+    fn value(&self) -> f64 {
+        self.get_percent() as f64
+    }
+}
+
+/// Read-only boolean state written by a derived endpoint.
+///
+/// Serves as the handler of a contact or occupancy sensor endpoint
+/// (`EndpointConfig::contact_sensor`), and can be the input of other derived endpoints.
+pub struct DerivedBinarySensor {
+    state: AtomicBool,
+    version: AtomicU32,
+    pusher: RwLock<Option<StatePusher>>,
+}
+
+impl DerivedBinarySensor {
+    /// Create a sensor (false until first evaluated).
+    pub fn new() -> Self {
+        Self {
+            state: AtomicBool::new(false),
+            version: AtomicU32::new(0),
+            pusher: RwLock::new(None),
+        }
+    }
+
+    /// Get the current state.
+    pub fn get(&self) -> bool {
+        self.state.load(Ordering::SeqCst)
+    }
+
+    /// Set the state and push it to Matter. Returns true if it changed.
+    fn set(&self, value: bool) -> bool {
+        if self.state.swap(value, Ordering::SeqCst) == value {
+            return false;
+        }
+        self.version.fetch_add(1, Ordering::SeqCst);
+        if let Some(pusher) = self.pusher.read().as_ref() {
+            pusher(value);
+        }
+        true
+    }
+}
+
+impl Default for DerivedBinarySensor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EndpointHandler for DerivedBinarySensor {
+    fn on_command(&self, _value: bool) {
+        // Computed from the inputs, commands are ignored
+    }
+
+    fn get_state(&self) -> bool {
+        self.get()
+    }
+
+    fn set_state_pusher(&self, pusher: StatePusher) {
+        *self.pusher.write() = Some(pusher);
+    }
+}
+
+impl DerivedInput for DerivedBinarySensor {
+    fn version(&self) -> u32 {
+        self.version.load(Ordering::SeqCst)
+    }
+
+    fn value(&self) -> f64 {
+        if self.get() { 1.0 } else { 0.0 }
+    }
+}
+
+/// Where the result of a derived endpoint goes.
+#[derive(Clone)]
+pub enum DerivedOutput {
+    /// Boolean (non-zero = true), e.g. a contact sensor
+    Binary(Arc<DerivedBinarySensor>),
+    /// Temperature in degrees Celsius
+    Temperature(Arc<TemperatureSensor>),
+    /// Relative humidity in percent
+    Humidity(Arc<HumiditySensor>),
+}
+
+impl DerivedOutput {
+    /// Write a result. Returns true if the reported value changed.
+    fn apply(&self, value: f64) -> bool {
+        match self {
+            Self::Binary(sensor) => sensor.set(value != 0.0),
+            Self::Temperature(sensor) => {
+                let changed = (value * 100.0) as i16 != sensor.get_centidegrees();
+                if changed {
+                    sensor.set_celsius(value as f32);
+                }
+                changed
+            }
+            Self::Humidity(sensor) => {
+                let percent = value.clamp(0.0, 100.0);
+                let changed = (percent * 100.0) as u16 != sensor.get_centipercent();
+                if changed {
+                    sensor.set_percent(percent as f32);
+                }
+                changed
+            }
+        }
+    }
+}
+
+/// An endpoint value computed from other values.
+pub struct DerivedEndpoint {
+    label: String,
+    expression: Expression,
+    inputs: Vec<(String, Arc<dyn DerivedInput>)>,
+    output: DerivedOutput,
+    /// Input versions of the last evaluation (None = not evaluated yet)
+    versions: Mutex<Option<Vec<u32>>>,
+}
+
+impl DerivedEndpoint {
+    /// Create a derived endpoint from an expression.
+    pub fn new(
+        label: impl Into<String>,
+        expression: &str,
+        output: DerivedOutput,
+    ) -> Result<Self, ExpressionError> {
+        Ok(Self {
+            label: label.into(),
+            expression: Expression::parse(expression)?,
+            inputs: Vec::new(),
+            output,
+            versions: Mutex::new(None),
+        })
+    }
+
+    /// Bind an input name of the expression to a value.
+    pub fn with_input(mut self, name: impl Into<String>, input: Arc<dyn DerivedInput>) -> Self {
+        self.inputs.push((name.into(), input));
+        self
+    }
+
+    /// Label for logging.
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Check that every input of the expression is bound.
+    fn check_inputs(&self) -> Result<(), ExpressionError> {
+        match self
+            .expression
+            .inputs()
+            .into_iter()
+            .find(|name| !self.inputs.iter().any(|(input, _)| input == name))
+        {
+            Some(name) => Err(ExpressionError::UnknownInput(name.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    /// Re-evaluate if an input changed since the last evaluation.
+    ///
+    /// Returns true if the output value changed. Nothing is evaluated until every
+    /// input is ready.
+    pub fn update(&self) -> bool {
+        if !self.inputs.iter().all(|(_, input)| input.is_ready()) {
+            return false;
+        }
+        let current: Vec<u32> = self
+            .inputs
+            .iter()
+            .map(|(_, input)| input.version())
+            .collect();
+        {
+            let mut versions = self.versions.lock();
+            if versions.as_ref() == Some(&current) {
+                return false;
+            }
+            *versions = Some(current);
+        }
+
+        let lookup = |name: &str| {
+            self.inputs
+                .iter()
+                .find(|(input, _)| input == name)
+                .map(|(_, input)| input.value())
+        };
+        match self.expression.eval(&lookup) {
+            Ok(value) if value.is_finite() => {
+                let changed = self.output.apply(value);
+                if changed {
+                    debug!("[Derived] {} = {}", self.label, value);
+                }
+                changed
+            }
+            Ok(value) => {
+                warn!(
+                    "[Derived] {}: {} evaluated to {}",
+                    self.label,
+                    self.expression.source(),
+                    value
+                );
+                false
+            }
+            Err(e) => {
+                warn!("[Derived] {}: {}", self.label, e);
+                false
+            }
+        }
+    }
+}
+
+/// All derived endpoints of a bridge instance.
+#[derive(Default)]
+pub struct DerivedEndpoints {
+    endpoints: Vec<DerivedEndpoint>,
+}
+
+impl DerivedEndpoints {
+    /// Create an empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a derived endpoint; fails if its expression uses an unbound input.
+    pub fn with_endpoint(mut self, endpoint: DerivedEndpoint) -> Result<Self, ExpressionError> {
+        endpoint.check_inputs()?;
+        self.endpoints.push(endpoint);
+        Ok(self)
+    }
+
+    /// Number of derived endpoints.
+    pub fn len(&self) -> usize {
+        self.endpoints.len()
+    }
+
+    /// Whether there are no derived endpoints.
+    pub fn is_empty(&self) -> bool {
+        self.endpoints.is_empty()
+    }
+
+    /// Re-evaluate all endpoints whose inputs changed.
+    ///
+    /// Derived endpoints can be inputs of others, so passes repeat while outputs change
+    /// (at most once per endpoint, which also stops cycles).
+    pub fn update(&self) {
+        for _ in 0..self.endpoints.len() {
+            let mut changed = false;
+            for endpoint in &self.endpoints {
+                changed |= endpoint.update();
+            }
+            if !changed {
+                break;
+            }
+        }
+    }
+
+    /// Pass a change notification to the inputs and re-evaluate.
+    pub fn endpoint_changed(&self, control: &BridgeControl, endpoint_id: Option<u16>) {
+        for endpoint in &self.endpoints {
+            for (_, input) in &endpoint.inputs {
+                input.endpoint_changed(control, endpoint_id);
+            }
+        }
+        self.update();
+    }
+
+    /// Start evaluating in a background task, driven by the bridge's change notifications.
+    ///
+    /// Start it before the Matter stack registers its endpoints, so endpoint inputs see
+    /// the registrations. Returns a JoinHandle that can be used to abort the task on
+    /// shutdown.
+    pub fn start(self, control: &BridgeControl) -> JoinHandle<()> {
+        let mut changes = control.subscribe_changes();
+        let control = control.clone();
+        tokio::spawn(async move {
+            self.endpoint_changed(&control, None);
+            loop {
+                match changes.recv().await {
+                    Ok(endpoint_id) => self.endpoint_changed(&control, Some(endpoint_id)),
+                    Err(RecvError::Lagged(_)) => self.endpoint_changed(&control, None),
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_derived_endpoints() {
+        let kitchen = Arc::new(BinarySensorHelper::new(false));
+        let bath = Arc::new(BinarySensorHelper::new(false));
+        let any_open = Arc::new(DerivedBinarySensor::new());
+        let all_open = Arc::new(DerivedBinarySensor::new());
+
+        let derived = DerivedEndpoints::new()
+            .with_endpoint(
+                DerivedEndpoint::new(
+                    "Any Window Open",
+                    "kitchen || bath",
+                    DerivedOutput::Binary(any_open.clone()),
+                )
+                .unwrap()
+                .with_input("kitchen", kitchen.clone())
+                .with_input("bath", bath.clone()),
+            )
+            .unwrap()
+            // Chained: uses the output of the first endpoint
+            .with_endpoint(
+                DerivedEndpoint::new(
+                    "All Open",
+                    "any_open && bath",
+                    DerivedOutput::Binary(all_open.clone()),
+                )
+                .unwrap()
+                .with_input("any_open", any_open.clone())
+                .with_input("bath", bath.clone()),
+            )
+            .unwrap();
+
+        derived.update();
+        assert!(!any_open.get());
+
+        kitchen.set(true);
+        derived.update();
+        assert!(any_open.get());
+        assert!(!all_open.get());
+
+        bath.set(true);
+        derived.update();
+        assert!(all_open.get());
+
+        let unbound = DerivedEndpoint::new(
+            "Average",
+            "avg(a, b)",
+            DerivedOutput::Binary(Arc::new(DerivedBinarySensor::new())),
+        )
+        .unwrap()
+        .with_input("a", kitchen.clone());
+        assert_eq!(
+            DerivedEndpoints::new().with_endpoint(unbound).err(),
+            Some(ExpressionError::UnknownInput("b".to_string()))
+        );
+    }
+}
@@ -0,0 +1,168 @@
+//! Device availability tracking for bridged devices.
+//!
+//! Input sources report whether a device is online (e.g., zigbee2mqtt availability
+//! topic) or simply that it was seen (any state message). Sleepy battery devices that
+//! never report availability go offline after a configurable staleness timeout.
+//!
+//! Availability is forwarded to the `ReachableState` of every endpoint of the device,
+//! which updates the Reachable attribute and emits ReachableChanged events.
+
+use crate::matter::clusters::ReachableState;
+use parking_lot::{Mutex, RwLock};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// Shared availability handle for one bridged device.
+///
+/// # Usage
+/// ```ignore
+/// let availability = Arc::new(DeviceAvailability::new().with_stale_timeout(Duration::from_secs(7200)));
+/// // Matter side: VirtualDevice::new("Sensor").with_availability(availability.clone())
+/// // Source side: availability.mark_seen() on every message
+/// ```
+pub struct DeviceAvailability {
+    /// Current availability as reported by the source
+    available: AtomicBool,
+    /// Last time the device was seen
+    last_seen: Mutex<Instant>,
+    /// Mark unavailable when not seen for this long (None = never stale)
+    stale_timeout: Option<Duration>,
+    /// Reachable states of all endpoints belonging to this device
+    targets: RwLock<Vec<Arc<ReachableState>>>,
+}
+
+impl DeviceAvailability {
+    /// Create a new availability handle (initially available, no staleness timeout).
+    pub fn new() -> Self {
+        Self {
+            available: AtomicBool::new(true),
+            last_seen: Mutex::new(Instant::now()),
+            stale_timeout: None,
+            targets: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Mark the device unavailable when it has not been seen for `timeout`.
+    pub fn with_stale_timeout(mut self, timeout: Duration) -> Self {
+        self.stale_timeout = Some(timeout);
+        self
+    }
+
+    /// Get the staleness timeout, if any.
+    pub fn stale_timeout(&self) -> Option<Duration> {
+        self.stale_timeout
+    }
+
+    /// Register an endpoint's reachable state (called when wiring to Matter stack).
+    pub fn add_target(&self, state: Arc<ReachableState>) {
+        state.set_available(self.is_available());
+        self.targets.write().push(state);
+    }
+
+    /// Get the current availability.
+    pub fn is_available(&self) -> bool {
+        self.available.load(Ordering::SeqCst)
+    }
+
+    /// Set availability explicitly (e.g., from an availability topic).
+    pub fn set_available(&self, available: bool) {
+        if available {
+            *self.last_seen.lock() = Instant::now();
+        }
+        if self.available.swap(available, Ordering::SeqCst) != available {
+            for state in self.targets.read().iter() {
+                state.set_available(available);
+            }
+        }
+    }
+
+    /// Record that the device was seen (any message) - marks it available.
+    pub fn mark_seen(&self) {
+        self.set_available(true);
+    }
+
+    /// Mark the device unavailable if it has not been seen within the staleness timeout.
+    ///
+    /// Returns true if the device transitioned to unavailable.
+    pub fn check_stale(&self) -> bool {
+        let Some(timeout) = self.stale_timeout else {
+            return false;
+        };
+        if !self.is_available() || self.last_seen.lock().elapsed() < timeout {
+            return false;
+        }
+        self.set_available(false);
+        true
+    }
+}
+
+impl Default for DeviceAvailability {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_available_updates_targets() {
+        let availability = DeviceAvailability::new();
+        let state = Arc::new(ReachableState::new());
+        availability.add_target(state.clone());
+        assert!(state.is_reachable());
+
+        availability.set_available(false);
+        assert!(!state.is_reachable());
+
+        availability.mark_seen();
+        assert!(state.is_reachable());
+    }
+
+    #[test]
+    fn test_new_target_inherits_availability() {
+        let availability = DeviceAvailability::new();
+        availability.set_available(false);
+
+        let state = Arc::new(ReachableState::new());
+        availability.add_target(state.clone());
+        assert!(!state.is_reachable());
+    }
+
+    #[test]
+    fn test_check_stale() {
+        let availability = DeviceAvailability::new().with_stale_timeout(Duration::ZERO);
+        let state = Arc::new(ReachableState::new());
+        availability.add_target(state.clone());
+
+        assert!(availability.check_stale());
+        assert!(!availability.is_available());
+        assert!(!state.is_reachable());
+
+        // Already unavailable - no second transition
+        assert!(!availability.check_stale());
+    }
+
+    #[test]
+    fn test_no_timeout_never_stale() {
+        let availability = DeviceAvailability::new();
+        assert!(!availability.check_stale());
+        assert!(availability.is_available());
+    }
+
+    #[test]
+    fn test_disabled_endpoint_stays_unreachable() {
+        let availability = DeviceAvailability::new();
+        let state = Arc::new(ReachableState::new());
+        availability.add_target(state.clone());
+
+        state.set_enabled(false);
+        availability.mark_seen();
+        assert!(!state.is_reachable());
+
+        state.set_enabled(true);
+        assert!(state.is_reachable());
+    }
+}
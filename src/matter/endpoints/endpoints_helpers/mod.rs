@@ -1,11 +1,16 @@
 //! Shared helpers for sensors and controls.
 //!
 //! This module contains utilities used by both sensors and controls:
+//! - `availability`: Source-driven device availability and staleness tracking
 //! - `notifier`: Live subscription update notifications
 //! - `traits`: Sensor and NotifiableSensor traits for change detection
+//! - `transform`: Calibration, smoothing and deadbands for measurement readings
 
+pub mod availability;
 pub mod notifier;
 pub mod traits;
+pub mod transform;
 
-pub use notifier::ClusterNotifier;
+pub use availability::DeviceAvailability;
+pub use notifier::{ChangeQueue, ClusterNotifier};
 pub use traits::{NotifiableSensor, Sensor};
@@ -2,15 +2,103 @@
 //!
 //! When sensors or controls change, they need to immediately notify the Matter subscription
 //! system so updates are pushed to controllers (like Home Assistant) instantly.
+//!
+//! Notifiers record exactly which attributes changed in a shared [`ChangeQueue`]. The
+//! stack drains the queue and reports only those paths to the subscription manager, so
+//! an update of one sensor does not make the stack look at every bridged endpoint.
 
 use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
 use embassy_sync::signal::Signal;
+use parking_lot::Mutex;
+use std::collections::HashSet;
+
+/// Path of a changed attribute.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AttributePath {
+    pub endpoint_id: u16,
+    pub cluster_id: u32,
+    pub attribute_id: u32,
+}
+
+/// Dirty attribute paths in the order they were first marked.
+#[derive(Default)]
+struct DirtySet {
+    order: Vec<AttributePath>,
+    pending: HashSet<AttributePath>,
+}
+
+/// Queue of attributes changed outside the Matter stack.
+///
+/// A path stays in the queue only once until it is drained, so a chatty sensor costs one
+/// entry per drain no matter how often it updates. The drain task is only woken when the
+/// queue goes from empty to non-empty.
+pub struct ChangeQueue {
+    dirty: Mutex<DirtySet>,
+    signal: Signal<CriticalSectionRawMutex, ()>,
+}
+
+impl ChangeQueue {
+    /// Create an empty queue.
+    pub fn new() -> Self {
+        Self {
+            dirty: Mutex::new(DirtySet::default()),
+            signal: Signal::new(),
+        }
+    }
+
+    /// Mark an attribute as changed.
+    pub fn mark(&self, path: AttributePath) {
+        let mut dirty = self.dirty.lock();
+        if !dirty.pending.insert(path) {
+            return; // Already queued
+        }
+        dirty.order.push(path);
+        if dirty.order.len() == 1 {
+            self.signal.signal(());
+        }
+    }
+
+    /// Take all queued paths (empty if nothing changed).
+    pub fn take(&self) -> Vec<AttributePath> {
+        let mut dirty = self.dirty.lock();
+        dirty.pending.clear();
+        std::mem::take(&mut dirty.order)
+    }
+
+    /// Wait until attributes changed and take them.
+    pub async fn wait(&self) -> Vec<AttributePath> {
+        loop {
+            let paths = self.take();
+            if !paths.is_empty() {
+                return paths;
+            }
+            self.signal.wait().await;
+        }
+    }
+
+    /// Number of queued paths.
+    pub fn len(&self) -> usize {
+        self.dirty.lock().order.len()
+    }
+
+    /// Whether no paths are queued.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for ChangeQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 /// Notifies Matter subscriptions when endpoint values change.
 ///
 /// This is the bridge between sensors/controls and the Matter stack.
-/// When a sensor or control calls `notify()`, it wakes the subscription processor
-/// to immediately push updates to controllers.
+/// A notifier belongs to one cluster of one endpoint and knows which attributes
+/// carry the value of its source. `notify()` marks those attributes as changed;
+/// `notify_attribute()` marks a single other attribute.
 ///
 /// # Usage
 /// ```ignore
@@ -20,27 +108,31 @@ use embassy_sync::signal::Signal;
 /// }
 /// ```
 pub struct ClusterNotifier {
-    signal: &'static Signal<CriticalSectionRawMutex, ()>,
+    queue: &'static ChangeQueue,
     endpoint_id: u16,
     cluster_id: u32,
+    attribute_ids: &'static [u32],
 }
 
 impl ClusterNotifier {
     /// Create a new notifier for a specific cluster.
     ///
     /// # Arguments
-    /// * `signal` - Static signal that wakes the subscription processor
+    /// * `queue` - Static change queue drained by the subscription processor
     /// * `endpoint_id` - Matter endpoint ID for this cluster
     /// * `cluster_id` - Matter cluster ID
+    /// * `attribute_ids` - Attributes marked by `notify()`
     pub fn new(
-        signal: &'static Signal<CriticalSectionRawMutex, ()>,
+        queue: &'static ChangeQueue,
         endpoint_id: u16,
         cluster_id: u32,
+        attribute_ids: &'static [u32],
     ) -> Self {
         Self {
-            signal,
+            queue,
             endpoint_id,
             cluster_id,
+            attribute_ids,
         }
     }
 
@@ -54,11 +146,61 @@ impl ClusterNotifier {
         self.cluster_id
     }
 
-    /// Notify that this cluster's data changed.
+    /// Notify that this cluster's value attributes changed.
     ///
-    /// Wakes the Matter subscription processor to push updates immediately.
+    /// Queues the attributes for the Matter subscription processor.
     /// This is a non-blocking operation.
     pub fn notify(&self) {
-        self.signal.signal(());
+        for &attribute_id in self.attribute_ids {
+            self.notify_attribute(attribute_id);
+        }
+    }
+
+    /// Notify that a single attribute of this cluster changed.
+    pub fn notify_attribute(&self, attribute_id: u32) {
+        self.queue.mark(AttributePath {
+            endpoint_id: self.endpoint_id,
+            cluster_id: self.cluster_id,
+            attribute_id,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_change_queue_dedup() {
+        let queue: &'static ChangeQueue = Box::leak(Box::new(ChangeQueue::new()));
+        let temperature = ClusterNotifier::new(queue, 5, 0x0402, &[0x0000]);
+        let battery = ClusterNotifier::new(queue, 6, 0x002F, &[0x000C, 0x000E]);
+
+        for _ in 0..100 {
+            temperature.notify();
+        }
+        battery.notify();
+        temperature.notify_attribute(0x0001);
+        assert_eq!(queue.len(), 4);
+
+        let paths = embassy_futures::block_on(queue.wait());
+        let attributes: Vec<_> = paths
+            .iter()
+            .map(|p| (p.endpoint_id, p.cluster_id, p.attribute_id))
+            .collect();
+        assert_eq!(
+            attributes,
+            [
+                (5, 0x0402, 0x0000),
+                (6, 0x002F, 0x000C),
+                (6, 0x002F, 0x000E),
+                (5, 0x0402, 0x0001)
+            ]
+        );
+        assert!(queue.is_empty());
+
+        // Drained paths can be marked again
+        temperature.notify();
+        assert_eq!(queue.take().len(), 1);
     }
 }
@@ -0,0 +1,411 @@
+//! Value transforms for measurement endpoints.
+//!
+//! A [`ValueTransform`] is a chain of steps applied to every raw reading before it is
+//! reported: calibration, unit conversion, smoothing, deadbands and clamping. The chains
+//! are configured per endpoint in a JSON file, keyed by `"<device>/<endpoint>"` like the
+//! NodeLabels:
+//!
+//! ```json
+//! {
+//!     "W100/Climate": {
+//!         "temperature": [
+//!             { "type": "calibrate", "offset": -0.4 },
+//!             { "type": "ema", "alpha": 0.3 },
+//!             { "type": "deadband", "min_change": 0.1 },
+//!             { "type": "clamp" }
+//!         ],
+//!         "humidity": [{ "type": "moving_average", "window": 5 }]
+//!     }
+//! }
+//! ```
+//!
+//! Steps run in the listed order. A deadband drops readings that changed less than
+//! `min_change` since the last reported value, and `clamp` without bounds limits
+//! readings to the MinMeasuredValue/MaxMeasuredValue of the cluster.
+
+use crate::matter::clusters::{relative_humidity, temperature_measurement};
+use serde::Deserialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Errors loading transforms.
+#[derive(Debug, Error)]
+pub enum TransformError {
+    #[error("failed to read {0}: {1}")]
+    Read(PathBuf, std::io::Error),
+
+    #[error("invalid transforms: {0}")]
+    Parse(#[from] serde_json::Error),
+
+    #[error("invalid transform for {0}: {1}")]
+    Invalid(String, String),
+}
+
+/// Unit of raw temperature readings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TemperatureUnit {
+    Celsius,
+    Fahrenheit,
+    Kelvin,
+}
+
+impl TemperatureUnit {
+    /// Convert a reading in this unit to degrees Celsius.
+    pub fn to_celsius(self, value: f64) -> f64 {
+        match self {
+            Self::Celsius => value,
+            Self::Fahrenheit => (value - 32.0) * 5.0 / 9.0,
+            Self::Kelvin => value - 273.15,
+        }
+    }
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+/// One step of a transform chain.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TransformStep {
+    /// `value * scale + offset`
+    Calibrate {
+        #[serde(default)]
+        offset: f64,
+        #[serde(default = "default_scale")]
+        scale: f64,
+    },
+    /// Convert temperature readings to Celsius
+    Unit { from: TemperatureUnit },
+    /// Average of the last `window` readings
+    MovingAverage { window: usize },
+    /// Exponential moving average (`alpha` = weight of the new reading, 0..=1)
+    Ema { alpha: f64 },
+    /// Drop readings that changed less than `min_change` since the last reported one
+    Deadband { min_change: f64 },
+    /// Limit to `min`/`max` (the cluster's measured range when absent)
+    Clamp {
+        #[serde(default)]
+        min: Option<f64>,
+        #[serde(default)]
+        max: Option<f64>,
+    },
+}
+
+impl TransformStep {
+    /// Check the parameters of the step for a measurement with the given range.
+    fn validate(&self, range: (f64, f64)) -> Result<(), String> {
+        match *self {
+            Self::Calibrate { offset, scale } if !offset.is_finite() || !scale.is_finite() => {
+                Err("calibrate needs finite offset and scale".to_string())
+            }
+            Self::MovingAverage { window: 0 } => {
+                Err("moving_average needs a window of at least 1".to_string())
+            }
+            Self::Ema { alpha } if alpha.is_nan() || alpha <= 0.0 || alpha > 1.0 => {
+                Err(format!("ema alpha must be in (0, 1], got {}", alpha))
+            }
+            Self::Deadband { min_change } if min_change.is_nan() || min_change < 0.0 => {
+                Err(format!(
+                    "deadband min_change must not be negative, got {}",
+                    min_change
+                ))
+            }
+            Self::Clamp { min, max } => {
+                if min.is_some_and(|min| !min.is_finite())
+                    || max.is_some_and(|max| !max.is_finite())
+                {
+                    return Err("clamp needs finite bounds".to_string());
+                }
+                // A single bound is combined with the other end of the measured range
+                let (min, max) = (min.unwrap_or(range.0), max.unwrap_or(range.1));
+                if min > max {
+                    return Err(format!("clamp min {} is above max {}", min, max));
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// State of a step between readings.
+enum StepState {
+    None,
+    /// Last readings of a moving average
+    Window(VecDeque<f64>),
+    /// Last smoothed or reported value
+    Last(Option<f64>),
+}
+
+/// A transform chain with the state of its steps.
+pub struct ValueTransform {
+    steps: Vec<(TransformStep, StepState)>,
+}
+
+impl ValueTransform {
+    /// Create a transform from its steps.
+    pub fn new(steps: Vec<TransformStep>) -> Self {
+        Self {
+            steps: steps
+                .into_iter()
+                .map(|step| {
+                    let state = match step {
+                        TransformStep::MovingAverage { window } => {
+                            StepState::Window(VecDeque::with_capacity(window))
+                        }
+                        TransformStep::Ema { .. } | TransformStep::Deadband { .. } => {
+                            StepState::Last(None)
+                        }
+                        _ => StepState::None,
+                    };
+                    (step, state)
+                })
+                .collect(),
+        }
+    }
+
+    /// Whether the chain has no steps.
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Start the deadband from a value reported before (e.g. restored at startup), so
+    /// the first reading is compared against it.
+    pub fn seed(&mut self, reported: f64) {
+        for (step, state) in &mut self.steps {
+            if let (TransformStep::Deadband { .. }, StepState::Last(last)) = (&*step, state) {
+                *last = Some(reported);
+            }
+        }
+    }
+
+    /// Transform a raw reading.
+    ///
+    /// `range` is the measurable range of the cluster (used by `clamp` without bounds).
+    /// Returns None when the reading should not be reported (deadband, or not a number).
+    pub fn apply(&mut self, raw: f64, range: (f64, f64)) -> Option<f64> {
+        if !raw.is_finite() {
+            return None;
+        }
+
+        let mut value = raw;
+        for (step, state) in &mut self.steps {
+            value = match (&*step, state) {
+                (TransformStep::Calibrate { offset, scale }, _) => value * scale + offset,
+                (TransformStep::Unit { from }, _) => from.to_celsius(value),
+                (TransformStep::MovingAverage { window }, StepState::Window(readings)) => {
+                    if readings.len() == *window {
+                        readings.pop_front();
+                    }
+                    readings.push_back(value);
+                    readings.iter().sum::<f64>() / readings.len() as f64
+                }
+                (TransformStep::Ema { alpha }, StepState::Last(last)) => {
+                    let smoothed = match *last {
+                        Some(last) => alpha * value + (1.0 - alpha) * last,
+                        None => value,
+                    };
+                    *last = Some(smoothed);
+                    smoothed
+                }
+                (TransformStep::Deadband { min_change }, StepState::Last(last)) => {
+                    if let Some(last) = *last
+                        && (value - last).abs() < *min_change
+                    {
+                        return None;
+                    }
+                    *last = Some(value);
+                    value
+                }
+                // Not f64::clamp, which panics when min > max
+                (TransformStep::Clamp { min, max }, _) => value
+                    .max(min.unwrap_or(range.0))
+                    .min(max.unwrap_or(range.1)),
+                _ => unreachable!("step state created by ValueTransform::new"),
+            };
+        }
+        Some(value)
+    }
+}
+
+/// Transform chains of one endpoint, by measurement.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EndpointTransforms {
+    #[serde(default)]
+    pub temperature: Vec<TransformStep>,
+    #[serde(default)]
+    pub humidity: Vec<TransformStep>,
+}
+
+/// The configured transforms of all endpoints.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TransformConfig {
+    endpoints: BTreeMap<String, EndpointTransforms>,
+}
+
+impl TransformConfig {
+    /// Parse transforms from JSON (endpoint key to transform chains).
+    pub fn from_json(json: &str) -> Result<Self, TransformError> {
+        let endpoints: BTreeMap<String, EndpointTransforms> = serde_json::from_str(json)?;
+        for (key, transforms) in &endpoints {
+            let invalid = |e| TransformError::Invalid(key.clone(), e);
+            for step in &transforms.temperature {
+                step.validate(temperature_measurement::MEASURED_RANGE)
+                    .map_err(invalid)?;
+            }
+            for step in &transforms.humidity {
+                if matches!(step, TransformStep::Unit { .. }) {
+                    return Err(invalid(
+                        "unit conversion only applies to temperature".to_string(),
+                    ));
+                }
+                step.validate(relative_humidity::MEASURED_RANGE)
+                    .map_err(invalid)?;
+            }
+        }
+        Ok(Self { endpoints })
+    }
+
+    /// Load transforms from a JSON file; a missing file means no transforms.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, TransformError> {
+        let path = path.as_ref();
+        match std::fs::read_to_string(path) {
+            Ok(json) => Self::from_json(&json),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(TransformError::Read(path.to_path_buf(), e)),
+        }
+    }
+
+    /// Number of endpoints with transforms.
+    pub fn len(&self) -> usize {
+        self.endpoints.len()
+    }
+
+    /// Whether no endpoint has transforms.
+    pub fn is_empty(&self) -> bool {
+        self.endpoints.is_empty()
+    }
+
+    /// Temperature transform of an endpoint, if configured.
+    pub fn temperature(&self, key: &str) -> Option<ValueTransform> {
+        self.endpoints
+            .get(key)
+            .filter(|transforms| !transforms.temperature.is_empty())
+            .map(|transforms| ValueTransform::new(transforms.temperature.clone()))
+    }
+
+    /// Humidity transform of an endpoint, if configured.
+    pub fn humidity(&self, key: &str) -> Option<ValueTransform> {
+        self.endpoints
+            .get(key)
+            .filter(|transforms| !transforms.humidity.is_empty())
+            .map(|transforms| ValueTransform::new(transforms.humidity.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RANGE: (f64, f64) = (-40.0, 125.0);
+
+    #[test]
+    fn test_transform_chain() {
+        let config = TransformConfig::from_json(
+            r#"{
+                "Attic/Climate": {
+                    "temperature": [
+                        { "type": "unit", "from": "fahrenheit" },
+                        { "type": "calibrate", "offset": -1, "scale": 2 },
+                        { "type": "deadband", "min_change": 0.5 },
+                        { "type": "clamp" }
+                    ],
+                    "humidity": [{ "type": "moving_average", "window": 2 }]
+                }
+            }"#,
+        )
+        .unwrap();
+        assert!(config.temperature("Attic/Other").is_none());
+
+        let mut temperature = config.temperature("Attic/Climate").unwrap();
+        // 50°F = 10°C, * 2 - 1
+        assert_eq!(temperature.apply(50.0, RANGE), Some(19.0));
+        // 50.36°F = 10.2°C -> 19.4: not a change of 0.5 yet
+        assert_eq!(temperature.apply(50.36, RANGE), None);
+        assert_eq!(temperature.apply(59.0, RANGE), Some(29.0));
+        // Clamped to MaxMeasuredValue
+        assert_eq!(temperature.apply(300.0, RANGE), Some(125.0));
+        assert_eq!(temperature.apply(f64::NAN, RANGE), None);
+
+        let mut humidity = config.humidity("Attic/Climate").unwrap();
+        assert_eq!(humidity.apply(40.0, (0.0, 100.0)), Some(40.0));
+        assert_eq!(humidity.apply(50.0, (0.0, 100.0)), Some(45.0));
+        assert_eq!(humidity.apply(60.0, (0.0, 100.0)), Some(55.0));
+
+        // A restored value seeds the deadband
+        let mut deadband = ValueTransform::new(vec![TransformStep::Deadband { min_change: 0.5 }]);
+        deadband.seed(20.0);
+        assert_eq!(deadband.apply(20.2, RANGE), None);
+        assert_eq!(deadband.apply(20.6, RANGE), Some(20.6));
+
+        let mut ema = ValueTransform::new(vec![TransformStep::Ema { alpha: 0.25 }]);
+        assert_eq!(ema.apply(20.0, RANGE), Some(20.0));
+        assert_eq!(ema.apply(24.0, RANGE), Some(21.0));
+
+        assert!(matches!(
+            TransformConfig::from_json(
+                r#"{ "A/B": { "humidity": [{ "type": "ema", "alpha": 2 }] } }"#
+            ),
+            Err(TransformError::Invalid(..))
+        ));
+        assert!(TransformConfig::from_json(r#"{ "A/B": { "pressure": [] } }"#).is_err());
+    }
+
+    #[test]
+    fn test_clamp_outside_measured_range() {
+        // A single bound beyond the other end of the cluster's range
+        for json in [
+            r#"{ "A/B": { "temperature": [{ "type": "clamp", "min": 130 }] } }"#,
+            r#"{ "A/B": { "temperature": [{ "type": "clamp", "max": -50 }] } }"#,
+            r#"{ "A/B": { "humidity": [{ "type": "clamp", "min": 101 }] } }"#,
+        ] {
+            assert!(
+                matches!(
+                    TransformConfig::from_json(json),
+                    Err(TransformError::Invalid(..))
+                ),
+                "{}",
+                json
+            );
+        }
+
+        let config = TransformConfig::from_json(
+            r#"{ "A/B": { "temperature": [{ "type": "clamp", "min": 5 }] } }"#,
+        )
+        .unwrap();
+        let mut clamp = config.temperature("A/B").unwrap();
+        assert_eq!(clamp.apply(-10.0, RANGE), Some(5.0));
+        assert_eq!(clamp.apply(200.0, RANGE), Some(125.0));
+
+        // Chains built directly are not validated and must not panic
+        let mut clamp = ValueTransform::new(vec![TransformStep::Clamp {
+            min: Some(130.0),
+            max: None,
+        }]);
+        assert_eq!(clamp.apply(20.0, RANGE), Some(125.0));
+    }
+
+    #[test]
+    fn test_unit_rejected_for_humidity() {
+        assert!(matches!(
+            TransformConfig::from_json(
+                r#"{ "A/B": { "humidity": [{ "type": "unit", "from": "fahrenheit" }] } }"#
+            ),
+            Err(TransformError::Invalid(..))
+        ));
+    }
+}
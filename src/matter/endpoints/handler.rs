@@ -3,8 +3,20 @@
 //! Implement this trait to connect your sensors/switches to the Matter protocol.
 //! - For sensors: push state changes via `set_state_pusher` callback
 //! - For switches: receive commands via `on_command` and push state via callback
+//! - For switches backed by another system: confirm commands via `command`
 
+use std::future::Future;
+use std::pin::Pin;
 use std::sync::Arc;
+use thiserror::Error;
+
+/// A command that was not carried out (e.g. not acknowledged by the device's broker).
+#[derive(Debug, Clone, Error)]
+#[error("{0}")]
+pub struct CommandError(pub String);
+
+/// Completion of a command, resolved once the device confirmed it.
+pub type CommandFuture = Pin<Box<dyn Future<Output = Result<(), CommandError>> + Send>>;
 
 /// Trait for bidirectional communication between Matter and your business logic.
 ///
@@ -58,4 +70,20 @@ pub trait EndpointHandler: Send + Sync + 'static {
     ///
     /// This enables live Matter subscription updates.
     fn set_state_pusher(&self, pusher: Arc<dyn Fn(bool) + Send + Sync>);
+
+    /// Whether Matter commands wait for `command` to confirm them.
+    ///
+    /// Return true for devices that can fail a command, so a controller sees the failure.
+    fn confirms_commands(&self) -> bool {
+        false
+    }
+
+    /// Called for Matter commands when `confirms_commands` returns true.
+    ///
+    /// The command succeeds or fails with the returned future. The default applies it
+    /// with `on_command` and succeeds immediately.
+    fn command(&self, value: bool) -> CommandFuture {
+        self.on_command(value);
+        Box::pin(std::future::ready(Ok(())))
+    }
 }
@@ -3,14 +3,16 @@
 //! This module organizes Matter endpoint components:
 //! - `sensors`: Read-only state (contact, occupancy, etc.)
 //! - `controls`: Read-write state (switches, lights, etc.)
-//! - `endpoints_helpers`: Shared utilities (notifier, traits)
+//! - `derived`: Values computed from other endpoints (expressions)
+//! - `endpoints_helpers`: Shared utilities (availability, notifier, traits)
 //! - `handler`: EndpointHandler trait for bidirectional communication
 
 pub mod controls;
+pub mod derived;
 pub mod endpoints_helpers;
 pub mod handler;
 pub mod sensors;
 
 // Re-export key types for convenience
-pub use endpoints_helpers::{ClusterNotifier, NotifiableSensor};
-pub use handler::EndpointHandler;
+pub use endpoints_helpers::{ChangeQueue, ClusterNotifier, DeviceAvailability, NotifiableSensor};
+pub use handler::{CommandError, CommandFuture, EndpointHandler};
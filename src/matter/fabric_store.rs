@@ -0,0 +1,125 @@
+//! Persistent bridge-side metadata of commissioned fabrics.
+//!
+//! rs-matter keeps the fabrics themselves in `matter.bin`, but not when a controller
+//! was last connected, and the fabric label belongs to the controller. This store
+//! keeps both a local alias and the last session time per fabric in `fabrics.json`.
+//!
+//! Entries are keyed by fabric ID and node ID rather than the fabric index, because
+//! indices are reused after a fabric is removed.
+
+use super::json_store::JsonStore;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Bridge-side metadata of one fabric.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FabricMeta {
+    /// Local alias set via the control plane
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+    /// Last time a session of this fabric was seen (Unix seconds)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_session: Option<u64>,
+}
+
+/// JSON-backed fabric metadata store.
+pub struct FabricStore {
+    fabrics: JsonStore<BTreeMap<String, FabricMeta>>,
+}
+
+impl FabricStore {
+    /// Load the store from `path` (empty if the file does not exist).
+    pub fn load(path: impl Into<PathBuf>) -> Self {
+        Self {
+            fabrics: JsonStore::load(path),
+        }
+    }
+
+    /// Build the key of a fabric.
+    pub fn key(fabric_id: u64, node_id: u64) -> String {
+        format!("{:016X}-{:016X}", fabric_id, node_id)
+    }
+
+    /// Get the metadata of a fabric (empty if nothing was recorded).
+    pub fn get(&self, key: &str) -> FabricMeta {
+        self.fabrics
+            .read(|fabrics| fabrics.get(key).cloned().unwrap_or_default())
+    }
+
+    /// Set or clear the local alias of a fabric.
+    pub fn set_alias(&self, key: &str, alias: Option<String>) {
+        self.fabrics.update(|fabrics| {
+            fabrics.entry(key.to_string()).or_default().alias = alias;
+        });
+    }
+
+    /// Record that the given fabrics have an active session now.
+    ///
+    /// The file is only written when a timestamp advanced by at least `resolution_secs`,
+    /// so periodic sampling does not rewrite it on every pass.
+    pub fn record_sessions<'a>(
+        &self,
+        keys: impl IntoIterator<Item = &'a str>,
+        resolution_secs: u64,
+    ) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        self.fabrics.update(|fabrics| {
+            for key in keys {
+                let meta = fabrics.entry(key.to_string()).or_default();
+                if meta
+                    .last_session
+                    .is_none_or(|last| now >= last + resolution_secs)
+                {
+                    meta.last_session = Some(now);
+                }
+            }
+        });
+    }
+
+    /// Forget a removed fabric.
+    pub fn remove(&self, key: &str) {
+        self.fabrics.update(|fabrics| fabrics.remove(key));
+    }
+
+    /// Forget all fabrics (factory reset).
+    pub fn clear(&self) -> std::io::Result<()> {
+        self.fabrics.clear()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alias_and_sessions_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fabrics.json");
+
+        let key = FabricStore::key(1, 0x1234);
+        let store = FabricStore::load(&path);
+        assert_eq!(store.get(&key), FabricMeta::default());
+
+        store.set_alias(&key, Some("Home Assistant".to_string()));
+        store.record_sessions([key.as_str()], 60);
+        let first = store.get(&key).last_session;
+        assert!(first.is_some());
+
+        // Within the resolution the timestamp is kept
+        store.record_sessions([key.as_str()], 60);
+        assert_eq!(store.get(&key).last_session, first);
+
+        let reloaded = FabricStore::load(&path);
+        let meta = reloaded.get(&key);
+        assert_eq!(meta.alias.as_deref(), Some("Home Assistant"));
+        assert_eq!(meta.last_session, first);
+
+        reloaded.remove(&key);
+        assert_eq!(FabricStore::load(&path).get(&key), FabricMeta::default());
+    }
+}
@@ -4,11 +4,18 @@
 //! by Matter cluster handlers (BooleanStateHandler, OccupancySensingHandler, OnOffHooks).
 
 use super::endpoints::endpoints_helpers::{ClusterNotifier, NotifiableSensor, Sensor};
-use super::endpoints::handler::EndpointHandler;
+use super::endpoints::handler::{CommandError, EndpointHandler};
+use super::runtime_state::{StartUpOnOff, StateHandle, resolve_start_up};
 use parking_lot::RwLock;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU32, Ordering};
 
+/// OnOff cluster attribute carrying the switch state
+pub const ON_OFF_ATTRIBUTE_ID: u32 = 0x0000;
+
+/// OnOff cluster attribute carrying the StartUpOnOff behavior
+pub const START_UP_ON_OFF_ATTRIBUTE_ID: u32 = 0x4003;
+
 /// Bridge for sensor endpoints (ContactSensor, OccupancySensor).
 ///
 /// Wraps an `EndpointHandler` and implements the `Sensor` trait needed by
@@ -49,6 +56,15 @@ impl SensorBridge {
         self.handler.get_state()
     }
 
+    /// Override the sensor state (e.g., from the local control plane).
+    ///
+    /// The value is passed to handler.on_command(); handlers that ignore commands
+    /// keep reporting their own state.
+    pub fn set(&self, value: bool) {
+        self.handler.on_command(value);
+        self.on_state_changed();
+    }
+
     /// Called when the handler pushes a state change.
     fn on_state_changed(&self) {
         self.version.fetch_add(1, Ordering::SeqCst);
@@ -79,10 +95,14 @@ impl NotifiableSensor for SensorBridge {
 /// - `set()` calls handler.on_command() and increments version
 /// - Version tracking enables subscription updates
 /// - Notifier pushes changes to Matter subscriptions
+/// - State handle persists OnOff/StartUpOnOff across restarts
 pub struct SwitchBridge {
     handler: Arc<dyn EndpointHandler>,
     version: AtomicU32,
     notifier: RwLock<Option<ClusterNotifier>>,
+    /// StartUpOnOff behavior (None = restore previous value)
+    start_up_on_off: RwLock<Option<StartUpOnOff>>,
+    state_handle: RwLock<Option<StateHandle>>,
 }
 
 impl SwitchBridge {
@@ -92,13 +112,15 @@ impl SwitchBridge {
             handler: handler.clone(),
             version: AtomicU32::new(0),
             notifier: RwLock::new(None),
+            start_up_on_off: RwLock::new(None),
+            state_handle: RwLock::new(None),
         });
 
         // Wire up the pusher so the handler can push state changes to Matter
         let bridge_weak = Arc::downgrade(&bridge);
-        handler.set_state_pusher(Arc::new(move |_value| {
+        handler.set_state_pusher(Arc::new(move |value| {
             if let Some(bridge) = bridge_weak.upgrade() {
-                bridge.on_state_changed();
+                bridge.on_state_changed(value);
             }
         }));
 
@@ -119,6 +141,21 @@ impl SwitchBridge {
         if let Some(notifier) = self.notifier.read().as_ref() {
             notifier.notify();
         }
+        self.persist(value);
+    }
+
+    /// Whether commands wait for the handler to confirm them.
+    pub fn confirms_commands(&self) -> bool {
+        self.handler.confirms_commands()
+    }
+
+    /// Set the switch state once the handler confirmed the command.
+    ///
+    /// On failure the state is left unchanged and the error is returned to Matter.
+    pub async fn set_confirmed(&self, value: bool) -> Result<(), CommandError> {
+        self.handler.command(value).await?;
+        self.on_state_changed(value);
+        Ok(())
     }
 
     /// Toggle the switch state and return the new value.
@@ -128,12 +165,53 @@ impl SwitchBridge {
         new_value
     }
 
+    /// Get the StartUpOnOff behavior (None = restore previous value).
+    pub fn start_up(&self) -> Option<StartUpOnOff> {
+        *self.start_up_on_off.read()
+    }
+
+    /// Set the StartUpOnOff behavior (called by Matter on attribute write).
+    pub fn set_start_up(&self, start_up: Option<StartUpOnOff>) {
+        *self.start_up_on_off.write() = start_up;
+        self.version.fetch_add(1, Ordering::SeqCst);
+        if let Some(notifier) = self.notifier.read().as_ref() {
+            notifier.notify_attribute(START_UP_ON_OFF_ATTRIBUTE_ID);
+        }
+        if let Some(handle) = self.state_handle.read().as_ref() {
+            handle.save_start_up(start_up);
+        }
+    }
+
+    /// Restore the persisted state and persist future changes.
+    ///
+    /// Applies StartUpOnOff (Off/On/Toggle, or the previous value when null) by
+    /// commanding the handler, so it does not keep its hard-coded initial value.
+    pub fn restore_state(&self, handle: StateHandle) {
+        let state = handle.state();
+        *self.start_up_on_off.write() = state.start_up_on_off;
+        let current = self.handler.get_state();
+        let on = resolve_start_up(state.start_up_on_off, state.on_off, current);
+        if on != current {
+            self.handler.on_command(on);
+        }
+        handle.save_on_off(on);
+        *self.state_handle.write() = Some(handle);
+    }
+
+    /// Persist the OnOff value (if persistence is enabled).
+    fn persist(&self, value: bool) {
+        if let Some(handle) = self.state_handle.read().as_ref() {
+            handle.save_on_off(value);
+        }
+    }
+
     /// Called when the handler pushes a state change from external source.
-    fn on_state_changed(&self) {
+    fn on_state_changed(&self, value: bool) {
         self.version.fetch_add(1, Ordering::SeqCst);
         if let Some(notifier) = self.notifier.read().as_ref() {
             notifier.notify();
         }
+        self.persist(value);
     }
 }
 
@@ -0,0 +1,179 @@
+//! Shared persistence for the small JSON files next to `matter.bin`.
+//!
+//! NodeLabels, runtime state, fabric metadata, the time configuration and the Binding
+//! table each keep a serde value in memory and write it atomically when it changes.
+//! A missing file starts from the default value. A file that cannot be parsed is moved
+//! aside to `<file>.corrupt` and logged, so the next write does not silently destroy it.
+
+use log::error;
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A JSON file holding one value of type `T`.
+pub struct JsonStore<T> {
+    path: PathBuf,
+    state: Mutex<StoreState<T>>,
+}
+
+struct StoreState<T> {
+    value: T,
+    /// Changes made with `update_deferred` that are not written yet
+    dirty: bool,
+}
+
+impl<T> JsonStore<T>
+where
+    T: Default + Clone + PartialEq + Serialize + DeserializeOwned,
+{
+    /// Load the store from `path`.
+    ///
+    /// A missing file results in the default value. An invalid file is moved to
+    /// `<file>.corrupt`, an unreadable one is logged; both start from the default value.
+    pub fn load(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let value = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                let backup = corrupt_path(&path);
+                match fs::rename(&path, &backup) {
+                    Ok(()) => error!("Invalid {:?} ({}), moved to {:?}", path, e, backup),
+                    Err(rename_error) => error!(
+                        "Invalid {:?} ({}), failed to move it to {:?}: {}",
+                        path, e, backup, rename_error
+                    ),
+                }
+                T::default()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => T::default(),
+            Err(e) => {
+                error!("Failed to read {:?}: {}", path, e);
+                T::default()
+            }
+        };
+
+        Self {
+            path,
+            state: Mutex::new(StoreState {
+                value,
+                dirty: false,
+            }),
+        }
+    }
+
+    /// Path of the file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Read the current value.
+    pub fn read<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        f(&self.state.lock().value)
+    }
+
+    /// Modify the value and write the file if it changed.
+    pub fn update<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        let mut state = self.state.lock();
+        let before = state.value.clone();
+        let result = f(&mut state.value);
+        if state.value != before || state.dirty {
+            self.write(&mut state);
+        }
+        result
+    }
+
+    /// Modify the value and write it later, with [`flush`](Self::flush).
+    ///
+    /// For values that change often (sensor readings), so each change does not
+    /// rewrite the file.
+    pub fn update_deferred<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        let mut state = self.state.lock();
+        let before = state.value.clone();
+        let result = f(&mut state.value);
+        if state.value != before {
+            state.dirty = true;
+        }
+        result
+    }
+
+    /// Write deferred changes, if any.
+    pub fn flush(&self) {
+        let mut state = self.state.lock();
+        if state.dirty {
+            self.write(&mut state);
+        }
+    }
+
+    /// Reset to the default value and delete the file (factory reset).
+    ///
+    /// The in-memory value is reset even if the file cannot be deleted.
+    pub fn clear(&self) -> std::io::Result<()> {
+        let mut state = self.state.lock();
+        state.value = T::default();
+        state.dirty = false;
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                error!("Failed to delete {:?}: {}", self.path, e);
+                Err(e)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn write(&self, state: &mut StoreState<T>) {
+        match write_json_atomic(&self.path, &state.value) {
+            Ok(()) => state.dirty = false,
+            Err(e) => error!("Failed to persist {:?}: {}", self.path, e),
+        }
+    }
+}
+
+/// Path an invalid file is moved to (`<file>.corrupt`).
+fn corrupt_path(path: &Path) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(".corrupt");
+    name.into()
+}
+
+/// Write a value as JSON to a temp file and rename it over the target.
+pub(crate) fn write_json_atomic(path: &Path, value: &impl Serialize) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let json = serde_json::to_string_pretty(value)?;
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, json)?;
+    fs::rename(&tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    type Map = BTreeMap<String, u32>;
+
+    #[test]
+    fn test_update_flush_and_corrupt_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("store.json");
+
+        let store = JsonStore::<Map>::load(&path);
+        store.update(|map| map.insert("a".to_string(), 1));
+        store.update_deferred(|map| map.insert("b".to_string(), 2));
+        assert_eq!(JsonStore::<Map>::load(&path).read(|map| map.len()), 1);
+        store.flush();
+        assert_eq!(JsonStore::<Map>::load(&path).read(|map| map.len()), 2);
+
+        fs::write(&path, "not json").unwrap();
+        let store = JsonStore::<Map>::load(&path);
+        assert!(store.read(|map| map.is_empty()));
+        assert_eq!(fs::read_to_string(corrupt_path(&path)).unwrap(), "not json");
+
+        store.update(|map| map.insert("c".to_string(), 3));
+        store.clear().unwrap();
+        assert!(!path.exists());
+    }
+}
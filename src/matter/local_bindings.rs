@@ -0,0 +1,347 @@
+//! Local button-to-endpoint bindings.
+//!
+//! A local binding connects an event of a button endpoint to an action on another
+//! endpoint of the same bridge, applied directly through [`BridgeControl`]. Unlike
+//! controller automations, they keep working while no controller is connected
+//! (e.g. Home Assistant being restarted), which light switches need.
+//!
+//! Bindings are read from a JSON list at startup:
+//!
+//! ```json
+//! [
+//!     { "source": "W100/Plus", "trigger": "single", "target": "Living Room/Light", "action": "toggle" },
+//!     { "source": "W100/Plus", "trigger": "double", "target": "Living Room", "action": "off" },
+//!     { "source": "Rocker", "trigger": "position:1", "target": 7, "action": "on" }
+//! ]
+//! ```
+//!
+//! Endpoints are addressed by `"<device>/<endpoint>"`, by a plain label (endpoint or
+//! device), or by endpoint ID. Triggers are `press`, `single`, `double`, `triple`,
+//! `multi_press:<n>`, `long`, `long_release` and `position:<n>` (latching switches).
+//! Actions are `toggle`, `on`, `off`, `set` (with `value`) and `step` (with `by`,
+//! optional `min`/`max`) for numeric values; the bridge has no LevelControl endpoints
+//! yet, so dimming a light needs a numeric target.
+
+use super::clusters::{SwitchEvent, SwitchEventListener};
+use super::control::{BridgeControl, ControlEndpoint, ControlError};
+use log::{info, warn};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Errors loading local bindings.
+#[derive(Debug, Error)]
+pub enum LocalBindingError {
+    #[error("failed to read {0}: {1}")]
+    Read(PathBuf, std::io::Error),
+
+    #[error("invalid local bindings: {0}")]
+    Parse(#[from] serde_json::Error),
+}
+
+/// Reference to an endpoint of the bridge.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum EndpointRef {
+    /// Matter endpoint ID
+    Id(u16),
+    /// `"<device>/<endpoint>"`, or the label of an endpoint or device
+    Label(String),
+}
+
+impl EndpointRef {
+    /// Whether this reference addresses an endpoint.
+    pub fn matches(&self, endpoint_id: u16, device: &str, label: &str) -> bool {
+        match self {
+            Self::Id(id) => *id == endpoint_id,
+            Self::Label(name) => match name.split_once('/') {
+                Some((ref_device, ref_label)) => ref_device == device && ref_label == label,
+                None => name == label,
+            },
+        }
+    }
+}
+
+/// Switch event a binding reacts to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Trigger {
+    /// Button pressed down (InitialPress)
+    Press,
+    /// Completed sequence of `n` presses (`single` = 1, `double` = 2, `triple` = 3)
+    MultiPress(u8),
+    /// Button held (LongPress)
+    Long,
+    /// Released after a hold (LongRelease)
+    LongRelease,
+    /// Latching switch moved to a position (SwitchLatched)
+    Position(u8),
+}
+
+impl Trigger {
+    /// Whether a switch event fires this trigger.
+    pub fn matches(self, event: SwitchEvent) -> bool {
+        match (self, event) {
+            (Self::Press, SwitchEvent::InitialPress)
+            | (Self::Long, SwitchEvent::LongPress)
+            | (Self::LongRelease, SwitchEvent::LongRelease) => true,
+            (Self::MultiPress(n), SwitchEvent::MultiPressComplete(count)) => n == count,
+            (Self::Position(n), SwitchEvent::SwitchLatched(position)) => n == position,
+            _ => false,
+        }
+    }
+}
+
+impl TryFrom<String> for Trigger {
+    type Error = String;
+
+    fn try_from(trigger: String) -> Result<Self, Self::Error> {
+        let number = |n: &str| {
+            n.parse::<u8>()
+                .map_err(|_| format!("invalid trigger {:?}", trigger))
+        };
+        match trigger.split_once(':') {
+            Some(("multi_press", n)) => Ok(Self::MultiPress(number(n)?)),
+            Some(("position", n)) => Ok(Self::Position(number(n)?)),
+            _ => match trigger.as_str() {
+                "press" => Ok(Self::Press),
+                "single" => Ok(Self::MultiPress(1)),
+                "double" => Ok(Self::MultiPress(2)),
+                "triple" => Ok(Self::MultiPress(3)),
+                "long" | "hold" => Ok(Self::Long),
+                "long_release" | "release" => Ok(Self::LongRelease),
+                _ => Err(format!("unknown trigger {:?}", trigger)),
+            },
+        }
+    }
+}
+
+/// Action applied to the target endpoint.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum BindingAction {
+    Toggle,
+    On,
+    Off,
+    /// Set a value (bool, number)
+    Set {
+        value: Value,
+    },
+    /// Add `by` to a numeric value, limited to `min`/`max`
+    Step {
+        by: f64,
+        #[serde(default)]
+        min: Option<f64>,
+        #[serde(default)]
+        max: Option<f64>,
+    },
+}
+
+/// A button event bound to an action on another endpoint.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct LocalBinding {
+    /// Button endpoint
+    pub source: EndpointRef,
+    /// Event of the button
+    pub trigger: Trigger,
+    /// Endpoint to control
+    pub target: EndpointRef,
+    /// Value kind on the target (needed when it has several values)
+    #[serde(default)]
+    pub kind: Option<String>,
+    #[serde(flatten)]
+    pub action: BindingAction,
+}
+
+/// The local bindings of a bridge instance.
+pub struct LocalBindings {
+    bindings: Vec<LocalBinding>,
+    control: BridgeControl,
+}
+
+impl LocalBindings {
+    /// Create an empty set of bindings.
+    pub fn new(control: BridgeControl) -> Self {
+        Self {
+            bindings: Vec::new(),
+            control,
+        }
+    }
+
+    /// Parse bindings from JSON (a list of bindings).
+    pub fn from_json(json: &str, control: BridgeControl) -> Result<Self, LocalBindingError> {
+        Ok(Self {
+            bindings: serde_json::from_str(json)?,
+            control,
+        })
+    }
+
+    /// Load bindings from a JSON file; a missing file means no bindings.
+    pub fn load(path: impl AsRef<Path>, control: BridgeControl) -> Result<Self, LocalBindingError> {
+        let path = path.as_ref();
+        match std::fs::read_to_string(path) {
+            Ok(json) => Self::from_json(&json, control),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::new(control)),
+            Err(e) => Err(LocalBindingError::Read(path.to_path_buf(), e)),
+        }
+    }
+
+    /// Number of bindings.
+    pub fn len(&self) -> usize {
+        self.bindings.len()
+    }
+
+    /// Whether there are no bindings.
+    pub fn is_empty(&self) -> bool {
+        self.bindings.is_empty()
+    }
+
+    /// Event listener applying the bindings of a button endpoint.
+    ///
+    /// Returns None when no binding has this endpoint as its source.
+    pub fn listener(
+        self: &Arc<Self>,
+        endpoint_id: u16,
+        device: &str,
+        label: &str,
+    ) -> Option<SwitchEventListener> {
+        let sources: Vec<usize> = (0..self.bindings.len())
+            .filter(|&i| self.bindings[i].source.matches(endpoint_id, device, label))
+            .collect();
+        if sources.is_empty() {
+            return None;
+        }
+
+        let bindings = self.clone();
+        Some(Arc::new(move |event| {
+            for &i in &sources {
+                let binding = &bindings.bindings[i];
+                if binding.trigger.matches(event) {
+                    bindings.apply(binding);
+                }
+            }
+        }))
+    }
+
+    /// Apply a binding's action to its target.
+    ///
+    /// Targets are resolved when the event fires, so every endpoint registered by then
+    /// can be addressed.
+    fn apply(&self, binding: &LocalBinding) {
+        let Some(target) = self.control.endpoints().into_iter().find(|endpoint| {
+            binding
+                .target
+                .matches(endpoint.endpoint_id, &endpoint.device, &endpoint.label)
+        }) else {
+            warn!("[Bindings] No endpoint for target {:?}", binding.target);
+            return;
+        };
+
+        match apply_action(&target, binding.kind.as_deref(), &binding.action) {
+            Ok(value) => info!(
+                "[Bindings] {}/{} set to {} ({:?})",
+                target.device, target.label, value, binding.trigger
+            ),
+            Err(e) => warn!("[Bindings] {}/{}: {}", target.device, target.label, e),
+        }
+    }
+}
+
+/// Apply an action to a value of an endpoint and return the new value.
+fn apply_action(
+    endpoint: &ControlEndpoint,
+    kind: Option<&str>,
+    action: &BindingAction,
+) -> Result<Value, ControlError> {
+    let point = endpoint.point(kind)?;
+    // Buttons would re-enter the bindings (and could loop)
+    if point.kind() == "button" {
+        return Err(ControlError::InvalidValue(
+            "buttons cannot be binding targets".to_string(),
+        ));
+    }
+
+    match action {
+        BindingAction::Toggle => point.toggle(),
+        BindingAction::On => point.set(&json!(true)),
+        BindingAction::Off => point.set(&json!(false)),
+        BindingAction::Set { value } => point.set(value),
+        BindingAction::Step { by, min, max } => {
+            let current = point.value();
+            let Some(current) = current.as_f64() else {
+                return Err(ControlError::InvalidValue(format!(
+                    "cannot step {} value {}",
+                    point.kind(),
+                    current
+                )));
+            };
+            let mut value = current + by;
+            if let Some(min) = min {
+                value = value.max(*min);
+            }
+            if let Some(max) = max {
+                value = value.min(*max);
+            }
+            point.set(&json!(value))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_local_bindings() {
+        let bindings = LocalBindings::from_json(
+            r#"[
+                { "source": "W100/Plus", "trigger": "single", "target": "Light", "action": "toggle" },
+                { "source": 12, "trigger": "position:2", "target": "Lamp/Light", "action": "set", "value": true },
+                { "source": "Plus", "trigger": "long", "target": 7, "kind": "temperature", "action": "step", "by": -0.5, "min": 16 }
+            ]"#,
+            BridgeControl::new(),
+        )
+        .unwrap();
+        assert_eq!(bindings.len(), 3);
+
+        let binding = &bindings.bindings[2];
+        assert_eq!(binding.trigger, Trigger::Long);
+        assert_eq!(binding.target, EndpointRef::Id(7));
+        assert_eq!(
+            binding.action,
+            BindingAction::Step {
+                by: -0.5,
+                min: Some(16.0),
+                max: None
+            }
+        );
+        assert!(binding.source.matches(3, "W100", "Plus"));
+        assert!(!binding.source.matches(3, "W100", "Minus"));
+
+        let source = &bindings.bindings[0].source;
+        assert!(source.matches(3, "W100", "Plus"));
+        assert!(!source.matches(3, "Other", "Plus"));
+
+        assert!(Trigger::MultiPress(1).matches(SwitchEvent::MultiPressComplete(1)));
+        assert!(!Trigger::MultiPress(2).matches(SwitchEvent::MultiPressComplete(3)));
+        assert!(
+            bindings.bindings[1]
+                .trigger
+                .matches(SwitchEvent::SwitchLatched(2))
+        );
+        assert_eq!(
+            Trigger::try_from("multi_press:4".to_string()),
+            Ok(Trigger::MultiPress(4))
+        );
+        assert!(Trigger::try_from("quadruple".to_string()).is_err());
+        assert!(
+            LocalBindings::from_json(
+                r#"[{ "source": "A", "trigger": "single", "target": "B", "action": "dim" }]"#,
+                BridgeControl::new()
+            )
+            .is_err()
+        );
+    }
+}
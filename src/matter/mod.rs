@@ -1,20 +1,33 @@
+mod attestation;
+mod binding_table;
 mod dev_att;
 mod device_info;
+mod diagnostics;
+mod fabric_store;
+mod json_store;
+mod local_bindings;
 mod logging_udp;
 mod netif;
+mod node_labels;
+mod runtime_state;
 mod stack;
+mod time_config;
 
 pub mod clusters;
+pub mod comm_data;
+pub mod control;
 pub mod device_types;
 pub mod endpoints;
 pub mod handler_bridge;
 pub mod virtual_device;
 
-pub use stack::{run_matter_stack, signal_shutdown};
+pub use control::BridgeControl;
+pub(crate) use json_store::JsonStore;
+pub use stack::{ShutdownHandle, get_comm_data_path, run_matter_stack};
 
 // Re-export from endpoints for convenience
 pub use endpoints::controls;
 pub use endpoints::sensors;
 
 // Re-export virtual device types
-pub use virtual_device::{EndpointConfig, VirtualDevice};
+pub use virtual_device::{EndpointCluster, EndpointConfig, VirtualDevice};
@@ -8,6 +8,7 @@ use std::collections::HashSet;
 use std::env;
 use std::ffi::CString;
 use std::net::{Ipv4Addr, Ipv6Addr};
+use std::path::Path;
 use std::sync::OnceLock;
 
 use nix::ifaddrs::getifaddrs;
@@ -61,6 +62,14 @@ pub fn get_interface_name() -> &'static str {
     })
 }
 
+/// Check whether an interface is a wireless (WiFi) interface.
+pub fn is_wireless(interface_name: &str) -> bool {
+    Path::new("/sys/class/net")
+        .join(interface_name)
+        .join("wireless")
+        .exists()
+}
+
 /// Detect the first suitable network interface.
 ///
 /// Returns the first non-loopback interface that has an IPv4 address and is running.
@@ -231,7 +240,11 @@ impl NetifDiag for FilteredNetifs {
             hw_addr: &hw_addr,
             ipv4_addrs: &ipv4_addrs,
             ipv6_addrs: &ipv6_addrs,
-            netif_type: InterfaceTypeEnum::Ethernet,
+            netif_type: if is_wireless(self.interface_name) {
+                InterfaceTypeEnum::WiFi
+            } else {
+                InterfaceTypeEnum::Ethernet
+            },
             netif_index,
         };
 
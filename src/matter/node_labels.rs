@@ -0,0 +1,87 @@
+//! Persistent storage for controller-assigned NodeLabels.
+//!
+//! Controllers can rename bridged devices by writing the NodeLabel attribute of the
+//! BridgedDeviceBasicInformation cluster. Those names are stored in a small JSON file
+//! next to `matter.bin` so they survive restarts.
+//!
+//! Labels are keyed by the configured device label (parent endpoints) or
+//! `"<device>/<endpoint>"` (child endpoints), so they are independent of endpoint IDs.
+
+use super::json_store::JsonStore;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// JSON-backed NodeLabel store.
+pub struct NodeLabelStore {
+    labels: JsonStore<BTreeMap<String, String>>,
+}
+
+impl NodeLabelStore {
+    /// Load the store from `path` (empty if the file does not exist).
+    pub fn load(path: impl Into<PathBuf>) -> Self {
+        Self {
+            labels: JsonStore::load(path),
+        }
+    }
+
+    /// Build the key for a child endpoint of a device.
+    pub fn endpoint_key(device_label: &str, endpoint_label: &str) -> String {
+        format!("{}/{}", device_label, endpoint_label)
+    }
+
+    /// Get the stored label for `key`, if any.
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.labels.read(|labels| labels.get(key).cloned())
+    }
+
+    /// Store a new label for `key` and write the file.
+    pub fn set(&self, key: &str, label: &str) {
+        self.labels.update(|labels| {
+            labels.insert(key.to_string(), label.to_string());
+        });
+    }
+
+    /// Forget all stored labels (factory reset).
+    ///
+    /// Endpoints keep their current label until the next start.
+    pub fn clear(&self) -> std::io::Result<()> {
+        self.labels.clear()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_and_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("labels.json");
+
+        let store = NodeLabelStore::load(&path);
+        assert_eq!(store.get("Door"), None);
+
+        store.set("Door", "Front Door");
+        store.set(
+            &NodeLabelStore::endpoint_key("Power Strip", "Outlet 1"),
+            "Lamp",
+        );
+
+        let reloaded = NodeLabelStore::load(&path);
+        assert_eq!(reloaded.get("Door").as_deref(), Some("Front Door"));
+        assert_eq!(
+            reloaded.get("Power Strip/Outlet 1").as_deref(),
+            Some("Lamp")
+        );
+    }
+
+    #[test]
+    fn test_invalid_file_is_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("labels.json");
+        std::fs::write(&path, "not json").unwrap();
+
+        let store = NodeLabelStore::load(&path);
+        assert_eq!(store.get("anything"), None);
+    }
+}
@@ -0,0 +1,240 @@
+//! Persistent runtime state for bridged endpoints.
+//!
+//! Switch states (OnOff, StartUpOnOff) and the last known sensor readings are stored
+//! in a small JSON file next to `matter.bin`. On boot, switches are restored following
+//! the StartUpOnOff semantics of the Matter spec (Off/On/Toggle/previous), and sensors
+//! report their last known value until the source reports again. Readings change often,
+//! so they are written in batches (see [`RuntimeStateStore::flush`]).
+//!
+//! Entries are keyed like NodeLabels: the configured device label (parent endpoints)
+//! or `"<device>/<endpoint>"` (child endpoints).
+
+use super::json_store::JsonStore;
+use rs_matter::dm::clusters::on_off::StartUpOnOffEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// StartUpOnOff behavior of an OnOff endpoint.
+///
+/// `None` (null in Matter) restores the previous OnOff value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StartUpOnOff {
+    /// Start in the off state
+    Off,
+    /// Start in the on state
+    On,
+    /// Start in the opposite of the previous state
+    Toggle,
+}
+
+impl StartUpOnOff {
+    /// Convert from the raw Matter enum value (0 = Off, 1 = On, 2 = Toggle).
+    pub fn from_raw(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::Off),
+            1 => Some(Self::On),
+            2 => Some(Self::Toggle),
+            _ => None,
+        }
+    }
+
+    /// Get the raw Matter enum value.
+    pub fn raw(self) -> u8 {
+        match self {
+            Self::Off => 0,
+            Self::On => 1,
+            Self::Toggle => 2,
+        }
+    }
+
+    /// Convert from the rs-matter OnOff cluster enum.
+    pub fn from_matter(value: StartUpOnOffEnum) -> Self {
+        match value {
+            StartUpOnOffEnum::Off => Self::Off,
+            StartUpOnOffEnum::On => Self::On,
+            StartUpOnOffEnum::Toggle => Self::Toggle,
+        }
+    }
+
+    /// Convert to the rs-matter OnOff cluster enum.
+    pub fn to_matter(self) -> StartUpOnOffEnum {
+        match self {
+            Self::Off => StartUpOnOffEnum::Off,
+            Self::On => StartUpOnOffEnum::On,
+            Self::Toggle => StartUpOnOffEnum::Toggle,
+        }
+    }
+}
+
+/// Resolve the OnOff value to apply at startup.
+///
+/// `previous` is the last persisted OnOff value; `default` is used when there is none.
+pub fn resolve_start_up(
+    start_up: Option<StartUpOnOff>,
+    previous: Option<bool>,
+    default: bool,
+) -> bool {
+    let previous = previous.unwrap_or(default);
+    match start_up {
+        Some(StartUpOnOff::Off) => false,
+        Some(StartUpOnOff::On) => true,
+        Some(StartUpOnOff::Toggle) => !previous,
+        None => previous,
+    }
+}
+
+/// Persisted runtime state of one endpoint.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EndpointState {
+    /// Last OnOff value (switch endpoints)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_off: Option<bool>,
+    /// StartUpOnOff behavior (switch endpoints, None = previous)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_up_on_off: Option<StartUpOnOff>,
+    /// Last sensor readings by name (e.g., "temperature")
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub readings: BTreeMap<String, f32>,
+}
+
+/// JSON-backed runtime state store.
+pub struct RuntimeStateStore {
+    entries: JsonStore<BTreeMap<String, EndpointState>>,
+}
+
+impl RuntimeStateStore {
+    /// Load the store from `path` (empty if the file does not exist).
+    pub fn load(path: impl Into<PathBuf>) -> Self {
+        Self {
+            entries: JsonStore::load(path),
+        }
+    }
+
+    /// Get the stored state for `key` (empty if none).
+    pub fn get(&self, key: &str) -> EndpointState {
+        self.entries
+            .read(|entries| entries.get(key).cloned().unwrap_or_default())
+    }
+
+    /// Update the state for `key` and write the file if anything changed.
+    pub fn update(&self, key: &str, f: impl FnOnce(&mut EndpointState)) {
+        self.entries
+            .update(|entries| f(entries.entry(key.to_string()).or_default()));
+    }
+
+    /// Update the state for `key`, written on the next [`flush`](Self::flush) or update.
+    pub fn update_deferred(&self, key: &str, f: impl FnOnce(&mut EndpointState)) {
+        self.entries
+            .update_deferred(|entries| f(entries.entry(key.to_string()).or_default()));
+    }
+
+    /// Write deferred changes (sensor readings), if any.
+    pub fn flush(&self) {
+        self.entries.flush();
+    }
+
+    /// Create a handle for the endpoint stored under `key`.
+    pub fn handle(self: &Arc<Self>, key: impl Into<String>) -> StateHandle {
+        StateHandle {
+            store: self.clone(),
+            key: key.into(),
+        }
+    }
+}
+
+/// Handle to the persisted state of one endpoint.
+#[derive(Clone)]
+pub struct StateHandle {
+    store: Arc<RuntimeStateStore>,
+    key: String,
+}
+
+impl StateHandle {
+    /// Get the stored state.
+    pub fn state(&self) -> EndpointState {
+        self.store.get(&self.key)
+    }
+
+    /// Get a stored sensor reading.
+    pub fn reading(&self, name: &str) -> Option<f32> {
+        self.state().readings.get(name).copied()
+    }
+
+    /// Persist the current OnOff value.
+    pub fn save_on_off(&self, on: bool) {
+        self.store
+            .update(&self.key, |state| state.on_off = Some(on));
+    }
+
+    /// Persist the StartUpOnOff behavior.
+    pub fn save_start_up(&self, start_up: Option<StartUpOnOff>) {
+        self.store
+            .update(&self.key, |state| state.start_up_on_off = start_up);
+    }
+
+    /// Persist a sensor reading (deferred until the store is flushed).
+    pub fn save_reading(&self, name: &str, value: f32) {
+        self.store.update_deferred(&self.key, |state| {
+            state.readings.insert(name.to_string(), value);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_start_up() {
+        assert!(!resolve_start_up(Some(StartUpOnOff::Off), Some(true), true));
+        assert!(resolve_start_up(Some(StartUpOnOff::On), Some(false), false));
+        assert!(resolve_start_up(
+            Some(StartUpOnOff::Toggle),
+            Some(false),
+            false
+        ));
+        assert!(!resolve_start_up(Some(StartUpOnOff::Toggle), None, true));
+        assert!(resolve_start_up(None, Some(true), false));
+        assert!(!resolve_start_up(None, None, false));
+    }
+
+    #[test]
+    fn test_raw_roundtrip() {
+        for start_up in [StartUpOnOff::Off, StartUpOnOff::On, StartUpOnOff::Toggle] {
+            assert_eq!(StartUpOnOff::from_raw(start_up.raw()), Some(start_up));
+        }
+        assert_eq!(StartUpOnOff::from_raw(3), None);
+    }
+
+    #[test]
+    fn test_save_and_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+
+        let store = Arc::new(RuntimeStateStore::load(&path));
+        let light = store.handle("Light/Light");
+        light.save_on_off(true);
+        light.save_start_up(Some(StartUpOnOff::Toggle));
+        store
+            .handle("Sensor/Climate")
+            .save_reading("temperature", 21.5);
+        assert_eq!(
+            RuntimeStateStore::load(&path).get("Sensor/Climate"),
+            EndpointState::default()
+        );
+        store.flush();
+
+        let reloaded = Arc::new(RuntimeStateStore::load(&path));
+        let state = reloaded.get("Light/Light");
+        assert_eq!(state.on_off, Some(true));
+        assert_eq!(state.start_up_on_off, Some(StartUpOnOff::Toggle));
+        assert_eq!(
+            reloaded.handle("Sensor/Climate").reading("temperature"),
+            Some(21.5)
+        );
+        assert_eq!(reloaded.get("Unknown"), EndpointState::default());
+    }
+}
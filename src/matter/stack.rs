@@ -1,31 +1,39 @@
 use super::clusters::{
-    BooleanStateHandler, BridgedDeviceInfo, BridgedHandler, GenericSwitchHandler,
-    GenericSwitchState, OccupancySensingHandler, RelativeHumidityHandler,
-    TemperatureMeasurementHandler, TimeSyncHandler,
+    BindingHandler, BooleanStateHandler, BridgedDeviceInfo, BridgedHandler, GenericSwitchHandler,
+    GestureConfig, NetworkDiagnosticsHandler, OccupancySensingHandler, PowerSourceHandler,
+    ReachableState, RelativeHumidityHandler, SoftwareDiagnosticsHandler, SwitchGestures,
+    TemperatureMeasurementHandler, TimeSyncHandler, network_diagnostics, software_diagnostics,
 };
-use super::device_info::DEV_INFO;
+use super::device_info::{DeviceIdentity, build_basic_info};
 use super::device_types::{
     DEV_TYPE_AGGREGATOR, DEV_TYPE_BRIDGED_NODE, DEV_TYPE_CONTACT_SENSOR, DEV_TYPE_GENERIC_SWITCH,
     DEV_TYPE_HUMIDITY_SENSOR, DEV_TYPE_OCCUPANCY_SENSOR, DEV_TYPE_ON_OFF_LIGHT,
-    DEV_TYPE_ON_OFF_PLUG_IN_UNIT, DEV_TYPE_TEMPERATURE_SENSOR, DEV_TYPE_VIDEO_DOORBELL,
+    DEV_TYPE_ON_OFF_PLUG_IN_UNIT, DEV_TYPE_POWER_SOURCE, DEV_TYPE_TEMPERATURE_SENSOR,
+    DEV_TYPE_VIDEO_DOORBELL,
 };
+use super::diagnostics::NodeDiagnostics;
 use super::endpoints::controls::{DeviceSwitch, LightSwitch, Switch};
-use super::handler_bridge::{SensorBridge, SwitchBridge};
+use super::handler_bridge::{ON_OFF_ATTRIBUTE_ID, SensorBridge, SwitchBridge};
 use super::logging_udp::LoggingUdpSocket;
 use super::netif::{FilteredNetifs, get_interface_name};
-use super::virtual_device::{EndpointKind, VirtualDevice, compute_schema_hash};
+use super::time_config::TimeConfigStore;
+use super::virtual_device::{
+    EndpointCluster, EndpointKind, VirtualDevice, VirtualDeviceError, compute_schema_hash,
+};
 use embassy_futures::select::{select, select4};
 use embassy_sync::blocking_mutex::raw::{CriticalSectionRawMutex, NoopRawMutex};
 use embassy_sync::signal::Signal;
-use log::{error, info};
+use log::{error, info, warn};
 use nix::ifaddrs::getifaddrs;
 use nix::net::if_::if_nametoindex;
 use nix::sys::socket::{AddressFamily, SockaddrLike};
 use rs_matter::dm::IMBuffer;
+use rs_matter::dm::clusters::basic_info::BasicInfoConfig;
 use rs_matter::dm::clusters::desc::{self, ClusterHandler as _, PartsMatcher};
+use rs_matter::dm::clusters::dev_att::DevAttDataFetcher;
 use rs_matter::dm::clusters::on_off::{self, OnOffHooks};
 use rs_matter::dm::devices;
-use rs_matter::dm::devices::test::{TEST_DEV_ATT, TEST_DEV_COMM};
+use rs_matter::dm::devices::test::TEST_DEV_ATT;
 use rs_matter::dm::endpoints;
 use rs_matter::dm::subscriptions::DefaultSubscriptions;
 use rs_matter::dm::{
@@ -35,80 +43,104 @@ use rs_matter::dm::{
 };
 use rs_matter::dm::{EventCollector, EventSource, MAX_PENDING_EVENTS, PendingEvent};
 use rs_matter::error::Error;
-use rs_matter::pairing::DiscoveryCapabilities;
-use rs_matter::pairing::qr::QrTextType;
 use rs_matter::persist::{NO_NETWORKS, Psm};
 use rs_matter::respond::DefaultResponder;
+use rs_matter::tlv::{Nullable, TLVElement};
 use rs_matter::transport::network::mdns::builtin::{BuiltinMdnsResponder, Host};
 use rs_matter::transport::network::mdns::{
     MDNS_IPV4_BROADCAST_ADDR, MDNS_IPV6_BROADCAST_ADDR, MDNS_SOCKET_DEFAULT_BIND_ADDR,
 };
-use rs_matter::utils::init::InitMaybeUninit;
+use rs_matter::utils::init::{Init, InitMaybeUninit};
 use rs_matter::utils::select::Coalesce;
 use rs_matter::utils::storage::pooled::PooledBuffers;
-use rs_matter::{MATTER_PORT, Matter, clusters, devices};
+use rs_matter::{Matter, clusters, devices};
 use socket2::{Domain, Protocol, Socket, Type};
-use static_cell::StaticCell;
 use std::collections::HashMap;
 use std::ffi::CString;
 use std::fs;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::num::NonZeroU8;
 use std::path::PathBuf;
 use std::pin::pin;
-use std::sync::atomic::AtomicBool;
 use std::sync::{Arc, OnceLock};
-
+use std::time::Duration;
+
+use super::attestation::FileDevAtt;
+use super::binding_table::BindingTableStore;
+use super::clusters::boolean_state::BooleanStateAttribute;
+use super::clusters::bridged_device_basic_info::BridgedDeviceBasicInfoAttribute;
+use super::clusters::generic_switch::GenericSwitchAttribute;
+use super::clusters::occupancy_sensing::OccupancySensingAttribute;
+use super::clusters::relative_humidity::RelativeHumidityAttribute;
+use super::clusters::temperature_measurement::TemperatureMeasurementAttribute;
 use super::clusters::{
-    boolean_state, generic_switch, occupancy_sensing, relative_humidity, temperature_measurement,
+    binding, boolean_state, bridged_device_basic_info, generic_switch, occupancy_sensing,
+    power_source, relative_humidity, temperature_measurement,
+};
+use super::comm_data::{CommissioningData, MAX_COMM_WINDOW_TIMEOUT_SECS, PakeVerifier};
+use super::control::{
+    BridgeControl, ControlEndpoint, ControlError, ControlPoint, FabricInfo, StackCommand,
 };
-use super::endpoints::{ClusterNotifier, NotifiableSensor};
+use super::endpoints::endpoints_helpers::transform::TransformConfig;
+use super::endpoints::{ChangeQueue, ClusterNotifier, DeviceAvailability, NotifiableSensor};
+use super::fabric_store::FabricStore;
+use super::local_bindings::LocalBindings;
+use super::node_labels::NodeLabelStore;
+use super::runtime_state::{RuntimeStateStore, StartUpOnOff};
 use crate::config::MatterConfig;
 
-/// Static cells for Matter resources (required for 'static lifetime)
-static MATTER: StaticCell<Matter> = StaticCell::new();
-static BUFFERS: StaticCell<PooledBuffers<10, NoopRawMutex, IMBuffer>> = StaticCell::new();
-static SUBSCRIPTIONS: StaticCell<DefaultSubscriptions> = StaticCell::new();
-static PSM: StaticCell<Psm<4096>> = StaticCell::new();
-/// Signal for sensor change notifications (wakes subscription processor)
-/// Uses CriticalSectionRawMutex because sensors are updated from different threads
-static SENSOR_NOTIFY: StaticCell<Signal<CriticalSectionRawMutex, ()>> = StaticCell::new();
-
-/// Signal for shutdown notification (wakes Matter stack to exit)
-/// Can be signaled from any thread (e.g., from tokio runtime on SIGTERM)
-/// Uses OnceLock so we can check if initialized before signaling
-static SHUTDOWN_SIGNAL: OnceLock<Signal<CriticalSectionRawMutex, ()>> = OnceLock::new();
+/// Shutdown handle of one bridge instance.
+///
+/// Can be signaled from any thread (e.g., from the tokio runtime on SIGTERM).
+/// The instance's Matter stack exits its event loop and returns from `run_matter_stack`.
+#[derive(Clone, Default)]
+pub struct ShutdownHandle {
+    signal: Arc<Signal<CriticalSectionRawMutex, ()>>,
+}
 
-/// Static hostname storage for mDNS (needs 'static lifetime for Host struct)
-static HOSTNAME: OnceLock<String> = OnceLock::new();
+impl ShutdownHandle {
+    /// Create a new shutdown handle.
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-/// Signal the Matter stack to shut down gracefully.
-///
-/// This can be called from any thread (e.g., from the tokio runtime on SIGTERM).
-/// The Matter stack will exit its event loop and return from `run_matter_stack`.
-pub fn signal_shutdown() {
-    // Get the shutdown signal if it was initialized
-    if let Some(signal) = SHUTDOWN_SIGNAL.get() {
+    /// Signal the Matter stack to shut down gracefully.
+    pub fn signal(&self) {
         info!("Signaling Matter stack shutdown");
-        signal.signal(());
-    } else {
-        // Stack wasn't started yet, nothing to do
-        info!("Shutdown signaled but Matter stack not running");
+        self.signal.signal(());
     }
+
+    /// Wait until shutdown is signaled.
+    async fn wait(&self) {
+        self.signal.wait().await;
+    }
+}
+
+/// Allocate `T` on the heap, initialize it in place and leak it.
+///
+/// Each `run_matter_stack` call allocates its own Matter resources this way, so several
+/// instances can run in one process. The stack and its handlers need `'static`
+/// references, so the allocation lives for the rest of the process.
+fn leak_init<T>(init: impl Init<T>) -> &'static mut T {
+    Box::leak(Box::<T>::new_uninit()).init_with(init)
 }
 
 /// Dynamic PartsMatcher that handles all parent-child relationships.
 /// Built from VirtualDevice configurations at runtime.
 #[derive(Debug)]
 pub struct DynamicPartsMatcher {
+    /// Aggregator endpoint ID (EP2, or EP1 without a master switch)
+    aggregator_id: u16,
     /// parent_endpoint_id -> child_endpoint_ids
     parent_to_children: Vec<(u16, Vec<u16>)>,
-    /// Aggregator (EP2) lists these parent IDs
+    /// Aggregator lists these parent IDs
     aggregator_parents: Vec<u16>,
 }
 
 impl DynamicPartsMatcher {
-    pub fn new() -> Self {
+    pub fn new(aggregator_id: u16) -> Self {
         Self {
+            aggregator_id,
             parent_to_children: Vec::new(),
             aggregator_parents: Vec::new(),
         }
@@ -135,13 +167,13 @@ impl DynamicPartsMatcher {
 
 impl Default for DynamicPartsMatcher {
     fn default() -> Self {
-        Self::new()
+        Self::new(AGGREGATOR_ENDPOINT_ID)
     }
 }
 
 impl PartsMatcher for DynamicPartsMatcher {
     fn matches(&self, our_endpoint: u16, endpoint: u16) -> bool {
-        if our_endpoint == 2 {
+        if our_endpoint == self.aggregator_id {
             // Aggregator returns all parent endpoints
             self.aggregator_parents.contains(&endpoint)
         } else {
@@ -155,12 +187,12 @@ impl PartsMatcher for DynamicPartsMatcher {
     }
 }
 
-/// Aggregated event source that collects events from multiple GenericSwitch states.
+/// Aggregated event source that collects events from multiple event-emitting states.
 ///
-/// This enables DynamicHandler to provide events from all button endpoints to the
-/// Matter subscription system.
+/// This enables DynamicHandler to provide events from all button endpoints and
+/// reachability changes to the Matter subscription system.
 pub struct AggregatedEventSource {
-    sources: Vec<Arc<GenericSwitchState>>,
+    sources: Vec<Arc<dyn EventSource + Send + Sync>>,
 }
 
 impl AggregatedEventSource {
@@ -170,8 +202,8 @@ impl AggregatedEventSource {
         }
     }
 
-    pub fn add(&mut self, state: Arc<GenericSwitchState>) {
-        self.sources.push(state);
+    pub fn add(&mut self, source: Arc<dyn EventSource + Send + Sync>) {
+        self.sources.push(source);
     }
 
     /// Returns true if any event sources are registered.
@@ -241,6 +273,10 @@ enum DynamicHandlerEntry {
     Humidity { handler: RelativeHumidityHandler },
     /// GenericSwitch cluster handler (for buttons)
     GenericSwitch { handler: GenericSwitchHandler },
+    /// PowerSource cluster handler (battery level)
+    PowerSource { handler: PowerSourceHandler },
+    /// Binding cluster handler (on buttons)
+    Binding { handler: BindingHandler },
 }
 
 /// Dynamic handler that routes requests based on (endpoint_id, cluster_id).
@@ -309,6 +345,8 @@ impl DynamicHandler {
     }
 
     pub fn add_bridged(&mut self, ep: u16, handler: BridgedHandler) {
+        // Also register the reachable state for ReachableChanged events
+        self.event_sources.add(handler.reachable().clone());
         self.handlers.insert(
             (ep, BridgedHandler::CLUSTER.id),
             DynamicHandlerEntry::Bridged { handler },
@@ -337,6 +375,29 @@ impl DynamicHandler {
             DynamicHandlerEntry::GenericSwitch { handler },
         );
     }
+
+    pub fn add_power_source(&mut self, ep: u16, handler: PowerSourceHandler) {
+        self.handlers.insert(
+            (ep, power_source::CLUSTER_ID),
+            DynamicHandlerEntry::PowerSource { handler },
+        );
+    }
+
+    pub fn add_binding(&mut self, ep: u16, handler: BindingHandler) {
+        self.handlers.insert(
+            (ep, binding::CLUSTER_ID),
+            DynamicHandlerEntry::Binding { handler },
+        );
+    }
+
+    /// Switch of an OnOff invoke whose handler confirms commands.
+    fn confirmed_onoff(&self, ctx: &impl InvokeContext) -> Option<&Arc<SwitchBridge>> {
+        let cmd = ctx.cmd();
+        match self.handlers.get(&(cmd.endpoint_id, cmd.cluster_id))? {
+            DynamicHandlerEntry::OnOff { bridge, .. } if bridge.confirms_commands() => Some(bridge),
+            _ => None,
+        }
+    }
 }
 
 impl Default for DynamicHandler {
@@ -379,6 +440,8 @@ impl Handler for DynamicHandler {
                 DynamicHandlerEntry::Temperature { handler } => handler.read(ctx, reply),
                 DynamicHandlerEntry::Humidity { handler } => handler.read(ctx, reply),
                 DynamicHandlerEntry::GenericSwitch { handler } => handler.read(ctx, reply),
+                DynamicHandlerEntry::PowerSource { handler } => handler.read(ctx, reply),
+                DynamicHandlerEntry::Binding { handler } => handler.read(ctx, reply),
             }
         } else {
             log::debug!(
@@ -400,6 +463,7 @@ impl Handler for DynamicHandler {
                 DynamicHandlerEntry::DeviceOnOff { dataver, switch } => {
                     write_device_onoff(dataver, switch, ctx)
                 }
+                DynamicHandlerEntry::Binding { handler } => handler.write(ctx),
                 _ => Err(rs_matter::error::ErrorCode::UnsupportedAccess.into()),
             }
         } else {
@@ -471,8 +535,9 @@ impl AsyncHandler for DynamicHandler {
         false // DynamicHandler is non-blocking
     }
 
-    fn invoke_awaits(&self, _ctx: impl InvokeContext) -> bool {
-        false // DynamicHandler is non-blocking
+    fn invoke_awaits(&self, ctx: impl InvokeContext) -> bool {
+        // Only OnOff commands confirmed by the device (e.g. over MQTT) are awaited
+        self.confirmed_onoff(&ctx).is_some()
     }
 
     async fn read(&self, ctx: impl ReadContext, reply: impl ReadReply) -> Result<(), Error> {
@@ -484,6 +549,9 @@ impl AsyncHandler for DynamicHandler {
     }
 
     async fn invoke(&self, ctx: impl InvokeContext, reply: impl InvokeReply) -> Result<(), Error> {
+        if let Some(bridge) = self.confirmed_onoff(&ctx) {
+            return invoke_confirmed_onoff(bridge, &ctx).await;
+        }
         Handler::invoke(self, ctx, reply)
     }
 
@@ -608,6 +676,11 @@ fn read_onoff(
         let mut tw = writer.writer();
         match attr.attr_id {
             0x00 => tw.bool(tag, bridge.get())?, // OnOff
+            0x4003 => match bridge.start_up() {
+                // StartUpOnOff
+                Some(start_up) => tw.u8(tag, start_up.raw())?,
+                None => tw.null(tag)?,
+            },
             other => {
                 log::debug!(
                     "OnOff cluster: unknown attr 0x{:04x} on endpoint {}",
@@ -621,6 +694,30 @@ fn read_onoff(
     writer.complete()
 }
 
+/// Invoke an OnOff command and wait until the device confirmed it.
+///
+/// A command the device did not confirm fails with a Failure status.
+async fn invoke_confirmed_onoff(
+    bridge: &SwitchBridge,
+    ctx: &impl InvokeContext,
+) -> Result<(), Error> {
+    let cmd = ctx.cmd();
+    // OnOff cluster commands: Off=0x00, On=0x01, Toggle=0x02
+    let value = match cmd.cmd_id {
+        0x00 => false,
+        0x01 => true,
+        0x02 => !bridge.get(),
+        _ => return Err(rs_matter::error::ErrorCode::CommandNotFound.into()),
+    };
+    bridge.set_confirmed(value).await.map_err(|e| {
+        warn!(
+            "OnOff command on endpoint {} failed: {}",
+            cmd.endpoint_id, e
+        );
+        rs_matter::error::ErrorCode::Failure.into()
+    })
+}
+
 /// Write handler for OnOff cluster.
 fn write_onoff(
     _dataver: &Dataver,
@@ -638,10 +735,25 @@ fn write_onoff(
             bridge.set(value);
             Ok(())
         }
+        0x4003 => {
+            // StartUpOnOff attribute (nullable enum)
+            bridge.set_start_up(read_start_up_on_off(&ctx.data())?);
+            Ok(())
+        }
         _ => Err(rs_matter::error::ErrorCode::UnsupportedAccess.into()),
     }
 }
 
+/// Decode a written StartUpOnOff value (null = restore previous value).
+fn read_start_up_on_off(data: &TLVElement) -> Result<Option<StartUpOnOff>, Error> {
+    if data.null().is_ok() {
+        return Ok(None);
+    }
+    StartUpOnOff::from_raw(data.u8()?)
+        .map(Some)
+        .ok_or_else(|| rs_matter::error::ErrorCode::ConstraintError.into())
+}
+
 /// Read handler for DeviceSwitch OnOff cluster (parent endpoints).
 fn read_device_onoff(
     dataver: &Dataver,
@@ -666,6 +778,11 @@ fn read_device_onoff(
         let mut tw = writer.writer();
         match attr.attr_id {
             0x00 => tw.bool(tag, switch.get())?, // OnOff
+            0x4003 => match switch.start_up_on_off().into_option() {
+                // StartUpOnOff
+                Some(start_up) => tw.u8(tag, StartUpOnOff::from_matter(start_up).raw())?,
+                None => tw.null(tag)?,
+            },
             other => {
                 log::debug!(
                     "DeviceOnOff cluster: unknown attr 0x{:04x} on endpoint {}",
@@ -697,6 +814,14 @@ fn write_device_onoff(
             switch.set_on_off(value);
             Ok(())
         }
+        0x4003 => {
+            // StartUpOnOff attribute (nullable enum)
+            let start_up = match read_start_up_on_off(&ctx.data())? {
+                Some(start_up) => Nullable::some(start_up.to_matter()),
+                None => Nullable::none(),
+            };
+            switch.set_start_up_on_off(start_up)
+        }
         _ => Err(rs_matter::error::ErrorCode::UnsupportedAccess.into()),
     }
 }
@@ -770,25 +895,132 @@ fn get_interface_index(interface_name: &str) -> Result<u32, Error> {
     })
 }
 
-/// Directory for persistence data
-const PERSIST_DIR: &str = ".config/virtual-matter-bridge";
+/// Persistence files (in the instance's storage directory, see `MatterConfig::persist_dir`)
 const PERSIST_FILE: &str = "matter.bin";
 const SCHEMA_FILE: &str = "schema.hash";
+const LABELS_FILE: &str = "labels.json";
+const STATE_FILE: &str = "state.json";
+const COMM_DATA_FILE: &str = "commissioning.json";
+const IDENTITY_FILE: &str = "identity.json";
+const FABRICS_FILE: &str = "fabrics.json";
+const DIAGNOSTICS_FILE: &str = "diagnostics.json";
+const TIME_FILE: &str = "time.json";
+const BINDINGS_FILE: &str = "bindings.json";
+const LOCAL_BINDINGS_FILE: &str = "local_bindings.json";
+const TRANSFORMS_FILE: &str = "transforms.json";
+
+/// Runtime state key of the bridge master on/off switch
+const MASTER_SWITCH_STATE_KEY: &str = "@master-switch";
+
+/// How often device availability is checked for staleness (and fabric sessions sampled,
+/// node diagnostics checkpointed, sensor readings written to the runtime state)
+const STALE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Minimum age before a fabric's recorded last session time is rewritten
+const FABRIC_SESSION_RESOLUTION_SECS: u64 = 60;
+
+/// mDNS hostname of an instance: the host name, suffixed with the instance name.
+///
+/// Several instances run on one host; each announces its own hostname (a DNS label, so
+/// other characters become `-` and it is cut to 63 bytes).
+fn mdns_hostname(host: &str, instance: Option<&str>) -> String {
+    let Some(instance) = instance else {
+        return host.to_string();
+    };
+    format!("{}-{}", host, instance)
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .take(63)
+        .collect()
+}
 
 /// Get the persistence file path
-fn get_persist_path() -> PathBuf {
-    dirs::home_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join(PERSIST_DIR)
-        .join(PERSIST_FILE)
+fn get_persist_path(config: &MatterConfig) -> PathBuf {
+    config.persist_dir().join(PERSIST_FILE)
 }
 
 /// Get the schema hash file path
-fn get_schema_path() -> PathBuf {
-    dirs::home_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join(PERSIST_DIR)
-        .join(SCHEMA_FILE)
+fn get_schema_path(config: &MatterConfig) -> PathBuf {
+    config.persist_dir().join(SCHEMA_FILE)
+}
+
+/// Get the NodeLabel store file path
+fn get_labels_path(config: &MatterConfig) -> PathBuf {
+    config.persist_dir().join(LABELS_FILE)
+}
+
+/// Get the runtime state (OnOff, StartUpOnOff, readings) file path
+fn get_state_path(config: &MatterConfig) -> PathBuf {
+    config.persist_dir().join(STATE_FILE)
+}
+
+/// Get the per-install commissioning data (discriminator, passcode) file path
+pub fn get_comm_data_path(config: &MatterConfig) -> PathBuf {
+    config.persist_dir().join(COMM_DATA_FILE)
+}
+
+/// Get the per-install device identity (serial number, UniqueID) file path
+fn get_identity_path(config: &MatterConfig) -> PathBuf {
+    config.persist_dir().join(IDENTITY_FILE)
+}
+
+/// Get the fabric metadata (alias, last session) file path
+fn get_fabrics_path(config: &MatterConfig) -> PathBuf {
+    config.persist_dir().join(FABRICS_FILE)
+}
+
+/// Get the node diagnostics (reboot count, operational time) file path
+fn get_diagnostics_path(config: &MatterConfig) -> PathBuf {
+    config.persist_dir().join(DIAGNOSTICS_FILE)
+}
+
+/// Get the controller-provided time configuration (UTC offset, time zone, DST) file path
+fn get_time_path(config: &MatterConfig) -> PathBuf {
+    config.persist_dir().join(TIME_FILE)
+}
+
+/// Get the Binding cluster table (controller-written binding targets) file path
+fn get_bindings_path(config: &MatterConfig) -> PathBuf {
+    config.persist_dir().join(BINDINGS_FILE)
+}
+
+/// Get the local button-to-endpoint bindings file path
+fn get_local_bindings_path(config: &MatterConfig) -> PathBuf {
+    config
+        .local_bindings
+        .clone()
+        .unwrap_or_else(|| config.persist_dir().join(LOCAL_BINDINGS_FILE))
+}
+
+/// Get the per-endpoint reading transforms file path
+fn get_transforms_path(config: &MatterConfig) -> PathBuf {
+    config
+        .transforms
+        .clone()
+        .unwrap_or_else(|| config.persist_dir().join(TRANSFORMS_FILE))
+}
+
+/// Create the ReachableState for a bridged endpoint.
+///
+/// Wires it to the subscription notifier and registers it with the device's
+/// availability handle (if the device has one).
+fn new_reachable_state(
+    endpoint_id: u16,
+    change_queue: &'static ChangeQueue,
+    availability: Option<&Arc<DeviceAvailability>>,
+) -> Arc<ReachableState> {
+    let state = Arc::new(ReachableState::new());
+    state.set_endpoint_id(endpoint_id);
+    state.set_notifier(ClusterNotifier::new(
+        change_queue,
+        endpoint_id,
+        bridged_device_basic_info::CLUSTER_ID,
+        &[BridgedDeviceBasicInfoAttribute::Reachable as u32],
+    ));
+    if let Some(availability) = availability {
+        availability.add_target(state.clone());
+    }
+    state
 }
 
 /// Check if schema has changed and reset persistence if needed.
@@ -796,6 +1028,7 @@ fn get_schema_path() -> PathBuf {
 /// Returns true if persistence was reset (commissioning window should open).
 fn check_schema_and_maybe_reset(
     virtual_devices: &[VirtualDevice],
+    master_switch: bool,
     persist_path: &PathBuf,
     schema_path: &PathBuf,
 ) -> bool {
@@ -811,7 +1044,7 @@ fn check_schema_and_maybe_reset(
     }
 
     // Compute current schema hash
-    let current_hash = compute_schema_hash(virtual_devices);
+    let current_hash = compute_schema_hash(virtual_devices, master_switch);
 
     // Try to read stored schema hash
     let stored_hash = fs::read_to_string(schema_path)
@@ -857,9 +1090,30 @@ fn check_schema_and_maybe_reset(
     }
 }
 
-/// Root endpoint cluster list with Time Synchronization added
+/// rs-matter's Ethernet root endpoint cluster list with Time Synchronization added
 const ROOT_CLUSTERS: &[Cluster<'static>] = clusters!(eth; TimeSyncHandler::CLUSTER);
 
+/// Cached root endpoint cluster list (lazily initialized)
+static ROOT_CLUSTERS_WITH_DIAG: OnceLock<&'static [Cluster<'static>]> = OnceLock::new();
+
+/// Get the root endpoint cluster list with diagnostics.
+///
+/// rs-matter's Ethernet Network Diagnostics cluster is replaced by the network
+/// diagnostics cluster of the detected interface (Ethernet or WiFi), and Software
+/// Diagnostics is added.
+fn root_clusters() -> &'static [Cluster<'static>] {
+    ROOT_CLUSTERS_WITH_DIAG.get_or_init(|| {
+        let mut clusters: Vec<Cluster<'static>> = ROOT_CLUSTERS
+            .iter()
+            .filter(|cluster| cluster.id != network_diagnostics::ETHERNET_CLUSTER_ID)
+            .cloned()
+            .collect();
+        clusters.push(network_diagnostics::cluster_for(get_interface_name()));
+        clusters.push(SoftwareDiagnosticsHandler::CLUSTER);
+        leak_vec(clusters)
+    })
+}
+
 /// Cached network interface filter (lazily initialized)
 static NETIFS: OnceLock<FilteredNetifs> = OnceLock::new();
 
@@ -873,11 +1127,49 @@ fn leak_slice<T: Clone>(items: &[T]) -> &'static [T] {
     Box::leak(items.to_vec().into_boxed_slice())
 }
 
+/// Leak a vector to get a 'static slice.
+fn leak_vec<T>(items: Vec<T>) -> &'static [T] {
+    Box::leak(items.into_boxed_slice())
+}
+
 /// Leak a value to get 'static lifetime.
 fn leak<T>(value: T) -> &'static T {
     Box::leak(Box::new(value))
 }
 
+/// Device type advertised for an endpoint cluster kind.
+fn kind_device_type(kind: EndpointKind) -> DeviceType {
+    match kind {
+        EndpointKind::ContactSensor => DEV_TYPE_CONTACT_SENSOR,
+        EndpointKind::OccupancySensor => DEV_TYPE_OCCUPANCY_SENSOR,
+        EndpointKind::Switch => DEV_TYPE_ON_OFF_PLUG_IN_UNIT,
+        EndpointKind::LightSwitch => DEV_TYPE_ON_OFF_LIGHT,
+        EndpointKind::VideoDoorbellCamera => DEV_TYPE_VIDEO_DOORBELL,
+        EndpointKind::TemperatureSensor => DEV_TYPE_TEMPERATURE_SENSOR,
+        EndpointKind::HumiditySensor => DEV_TYPE_HUMIDITY_SENSOR,
+        EndpointKind::GenericSwitch | EndpointKind::LatchingSwitch => DEV_TYPE_GENERIC_SWITCH,
+        EndpointKind::PowerSource => DEV_TYPE_POWER_SOURCE,
+    }
+}
+
+/// Functional cluster exposed for an endpoint cluster kind (besides Descriptor/Bridged).
+fn kind_cluster(kind: EndpointKind) -> Option<Cluster<'static>> {
+    match kind {
+        EndpointKind::ContactSensor => Some(BooleanStateHandler::CLUSTER),
+        EndpointKind::OccupancySensor => Some(OccupancySensingHandler::CLUSTER),
+        EndpointKind::Switch => Some(Switch::CLUSTER),
+        EndpointKind::LightSwitch => Some(LightSwitch::CLUSTER),
+        // TODO: Add CameraAvStreamMgmtHandler::CLUSTER and WebRtcTransportProviderHandler::CLUSTER
+        // when handlers are wired (see TODO in run_matter_stack)
+        EndpointKind::VideoDoorbellCamera => None,
+        EndpointKind::TemperatureSensor => Some(TemperatureMeasurementHandler::CLUSTER),
+        EndpointKind::HumiditySensor => Some(RelativeHumidityHandler::CLUSTER),
+        EndpointKind::GenericSwitch => Some(GenericSwitchHandler::CLUSTER),
+        EndpointKind::LatchingSwitch => Some(GenericSwitchHandler::LATCHING_CLUSTER),
+        EndpointKind::PowerSource => Some(PowerSourceHandler::CLUSTER),
+    }
+}
+
 /// Mapping of allocated endpoint IDs for a virtual device.
 #[derive(Debug, Clone)]
 pub struct EndpointMapping {
@@ -887,10 +1179,18 @@ pub struct EndpointMapping {
     pub child_ids: Vec<u16>,
 }
 
+/// Endpoint ID of the bridge master on/off switch (when enabled)
+pub const MASTER_SWITCH_ENDPOINT_ID: u16 = 1;
+
+/// Endpoint ID of the Aggregator when the master switch is enabled
+pub const AGGREGATOR_ENDPOINT_ID: u16 = 2;
+
 /// Result of building the dynamic node.
 pub struct BuiltNode {
     /// The static Node reference
     pub node: &'static Node<'static>,
+    /// Aggregator endpoint ID (EP2, or EP1 without a master switch)
+    pub aggregator_id: u16,
     /// The dynamic parts matcher
     pub parts_matcher: &'static DynamicPartsMatcher,
     /// Endpoint mappings for each virtual device (in order)
@@ -899,30 +1199,49 @@ pub struct BuiltNode {
 
 /// Build the Matter Node dynamically from virtual device configurations.
 ///
-/// Returns the node with 'static lifetime (via Box::leak) and the parts matcher.
-pub fn build_node(virtual_devices: &[VirtualDevice]) -> BuiltNode {
+/// With `master_switch`, endpoint 1 is the bridge master on/off and the Aggregator is
+/// endpoint 2. Without it, the Aggregator moves to endpoint 1.
+///
+/// Returns the node with 'static lifetime (via Box::leak) and the parts matcher, or an
+/// error if an endpoint carries the same cluster twice.
+pub fn build_node(
+    virtual_devices: &[VirtualDevice],
+    master_switch: bool,
+) -> Result<BuiltNode, VirtualDeviceError> {
+    for device in virtual_devices {
+        device.validate()?;
+    }
+
+    let aggregator_id = if master_switch {
+        AGGREGATOR_ENDPOINT_ID
+    } else {
+        MASTER_SWITCH_ENDPOINT_ID
+    };
+
     let mut endpoints_vec = Vec::new();
-    let mut parts_matcher = DynamicPartsMatcher::new();
+    let mut parts_matcher = DynamicPartsMatcher::new(aggregator_id);
     let mut mappings = Vec::new();
-    let mut next_id: u16 = 3; // Start after Root(0), Doorbell(1), Aggregator(2)
+    let mut next_id: u16 = aggregator_id + 1; // Start after Root, master switch, Aggregator
 
     // Endpoint 0: Root node
     endpoints_vec.push(Endpoint {
         id: endpoints::ROOT_ENDPOINT_ID,
         device_types: devices!(devices::DEV_TYPE_ROOT_NODE),
-        clusters: ROOT_CLUSTERS,
+        clusters: root_clusters(),
     });
 
-    // Endpoint 1: Bridge master on/off control
-    endpoints_vec.push(Endpoint {
-        id: 1,
-        device_types: devices!(DEV_TYPE_ON_OFF_PLUG_IN_UNIT),
-        clusters: clusters!(desc::DescHandler::CLUSTER, Switch::CLUSTER),
-    });
+    // Endpoint 1: Bridge master on/off control (optional)
+    if master_switch {
+        endpoints_vec.push(Endpoint {
+            id: MASTER_SWITCH_ENDPOINT_ID,
+            device_types: devices!(DEV_TYPE_ON_OFF_PLUG_IN_UNIT),
+            clusters: clusters!(desc::DescHandler::CLUSTER, Switch::CLUSTER),
+        });
+    }
 
-    // Endpoint 2: Aggregator (bridge root - will use DynamicPartsMatcher)
+    // Aggregator (bridge root - will use DynamicPartsMatcher)
     endpoints_vec.push(Endpoint {
-        id: 2,
+        id: aggregator_id,
         device_types: devices!(DEV_TYPE_AGGREGATOR),
         clusters: clusters!(desc::DescHandler::CLUSTER),
     });
@@ -932,19 +1251,30 @@ pub fn build_node(virtual_devices: &[VirtualDevice]) -> BuiltNode {
         let parent_id = next_id;
         next_id += 1;
 
-        // Get device types for parent (always OnOffPlugInUnit - parent is generic on/off switch)
-        let parent_device_types: &'static [DeviceType] =
-            leak_slice(&[DEV_TYPE_ON_OFF_PLUG_IN_UNIT, DEV_TYPE_BRIDGED_NODE]);
+        // Parent endpoint: bridged node, plus OnOff cluster for device-level control if enabled
+        let (parent_device_types, parent_clusters): (
+            &'static [DeviceType],
+            &'static [Cluster<'static>],
+        ) = if device.device_switch {
+            (
+                leak_slice(&[DEV_TYPE_ON_OFF_PLUG_IN_UNIT, DEV_TYPE_BRIDGED_NODE]),
+                clusters!(
+                    desc::DescHandler::CLUSTER,
+                    BridgedHandler::CLUSTER,
+                    DeviceSwitch::CLUSTER
+                ),
+            )
+        } else {
+            (
+                leak_slice(&[DEV_TYPE_BRIDGED_NODE]),
+                clusters!(desc::DescHandler::CLUSTER, BridgedHandler::CLUSTER),
+            )
+        };
 
-        // Parent endpoint (bridged node with OnOff cluster for device-level control)
         endpoints_vec.push(Endpoint {
             id: parent_id,
             device_types: parent_device_types,
-            clusters: clusters!(
-                desc::DescHandler::CLUSTER,
-                BridgedHandler::CLUSTER,
-                DeviceSwitch::CLUSTER
-            ),
+            clusters: parent_clusters,
         });
 
         // Add child endpoints
@@ -954,73 +1284,28 @@ pub fn build_node(virtual_devices: &[VirtualDevice]) -> BuiltNode {
             next_id += 1;
             child_ids.push(child_id);
 
-            let (device_types, clusters): (&'static [DeviceType], &'static [Cluster<'static>]) =
-                match ep_config.kind {
-                    EndpointKind::ContactSensor => (
-                        leak_slice(&[DEV_TYPE_CONTACT_SENSOR]),
-                        clusters!(
-                            desc::DescHandler::CLUSTER,
-                            BridgedHandler::CLUSTER,
-                            BooleanStateHandler::CLUSTER
-                        ),
-                    ),
-                    EndpointKind::OccupancySensor => (
-                        leak_slice(&[DEV_TYPE_OCCUPANCY_SENSOR]),
-                        clusters!(
-                            desc::DescHandler::CLUSTER,
-                            BridgedHandler::CLUSTER,
-                            OccupancySensingHandler::CLUSTER
-                        ),
-                    ),
-                    EndpointKind::Switch => (
-                        leak_slice(&[DEV_TYPE_ON_OFF_PLUG_IN_UNIT]),
-                        clusters!(
-                            desc::DescHandler::CLUSTER,
-                            BridgedHandler::CLUSTER,
-                            Switch::CLUSTER
-                        ),
-                    ),
-                    EndpointKind::LightSwitch => (
-                        leak_slice(&[DEV_TYPE_ON_OFF_LIGHT]),
-                        clusters!(
-                            desc::DescHandler::CLUSTER,
-                            BridgedHandler::CLUSTER,
-                            LightSwitch::CLUSTER
-                        ),
-                    ),
-                    EndpointKind::VideoDoorbellCamera => (
-                        leak_slice(&[DEV_TYPE_VIDEO_DOORBELL]),
-                        clusters!(
-                            desc::DescHandler::CLUSTER,
-                            BridgedHandler::CLUSTER // TODO: Add CameraAvStreamMgmtHandler::CLUSTER and WebRtcTransportProviderHandler::CLUSTER
-                                                    // when handlers are wired (see TODO at ~line 1145)
-                        ),
-                    ),
-                    EndpointKind::TemperatureSensor => (
-                        leak_slice(&[DEV_TYPE_TEMPERATURE_SENSOR]),
-                        clusters!(
-                            desc::DescHandler::CLUSTER,
-                            BridgedHandler::CLUSTER,
-                            TemperatureMeasurementHandler::CLUSTER
-                        ),
-                    ),
-                    EndpointKind::HumiditySensor => (
-                        leak_slice(&[DEV_TYPE_HUMIDITY_SENSOR]),
-                        clusters!(
-                            desc::DescHandler::CLUSTER,
-                            BridgedHandler::CLUSTER,
-                            RelativeHumidityHandler::CLUSTER
-                        ),
-                    ),
-                    EndpointKind::GenericSwitch => (
-                        leak_slice(&[DEV_TYPE_GENERIC_SWITCH]),
-                        clusters!(
-                            desc::DescHandler::CLUSTER,
-                            BridgedHandler::CLUSTER,
-                            GenericSwitchHandler::CLUSTER
-                        ),
-                    ),
-                };
+            // Device types and clusters of all cluster providers on this endpoint
+            let mut device_types: Vec<DeviceType> = Vec::new();
+            let mut clusters = vec![desc::DescHandler::CLUSTER, BridgedHandler::CLUSTER];
+            for kind in ep_config.kinds() {
+                let device_type = kind_device_type(kind);
+                if !device_types.iter().any(|dt| dt.dtype == device_type.dtype) {
+                    device_types.push(device_type);
+                }
+                clusters.extend(kind_cluster(kind));
+                // Buttons can be bound to the endpoints they control
+                if matches!(
+                    kind,
+                    EndpointKind::GenericSwitch | EndpointKind::LatchingSwitch
+                ) && !clusters
+                    .iter()
+                    .any(|cluster| cluster.id == binding::CLUSTER_ID)
+                {
+                    clusters.push(BindingHandler::CLUSTER);
+                }
+            }
+            let device_types = leak_vec(device_types);
+            let clusters = leak_vec(clusters);
 
             endpoints_vec.push(Endpoint {
                 id: child_id,
@@ -1044,10 +1329,296 @@ pub fn build_node(virtual_devices: &[VirtualDevice]) -> BuiltNode {
 
     let parts_matcher = leak(parts_matcher);
 
-    BuiltNode {
+    Ok(BuiltNode {
         node,
+        aggregator_id,
         parts_matcher,
         mappings,
+    })
+}
+
+/// Get the index and metadata key of every commissioned fabric.
+fn fabric_keys(matter: &Matter) -> Vec<(u8, String)> {
+    matter.with_state(|state| {
+        state
+            .fabrics
+            .iter()
+            .map(|fabric| {
+                (
+                    fabric.fab_idx().get(),
+                    FabricStore::key(fabric.fabric_id(), fabric.node_id()),
+                )
+            })
+            .collect()
+    })
+}
+
+/// Get the metadata key of the fabric with index `fabric_index`.
+fn fabric_key(matter: &Matter, fabric_index: u8) -> Result<String, ControlError> {
+    fabric_keys(matter)
+        .into_iter()
+        .find(|(index, _)| *index == fabric_index)
+        .map(|(_, key)| key)
+        .ok_or(ControlError::UnknownFabric(fabric_index))
+}
+
+/// Read the commissioned fabrics from the Matter instance.
+fn list_fabrics(matter: &Matter, fabric_store: &FabricStore) -> Vec<FabricInfo> {
+    matter.with_state(|state| {
+        state
+            .fabrics
+            .iter()
+            .map(|fabric| {
+                let meta =
+                    fabric_store.get(&FabricStore::key(fabric.fabric_id(), fabric.node_id()));
+                FabricInfo {
+                    fabric_index: fabric.fab_idx().get(),
+                    fabric_id: fabric.fabric_id(),
+                    node_id: fabric.node_id(),
+                    vendor_id: fabric.vendor_id(),
+                    label: fabric.label().to_string(),
+                    alias: meta.alias,
+                    last_session: meta.last_session,
+                }
+            })
+            .collect()
+    })
+}
+
+/// Record the last session time of every fabric with an active session.
+fn record_fabric_sessions(matter: &Matter, fabric_store: &FabricStore) {
+    let active: Vec<u8> = matter
+        .transport_mgr
+        .session_mgr
+        .borrow()
+        .iter()
+        .map(|session| session.get_local_fabric_idx())
+        .filter(|index| *index != 0)
+        .collect();
+    if active.is_empty() {
+        return;
+    }
+    let keys: Vec<String> = fabric_keys(matter)
+        .into_iter()
+        .filter(|(index, _)| active.contains(index))
+        .map(|(_, key)| key)
+        .collect();
+    fabric_store.record_sessions(
+        keys.iter().map(String::as_str),
+        FABRIC_SESSION_RESOLUTION_SECS,
+    );
+}
+
+/// Remove a fabric from the Matter instance.
+///
+/// The fabric's ACL entries are stored with the fabric and go with it; its sessions
+/// and subscriptions are dropped so the controller loses access immediately.
+fn remove_fabric(
+    matter: &Matter,
+    subscriptions: &DefaultSubscriptions,
+    fabric_index: u8,
+) -> Result<(), Error> {
+    let fab_idx = NonZeroU8::new(fabric_index).ok_or(rs_matter::error::ErrorCode::NotFound)?;
+    matter.with_state(|state| state.fabrics.remove(fab_idx, &matter.transport_mgr.mdns))?;
+    matter
+        .transport_mgr
+        .session_mgr
+        .borrow_mut()
+        .remove_for_fabric(fab_idx);
+    subscriptions.remove(Some(fab_idx), None, None);
+    matter.notify_persist();
+    Ok(())
+}
+
+/// Open an Enhanced Commissioning Method window for one-time commissioning data.
+///
+/// Same as an AdministratorCommissioning OpenCommissioningWindow command: the PASE
+/// manager only gets a SPAKE2+ verifier, derived here from the one-time passcode with a
+/// fresh salt, and mDNS advertises the window with the one-time discriminator.
+fn open_enhanced_comm_window(
+    matter: &Matter,
+    data: &CommissioningData,
+    timeout_secs: u16,
+) -> Result<(), Error> {
+    let verifier = PakeVerifier::generate(data.passcode);
+    matter.pase_mgr.borrow_mut().open_enhanced_comm_window(
+        &verifier.verifier,
+        &verifier.salt,
+        verifier.iterations,
+        data.discriminator,
+        timeout_secs,
+        &matter.transport_mgr.mdns,
+    )
+}
+
+/// Close the open commissioning window, returning whether one was open.
+fn revoke_comm_window(matter: &Matter) -> Result<bool, Error> {
+    matter
+        .pase_mgr
+        .borrow_mut()
+        .close_comm_window(&matter.transport_mgr.mdns)
+}
+
+/// Serve a control plane request on the Matter stack thread.
+#[allow(clippy::too_many_arguments)]
+fn handle_stack_command(
+    matter: &Matter,
+    subscriptions: &DefaultSubscriptions,
+    dev_info: &BasicInfoConfig,
+    comm_data: &CommissioningData,
+    fabric_store: &FabricStore,
+    label_store: &NodeLabelStore,
+    binding_store: &BindingTableStore,
+    time_store: &TimeConfigStore,
+    node_diagnostics: &NodeDiagnostics,
+    command: StackCommand,
+) -> Result<serde_json::Value, ControlError> {
+    let matter_error = |e: Error| ControlError::Matter(format!("{:?}", e));
+    match command {
+        StackCommand::OpenCommissioningWindow {
+            timeout_secs,
+            enhanced,
+        } => {
+            let window_data = if enhanced {
+                let one_time = CommissioningData::generate();
+                open_enhanced_comm_window(matter, &one_time, timeout_secs).map_err(matter_error)?;
+                one_time
+            } else {
+                matter
+                    .open_basic_comm_window(timeout_secs)
+                    .map_err(matter_error)?;
+                *comm_data
+            };
+            let pairing_code = crate::onboarding::generate_pairing_code(
+                window_data.discriminator,
+                window_data.passcode,
+            );
+            let qr_payload = crate::onboarding::generate_qr_payload(
+                dev_info.vid,
+                dev_info.pid,
+                window_data.discriminator,
+                window_data.passcode,
+            );
+            info!(
+                "Opened {} commissioning window for {} seconds",
+                if enhanced { "enhanced" } else { "basic" },
+                timeout_secs
+            );
+            info!("  Pairing code: {}", pairing_code);
+            info!("  QR payload: {}", qr_payload);
+            Ok(serde_json::json!({
+                "enhanced": enhanced,
+                "timeout_secs": timeout_secs,
+                "discriminator": window_data.discriminator,
+                "pairing_code": pairing_code,
+                "qr_payload": qr_payload,
+            }))
+        }
+        StackCommand::RevokeCommissioningWindow => {
+            let was_open = revoke_comm_window(matter).map_err(matter_error)?;
+            if was_open {
+                info!("Commissioning window revoked");
+            }
+            Ok(serde_json::json!(was_open))
+        }
+        StackCommand::ListFabrics => {
+            record_fabric_sessions(matter, fabric_store);
+            serde_json::to_value(list_fabrics(matter, fabric_store))
+                .map_err(|e| ControlError::Matter(e.to_string()))
+        }
+        StackCommand::SetFabricAlias {
+            fabric_index,
+            alias,
+        } => {
+            let key = fabric_key(matter, fabric_index)?;
+            let alias = alias.filter(|alias| !alias.is_empty());
+            info!("Fabric {} alias set to {:?}", fabric_index, alias);
+            fabric_store.set_alias(&key, alias);
+            Ok(serde_json::json!(true))
+        }
+        StackCommand::RemoveFabric { fabric_index } => {
+            let key = fabric_key(matter, fabric_index)?;
+            remove_fabric(matter, subscriptions, fabric_index).map_err(matter_error)?;
+            fabric_store.remove(&key);
+            binding_store.remove_fabric(fabric_index);
+            info!("Removed fabric {}", fabric_index);
+
+            // Without any fabric left, the bridge is reachable only by commissioning
+            if !matter.is_commissioned() {
+                matter
+                    .open_basic_comm_window(MAX_COMM_WINDOW_TIMEOUT_SECS)
+                    .map_err(matter_error)?;
+                info!(
+                    "No fabrics left, opened commissioning window for {} seconds",
+                    MAX_COMM_WINDOW_TIMEOUT_SECS
+                );
+            }
+            Ok(serde_json::json!(true))
+        }
+        StackCommand::FactoryReset => {
+            warn!("Factory reset: removing all fabrics and controller-assigned state");
+            // Carry on past failures so a single bad fabric doesn't leave the rest behind
+            let mut errors = Vec::new();
+            let fabrics = fabric_keys(matter);
+            for (fabric_index, _) in &fabrics {
+                if let Err(e) = remove_fabric(matter, subscriptions, *fabric_index) {
+                    errors.push(format!("removing fabric {}: {:?}", fabric_index, e));
+                }
+            }
+            for (what, result) in [
+                ("fabric store", fabric_store.clear()),
+                ("node labels", label_store.clear()),
+                ("binding table", binding_store.clear()),
+                ("time configuration", time_store.clear()),
+            ] {
+                if let Err(e) = result {
+                    errors.push(format!("clearing {}: {}", what, e));
+                }
+            }
+
+            // Replace a window opened for another controller with the per-install one
+            if let Err(e) = revoke_comm_window(matter) {
+                errors.push(format!("revoking commissioning window: {:?}", e));
+            }
+            if let Err(e) = matter.open_basic_comm_window(MAX_COMM_WINDOW_TIMEOUT_SECS) {
+                errors.push(format!("opening commissioning window: {:?}", e));
+            }
+            if !errors.is_empty() {
+                error!("Factory reset incomplete: {}", errors.join("; "));
+                return Err(ControlError::Matter(format!(
+                    "factory reset incomplete: {}",
+                    errors.join("; ")
+                )));
+            }
+            let pairing_code = crate::onboarding::generate_pairing_code(
+                comm_data.discriminator,
+                comm_data.passcode,
+            );
+            info!(
+                "Factory reset complete ({} fabrics removed), commissioning window open for {} seconds",
+                fabrics.len(),
+                MAX_COMM_WINDOW_TIMEOUT_SECS
+            );
+            info!("  Pairing code: {}", pairing_code);
+            Ok(serde_json::json!({
+                "removed_fabrics": fabrics.len(),
+                "timeout_secs": MAX_COMM_WINDOW_TIMEOUT_SECS,
+                "pairing_code": pairing_code,
+            }))
+        }
+        StackCommand::Diagnostics => {
+            let memory = software_diagnostics::memory_stats();
+            Ok(serde_json::json!({
+                "reboot_count": node_diagnostics.reboot_count(),
+                "boot_reason": node_diagnostics.boot_reason().as_str(),
+                "uptime_secs": node_diagnostics.uptime_secs(),
+                "total_operational_hours": node_diagnostics.total_operational_hours(),
+                "interface": get_interface_name(),
+                "memory_used": memory.used,
+                "memory_free": memory.free,
+                "threads": software_diagnostics::threads().len(),
+            }))
+        }
     }
 }
 
@@ -1059,33 +1630,76 @@ pub fn build_node(virtual_devices: &[VirtualDevice]) -> BuiltNode {
 /// - Matter protocol communication
 ///
 /// The virtual devices define what bridged devices to expose.
+/// Without `virtual_bridge_onoff`, no master switch endpoint is created.
 ///
-/// Note: Currently uses test device credentials for development.
+/// Commissioning uses the configured or per-install generated discriminator and passcode.
+/// Device attestation uses the configured credential files, falling back to test credentials.
+/// Endpoints are registered with `control`, whose queued requests are served by the stack.
 pub async fn run_matter_stack(
     config: &MatterConfig,
     // Bridge master on/off switch (controls EP1, cascades to all parent DeviceSwitches)
-    virtual_bridge_onoff: Arc<Switch>,
-    // Dynamic virtual devices (bridged under EP2 Aggregator)
+    virtual_bridge_onoff: Option<Arc<Switch>>,
+    // Dynamic virtual devices (bridged under the Aggregator)
     virtual_devices: Vec<VirtualDevice>,
+    // Stops this instance's stack
+    shutdown: ShutdownHandle,
+    // Registry and request queue of the local control plane
+    control: BridgeControl,
 ) -> Result<(), Error> {
     info!("Initializing Matter stack...");
 
     // Build the dynamic node structure
-    let built_node = build_node(&virtual_devices);
+    let built_node = match build_node(&virtual_devices, virtual_bridge_onoff.is_some()) {
+        Ok(built_node) => built_node,
+        Err(e) => {
+            error!("Invalid device configuration: {}", e);
+            return Err(rs_matter::error::ErrorCode::InvalidData.into());
+        }
+    };
     info!(
         "Built Matter node with {} endpoints ({} virtual devices)",
         built_node.node.endpoints.len(),
         virtual_devices.len()
     );
 
-    // Initialize the Matter instance in static memory
-    let matter = MATTER.uninit().init_with(Matter::init(
-        &DEV_INFO,
-        TEST_DEV_COMM,
-        &TEST_DEV_ATT,
+    // Root BasicInformation from configuration (serial/UniqueID generated once per install)
+    let identity = DeviceIdentity::resolve(config, &get_identity_path(config));
+    let dev_info = build_basic_info(config, &identity);
+    control.set_unique_id(&identity.unique_id);
+    info!(
+        "Device: {} (VID 0x{:04X}, PID 0x{:04X}, serial {}, version {})",
+        dev_info.product_name, dev_info.vid, dev_info.pid, dev_info.serial_no, dev_info.sw_ver_str
+    );
+
+    // Discriminator and passcode: configured, or generated once per install
+    let comm_data = CommissioningData::resolve(config, &get_comm_data_path(config));
+
+    // Device attestation: configured credential files, or the Matter SDK test credentials
+    let dev_att: &'static dyn DevAttDataFetcher = match &config.attestation {
+        Some(attestation) => match FileDevAtt::load(attestation, dev_info) {
+            Ok(dev_att) => {
+                info!("Loaded device attestation credentials from files");
+                Box::leak(Box::new(dev_att))
+            }
+            Err(e) => {
+                error!("{}", e);
+                return Err(rs_matter::error::ErrorCode::InvalidData.into());
+            }
+        },
+        None => {
+            warn!("Using Matter SDK test attestation credentials (uncertified device)");
+            &TEST_DEV_ATT
+        }
+    };
+
+    // Initialize the Matter instance (owned by this bridge instance)
+    let matter = leak_init(Matter::init(
+        dev_info,
+        comm_data.basic_comm_data(),
+        dev_att,
         rs_matter::utils::epoch::sys_epoch,
         rs_matter::utils::rand::sys_rand,
-        MATTER_PORT,
+        config.port,
     ));
     // Use shared reference going forward (avoid moving the &mut)
     let matter: &'static Matter = &*matter;
@@ -1129,10 +1743,7 @@ pub async fn run_matter_stack(
         error!("Failed to create UDP socket: {}", e);
         rs_matter::error::ErrorCode::StdIoError
     })?;
-    raw_socket.set_reuse_address(true).map_err(|e| {
-        error!("Failed to set SO_REUSEADDR: {}", e);
-        rs_matter::error::ErrorCode::StdIoError
-    })?;
+    // No SO_REUSEADDR: a second instance on the same port has to fail, not share it
     raw_socket.set_only_v6(false).map_err(|e| {
         error!("Failed to set IPV6_V6ONLY=false: {}", e);
         rs_matter::error::ErrorCode::StdIoError
@@ -1142,9 +1753,17 @@ pub async fn run_matter_stack(
         rs_matter::error::ErrorCode::StdIoError
     })?;
 
-    let bind_addr = SocketAddr::new(IpAddr::V6(ipv6_addr), MATTER_PORT);
+    let bind_addr = SocketAddr::new(IpAddr::V6(ipv6_addr), config.port);
     raw_socket.bind(&bind_addr.into()).map_err(|e| {
-        error!("Failed to bind UDP socket to {:?}: {}", bind_addr, e);
+        if e.kind() == std::io::ErrorKind::AddrInUse {
+            error!(
+                "Matter port {} is already in use (another bridge instance?), set MATTER_PORT \
+                 to a free port",
+                config.port
+            );
+        } else {
+            error!("Failed to bind UDP socket to {:?}: {}", bind_addr, e);
+        }
         rs_matter::error::ErrorCode::StdIoError
     })?;
     let socket = async_io::Async::<UdpSocket>::new(raw_socket.into()).map_err(|e| {
@@ -1154,8 +1773,8 @@ pub async fn run_matter_stack(
     info!("Matter UDP socket bound to {:?}", bind_addr);
 
     // Initialize Psm (Persistent State Manager) and load existing state
-    let persist_path = get_persist_path();
-    let schema_path = get_schema_path();
+    let persist_path = get_persist_path(config);
+    let schema_path = get_schema_path(config);
 
     if let Some(parent) = persist_path.parent()
         && let Err(e) = fs::create_dir_all(parent)
@@ -1164,9 +1783,53 @@ pub async fn run_matter_stack(
     }
 
     // Check if schema changed and reset persistence if needed
-    let schema_reset = check_schema_and_maybe_reset(&virtual_devices, &persist_path, &schema_path);
+    let schema_reset = check_schema_and_maybe_reset(
+        &virtual_devices,
+        virtual_bridge_onoff.is_some(),
+        &persist_path,
+        &schema_path,
+    );
 
-    let psm = PSM.uninit().init_with(Psm::init());
+    // Controller-assigned NodeLabels (survive restarts and schema resets)
+    let label_store = Arc::new(NodeLabelStore::load(get_labels_path(config)));
+    // Switch states and last sensor readings (StartUpOnOff is applied on restore)
+    let state_store = Arc::new(RuntimeStateStore::load(get_state_path(config)));
+    // Bridge-side fabric aliases and last session times
+    let fabric_store = FabricStore::load(get_fabrics_path(config));
+    // Reboot count, boot reason and operational hours (GeneralDiagnostics)
+    let node_diagnostics =
+        NodeDiagnostics::start(get_diagnostics_path(config), dev_info.sw_ver_str);
+    // Controller-provided UTC time, time zone and DST offsets (Time Synchronization)
+    let time_store = Arc::new(TimeConfigStore::load(get_time_path(config)));
+    // Controller-written Binding targets (fabric-scoped, gone with the fabrics)
+    let binding_store = Arc::new(BindingTableStore::load(get_bindings_path(config)));
+    if schema_reset {
+        // Failures are logged by the store
+        let _ = binding_store.clear();
+    }
+    // Button-to-endpoint bindings applied inside the bridge
+    let local_bindings = Arc::new(
+        LocalBindings::load(get_local_bindings_path(config), control.clone()).unwrap_or_else(|e| {
+            error!("Ignoring local bindings: {}", e);
+            LocalBindings::new(control.clone())
+        }),
+    );
+    if !local_bindings.is_empty() {
+        info!("Loaded {} local bindings", local_bindings.len());
+    }
+    // Calibration/smoothing of measurement readings
+    let transforms = TransformConfig::load(get_transforms_path(config)).unwrap_or_else(|e| {
+        error!("Ignoring reading transforms: {}", e);
+        TransformConfig::default()
+    });
+    if !transforms.is_empty() {
+        info!(
+            "Loaded reading transforms for {} endpoints",
+            transforms.len()
+        );
+    }
+
+    let psm = leak_init(Psm::init());
     // Only load if persistence file exists (may have been deleted by schema check)
     if persist_path.exists() {
         if let Err(e) = psm.load(&persist_path, matter, NO_NETWORKS) {
@@ -1186,29 +1849,35 @@ pub async fn run_matter_stack(
     }
 
     // Only open commissioning window if device is not already commissioned
-    const COMM_WINDOW_TIMEOUT_SECS: u16 = 900;
     if matter.is_commissioned() {
         info!("Device already commissioned, skipping commissioning window");
-        info!("  (Delete {:?} to reset commissioning)", persist_path);
+        info!(
+            "  (Run `vmbctl fabrics` to list or remove controllers, `vmbctl factory-reset` to reset)"
+        );
+        info!("  (Run `vmbctl commission` to share with another controller)");
     } else {
         info!(
             "Opening commissioning window for {} seconds...",
-            COMM_WINDOW_TIMEOUT_SECS
+            MAX_COMM_WINDOW_TIMEOUT_SECS
         );
-        matter.open_basic_comm_window(COMM_WINDOW_TIMEOUT_SECS)?;
+        matter.open_basic_comm_window(MAX_COMM_WINDOW_TIMEOUT_SECS)?;
 
         info!("Matter device ready for commissioning");
-        info!("  Discriminator: {}", TEST_DEV_COMM.discriminator);
-        info!("  Passcode: {}", TEST_DEV_COMM.password);
+        info!("  Discriminator: {}", comm_data.discriminator);
+        info!("  Passcode: {}", comm_data.passcode);
+        info!(
+            "  Pairing code: {}",
+            crate::onboarding::generate_pairing_code(comm_data.discriminator, comm_data.passcode)
+        );
 
         // Try auto-commission if server URL is configured
         if let Some(ref server_url) = config.server_url {
             info!("Auto-commission enabled: {}", server_url);
             // Run auto-commission in a separate thread (prints pairing code on failure)
             let url = server_url.clone();
-            let discriminator = config.discriminator;
-            let passcode = config.passcode;
-            let vendor_id = config.vendor_id;
+            let discriminator = comm_data.discriminator;
+            let passcode = comm_data.passcode;
+            let unique_id = identity.unique_id.clone();
             std::thread::spawn(move || {
                 let rt = tokio::runtime::Runtime::new().unwrap();
                 rt.block_on(async {
@@ -1216,7 +1885,7 @@ pub async fn run_matter_stack(
                     if schema_reset {
                         info!("Schema changed, cleaning up old nodes from controller");
                         if let Err(e) =
-                            crate::commissioning::remove_bridge_nodes(&url, vendor_id).await
+                            crate::commissioning::remove_bridge_nodes(&url, &unique_id).await
                         {
                             log::warn!("Failed to cleanup old nodes: {}", e);
                         }
@@ -1229,10 +1898,8 @@ pub async fn run_matter_stack(
                         }
                         Err(e) => {
                             log::warn!("Auto-commission failed: {}", e);
-                            let pairing_code = crate::commissioning::generate_pairing_code(
-                                discriminator,
-                                passcode,
-                            );
+                            let pairing_code =
+                                crate::onboarding::generate_pairing_code(discriminator, passcode);
                             info!("Commission manually with pairing code: {}", pairing_code);
                         }
                     }
@@ -1240,39 +1907,40 @@ pub async fn run_matter_stack(
             });
         } else {
             // No server URL configured - show QR code for manual commissioning
-            if let Err(e) = matter.print_standard_qr_text(DiscoveryCapabilities::IP) {
-                error!("Failed to print QR text: {:?}", e);
-            }
-
-            if let Err(e) =
-                matter.print_standard_qr_code(QrTextType::Unicode, DiscoveryCapabilities::IP)
-            {
-                error!("Failed to print QR code: {:?}", e);
+            let qr_payload = crate::onboarding::generate_qr_payload(
+                dev_info.vid,
+                dev_info.pid,
+                comm_data.discriminator,
+                comm_data.passcode,
+            );
+            info!("  QR payload: {}", qr_payload);
+            match crate::onboarding::render_terminal(&qr_payload) {
+                Ok(qr_code) => info!("\n{}", qr_code),
+                Err(e) => error!("Failed to render QR code: {}", e),
             }
         }
     }
 
-    // Initialize pooled buffers in static memory
-    let buffers = BUFFERS.uninit().init_with(PooledBuffers::init(0));
+    // Initialize pooled buffers
+    let buffers: &'static PooledBuffers<10, NoopRawMutex, IMBuffer> =
+        leak_init(PooledBuffers::init(0));
 
-    // Initialize subscriptions manager in static memory
-    let subscriptions = SUBSCRIPTIONS
-        .uninit()
-        .init_with(DefaultSubscriptions::init());
+    // Initialize subscriptions manager
+    let subscriptions: &'static DefaultSubscriptions = leak_init(DefaultSubscriptions::init());
 
-    // Initialize sensor notification signal
-    let sensor_notify = SENSOR_NOTIFY.uninit().init_with(Signal::new());
-    let sensor_notify_ref: &'static Signal<CriticalSectionRawMutex, ()> = sensor_notify;
+    // Attributes changed outside Matter (wakes subscription processor)
+    // Shared by all notifiers because sensors are updated from different threads
+    let change_queue: &'static ChangeQueue = leak(ChangeQueue::new());
 
-    // Initialize shutdown signal (can be triggered from any thread via signal_shutdown())
-    let shutdown_signal_ref: &'static Signal<CriticalSectionRawMutex, ()> =
-        SHUTDOWN_SIGNAL.get_or_init(Signal::new);
-
-    // Create DynamicHandler for virtual device endpoints (EP3+)
+    // Create DynamicHandler for master/aggregator descriptors and virtual device endpoints
     let mut dynamic_handler = DynamicHandler::new();
 
-    // Collect cluster change notifications for sensor forwarding
-    let mut notification_endpoints: Vec<(u16, u32)> = Vec::new();
+    // Collect availability handles with a staleness timeout for periodic checks
+    let stale_devices: Vec<Arc<DeviceAvailability>> = virtual_devices
+        .iter()
+        .filter_map(|d| d.availability.clone())
+        .filter(|a| a.stale_timeout().is_some())
+        .collect();
 
     // Collect parent DeviceSwitches for virtual_bridge_onoff cascade
     let mut parent_device_switches: Vec<Arc<DeviceSwitch>> = Vec::new();
@@ -1281,9 +1949,13 @@ pub async fn run_matter_stack(
         let mapping = &built_node.mappings[device_idx];
         let parent_id = mapping.parent_id;
 
-        // Create the parent DeviceSwitch for this virtual device
-        let device_switch = Arc::new(DeviceSwitch::new(true));
-        parent_device_switches.push(device_switch.clone());
+        // Create the parent DeviceSwitch for this virtual device (if enabled)
+        let device_switch = device
+            .device_switch
+            .then(|| Arc::new(DeviceSwitch::new(true)));
+        if let Some(device_switch) = &device_switch {
+            parent_device_switches.push(device_switch.clone());
+        }
 
         // Add descriptor handler for parent (with parts matcher for children)
         dynamic_handler.add_desc_with_parts(
@@ -1292,192 +1964,318 @@ pub async fn run_matter_stack(
             built_node.parts_matcher,
         );
 
-        // Add bridged device info handler for parent (always reachable)
+        // Add bridged device info handler for parent (reachable follows source availability)
         // Use device_info if provided, otherwise create from label
         let parent_device_info = device
             .device_info
             .clone()
-            .unwrap_or_else(|| BridgedDeviceInfo::new(device.label));
-        dynamic_handler.add_bridged(
+            .unwrap_or_else(|| BridgedDeviceInfo::new(device.label.as_str()));
+        let parent_reachable =
+            new_reachable_state(parent_id, change_queue, device.availability.as_ref());
+        let mut parent_bridged = BridgedHandler::new(
+            Dataver::new_rand(matter.rand()),
+            parent_device_info,
+            parent_reachable,
+        )
+        .with_label_store(label_store.clone(), device.label.as_str());
+        if let Some(listener) = &device.label_listener {
+            parent_bridged = parent_bridged.with_label_listener(listener.clone());
+        }
+        parent_bridged.set_notifier(ClusterNotifier::new(
+            change_queue,
             parent_id,
-            BridgedHandler::new_always_reachable(
-                Dataver::new_rand(matter.rand()),
-                parent_device_info,
-            ),
-        );
+            bridged_device_basic_info::CLUSTER_ID,
+            &[BridgedDeviceBasicInfoAttribute::NodeLabel as u32],
+        ));
+        dynamic_handler.add_bridged(parent_id, parent_bridged);
 
         // Add OnOff handler for parent (device-level switch)
-        dynamic_handler.add_device_onoff(
-            parent_id,
-            Dataver::new_rand(matter.rand()),
-            device_switch.clone(),
-        );
+        if let Some(device_switch) = &device_switch {
+            // Push changes made outside Matter (control plane) to subscribers
+            device_switch.helper().set_notifier(ClusterNotifier::new(
+                change_queue,
+                parent_id,
+                DeviceSwitch::CLUSTER.id,
+                &[ON_OFF_ATTRIBUTE_ID],
+            ));
+            control.register(
+                ControlEndpoint::new(parent_id, device.label.as_str(), device.label.as_str())
+                    .with_point(ControlPoint::DeviceSwitch(device_switch.clone())),
+            );
+            dynamic_handler.add_device_onoff(
+                parent_id,
+                Dataver::new_rand(matter.rand()),
+                device_switch.clone(),
+            );
+        }
 
         // Create handlers for each child endpoint
         for (child_idx, ep_config) in device.endpoints.iter().enumerate() {
             let child_id = mapping.child_ids[child_idx];
-
-            // Create reachable flag for this child (controlled by parent DeviceSwitch)
-            let child_reachable = Arc::new(AtomicBool::new(true));
-            device_switch.add_child_reachable(child_reachable.clone());
+            // Key for the persisted NodeLabel and runtime state of this child
+            let child_key = NodeLabelStore::endpoint_key(&device.label, &ep_config.label);
+
+            // Create reachable state for this child (parent DeviceSwitch + source availability)
+            let child_reachable =
+                new_reachable_state(child_id, change_queue, device.availability.as_ref());
+            if let Some(device_switch) = &device_switch {
+                device_switch.add_child_reachable(child_reachable.clone());
+            }
 
             // Add descriptor handler for child (no parts)
             dynamic_handler.add_desc(child_id, Dataver::new_rand(matter.rand()));
 
             // Add bridged device info handler for child (with dynamic reachable)
             // Child endpoints use label only (device info is on parent)
-            dynamic_handler.add_bridged(
+            let child_bridged = BridgedHandler::new_with_name(
+                Dataver::new_rand(matter.rand()),
+                ep_config.label.as_str(),
+                child_reachable,
+            )
+            .with_label_store(label_store.clone(), child_key.as_str());
+            child_bridged.set_notifier(ClusterNotifier::new(
+                change_queue,
                 child_id,
-                BridgedHandler::new_with_name(
-                    Dataver::new_rand(matter.rand()),
-                    ep_config.label,
-                    child_reachable,
-                ),
-            );
-
-            match ep_config.kind {
-                EndpointKind::ContactSensor => {
-                    let bridge = SensorBridge::new(ep_config.handler.clone());
-                    bridge.set_notifier(ClusterNotifier::new(
-                        sensor_notify_ref,
-                        child_id,
-                        boolean_state::CLUSTER_ID,
-                    ));
-                    notification_endpoints.push((child_id, boolean_state::CLUSTER_ID));
-                    dynamic_handler.add_boolean_state(
-                        child_id,
-                        Dataver::new_rand(matter.rand()),
-                        bridge,
-                    );
-                }
-                EndpointKind::OccupancySensor => {
-                    let bridge = SensorBridge::new(ep_config.handler.clone());
-                    bridge.set_notifier(ClusterNotifier::new(
-                        sensor_notify_ref,
-                        child_id,
-                        occupancy_sensing::CLUSTER_ID,
-                    ));
-                    notification_endpoints.push((child_id, occupancy_sensing::CLUSTER_ID));
-                    dynamic_handler.add_occupancy_sensing(
-                        child_id,
-                        Dataver::new_rand(matter.rand()),
-                        bridge,
-                    );
-                }
-                EndpointKind::Switch | EndpointKind::LightSwitch => {
-                    let bridge = SwitchBridge::new(ep_config.handler.clone());
-                    // Set notifier for switch subscription updates
-                    bridge.set_notifier(ClusterNotifier::new(
-                        sensor_notify_ref,
-                        child_id,
-                        Switch::CLUSTER.id,
-                    ));
-                    notification_endpoints.push((child_id, Switch::CLUSTER.id));
-                    // Add child switch to parent's cascade list
-                    device_switch.add_child_switch(bridge.clone());
-                    dynamic_handler.add_onoff(child_id, Dataver::new_rand(matter.rand()), bridge);
-                }
-                EndpointKind::VideoDoorbellCamera => {
-                    // TODO: Camera handlers are async and need different handling than DynamicHandler.
-                    // For now, VideoDoorbellCamera endpoints are registered but handlers are not wired.
-                    // This requires extending DynamicHandler to support async handlers or
-                    // building a separate async handler chain for camera endpoints.
-                    log::warn!(
-                        "VideoDoorbellCamera endpoint {} registered but camera handlers not yet wired",
-                        child_id
-                    );
-                }
-                EndpointKind::TemperatureSensor => {
-                    // Use sensor from EndpointConfig (created by caller)
-                    if let Some(sensor) = &ep_config.temperature_sensor {
+                bridged_device_basic_info::CLUSTER_ID,
+                &[BridgedDeviceBasicInfoAttribute::NodeLabel as u32],
+            ));
+            dynamic_handler.add_bridged(child_id, child_bridged);
+
+            // Values of this endpoint exposed to the control plane
+            let mut control_endpoint =
+                ControlEndpoint::new(child_id, device.label.as_str(), ep_config.label.as_str());
+
+            for cluster in &ep_config.clusters {
+                match cluster {
+                    EndpointCluster::ContactSensor(handler) => {
+                        let bridge = SensorBridge::new(handler.clone());
+                        bridge.set_notifier(ClusterNotifier::new(
+                            change_queue,
+                            child_id,
+                            boolean_state::CLUSTER_ID,
+                            &[BooleanStateAttribute::StateValue as u32],
+                        ));
+                        control_endpoint
+                            .points
+                            .push(ControlPoint::Contact(bridge.clone()));
+                        dynamic_handler.add_boolean_state(
+                            child_id,
+                            Dataver::new_rand(matter.rand()),
+                            bridge,
+                        );
+                    }
+                    EndpointCluster::OccupancySensor(handler) => {
+                        let bridge = SensorBridge::new(handler.clone());
+                        bridge.set_notifier(ClusterNotifier::new(
+                            change_queue,
+                            child_id,
+                            occupancy_sensing::CLUSTER_ID,
+                            &[OccupancySensingAttribute::Occupancy as u32],
+                        ));
+                        control_endpoint
+                            .points
+                            .push(ControlPoint::Occupancy(bridge.clone()));
+                        dynamic_handler.add_occupancy_sensing(
+                            child_id,
+                            Dataver::new_rand(matter.rand()),
+                            bridge,
+                        );
+                    }
+                    EndpointCluster::Switch(handler) | EndpointCluster::LightSwitch(handler) => {
+                        let bridge = SwitchBridge::new(handler.clone());
+                        // Set notifier for switch subscription updates
+                        bridge.set_notifier(ClusterNotifier::new(
+                            change_queue,
+                            child_id,
+                            Switch::CLUSTER.id,
+                            &[ON_OFF_ATTRIBUTE_ID],
+                        ));
+                        // Restore the last state (StartUpOnOff) before the parent cascade
+                        bridge.restore_state(state_store.handle(child_key.as_str()));
+                        // Add child switch to parent's cascade list
+                        if let Some(device_switch) = &device_switch {
+                            device_switch.add_child_switch(bridge.clone());
+                        }
+                        control_endpoint
+                            .points
+                            .push(ControlPoint::OnOff(bridge.clone()));
+                        dynamic_handler.add_onoff(
+                            child_id,
+                            Dataver::new_rand(matter.rand()),
+                            bridge,
+                        );
+                    }
+                    EndpointCluster::VideoDoorbellCamera(_) => {
+                        // TODO: Camera handlers are async and need different handling than DynamicHandler.
+                        // For now, VideoDoorbellCamera endpoints are registered but handlers are not wired.
+                        // This requires extending DynamicHandler to support async handlers or
+                        // building a separate async handler chain for camera endpoints.
+                        log::warn!(
+                            "VideoDoorbellCamera endpoint {} registered but camera handlers not yet wired",
+                            child_id
+                        );
+                    }
+                    EndpointCluster::TemperatureSensor(sensor) => {
                         // Set notifier for subscription updates
                         sensor.set_notifier(ClusterNotifier::new(
-                            sensor_notify_ref,
+                            change_queue,
                             child_id,
                             temperature_measurement::CLUSTER_ID,
+                            &[TemperatureMeasurementAttribute::MeasuredValue as u32],
                         ));
-                        notification_endpoints
-                            .push((child_id, temperature_measurement::CLUSTER_ID));
+                        if let Some(transform) = transforms.temperature(&child_key) {
+                            sensor.set_transform(transform);
+                        }
+                        sensor.restore_state(state_store.handle(child_key.as_str()));
+                        control_endpoint
+                            .points
+                            .push(ControlPoint::Temperature(sensor.clone()));
 
                         let handler = TemperatureMeasurementHandler::new(
                             Dataver::new_rand(matter.rand()),
                             sensor.clone(),
                         );
                         dynamic_handler.add_temperature(child_id, handler);
-                    } else {
-                        log::warn!(
-                            "TemperatureSensor endpoint {} missing sensor in config",
-                            child_id
-                        );
                     }
-                }
-                EndpointKind::HumiditySensor => {
-                    // Use sensor from EndpointConfig (created by caller)
-                    if let Some(sensor) = &ep_config.humidity_sensor {
+                    EndpointCluster::HumiditySensor(sensor) => {
                         // Set notifier for subscription updates
                         sensor.set_notifier(ClusterNotifier::new(
-                            sensor_notify_ref,
+                            change_queue,
                             child_id,
                             relative_humidity::CLUSTER_ID,
+                            &[RelativeHumidityAttribute::MeasuredValue as u32],
                         ));
-                        notification_endpoints.push((child_id, relative_humidity::CLUSTER_ID));
+                        if let Some(transform) = transforms.humidity(&child_key) {
+                            sensor.set_transform(transform);
+                        }
+                        sensor.restore_state(state_store.handle(child_key.as_str()));
+                        control_endpoint
+                            .points
+                            .push(ControlPoint::Humidity(sensor.clone()));
 
                         let handler = RelativeHumidityHandler::new(
                             Dataver::new_rand(matter.rand()),
                             sensor.clone(),
                         );
                         dynamic_handler.add_humidity(child_id, handler);
-                    } else {
-                        log::warn!(
-                            "HumiditySensor endpoint {} missing sensor in config",
-                            child_id
-                        );
                     }
-                }
-                EndpointKind::GenericSwitch => {
-                    // Use state from EndpointConfig (created by caller)
-                    if let Some(state) = &ep_config.generic_switch_state {
+                    EndpointCluster::GenericSwitch(state) => {
                         // Set endpoint ID so events know where they came from
                         state.set_endpoint_id(child_id);
                         // Set notifier for subscription updates when events are recorded
                         state.set_notifier(ClusterNotifier::new(
-                            sensor_notify_ref,
+                            change_queue,
                             child_id,
                             generic_switch::CLUSTER_ID,
+                            &[GenericSwitchAttribute::CurrentPosition as u32],
                         ));
-                        notification_endpoints.push((child_id, generic_switch::CLUSTER_ID));
+                        // Raw edges from the control plane become presses, long
+                        // presses and multi-press sequences
+                        let gestures = (!state.is_latching())
+                            .then(|| SwitchGestures::new(state.clone(), GestureConfig::default()));
+                        control_endpoint
+                            .points
+                            .push(ControlPoint::Button(state.clone(), gestures));
                         let handler = GenericSwitchHandler::new(
                             Dataver::new_rand(matter.rand()),
                             state.clone(),
                         );
                         dynamic_handler.add_generic_switch(child_id, handler);
+                        dynamic_handler.add_binding(
+                            child_id,
+                            BindingHandler::new(
+                                Dataver::new_rand(matter.rand()),
+                                binding_store.clone(),
+                                child_key.as_str(),
+                            ),
+                        );
+                        if let Some(listener) =
+                            local_bindings.listener(child_id, &device.label, &ep_config.label)
+                        {
+                            state.add_event_listener(listener);
+                        }
                         info!(
                             "[Matter] GenericSwitch endpoint {} registered for '{}'",
                             child_id, ep_config.label
                         );
-                    } else {
-                        log::warn!(
-                            "GenericSwitch endpoint {} missing state in config",
-                            child_id
+                    }
+                    EndpointCluster::PowerSource(source) => {
+                        // Set endpoint ID for the EndpointList attribute
+                        source.set_endpoint_id(child_id);
+                        // Set notifier for subscription updates
+                        source.set_notifier(ClusterNotifier::new(
+                            change_queue,
+                            child_id,
+                            power_source::CLUSTER_ID,
+                            power_source::BATTERY_ATTRIBUTES,
+                        ));
+                        source.restore_state(state_store.handle(child_key.as_str()));
+                        control_endpoint
+                            .points
+                            .push(ControlPoint::Battery(source.clone()));
+
+                        let handler = PowerSourceHandler::new(
+                            Dataver::new_rand(matter.rand()),
+                            source.clone(),
                         );
+                        dynamic_handler.add_power_source(child_id, handler);
                     }
                 }
             }
+            control.register(control_endpoint);
+        }
+
+        // Restore the device switch last, so an OFF state cascades to the restored children
+        if let Some(device_switch) = &device_switch {
+            device_switch.restore_state(state_store.handle(device.label.as_str()));
         }
     }
 
     // Wire up virtual_bridge_onoff to cascade to all parent DeviceSwitches
-    for device_switch in &parent_device_switches {
-        virtual_bridge_onoff.add_cascade_target(device_switch.clone());
+    if let Some(virtual_bridge_onoff) = &virtual_bridge_onoff {
+        for device_switch in &parent_device_switches {
+            virtual_bridge_onoff.add_cascade_target(device_switch.clone());
+        }
+        // Restored after the targets are registered, so an OFF state cascades to them
+        virtual_bridge_onoff.restore_state(state_store.handle(MASTER_SWITCH_STATE_KEY));
+        virtual_bridge_onoff
+            .helper()
+            .set_notifier(ClusterNotifier::new(
+                change_queue,
+                MASTER_SWITCH_ENDPOINT_ID,
+                Switch::CLUSTER.id,
+                &[ON_OFF_ATTRIBUTE_ID],
+            ));
+        control.register(
+            ControlEndpoint::new(MASTER_SWITCH_ENDPOINT_ID, "Bridge", "Master Switch")
+                .with_point(ControlPoint::MasterSwitch(virtual_bridge_onoff.clone())),
+        );
+
+        // Descriptor for the master switch endpoint
+        dynamic_handler.add_desc(MASTER_SWITCH_ENDPOINT_ID, Dataver::new_rand(matter.rand()));
     }
 
-    // Create time sync handler for root endpoint
-    let time_sync_handler = TimeSyncHandler::new(Dataver::new_rand(matter.rand()));
+    // Descriptor for the Aggregator (lists all parent endpoints)
+    dynamic_handler.add_desc_with_parts(
+        built_node.aggregator_id,
+        Dataver::new_rand(matter.rand()),
+        built_node.parts_matcher,
+    );
+
+    // Create time sync and diagnostics handlers for root endpoint
+    let time_sync_handler =
+        TimeSyncHandler::new(Dataver::new_rand(matter.rand()), time_store.clone());
+    let software_diag_handler = SoftwareDiagnosticsHandler::new(Dataver::new_rand(matter.rand()));
+    let network_diag_handler =
+        NetworkDiagnosticsHandler::new(Dataver::new_rand(matter.rand()), interface_name);
 
     // Create OnOff handler for bridge master on/off (endpoint 1)
+    // Without a master switch, EP1 has no OnOff cluster in the node, so this handler is
+    // never routed to; a detached placeholder switch keeps the handler chain static.
+    let virtual_bridge_onoff = virtual_bridge_onoff.unwrap_or_else(|| Arc::new(Switch::new(false)));
     let virtual_bridge_onoff_handler = on_off::OnOffHandler::new_standalone(
         Dataver::new_rand(matter.rand()),
-        1,
+        MASTER_SWITCH_ENDPOINT_ID,
         virtual_bridge_onoff.as_ref(),
     );
 
@@ -1485,7 +2283,7 @@ pub async fn run_matter_stack(
     let handler = (
         built_node.node,
         endpoints::with_eth(
-            &(),
+            &node_diagnostics,
             get_netifs(),
             matter.rand(),
             endpoints::with_sys(
@@ -1497,33 +2295,27 @@ pub async fn run_matter_stack(
                         EpClMatcher::new(Some(0), Some(TimeSyncHandler::CLUSTER.id)),
                         Async(&time_sync_handler),
                     )
-                    // === Endpoint 1: Bridge master on/off control ===
                     .chain(
-                        EpClMatcher::new(Some(1), Some(desc::DescHandler::CLUSTER.id)),
-                        Async(desc::DescHandler::new(Dataver::new_rand(matter.rand())).adapt()),
+                        EpClMatcher::new(Some(0), Some(SoftwareDiagnosticsHandler::CLUSTER.id)),
+                        Async(&software_diag_handler),
                     )
+                    // === Endpoint 1: Bridge master on/off control (optional) ===
                     .chain(
-                        EpClMatcher::new(Some(1), Some(Switch::CLUSTER.id)),
+                        EpClMatcher::new(Some(MASTER_SWITCH_ENDPOINT_ID), Some(Switch::CLUSTER.id)),
                         on_off::HandlerAsyncAdaptor(&virtual_bridge_onoff_handler),
                     )
-                    // === Endpoint 2: Aggregator ===
-                    .chain(
-                        EpClMatcher::new(Some(2), Some(desc::DescHandler::CLUSTER.id)),
-                        Async(
-                            desc::DescHandler::new_matching(
-                                Dataver::new_rand(matter.rand()),
-                                built_node.parts_matcher,
-                            )
-                            .adapt(),
-                        ),
-                    )
-                    // === Endpoint 3+: Virtual Devices (dynamic) ===
+                    // === Master/Aggregator descriptors and Virtual Devices (dynamic) ===
                     // Use &dynamic_handler directly (not Async wrapper) to preserve as_event_source()
                     .chain(
                         &dynamic_handler, // Only matches (ep, cl) pairs we have handlers for
                         &dynamic_handler,
                     ),
             ),
+        )
+        // Checked before rs-matter's Ethernet Network Diagnostics handler
+        .chain(
+            EpClMatcher::new(Some(0), Some(network_diag_handler.cluster().id)),
+            Async(&network_diag_handler),
         ),
     );
 
@@ -1589,11 +2381,16 @@ pub async fn run_matter_stack(
 
     info!("mDNS socket bound to {:?}", MDNS_SOCKET_DEFAULT_BIND_ADDR);
 
-    let hostname =
-        HOSTNAME.get_or_init(|| gethostname::gethostname().to_string_lossy().into_owned());
+    // Distinct per instance, so several instances on one host announce separate hosts
+    let hostname: &'static str = mdns_hostname(
+        &gethostname::gethostname().to_string_lossy(),
+        config.instance.as_deref(),
+    )
+    .leak();
+    info!("mDNS hostname: {}", hostname);
 
     let host = Host {
-        id: 0,
+        id: config.port,
         hostname,
         ip: ipv4_addrs[0].octets().into(),
         ipv6: ipv6_addr.octets().into(),
@@ -1625,18 +2422,66 @@ pub async fn run_matter_stack(
     // Sensor notification forwarding task
     let mut sensor_forward = pin!(async {
         loop {
-            sensor_notify_ref.wait().await;
-            // Notify all registered sensor endpoints
-            // Using attr_id 0 as the primary measured value attribute for sensor clusters
-            for (endpoint_id, cluster_id) in &notification_endpoints {
-                subscriptions.notify_attribute_changed(*endpoint_id, *cluster_id, 0);
+            // Report only the attributes that changed since the last drain
+            let mut changed_endpoints = Vec::new();
+            for path in change_queue.wait().await {
+                subscriptions.notify_attribute_changed(
+                    path.endpoint_id,
+                    path.cluster_id,
+                    path.attribute_id,
+                );
+                if !changed_endpoints.contains(&path.endpoint_id) {
+                    changed_endpoints.push(path.endpoint_id);
+                }
+            }
+            // Also tell control plane subscribers (e.g. the MQTT mirror)
+            for endpoint_id in changed_endpoints {
+                control.endpoint_changed(endpoint_id);
             }
         }
     });
 
-    // Shutdown task - completes when signal_shutdown() is called
+    // Staleness task - marks sleepy devices unreachable when not seen within their timeout
+    // and records which fabrics have active sessions
+    let mut stale_check = pin!(async {
+        loop {
+            async_io::Timer::after(STALE_CHECK_INTERVAL).await;
+            for availability in &stale_devices {
+                availability.check_stale();
+            }
+            record_fabric_sessions(matter, &fabric_store);
+            node_diagnostics.checkpoint();
+            state_store.flush();
+        }
+    });
+
+    // Control task - serves control plane requests that need the Matter instance
+    let mut control_task = pin!(async {
+        loop {
+            let request = control.next_request().await;
+            // Skip requests whose requester gave up waiting (e.g., queued before startup)
+            if request.reply.is_closed() {
+                continue;
+            }
+            let result = handle_stack_command(
+                matter,
+                subscriptions,
+                dev_info,
+                &comm_data,
+                &fabric_store,
+                &label_store,
+                &binding_store,
+                &time_store,
+                &node_diagnostics,
+                request.command,
+            );
+            let _ = request.reply.send(result);
+        }
+    });
+
+    // Shutdown task - completes when the instance's ShutdownHandle is signaled
     let mut shutdown_task = pin!(async {
-        shutdown_signal_ref.wait().await;
+        shutdown.wait().await;
         info!("Matter stack received shutdown signal, exiting...");
         Ok::<_, Error>(())
     });
@@ -1647,15 +2492,25 @@ pub async fn run_matter_stack(
         select(&mut respond, &mut dm_job).coalesce(),
         select(
             &mut persist,
-            select(&mut sensor_forward, &mut shutdown_task).coalesce(),
+            select4(
+                &mut sensor_forward,
+                &mut stale_check,
+                &mut control_task,
+                &mut shutdown_task,
+            )
+            .coalesce(),
         )
         .coalesce(),
     )
     .coalesce()
     .await;
 
+    state_store.flush();
     match &result {
-        Ok(()) => info!("Matter stack shut down gracefully"),
+        Ok(()) => {
+            node_diagnostics.shutdown();
+            info!("Matter stack shut down gracefully");
+        }
         Err(e) => error!("Matter stack error: {:?}", e),
     }
 
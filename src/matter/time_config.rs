@@ -0,0 +1,409 @@
+//! Controller-provided time, time zone and DST configuration.
+//!
+//! Controllers set the node's notion of UTC time, its time zone list and its DST offset
+//! list via the Time Synchronization cluster. The bridge does not touch the host clock:
+//! SetUTCTime is stored as an offset to the host clock, so the time keeps running across
+//! restarts. Everything is stored in a small JSON file next to `matter.bin`.
+//!
+//! Times are Matter epoch microseconds (since 2000-01-01 00:00:00 UTC), offsets are
+//! seconds. Local time is UTC plus the active time zone offset plus the active DST offset,
+//! so time-based features see the same local time the controller configured.
+
+use super::json_store::JsonStore;
+use chrono::{DateTime, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+/// Seconds between the Unix epoch and the Matter epoch (2000-01-01)
+const MATTER_EPOCH_OFFSET_SECS: u64 = 946_684_800;
+
+/// Maximum number of time zone entries (TimeZoneListMaxSize)
+pub const TIME_ZONE_LIST_MAX_SIZE: usize = 2;
+
+/// Maximum number of DST offset entries (DSTOffsetListMaxSize)
+pub const DST_OFFSET_LIST_MAX_SIZE: usize = 6;
+
+/// Maximum length of a time zone name
+const TIME_ZONE_NAME_MAX_LEN: usize = 64;
+
+/// Valid range of a time zone offset (UTC-12:00 to UTC+14:00)
+const TIME_ZONE_OFFSET_RANGE: std::ops::RangeInclusive<i32> = -43_200..=50_400;
+
+/// GranularityEnum: SecondsGranularity (assumed for the host clock)
+pub const GRANULARITY_SECONDS: u8 = 2;
+
+/// GranularityEnum: MicrosecondsGranularity (highest value)
+pub const GRANULARITY_MAX: u8 = 4;
+
+/// TimeSourceEnum: Unknown (the host clock)
+pub const TIME_SOURCE_UNKNOWN: u8 = 1;
+
+/// TimeSourceEnum: Admin (set by a controller without a source)
+pub const TIME_SOURCE_ADMIN: u8 = 2;
+
+/// Errors when applying controller-provided time configuration.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum TimeConfigError {
+    /// The node already has time of a better granularity
+    #[error("time not accepted (current granularity is better)")]
+    TimeNotAccepted,
+
+    /// The list exceeds the supported size
+    #[error("list has {0} entries, at most {1} are supported")]
+    ListTooLong(usize, usize),
+
+    /// An entry violates a constraint
+    #[error("invalid entry: {0}")]
+    InvalidEntry(&'static str),
+}
+
+/// TimeZoneStruct
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeZoneEntry {
+    /// Offset to UTC in seconds
+    pub offset: i32,
+    /// Matter epoch microseconds from which this entry applies
+    pub valid_at: u64,
+    /// Time zone name (e.g., "Europe/Berlin")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+/// DSTOffsetStruct
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DstOffsetEntry {
+    /// DST offset in seconds
+    pub offset: i32,
+    /// Matter epoch microseconds from which this entry applies
+    pub valid_starting: u64,
+    /// Matter epoch microseconds until which this entry applies (open-ended if `None`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub valid_until: Option<u64>,
+}
+
+/// Persisted time configuration.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct TimeConfig {
+    /// Controller time minus host time in microseconds
+    #[serde(default)]
+    utc_offset_us: i64,
+    time_source: u8,
+    time_zones: Vec<TimeZoneEntry>,
+    #[serde(default)]
+    dst_offsets: Vec<DstOffsetEntry>,
+}
+
+impl Default for TimeConfig {
+    fn default() -> Self {
+        Self {
+            utc_offset_us: 0,
+            time_source: TIME_SOURCE_UNKNOWN,
+            // The spec default is a single UTC entry valid from the epoch
+            time_zones: vec![TimeZoneEntry {
+                offset: 0,
+                valid_at: 0,
+                name: None,
+            }],
+            dst_offsets: Vec::new(),
+        }
+    }
+}
+
+/// JSON-backed time configuration store.
+pub struct TimeConfigStore {
+    config: JsonStore<TimeConfig>,
+    /// Granularity of the last SetUTCTime (not persisted: after a restart the stored
+    /// offset only runs on the host clock)
+    granularity: AtomicU8,
+}
+
+impl TimeConfigStore {
+    /// Load the store from `path`.
+    ///
+    /// Without a file the node uses the host clock, UTC and no DST.
+    pub fn load(path: impl Into<PathBuf>) -> Self {
+        Self {
+            config: JsonStore::load(path),
+            granularity: AtomicU8::new(GRANULARITY_SECONDS),
+        }
+    }
+
+    /// Current UTC time in Matter epoch microseconds.
+    pub fn utc_now(&self) -> u64 {
+        host_now_us().saturating_add_signed(self.config.read(|config| config.utc_offset_us))
+    }
+
+    /// Current local time in Matter epoch microseconds.
+    pub fn local_now(&self) -> u64 {
+        self.local_time(self.utc_now())
+    }
+
+    /// Current local time as a calendar date and time (for time-based features).
+    pub fn local_datetime(&self) -> NaiveDateTime {
+        let unix_us = self.local_now() as i64 - (MATTER_EPOCH_OFFSET_SECS * 1_000_000) as i64;
+        DateTime::from_timestamp_micros(unix_us)
+            .unwrap_or_default()
+            .naive_utc()
+    }
+
+    /// Local time for a UTC time (both Matter epoch microseconds).
+    pub fn local_time(&self, utc: u64) -> u64 {
+        let (zone_offset, dst_offset) = self.config.read(|config| {
+            let zone_offset = config
+                .time_zones
+                .iter()
+                .rev()
+                .find(|zone| zone.valid_at <= utc)
+                .map_or(0, |zone| zone.offset);
+            let dst_offset = config
+                .dst_offsets
+                .iter()
+                .find(|dst| {
+                    dst.valid_starting <= utc && dst.valid_until.is_none_or(|until| utc < until)
+                })
+                .map_or(0, |dst| dst.offset);
+            (zone_offset, dst_offset)
+        });
+        let offset_us = i64::from(zone_offset + dst_offset) * 1_000_000;
+        utc.saturating_add_signed(offset_us)
+    }
+
+    /// Granularity of the current UTC time (GranularityEnum).
+    pub fn granularity(&self) -> u8 {
+        self.granularity.load(Ordering::Relaxed)
+    }
+
+    /// Source of the current UTC time (TimeSourceEnum).
+    pub fn time_source(&self) -> u8 {
+        self.config.read(|config| config.time_source)
+    }
+
+    /// Configured time zones.
+    pub fn time_zones(&self) -> Vec<TimeZoneEntry> {
+        self.config.read(|config| config.time_zones.clone())
+    }
+
+    /// Configured DST offsets.
+    pub fn dst_offsets(&self) -> Vec<DstOffsetEntry> {
+        self.config.read(|config| config.dst_offsets.clone())
+    }
+
+    /// Apply SetUTCTime.
+    ///
+    /// Time of a coarser granularity than the one set since the start is rejected.
+    pub fn set_utc_time(
+        &self,
+        utc: u64,
+        granularity: u8,
+        time_source: Option<u8>,
+    ) -> Result<(), TimeConfigError> {
+        if granularity == 0 || granularity > GRANULARITY_MAX {
+            return Err(TimeConfigError::InvalidEntry("granularity"));
+        }
+        self.update(|config| {
+            if granularity < self.granularity() {
+                return Err(TimeConfigError::TimeNotAccepted);
+            }
+            config.utc_offset_us = utc as i64 - host_now_us() as i64;
+            self.granularity.store(granularity, Ordering::Relaxed);
+            config.time_source = time_source.unwrap_or(TIME_SOURCE_ADMIN);
+            Ok(())
+        })
+    }
+
+    /// Apply SetTimeZone.
+    ///
+    /// Without a time zone database the DST offsets of the new zone are unknown, so the
+    /// DST offset list is cleared. Returns whether DST offsets are required (always true).
+    pub fn set_time_zones(&self, time_zones: Vec<TimeZoneEntry>) -> Result<bool, TimeConfigError> {
+        if time_zones.is_empty() || time_zones.len() > TIME_ZONE_LIST_MAX_SIZE {
+            return Err(TimeConfigError::ListTooLong(
+                time_zones.len(),
+                TIME_ZONE_LIST_MAX_SIZE,
+            ));
+        }
+        if time_zones[0].valid_at != 0 {
+            return Err(TimeConfigError::InvalidEntry(
+                "first time zone must be valid from 0",
+            ));
+        }
+        for zone in &time_zones {
+            if !TIME_ZONE_OFFSET_RANGE.contains(&zone.offset) {
+                return Err(TimeConfigError::InvalidEntry("time zone offset"));
+            }
+            if zone
+                .name
+                .as_ref()
+                .is_some_and(|name| name.len() > TIME_ZONE_NAME_MAX_LEN)
+            {
+                return Err(TimeConfigError::InvalidEntry("time zone name"));
+            }
+        }
+        if time_zones
+            .windows(2)
+            .any(|pair| pair[0].valid_at >= pair[1].valid_at)
+        {
+            return Err(TimeConfigError::InvalidEntry("time zones not ordered"));
+        }
+
+        self.update(|config| {
+            config.time_zones = time_zones;
+            config.dst_offsets.clear();
+            Ok(())
+        })?;
+        Ok(true)
+    }
+
+    /// Apply SetDSTOffset.
+    ///
+    /// Entries must be ordered and must not overlap; only the last may be open-ended.
+    pub fn set_dst_offsets(&self, dst_offsets: Vec<DstOffsetEntry>) -> Result<(), TimeConfigError> {
+        if dst_offsets.len() > DST_OFFSET_LIST_MAX_SIZE {
+            return Err(TimeConfigError::ListTooLong(
+                dst_offsets.len(),
+                DST_OFFSET_LIST_MAX_SIZE,
+            ));
+        }
+        for (i, dst) in dst_offsets.iter().enumerate() {
+            let next = dst_offsets.get(i + 1);
+            match (dst.valid_until, next) {
+                (Some(until), _) if until <= dst.valid_starting => {
+                    return Err(TimeConfigError::InvalidEntry("empty DST period"));
+                }
+                (None, Some(_)) => {
+                    return Err(TimeConfigError::InvalidEntry("open-ended DST period"));
+                }
+                (Some(until), Some(next)) if until > next.valid_starting => {
+                    return Err(TimeConfigError::InvalidEntry("overlapping DST periods"));
+                }
+                _ => {}
+            }
+        }
+
+        self.update(|config| {
+            config.dst_offsets = dst_offsets;
+            Ok(())
+        })
+    }
+
+    /// Forget the controller-provided time, time zones and DST offsets (factory reset).
+    ///
+    /// The node falls back to the host clock, UTC and no DST.
+    pub fn clear(&self) -> std::io::Result<()> {
+        self.granularity
+            .store(GRANULARITY_SECONDS, Ordering::Relaxed);
+        self.config.clear()
+    }
+
+    /// Apply `f` and write the file if it succeeds.
+    fn update(
+        &self,
+        f: impl FnOnce(&mut TimeConfig) -> Result<(), TimeConfigError>,
+    ) -> Result<(), TimeConfigError> {
+        self.config.update(f)
+    }
+}
+
+/// Host clock in Matter epoch microseconds.
+fn host_now_us() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_micros() as u64)
+        .unwrap_or_default()
+        .saturating_sub(MATTER_EPOCH_OFFSET_SECS * 1_000_000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR_US: u64 = 3_600_000_000;
+
+    #[test]
+    fn test_local_time_and_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("time.json");
+
+        let store = TimeConfigStore::load(&path);
+        let utc = 800 * HOUR_US;
+        assert_eq!(store.local_time(utc), utc);
+
+        // Coarser time than the host clock is rejected, finer time is accepted
+        assert_eq!(
+            store.set_utc_time(store.utc_now(), 1, None),
+            Err(TimeConfigError::TimeNotAccepted)
+        );
+        store
+            .set_utc_time(store.utc_now() + HOUR_US, 3, None)
+            .unwrap();
+        assert_eq!(store.time_source(), TIME_SOURCE_ADMIN);
+        assert!(store.utc_now() > host_now_us() + HOUR_US - 1_000_000);
+
+        let zone = |offset, valid_at| TimeZoneEntry {
+            offset,
+            valid_at,
+            name: None,
+        };
+        assert_eq!(
+            store.set_time_zones(vec![zone(3600, 10)]),
+            Err(TimeConfigError::InvalidEntry(
+                "first time zone must be valid from 0"
+            ))
+        );
+        assert_eq!(store.set_time_zones(vec![zone(3600, 0)]), Ok(true));
+        store
+            .set_dst_offsets(vec![DstOffsetEntry {
+                offset: 3600,
+                valid_starting: 700 * HOUR_US,
+                valid_until: Some(900 * HOUR_US),
+            }])
+            .unwrap();
+        assert_eq!(store.local_time(utc), utc + 2 * HOUR_US);
+        assert_eq!(store.local_time(900 * HOUR_US), 901 * HOUR_US);
+
+        let reloaded = TimeConfigStore::load(&path);
+        assert_eq!(reloaded.local_time(utc), utc + 2 * HOUR_US);
+        // The granularity starts over, so the time can be set again after a restart
+        assert_eq!(reloaded.granularity(), GRANULARITY_SECONDS);
+        reloaded
+            .set_utc_time(utc, GRANULARITY_SECONDS, None)
+            .unwrap();
+
+        // A new time zone clears the DST offsets
+        reloaded.set_time_zones(vec![zone(-18000, 0)]).unwrap();
+        assert!(reloaded.dst_offsets().is_empty());
+        assert_eq!(reloaded.local_time(utc), utc - 5 * HOUR_US);
+
+        // A factory reset forgets everything the controllers set
+        reloaded.clear().unwrap();
+        assert_eq!(reloaded.local_time(utc), utc);
+        assert_eq!(reloaded.time_source(), TIME_SOURCE_UNKNOWN);
+        assert!(reloaded.utc_now() < host_now_us() + 1_000_000);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_dst_offsets_validated() {
+        let store = TimeConfigStore::load("/nonexistent/time.json");
+        let dst = |valid_starting, valid_until| DstOffsetEntry {
+            offset: 3600,
+            valid_starting,
+            valid_until,
+        };
+        assert!(matches!(
+            store.set_dst_offsets(vec![dst(10, None), dst(20, Some(30))]),
+            Err(TimeConfigError::InvalidEntry(_))
+        ));
+        assert!(matches!(
+            store.set_dst_offsets(vec![dst(10, Some(25)), dst(20, Some(30))]),
+            Err(TimeConfigError::InvalidEntry(_))
+        ));
+        assert!(matches!(
+            store.set_dst_offsets(vec![dst(0, Some(1)); DST_OFFSET_LIST_MAX_SIZE + 1]),
+            Err(TimeConfigError::ListTooLong(..))
+        ));
+    }
+}
@@ -3,15 +3,35 @@
 //! A Virtual Device represents a parent endpoint with one or more child Endpoints.
 //! This module provides the configuration types needed to define devices at startup.
 
-use super::clusters::{BridgedDeviceInfo, GenericSwitchState, HumiditySensor, TemperatureSensor};
-use super::endpoints::EndpointHandler;
+use super::clusters::{
+    BridgedDeviceInfo, GenericSwitchState, HumiditySensor, NodeLabelListener, PowerSource,
+    TemperatureSensor,
+};
+use super::endpoints::{DeviceAvailability, EndpointHandler};
 use std::hash::{DefaultHasher, Hash, Hasher};
 use std::sync::Arc;
+use thiserror::Error;
+
+/// Errors in the structure of a Virtual Device.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum VirtualDeviceError {
+    /// Two cluster providers of an endpoint would expose the same Matter cluster
+    #[error(
+        "endpoint '{endpoint}' of '{device}' has {first:?} and {second:?}, which use the same cluster"
+    )]
+    DuplicateCluster {
+        device: String,
+        endpoint: String,
+        first: EndpointKind,
+        second: EndpointKind,
+    },
+}
 
-/// Type of endpoint (determines which cluster handler to use).
+/// Type of endpoint cluster (determines which cluster handler to use).
 ///
-/// This defines what kind of child endpoint to create within a Virtual Device.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// This defines what kind of functional cluster a child endpoint carries within a
+/// Virtual Device. An endpoint can carry several kinds (see [`EndpointConfig`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum EndpointKind {
     /// Contact sensor using BooleanState cluster (0x0045)
     ContactSensor,
@@ -29,159 +49,227 @@ pub enum EndpointKind {
     HumiditySensor,
     /// Generic switch using GenericSwitch cluster (0x003B) - for buttons
     GenericSwitch,
+    /// Battery power source using PowerSource cluster (0x002F)
+    PowerSource,
+    /// Latching generic switch using GenericSwitch cluster (0x003B) - rockers, dials
+    LatchingSwitch,
+}
+
+impl EndpointKind {
+    /// Stable ID of this kind in the schema hash.
+    ///
+    /// Never renumber: a changed ID resets the persisted state of every device with
+    /// this kind. New kinds take the next free ID.
+    pub fn schema_id(self) -> u32 {
+        match self {
+            Self::ContactSensor => 0,
+            Self::OccupancySensor => 1,
+            Self::Switch => 2,
+            Self::LightSwitch => 3,
+            Self::VideoDoorbellCamera => 4,
+            Self::TemperatureSensor => 5,
+            Self::HumiditySensor => 6,
+            Self::GenericSwitch => 7,
+            Self::PowerSource => 8,
+            Self::LatchingSwitch => 9,
+        }
+    }
+
+    /// Whether two kinds expose the same Matter cluster (and cannot share an endpoint).
+    fn conflicts_with(self, other: EndpointKind) -> bool {
+        use EndpointKind::{GenericSwitch, LatchingSwitch, LightSwitch, Switch};
+        self == other
+            || matches!(
+                (self, other),
+                (Switch, LightSwitch)
+                    | (LightSwitch, Switch)
+                    | (GenericSwitch, LatchingSwitch)
+                    | (LatchingSwitch, GenericSwitch)
+            )
+    }
+}
+
+/// A functional cluster provider on a child endpoint.
+///
+/// Each variant carries the shared state or handler that drives the cluster.
+pub enum EndpointCluster {
+    /// BooleanState cluster driven by a boolean handler
+    ContactSensor(Arc<dyn EndpointHandler>),
+    /// OccupancySensing cluster driven by a boolean handler
+    OccupancySensor(Arc<dyn EndpointHandler>),
+    /// OnOff cluster (plug-in unit) driven by a boolean handler
+    Switch(Arc<dyn EndpointHandler>),
+    /// OnOff cluster (light) driven by a boolean handler
+    LightSwitch(Arc<dyn EndpointHandler>),
+    /// Camera clusters driven by a boolean handler (streaming not yet wired)
+    VideoDoorbellCamera(Arc<dyn EndpointHandler>),
+    /// TemperatureMeasurement cluster
+    TemperatureSensor(Arc<TemperatureSensor>),
+    /// RelativeHumidityMeasurement cluster
+    HumiditySensor(Arc<HumiditySensor>),
+    /// GenericSwitch cluster (button events, or positions of a latching switch)
+    GenericSwitch(Arc<GenericSwitchState>),
+    /// PowerSource cluster (battery level)
+    PowerSource(Arc<PowerSource>),
+}
+
+impl EndpointCluster {
+    /// Get the kind of this cluster provider.
+    pub fn kind(&self) -> EndpointKind {
+        match self {
+            Self::ContactSensor(_) => EndpointKind::ContactSensor,
+            Self::OccupancySensor(_) => EndpointKind::OccupancySensor,
+            Self::Switch(_) => EndpointKind::Switch,
+            Self::LightSwitch(_) => EndpointKind::LightSwitch,
+            Self::VideoDoorbellCamera(_) => EndpointKind::VideoDoorbellCamera,
+            Self::TemperatureSensor(_) => EndpointKind::TemperatureSensor,
+            Self::HumiditySensor(_) => EndpointKind::HumiditySensor,
+            Self::GenericSwitch(state) if state.is_latching() => EndpointKind::LatchingSwitch,
+            Self::GenericSwitch(_) => EndpointKind::GenericSwitch,
+            Self::PowerSource(_) => EndpointKind::PowerSource,
+        }
+    }
 }
 
 /// Configuration for a child endpoint within a Virtual Device.
 ///
-/// Each endpoint has a label (displayed in controllers), a kind (determines
-/// the cluster), and a handler for bidirectional communication.
+/// Each endpoint has a label (displayed in controllers) and one or more cluster
+/// providers. Single-cluster endpoints are created with the kind-specific
+/// constructors; composite endpoints (e.g., temperature + humidity + battery of one
+/// physical sensor) are built with [`EndpointConfig::new`] and `with_*`.
+///
+/// Each cluster kind may appear at most once per endpoint, and Switch/LightSwitch
+/// (OnOff) as well as momentary/latching GenericSwitch are mutually exclusive. This is
+/// checked when the node is built (see [`VirtualDevice::validate`]).
+///
+/// # Example
+/// ```ignore
+/// let climate = EndpointConfig::new("Climate")
+///     .with_temperature_sensor(temperature)
+///     .with_humidity_sensor(humidity)
+///     .with_power_source(battery);
+/// ```
 pub struct EndpointConfig {
     /// Label displayed in Matter controllers
-    pub label: &'static str,
-    /// Type of endpoint (determines cluster handler)
-    pub kind: EndpointKind,
-    /// Handler for bidirectional communication with business logic (boolean sensors/switches)
-    pub handler: Arc<dyn EndpointHandler>,
-    /// Optional temperature sensor (for TemperatureSensor endpoints)
-    pub temperature_sensor: Option<Arc<TemperatureSensor>>,
-    /// Optional humidity sensor (for HumiditySensor endpoints)
-    pub humidity_sensor: Option<Arc<HumiditySensor>>,
-    /// Optional generic switch state (for GenericSwitch endpoints)
-    pub generic_switch_state: Option<Arc<GenericSwitchState>>,
+    pub label: String,
+    /// Functional clusters on this endpoint (in order)
+    pub clusters: Vec<EndpointCluster>,
 }
 
 impl EndpointConfig {
-    /// Create a contact sensor endpoint (BooleanState cluster).
+    /// Create an endpoint without clusters.
     ///
-    /// Used for door/window sensors that report open/closed state.
-    pub fn contact_sensor(label: &'static str, handler: Arc<dyn EndpointHandler>) -> Self {
+    /// Use `with_cluster` or the `with_*` helpers to add cluster providers.
+    pub fn new(label: impl Into<String>) -> Self {
         Self {
-            label,
-            kind: EndpointKind::ContactSensor,
-            handler,
-            temperature_sensor: None,
-            humidity_sensor: None,
-            generic_switch_state: None,
+            label: label.into(),
+            clusters: Vec::new(),
         }
     }
 
+    /// Create an endpoint with a single cluster provider.
+    fn single(label: impl Into<String>, cluster: EndpointCluster) -> Self {
+        Self::new(label).with_cluster(cluster)
+    }
+
+    /// Add a cluster provider to this endpoint.
+    ///
+    /// Returns self for method chaining.
+    pub fn with_cluster(mut self, cluster: EndpointCluster) -> Self {
+        self.clusters.push(cluster);
+        self
+    }
+
+    /// Add a TemperatureMeasurement cluster to this endpoint.
+    pub fn with_temperature_sensor(self, sensor: Arc<TemperatureSensor>) -> Self {
+        self.with_cluster(EndpointCluster::TemperatureSensor(sensor))
+    }
+
+    /// Add a RelativeHumidityMeasurement cluster to this endpoint.
+    pub fn with_humidity_sensor(self, sensor: Arc<HumiditySensor>) -> Self {
+        self.with_cluster(EndpointCluster::HumiditySensor(sensor))
+    }
+
+    /// Add a PowerSource (battery) cluster to this endpoint.
+    pub fn with_power_source(self, source: Arc<PowerSource>) -> Self {
+        self.with_cluster(EndpointCluster::PowerSource(source))
+    }
+
+    /// Iterate over the cluster kinds of this endpoint (in order).
+    pub fn kinds(&self) -> impl Iterator<Item = EndpointKind> + '_ {
+        self.clusters.iter().map(EndpointCluster::kind)
+    }
+
+    /// Create a contact sensor endpoint (BooleanState cluster).
+    ///
+    /// Used for door/window sensors that report open/closed state.
+    pub fn contact_sensor(label: impl Into<String>, handler: Arc<dyn EndpointHandler>) -> Self {
+        Self::single(label, EndpointCluster::ContactSensor(handler))
+    }
+
     /// Create an occupancy sensor endpoint (OccupancySensing cluster).
     ///
     /// Used for motion/presence sensors.
-    pub fn occupancy_sensor(label: &'static str, handler: Arc<dyn EndpointHandler>) -> Self {
-        Self {
-            label,
-            kind: EndpointKind::OccupancySensor,
-            handler,
-            temperature_sensor: None,
-            humidity_sensor: None,
-            generic_switch_state: None,
-        }
+    pub fn occupancy_sensor(label: impl Into<String>, handler: Arc<dyn EndpointHandler>) -> Self {
+        Self::single(label, EndpointCluster::OccupancySensor(handler))
     }
 
     /// Create a switch endpoint (OnOff cluster, plug-in unit appearance).
     ///
     /// Used for power outlets, relays, or generic switches.
-    pub fn switch(label: &'static str, handler: Arc<dyn EndpointHandler>) -> Self {
-        Self {
-            label,
-            kind: EndpointKind::Switch,
-            handler,
-            temperature_sensor: None,
-            humidity_sensor: None,
-            generic_switch_state: None,
-        }
+    pub fn switch(label: impl Into<String>, handler: Arc<dyn EndpointHandler>) -> Self {
+        Self::single(label, EndpointCluster::Switch(handler))
     }
 
     /// Create a light switch endpoint (OnOff cluster, light appearance).
     ///
     /// Used for lights - appears as a light in controllers.
-    pub fn light_switch(label: &'static str, handler: Arc<dyn EndpointHandler>) -> Self {
-        Self {
-            label,
-            kind: EndpointKind::LightSwitch,
-            handler,
-            temperature_sensor: None,
-            humidity_sensor: None,
-            generic_switch_state: None,
-        }
+    pub fn light_switch(label: impl Into<String>, handler: Arc<dyn EndpointHandler>) -> Self {
+        Self::single(label, EndpointCluster::LightSwitch(handler))
     }
 
     /// Create a video doorbell camera endpoint (CameraAvStreamMgmt + WebRtcTransportProvider clusters).
     ///
     /// Used for video doorbells and cameras with streaming capability.
-    pub fn video_doorbell_camera(label: &'static str, handler: Arc<dyn EndpointHandler>) -> Self {
-        Self {
-            label,
-            kind: EndpointKind::VideoDoorbellCamera,
-            handler,
-            temperature_sensor: None,
-            humidity_sensor: None,
-            generic_switch_state: None,
-        }
+    pub fn video_doorbell_camera(
+        label: impl Into<String>,
+        handler: Arc<dyn EndpointHandler>,
+    ) -> Self {
+        Self::single(label, EndpointCluster::VideoDoorbellCamera(handler))
     }
 
     /// Create a temperature sensor endpoint (TemperatureMeasurement cluster).
     ///
     /// Used for temperature sensors that report temperature values.
     /// The sensor Arc can be cloned and used to update the temperature from external sources.
-    pub fn temperature_sensor(label: &'static str, sensor: Arc<TemperatureSensor>) -> Self {
-        // Create a dummy handler - not used for temperature sensors
-        let handler = Arc::new(DummyHandler);
-        Self {
-            label,
-            kind: EndpointKind::TemperatureSensor,
-            handler,
-            temperature_sensor: Some(sensor),
-            humidity_sensor: None,
-            generic_switch_state: None,
-        }
+    pub fn temperature_sensor(label: impl Into<String>, sensor: Arc<TemperatureSensor>) -> Self {
+        Self::single(label, EndpointCluster::TemperatureSensor(sensor))
     }
 
     /// Create a humidity sensor endpoint (RelativeHumidityMeasurement cluster).
     ///
     /// Used for humidity sensors that report relative humidity.
     /// The sensor Arc can be cloned and used to update the humidity from external sources.
-    pub fn humidity_sensor(label: &'static str, sensor: Arc<HumiditySensor>) -> Self {
-        // Create a dummy handler - not used for humidity sensors
-        let handler = Arc::new(DummyHandler);
-        Self {
-            label,
-            kind: EndpointKind::HumiditySensor,
-            handler,
-            temperature_sensor: None,
-            humidity_sensor: Some(sensor),
-            generic_switch_state: None,
-        }
+    pub fn humidity_sensor(label: impl Into<String>, sensor: Arc<HumiditySensor>) -> Self {
+        Self::single(label, EndpointCluster::HumiditySensor(sensor))
     }
 
     /// Create a generic switch endpoint (GenericSwitch cluster).
     ///
     /// Used for physical buttons that emit press/release events.
     /// The state Arc can be cloned and used to trigger button events from external sources.
-    pub fn generic_switch(label: &'static str, state: Arc<GenericSwitchState>) -> Self {
-        // Create a dummy handler - not used for generic switches
-        let handler = Arc::new(DummyHandler);
-        Self {
-            label,
-            kind: EndpointKind::GenericSwitch,
-            handler,
-            temperature_sensor: None,
-            humidity_sensor: None,
-            generic_switch_state: Some(state),
-        }
+    /// A [`GenericSwitchState::latching`] state exposes a latching switch (rocker, dial)
+    /// whose position is set by the source instead.
+    pub fn generic_switch(label: impl Into<String>, state: Arc<GenericSwitchState>) -> Self {
+        Self::single(label, EndpointCluster::GenericSwitch(state))
     }
-}
-
-/// Dummy handler for endpoints that don't use the EndpointHandler interface.
-struct DummyHandler;
 
-impl EndpointHandler for DummyHandler {
-    fn on_command(&self, _value: bool) {}
-    fn get_state(&self) -> bool {
-        false
+    /// Create a battery power source endpoint (PowerSource cluster).
+    ///
+    /// Usually added to an existing endpoint with `with_power_source` instead.
+    pub fn power_source(label: impl Into<String>, source: Arc<PowerSource>) -> Self {
+        Self::single(label, EndpointCluster::PowerSource(source))
     }
-    fn set_state_pusher(&self, _pusher: Arc<dyn Fn(bool) + Send + Sync>) {}
 }
 
 /// A Virtual Device (parent endpoint) with one or more child Endpoints.
@@ -199,22 +287,31 @@ impl EndpointHandler for DummyHandler {
 /// ```
 pub struct VirtualDevice {
     /// Label displayed in Matter controllers
-    pub label: &'static str,
+    pub label: String,
     /// Child endpoints with functional clusters
     pub endpoints: Vec<EndpointConfig>,
     /// Optional device info (vendor, product, serial, etc.)
     pub device_info: Option<BridgedDeviceInfo>,
+    /// Optional callback invoked when a controller renames the device (NodeLabel write)
+    pub label_listener: Option<NodeLabelListener>,
+    /// Optional source-driven availability (drives the Reachable attribute)
+    pub availability: Option<Arc<DeviceAvailability>>,
+    /// Whether the parent endpoint exposes a device-level OnOff switch
+    pub device_switch: bool,
 }
 
 impl VirtualDevice {
     /// Create a new Virtual Device with the given label.
     ///
     /// Use `with_endpoint` to add child endpoints.
-    pub fn new(label: &'static str) -> Self {
+    pub fn new(label: impl Into<String>) -> Self {
         Self {
-            label,
+            label: label.into(),
             endpoints: Vec::new(),
             device_info: None,
+            label_listener: None,
+            availability: None,
+            device_switch: true,
         }
     }
 
@@ -245,17 +342,74 @@ impl VirtualDevice {
         self
     }
 
+    /// Set a callback invoked when a controller writes a new NodeLabel for this device.
+    ///
+    /// Use this to propagate renames to the underlying source, e.g.
+    /// [`MqttIntegration::label_listener`](crate::input::mqtt::MqttIntegration::label_listener).
+    pub fn with_label_listener(mut self, listener: NodeLabelListener) -> Self {
+        self.label_listener = Some(listener);
+        self
+    }
+
+    /// Drive the Reachable attribute of this device from its input source.
+    ///
+    /// The same handle is given to the source (e.g., MQTT integration), which marks the
+    /// device available/unavailable or seen. All endpoints of the device follow it.
+    pub fn with_availability(mut self, availability: Arc<DeviceAvailability>) -> Self {
+        self.availability = Some(availability);
+        self
+    }
+
+    /// Expose the parent as a pure Bridged Node, without the device-level OnOff switch.
+    ///
+    /// Use this for devices where a power toggle makes no sense (e.g., thermometers).
+    /// Children are then never disabled by a parent switch or the bridge master switch.
+    pub fn without_device_switch(mut self) -> Self {
+        self.device_switch = false;
+        self
+    }
+
+    /// Check that no endpoint carries the same cluster twice.
+    pub fn validate(&self) -> Result<(), VirtualDeviceError> {
+        for endpoint in &self.endpoints {
+            let kinds: Vec<EndpointKind> = endpoint.kinds().collect();
+            for (i, &first) in kinds.iter().enumerate() {
+                if let Some(&second) = kinds[i + 1..]
+                    .iter()
+                    .find(|&&kind| first.conflicts_with(kind))
+                {
+                    return Err(VirtualDeviceError::DuplicateCluster {
+                        device: self.label.clone(),
+                        endpoint: endpoint.label.clone(),
+                        first,
+                        second,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Compute a hash of this device's structure for schema versioning.
     ///
-    /// The hash includes label and all endpoint kinds/labels.
+    /// The hash includes label, the device switch flag and all endpoint cluster kinds/labels.
     /// This is used to detect when the device structure changes and
     /// persistence needs to be reset.
     pub fn schema_hash(&self) -> u64 {
         let mut hasher = DefaultHasher::new();
         self.label.hash(&mut hasher);
+        // Only hashed when disabled, so existing default layouts keep their hash
+        if !self.device_switch {
+            "no-device-switch".hash(&mut hasher);
+        }
         self.endpoints.len().hash(&mut hasher);
         for endpoint in &self.endpoints {
-            endpoint.kind.hash(&mut hasher);
+            // Kinds are hashed one by one (no count), so single-cluster endpoints
+            // keep the hash they had before composite endpoints existed
+            for kind in endpoint.kinds() {
+                // Hashed like the former derived discriminant, so stored hashes stay valid
+                (kind.schema_id() as isize).hash(&mut hasher);
+            }
             endpoint.label.hash(&mut hasher);
         }
         hasher.finish()
@@ -264,13 +418,111 @@ impl VirtualDevice {
 
 /// Compute a combined schema hash for all virtual devices.
 ///
-/// This creates a deterministic hash of the entire device configuration,
+/// This creates a deterministic hash of the entire device configuration
+/// (including whether the bridge master switch exists, as it shifts endpoint IDs),
 /// used to detect when any device structure changes between runs.
-pub fn compute_schema_hash(devices: &[VirtualDevice]) -> u64 {
+pub fn compute_schema_hash(devices: &[VirtualDevice], master_switch: bool) -> u64 {
     let mut hasher = DefaultHasher::new();
+    // Only hashed when disabled, so existing default layouts keep their hash
+    if !master_switch {
+        "no-master-switch".hash(&mut hasher);
+    }
     devices.len().hash(&mut hasher);
     for device in devices {
         device.schema_hash().hash(&mut hasher);
     }
     hasher.finish()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duplicate_clusters_rejected() {
+        let temperature = Arc::new(TemperatureSensor::new(20.0));
+        let humidity = Arc::new(HumiditySensor::new(50.0));
+
+        let climate = VirtualDevice::new("Sensor").with_endpoint(
+            EndpointConfig::new("Climate")
+                .with_temperature_sensor(temperature.clone())
+                .with_humidity_sensor(humidity),
+        );
+        assert_eq!(climate.validate(), Ok(()));
+
+        let twice = VirtualDevice::new("Sensor").with_endpoint(
+            EndpointConfig::temperature_sensor("Climate", temperature.clone())
+                .with_temperature_sensor(temperature),
+        );
+        assert_eq!(
+            twice.validate(),
+            Err(VirtualDeviceError::DuplicateCluster {
+                device: "Sensor".to_string(),
+                endpoint: "Climate".to_string(),
+                first: EndpointKind::TemperatureSensor,
+                second: EndpointKind::TemperatureSensor,
+            })
+        );
+
+        let buttons = VirtualDevice::new("Remote").with_endpoint(
+            EndpointConfig::generic_switch("Button", Arc::new(GenericSwitchState::new()))
+                .with_cluster(EndpointCluster::GenericSwitch(Arc::new(
+                    GenericSwitchState::latching(2),
+                ))),
+        );
+        assert!(buttons.validate().is_err());
+    }
+
+    #[test]
+    fn test_schema_ids_stable() {
+        use EndpointKind::*;
+        let kinds = [
+            ContactSensor,
+            OccupancySensor,
+            Switch,
+            LightSwitch,
+            VideoDoorbellCamera,
+            TemperatureSensor,
+            HumiditySensor,
+            GenericSwitch,
+            PowerSource,
+            LatchingSwitch,
+        ];
+        let ids: std::collections::BTreeSet<u32> = kinds.iter().map(|k| k.schema_id()).collect();
+        assert_eq!(ids.len(), kinds.len());
+
+        // Same hash input as the derived Hash of the original enum order
+        #[derive(Hash)]
+        enum Legacy {
+            A,
+            B,
+            C,
+            D,
+            E,
+            F,
+            G,
+            H,
+            I,
+            J,
+        }
+        let legacy = [
+            Legacy::A,
+            Legacy::B,
+            Legacy::C,
+            Legacy::D,
+            Legacy::E,
+            Legacy::F,
+            Legacy::G,
+            Legacy::H,
+            Legacy::I,
+            Legacy::J,
+        ];
+        for (kind, legacy) in kinds.into_iter().zip(legacy) {
+            let mut expected = DefaultHasher::new();
+            legacy.hash(&mut expected);
+            let mut actual = DefaultHasher::new();
+            (kind.schema_id() as isize).hash(&mut actual);
+            assert_eq!(actual.finish(), expected.finish(), "{:?}", kind);
+        }
+    }
+}
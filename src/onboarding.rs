@@ -0,0 +1,520 @@
+//! Matter onboarding payloads.
+//!
+//! Generates and parses the `MT:` QR code payload (Matter spec Section 5.1.3) and
+//! generates the 11-digit manual pairing code (Section 5.1.4), and renders QR codes
+//! for the terminal or as SVG/PNG files. Everything here works offline from the
+//! commissioning data, so tools can show the codes while the bridge is not running.
+
+use qrcodegen::{QrCode, QrCodeEcc};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Prefix of a QR code payload
+pub const QR_PREFIX: &str = "MT:";
+
+/// Discovery capabilities bit for SoftAP commissioning
+pub const DISCOVERY_SOFT_AP: u8 = 0x01;
+/// Discovery capabilities bit for BLE commissioning
+pub const DISCOVERY_BLE: u8 = 0x02;
+/// Discovery capabilities bit for on-network (IP) commissioning
+pub const DISCOVERY_ON_NETWORK: u8 = 0x04;
+
+/// Base38 alphabet used by the QR code payload
+const BASE38_CHARS: &[u8; 38] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ-.";
+
+/// Size of the packed payload in bytes (88 bits)
+const PAYLOAD_BYTES: usize = 11;
+
+/// Light modules around the code in SVG/PNG output (as recommended by ISO 18004)
+const FILE_QUIET_ZONE: i32 = 4;
+
+/// Light modules around the code in terminal output
+const TERMINAL_QUIET_ZONE: i32 = 2;
+
+/// Error types for onboarding payloads.
+#[derive(Debug, Error)]
+pub enum OnboardingError {
+    /// The payload does not start with `MT:`.
+    #[error("payload must start with \"{QR_PREFIX}\"")]
+    MissingPrefix,
+
+    /// The payload contains a character outside the Base38 alphabet.
+    #[error("invalid Base38 character '{0}'")]
+    InvalidCharacter(char),
+
+    /// The payload is too short or has a truncated Base38 chunk.
+    #[error("invalid payload length")]
+    InvalidLength,
+
+    /// A Base38 chunk decodes to more bits than it may carry.
+    #[error("invalid Base38 chunk")]
+    InvalidChunk,
+
+    /// Only payload version 0 is defined.
+    #[error("unsupported payload version {0}")]
+    UnsupportedVersion(u8),
+
+    /// Unknown commissioning flow value.
+    #[error("invalid commissioning flow {0}")]
+    InvalidFlow(u8),
+
+    /// The passcode is out of range or one of the trivial passcodes.
+    #[error("invalid passcode {0}")]
+    InvalidPasscode(u32),
+
+    /// The QR code could not be rendered.
+    #[error("failed to render QR code: {0}")]
+    Render(String),
+}
+
+/// How a device enters commissioning mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommissioningFlow {
+    /// Commissionable when powered on (or on request, for the bridge)
+    Standard,
+    /// Requires a user action described in the device manual
+    UserIntent,
+    /// Requires vendor-specific steps
+    Custom,
+}
+
+impl CommissioningFlow {
+    fn from_bits(bits: u8) -> Result<Self, OnboardingError> {
+        match bits {
+            0 => Ok(Self::Standard),
+            1 => Ok(Self::UserIntent),
+            2 => Ok(Self::Custom),
+            _ => Err(OnboardingError::InvalidFlow(bits)),
+        }
+    }
+
+    fn bits(self) -> u8 {
+        match self {
+            Self::Standard => 0,
+            Self::UserIntent => 1,
+            Self::Custom => 2,
+        }
+    }
+}
+
+/// Contents of a Matter onboarding payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OnboardingPayload {
+    pub vendor_id: u16,
+    pub product_id: u16,
+    pub flow: CommissioningFlow,
+    /// Bitmap of `DISCOVERY_*` capabilities
+    pub discovery: u8,
+    /// 12-bit long discriminator
+    pub discriminator: u16,
+    /// 27-bit setup passcode
+    pub passcode: u32,
+}
+
+impl OnboardingPayload {
+    /// Payload for standard on-network commissioning, as used by the bridge.
+    pub fn new(vendor_id: u16, product_id: u16, discriminator: u16, passcode: u32) -> Self {
+        Self {
+            vendor_id,
+            product_id,
+            flow: CommissioningFlow::Standard,
+            discovery: DISCOVERY_ON_NETWORK,
+            discriminator: discriminator & 0x0FFF,
+            passcode: passcode & 0x07FF_FFFF,
+        }
+    }
+
+    /// Parse a `MT:` QR code payload.
+    ///
+    /// Optional TLV data after the 88-bit payload is ignored.
+    pub fn parse(payload: &str) -> Result<Self, OnboardingError> {
+        let encoded = payload
+            .trim()
+            .strip_prefix(QR_PREFIX)
+            .ok_or(OnboardingError::MissingPrefix)?;
+        let bytes = base38_decode(encoded)?;
+        if bytes.len() < PAYLOAD_BYTES {
+            return Err(OnboardingError::InvalidLength);
+        }
+        let mut buf = [0u8; 16];
+        buf[..PAYLOAD_BYTES].copy_from_slice(&bytes[..PAYLOAD_BYTES]);
+        let bits = u128::from_le_bytes(buf);
+        let field = |offset: u32, width: u32| (bits >> offset) & ((1u128 << width) - 1);
+
+        let version = field(0, 3) as u8;
+        if version != 0 {
+            return Err(OnboardingError::UnsupportedVersion(version));
+        }
+        let parsed = Self {
+            vendor_id: field(3, 16) as u16,
+            product_id: field(19, 16) as u16,
+            flow: CommissioningFlow::from_bits(field(35, 2) as u8)?,
+            discovery: field(37, 8) as u8,
+            discriminator: field(45, 12) as u16,
+            passcode: field(57, 27) as u32,
+        };
+        if !crate::matter::comm_data::is_valid_passcode(parsed.passcode) {
+            return Err(OnboardingError::InvalidPasscode(parsed.passcode));
+        }
+        Ok(parsed)
+    }
+
+    /// Encode as a `MT:` QR code payload.
+    ///
+    /// The 88-bit payload (version, VID, PID, flow, discovery capabilities, long
+    /// discriminator, passcode, padding) is packed LSB first and Base38 encoded.
+    pub fn qr_payload(&self) -> String {
+        // (value, bit width) in payload order; version 0 and zero padding
+        let fields: [(u128, u32); 8] = [
+            (0, 3),
+            (self.vendor_id as u128, 16),
+            (self.product_id as u128, 16),
+            (self.flow.bits() as u128, 2),
+            (self.discovery as u128, 8),
+            ((self.discriminator & 0x0FFF) as u128, 12),
+            ((self.passcode & 0x07FF_FFFF) as u128, 27),
+            (0, 4),
+        ];
+        let mut bits: u128 = 0;
+        let mut offset = 0;
+        for (value, width) in fields {
+            bits |= value << offset;
+            offset += width;
+        }
+        format!(
+            "{}{}",
+            QR_PREFIX,
+            base38_encode(&bits.to_le_bytes()[..PAYLOAD_BYTES])
+        )
+    }
+
+    /// The 11-digit manual pairing code (short discriminator only).
+    pub fn manual_code(&self) -> String {
+        generate_pairing_code(self.discriminator, self.passcode)
+    }
+}
+
+impl fmt::Display for OnboardingPayload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.qr_payload())
+    }
+}
+
+impl FromStr for OnboardingPayload {
+    type Err = OnboardingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+/// Generate the `MT:` QR code payload for on-network commissioning.
+pub fn generate_qr_payload(
+    vendor_id: u16,
+    product_id: u16,
+    discriminator: u16,
+    passcode: u32,
+) -> String {
+    OnboardingPayload::new(vendor_id, product_id, discriminator, passcode).qr_payload()
+}
+
+/// Generate the manual pairing code from discriminator and passcode.
+///
+/// Matter short manual pairing code is an 11-digit decimal number:
+/// - Chunk 1 (1 digit): discriminator bits 11-10
+/// - Chunk 2 (5 digits): discriminator bits 9-8 (upper 2 bits) + passcode bits 13-0 (lower 14 bits)
+/// - Chunk 3 (4 digits): passcode bits 26-14
+/// - Check digit (1 digit): Verhoeff checksum
+///
+/// Reference: Matter spec Section 5.1.4.1, connectedhomeip ManualSetupPayloadGenerator.h
+pub fn generate_pairing_code(discriminator: u16, passcode: u32) -> String {
+    // Chunk 1: top 2 bits of discriminator (bits 11-10)
+    let chunk1 = (discriminator >> 10) & 0x03;
+
+    // Chunk 2: discriminator bits 9-8 in upper 2 bits, passcode bits 13-0 in lower 14 bits
+    let discriminator_bits_9_8 = ((discriminator >> 8) & 0x03) as u32;
+    let passcode_bits_13_0 = passcode & 0x3FFF;
+    let chunk2 = (discriminator_bits_9_8 << 14) | passcode_bits_13_0;
+
+    // Chunk 3: passcode bits 26-14
+    let chunk3 = (passcode >> 14) & 0x1FFF;
+
+    // Format as 10 digits (1 + 5 + 4), then add Verhoeff check digit
+    let payload = format!("{}{:05}{:04}", chunk1, chunk2, chunk3);
+    let check_digit = verhoeff_checksum(&payload);
+    format!("{}{}", payload, check_digit)
+}
+
+/// Compute Verhoeff check digit for a string of decimal digits.
+fn verhoeff_checksum(input: &str) -> u8 {
+    // Verhoeff dihedral group D5 multiplication table
+    const D: [[u8; 10]; 10] = [
+        [0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
+        [1, 2, 3, 4, 0, 6, 7, 8, 9, 5],
+        [2, 3, 4, 0, 1, 7, 8, 9, 5, 6],
+        [3, 4, 0, 1, 2, 8, 9, 5, 6, 7],
+        [4, 0, 1, 2, 3, 9, 5, 6, 7, 8],
+        [5, 9, 8, 7, 6, 0, 4, 3, 2, 1],
+        [6, 5, 9, 8, 7, 1, 0, 4, 3, 2],
+        [7, 6, 5, 9, 8, 2, 1, 0, 4, 3],
+        [8, 7, 6, 5, 9, 3, 2, 1, 0, 4],
+        [9, 8, 7, 6, 5, 4, 3, 2, 1, 0],
+    ];
+
+    // Verhoeff permutation table
+    const P: [[u8; 10]; 8] = [
+        [0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
+        [1, 5, 7, 6, 2, 8, 3, 0, 9, 4],
+        [5, 8, 0, 3, 7, 9, 6, 1, 4, 2],
+        [8, 9, 1, 6, 0, 4, 3, 5, 2, 7],
+        [9, 4, 5, 3, 1, 2, 6, 8, 7, 0],
+        [4, 2, 8, 6, 5, 7, 3, 9, 0, 1],
+        [2, 7, 9, 3, 8, 0, 6, 4, 1, 5],
+        [7, 0, 4, 6, 9, 1, 3, 2, 5, 8],
+    ];
+
+    // Verhoeff inverse table
+    const INV: [u8; 10] = [0, 4, 3, 2, 1, 5, 6, 7, 8, 9];
+
+    let digits: Vec<u8> = input.bytes().map(|b| b - b'0').collect();
+    let mut c: u8 = 0;
+    for (i, &d) in digits.iter().rev().enumerate() {
+        c = D[c as usize][P[(i + 1) % 8][d as usize] as usize];
+    }
+    INV[c as usize]
+}
+
+/// Number of Base38 characters encoding a chunk of `bytes` bytes.
+fn base38_chars(bytes: usize) -> usize {
+    match bytes {
+        3 => 5,
+        2 => 4,
+        _ => 2,
+    }
+}
+
+/// Base38 encode: 3 bytes -> 5 chars, trailing 2 bytes -> 4 chars, trailing byte -> 2 chars.
+fn base38_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let mut value = chunk
+            .iter()
+            .rev()
+            .fold(0u32, |acc, &byte| (acc << 8) | byte as u32);
+        for _ in 0..base38_chars(chunk.len()) {
+            encoded.push(BASE38_CHARS[(value % 38) as usize] as char);
+            value /= 38;
+        }
+    }
+    encoded
+}
+
+/// Base38 decode (inverse of [`base38_encode`]).
+fn base38_decode(encoded: &str) -> Result<Vec<u8>, OnboardingError> {
+    let digits = encoded
+        .chars()
+        .map(|c| {
+            BASE38_CHARS
+                .iter()
+                .position(|&b| b as char == c)
+                .map(|pos| pos as u32)
+                .ok_or(OnboardingError::InvalidCharacter(c))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut bytes = Vec::new();
+    for chunk in digits.chunks(5) {
+        let len = match chunk.len() {
+            5 => 3,
+            4 => 2,
+            2 => 1,
+            _ => return Err(OnboardingError::InvalidLength),
+        };
+        let value = chunk
+            .iter()
+            .rev()
+            .fold(0u32, |acc, &digit| acc * 38 + digit);
+        if value >> (8 * len) != 0 {
+            return Err(OnboardingError::InvalidChunk);
+        }
+        bytes.extend_from_slice(&value.to_le_bytes()[..len]);
+    }
+    Ok(bytes)
+}
+
+/// Encode a payload as a QR code.
+fn qr_code(payload: &str) -> Result<QrCode, OnboardingError> {
+    QrCode::encode_text(payload, QrCodeEcc::Medium)
+        .map_err(|e| OnboardingError::Render(e.to_string()))
+}
+
+/// Render a payload as a QR code for the terminal.
+///
+/// Uses Unicode half blocks (two modules per character) drawing the light modules,
+/// so the code scans on terminals with a dark background.
+pub fn render_terminal(payload: &str) -> Result<String, OnboardingError> {
+    let qr = qr_code(payload)?;
+    let range = -TERMINAL_QUIET_ZONE..qr.size() + TERMINAL_QUIET_ZONE;
+    let mut out = String::new();
+    for y in range.clone().step_by(2) {
+        for x in range.clone() {
+            // Modules outside the code (quiet zone) are light
+            let top = !qr.get_module(x, y);
+            let bottom = y + 1 < range.end && !qr.get_module(x, y + 1);
+            out.push(match (top, bottom) {
+                (true, true) => '█',
+                (true, false) => '▀',
+                (false, true) => '▄',
+                (false, false) => ' ',
+            });
+        }
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Render a payload as an SVG document (one unit per module).
+pub fn render_svg(payload: &str) -> Result<String, OnboardingError> {
+    let qr = qr_code(payload)?;
+    let dimension = qr.size() + 2 * FILE_QUIET_ZONE;
+    let mut path = String::new();
+    for y in 0..qr.size() {
+        for x in 0..qr.size() {
+            if qr.get_module(x, y) {
+                path.push_str(&format!(
+                    "M{},{}h1v1h-1z",
+                    x + FILE_QUIET_ZONE,
+                    y + FILE_QUIET_ZONE
+                ));
+            }
+        }
+    }
+    Ok(format!(
+        concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" version=\"1.1\" ",
+            "viewBox=\"0 0 {0} {0}\" shape-rendering=\"crispEdges\">\n",
+            "<title>{1}</title>\n",
+            "<rect width=\"100%\" height=\"100%\" fill=\"#FFFFFF\"/>\n",
+            "<path d=\"{2}\" fill=\"#000000\"/>\n",
+            "</svg>\n"
+        ),
+        dimension, payload, path
+    ))
+}
+
+/// Render a payload as a grayscale PNG image with `scale` pixels per module.
+pub fn render_png(payload: &str, scale: u32) -> Result<Vec<u8>, OnboardingError> {
+    let qr = qr_code(payload)?;
+    let scale = scale.max(1);
+    let modules = (qr.size() + 2 * FILE_QUIET_ZONE) as u32;
+    let dimension = modules * scale;
+
+    let mut pixels = Vec::with_capacity((dimension * dimension) as usize);
+    for py in 0..dimension {
+        for px in 0..dimension {
+            let x = (px / scale) as i32 - FILE_QUIET_ZONE;
+            let y = (py / scale) as i32 - FILE_QUIET_ZONE;
+            pixels.push(if qr.get_module(x, y) { 0x00 } else { 0xFF });
+        }
+    }
+
+    let render_error = |e: png::EncodingError| OnboardingError::Render(e.to_string());
+    let mut png_data = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png_data, dimension, dimension);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(render_error)?;
+    writer.write_image_data(&pixels).map_err(render_error)?;
+    writer.finish().map_err(render_error)?;
+    Ok(png_data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pairing_code() {
+        // Matter SDK test device (discriminator 3840, passcode 20202021)
+        assert_eq!(generate_pairing_code(3840, 20202021), "34970112332");
+    }
+
+    #[test]
+    fn test_qr_payload() {
+        // Matter SDK test device with on-network discovery
+        assert_eq!(
+            generate_qr_payload(0xFFF1, 0x8001, 3840, 20202021),
+            "MT:-24J0AFN00KA0648G00"
+        );
+        assert_eq!(
+            generate_qr_payload(0xFFF1, 0x8000, 3840, 20202021),
+            "MT:Y.K90AFN00KA0648G00"
+        );
+
+        // Matter SDK test device with BLE discovery
+        let ble = OnboardingPayload {
+            discovery: DISCOVERY_BLE,
+            ..OnboardingPayload::new(0xFFF1, 0x8000, 3840, 20202021)
+        };
+        assert_eq!(ble.qr_payload(), "MT:Y.K9042C00KA0648G00");
+    }
+
+    #[test]
+    fn test_parse_roundtrip() {
+        let parsed: OnboardingPayload = "MT:Y.K9042C00KA0648G00".parse().unwrap();
+        assert_eq!(parsed.vendor_id, 0xFFF1);
+        assert_eq!(parsed.product_id, 0x8000);
+        assert_eq!(parsed.flow, CommissioningFlow::Standard);
+        assert_eq!(parsed.discovery, DISCOVERY_BLE);
+        assert_eq!(parsed.discriminator, 3840);
+        assert_eq!(parsed.passcode, 20202021);
+        assert_eq!(parsed.manual_code(), "34970112332");
+
+        let payload = OnboardingPayload::new(0x1234, 0x5678, 0xABC, 12345679);
+        assert_eq!(
+            OnboardingPayload::parse(&payload.qr_payload()).unwrap(),
+            payload
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid() {
+        assert!(matches!(
+            OnboardingPayload::parse("Y.K9042C00KA0648G00"),
+            Err(OnboardingError::MissingPrefix)
+        ));
+        assert!(matches!(
+            OnboardingPayload::parse("MT:Y.K9042C00KA0648G0a"),
+            Err(OnboardingError::InvalidCharacter('a'))
+        ));
+        assert!(matches!(
+            OnboardingPayload::parse("MT:Y.K9042C00KA0648G0"),
+            Err(OnboardingError::InvalidLength)
+        ));
+        // Trivial passcode 11111111
+        let trivial = OnboardingPayload::new(0xFFF1, 0x8000, 3840, 11111111);
+        assert!(matches!(
+            OnboardingPayload::parse(&trivial.qr_payload()),
+            Err(OnboardingError::InvalidPasscode(11111111))
+        ));
+    }
+
+    #[test]
+    fn test_render() {
+        let payload = generate_qr_payload(0xFFF1, 0x8000, 3840, 20202021);
+        let svg = render_svg(&payload).unwrap();
+        assert!(svg.contains("<svg") && svg.contains(&payload));
+
+        let png = render_png(&payload, 4).unwrap();
+        assert_eq!(&png[1..4], b"PNG");
+
+        // Version 2 (25 modules) plus quiet zone, two rows per line
+        let terminal = render_terminal(&payload).unwrap();
+        let lines: Vec<_> = terminal.lines().collect();
+        let modules = 25 + 2 * TERMINAL_QUIET_ZONE as usize;
+        assert!(lines.iter().all(|line| line.chars().count() == modules));
+        assert_eq!(lines.len(), modules.div_ceil(2));
+    }
+}